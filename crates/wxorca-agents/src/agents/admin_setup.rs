@@ -2,40 +2,99 @@
 //!
 //! Helps administrators set up and configure WatsonX Orchestrate.
 
-use super::{route_by_tools, AnalyzeQueryNode, ExecuteToolsNode};
-use crate::state::AgentType;
+use super::layout::{Edge, GraphLayout, Wiring};
+use super::templates::AdminTemplates;
+use super::{
+    append_degraded_notice, append_followups, awaiting_tool_results, new_tool_call_id, observe,
+    promote_context, queue_tool_call, record_relevant_docs, request_user_input, respond_with_llm,
+    route_by_tools, AnalyzeQueryNode, DocLinks, ExecuteToolsNode, GraphOptions, LlmClient,
+    RenderContext, ResponseRenderer, SearchGate, DEFAULT_TOOL_TIMEOUT,
+};
+use crate::state::{AgentType, DocCategory, Language, UserRole};
 use oxidizedgraph::prelude::*;
 use std::sync::Arc;
 
 /// Agent for guiding administrators through WatsonX Orchestrate setup
 pub struct AdminSetupAgent;
 
+/// Flow: analyze -> search_docs -> respond, running tool calls in between
+const WIRING: Wiring = Wiring {
+    entry: "analyze",
+    edges: &[
+        Edge::Fixed("analyze", "search_docs"),
+        Edge::Fixed("search_docs", "respond"),
+        // If tools are needed, execute them and loop back to respond
+        Edge::Conditional(
+            "respond",
+            route_by_tools,
+            &["execute_tools", transitions::END],
+        ),
+        Edge::Fixed("execute_tools", "respond"),
+    ],
+};
+
 impl AdminSetupAgent {
     /// Build the agent graph for admin setup guidance
     pub fn build_graph(tool_registry: Arc<ToolRegistry>) -> Result<CompiledGraph, GraphError> {
-        let system_prompt = AgentType::AdminSetup.system_prompt().to_string();
+        Self::build_graph_with_options(tool_registry, GraphOptions::default())
+    }
 
-        GraphBuilder::new()
+    /// Build the agent graph with optional node observer and LLM
+    pub fn build_graph_with_options(
+        tool_registry: Arc<ToolRegistry>,
+        options: GraphOptions,
+    ) -> Result<CompiledGraph, GraphError> {
+        let system_prompt = options.system_prompt(AgentType::AdminSetup);
+        let renderer = options.renderer(AgentType::AdminSetup);
+        let GraphOptions {
+            observer,
+            llm,
+            min_keywords,
+            classifier,
+            tool_timeout,
+            ..
+        } = options;
+        let tool_timeout = tool_timeout.unwrap_or(DEFAULT_TOOL_TIMEOUT);
+        let gate = SearchGate::new(min_keywords);
+
+        let builder = GraphBuilder::new()
             .name("admin_setup_agent")
             .description("Guides administrators through WatsonX Orchestrate setup and configuration")
             // Analyze the user's query
-            .add_node(AnalyzeQueryNode::new("analyze"))
+            .add_node(observe(
+                AnalyzeQueryNode::new("analyze").with_classifier(classifier.unwrap_or_default()),
+                &observer,
+            ))
             // Search documentation for relevant info
-            .add_node(AdminSearchNode::new("search_docs", system_prompt.clone()))
+            .add_node(observe(
+                AdminSearchNode::new("search_docs", system_prompt.clone()).with_gate(gate),
+                &observer,
+            ))
             // Generate response with admin-specific guidance
-            .add_node(AdminResponseNode::new("respond", system_prompt))
+            .add_node(observe(
+                AdminResponseNode::new("respond", system_prompt)
+                    .with_llm(llm)
+                    .with_renderer(renderer),
+                &observer,
+            ))
             // Execute any tool calls
-            .add_node(ExecuteToolsNode::new("execute_tools", tool_registry))
-            // Set entry point
-            .set_entry_point("analyze")
-            // Flow: analyze -> search_docs -> respond
-            .add_edge("analyze", "search_docs")
-            .add_edge("search_docs", "respond")
-            // Conditional: if tools needed, execute them
-            .add_conditional_edge("respond", route_by_tools)
-            // After tools, loop back to respond
-            .add_edge("execute_tools", "respond")
-            .compile()
+            .add_node(observe(
+                ExecuteToolsNode::new("execute_tools", tool_registry).with_timeout(tool_timeout),
+                &observer,
+            ));
+        WIRING.apply(builder).compile()
+    }
+
+    /// Describe the graph built by [`Self::build_graph`] for export
+    pub(crate) fn layout(tool_registry: Arc<ToolRegistry>) -> GraphLayout {
+        let system_prompt = AgentType::AdminSetup.system_prompt().to_string();
+
+        GraphLayout::new("admin_setup_agent")
+            .node(&AnalyzeQueryNode::new("analyze"))
+            .node(&AdminSearchNode::new("search_docs", system_prompt.clone()))
+            .node(&AdminResponseNode::new("respond", system_prompt))
+            .node(&ExecuteToolsNode::new("execute_tools", tool_registry))
+            .wiring(&WIRING)
     }
 }
 
@@ -43,6 +102,7 @@ impl AdminSetupAgent {
 struct AdminSearchNode {
     id: String,
     _system_prompt: String,
+    gate: SearchGate,
 }
 
 impl AdminSearchNode {
@@ -50,8 +110,15 @@ impl AdminSearchNode {
         Self {
             id: id.into(),
             _system_prompt: system_prompt,
+            gate: SearchGate::default(),
         }
     }
+
+    /// Only search for queries `gate` allows
+    fn with_gate(mut self, gate: SearchGate) -> Self {
+        self.gate = gate;
+        self
+    }
 }
 
 #[async_trait::async_trait]
@@ -75,7 +142,7 @@ impl NodeExecutor for AdminSearchNode {
                 .unwrap_or_default()
         };
 
-        if query.is_empty() {
+        if !self.gate.allows(&query) {
             return Ok(NodeOutput::cont());
         }
 
@@ -86,16 +153,16 @@ impl NodeExecutor for AdminSearchNode {
                 .map_err(|e| NodeError::Other(format!("Failed to write state: {}", e)))?;
 
             let tool_call = ToolCall {
-                id: uuid::Uuid::new_v4().to_string(),
+                id: new_tool_call_id(&guard),
                 name: "search_wxo_docs".to_string(),
                 arguments: serde_json::json!({
                     "query": query,
-                    "category": "admin",
+                    "category": DocCategory::Admin,
                     "limit": 5
                 }),
             };
 
-            guard.tool_calls.push(tool_call);
+            queue_tool_call(&mut guard, tool_call);
         }
 
         Ok(NodeOutput::cont())
@@ -106,6 +173,8 @@ impl NodeExecutor for AdminSearchNode {
 struct AdminResponseNode {
     id: String,
     system_prompt: String,
+    renderer: Arc<dyn ResponseRenderer>,
+    llm: Option<Arc<dyn LlmClient>>,
 }
 
 impl AdminResponseNode {
@@ -113,7 +182,23 @@ impl AdminResponseNode {
         Self {
             id: id.into(),
             system_prompt,
+            renderer: Arc::new(AdminSetupRenderer::default()),
+            llm: None,
+        }
+    }
+
+    /// Generate replies with `llm` instead of the built-in template
+    fn with_llm(mut self, llm: Option<Arc<dyn LlmClient>>) -> Self {
+        self.llm = llm;
+        self
+    }
+
+    /// Render template replies with `renderer` instead of [`AdminSetupRenderer`]
+    fn with_renderer(mut self, renderer: Option<Arc<dyn ResponseRenderer>>) -> Self {
+        if let Some(renderer) = renderer {
+            self.renderer = renderer;
         }
+        self
     }
 }
 
@@ -128,88 +213,279 @@ impl NodeExecutor for AdminResponseNode {
     }
 
     async fn execute(&self, state: SharedState) -> Result<NodeOutput, NodeError> {
+        if awaiting_tool_results(&state)? {
+            return Ok(NodeOutput::cont());
+        }
+
+        if let Some(llm) = &self.llm {
+            return respond_with_llm(
+                llm.as_ref(),
+                AgentType::AdminSetup,
+                &self.system_prompt,
+                &state,
+            )
+            .await;
+        }
+
         let mut guard = state
             .write()
             .map_err(|e| NodeError::Other(format!("Failed to write state: {}", e)))?;
 
-        // Get the original query and any tool results
         let query = guard
             .get_context::<String>("original_query")
             .unwrap_or_default();
+        let context = RenderContext::from_state(&guard);
+        record_relevant_docs(&mut guard, &context.tool_results);
 
-        // Get tool results if any
-        let tool_results: Vec<String> = guard
-            .messages
-            .iter()
-            .filter(|m| m.role == MessageRole::Tool)
-            .map(|m| m.content.clone())
-            .collect();
-
-        // Generate response (in a real implementation, this would call an LLM)
-        let response = generate_admin_response(&query, &tool_results, &self.system_prompt);
+        let response = self.renderer.render(&query, &context);
+        let response = append_degraded_notice(&context, &response);
 
+        promote_context(&mut guard);
+        let response = append_followups(&mut guard, AgentType::AdminSetup, &response);
         guard.add_assistant_message(&response);
-        guard.mark_complete();
+        if self.renderer.asks_for_input(&query, &context) {
+            request_user_input(&mut guard);
+        } else {
+            guard.mark_complete();
+        }
 
         Ok(NodeOutput::finish())
     }
 }
 
-fn generate_admin_response(query: &str, tool_results: &[String], _system_prompt: &str) -> String {
+/// Default [`ResponseRenderer`] for the admin setup agent
+#[derive(Debug, Clone)]
+pub struct AdminSetupRenderer {
+    doc_links: DocLinks,
+}
+
+impl AdminSetupRenderer {
+    /// Link to the docs at `doc_links`
+    pub fn new(doc_links: DocLinks) -> Self {
+        Self { doc_links }
+    }
+}
+
+impl Default for AdminSetupRenderer {
+    fn default() -> Self {
+        Self::new(DocLinks::from_env())
+    }
+}
+
+impl ResponseRenderer for AdminSetupRenderer {
+    fn render(&self, query: &str, context: &RenderContext) -> String {
+        generate_admin_response(
+            query,
+            &context.tool_results,
+            &self.doc_links,
+            deployment_for(query, context),
+            context.language,
+            context.user_role,
+            AgentType::AdminSetup.system_prompt(),
+        )
+    }
+
+    /// Setup, security and integration replies ask which deployment the
+    /// user is on when that isn't known yet
+    fn asks_for_input(&self, query: &str, context: &RenderContext) -> bool {
+        AdminTopic::detect(query).depends_on_deployment()
+            && deployment_for(query, context).is_none()
+    }
+}
+
+/// The deployment type from the session context, falling back to one
+/// mentioned in the query
+fn deployment_for(query: &str, context: &RenderContext) -> Option<DeploymentType> {
+    context
+        .get::<String>("deployment_type")
+        .and_then(|d| DeploymentType::parse(&d))
+        .or_else(|| DeploymentType::parse(query))
+}
+
+/// How a WXO instance is deployed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DeploymentType {
+    /// IBM-hosted (IBM Cloud or AWS)
+    Saas,
+    /// Self-managed, e.g. on Cloud Pak for Data
+    OnPrem,
+}
+
+impl DeploymentType {
+    /// Recognize a deployment type in free text, e.g. `WxoContext::deployment_type`
+    fn parse(text: &str) -> Option<Self> {
+        let text = text.to_lowercase();
+        let mentions = |keywords: &[&str]| keywords.iter().any(|kw| text.contains(kw));
+
+        // Check on-prem first: "cloud pak" would otherwise look like SaaS
+        if mentions(&[
+            "on-prem",
+            "on prem",
+            "onprem",
+            "on_prem",
+            "self-hosted",
+            "self-managed",
+            "cloud pak",
+            "openshift",
+        ]) {
+            Some(DeploymentType::OnPrem)
+        } else if mentions(&["saas", "ibm cloud", "aws", "hosted"]) {
+            Some(DeploymentType::Saas)
+        } else {
+            None
+        }
+    }
+}
+
+/// Query keywords for each admin topic, in every supported language
+const SETUP_KEYWORDS: &[&str] = &["setup", "install", "instalar", "instalación"];
+const USER_KEYWORDS: &[&str] = &["user", "permission", "usuario", "permiso"];
+const SECURITY_KEYWORDS: &[&str] = &["security", "authentication", "seguridad", "autenticación"];
+const INTEGRATION_KEYWORDS: &[&str] = &["integration", "integración"];
+
+/// What an admin question is about, which picks the reply template
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AdminTopic {
+    Setup,
+    Users,
+    Security,
+    Integration,
+    General,
+}
+
+impl AdminTopic {
+    /// The topic of `query`, checking setup, users, security and integration
+    /// keywords in that order
+    fn detect(query: &str) -> Self {
+        let query_lower = query.to_lowercase();
+        let mentions = |keywords: &[&str]| keywords.iter().any(|kw| query_lower.contains(kw));
+
+        if mentions(SETUP_KEYWORDS) {
+            AdminTopic::Setup
+        } else if mentions(USER_KEYWORDS) {
+            AdminTopic::Users
+        } else if mentions(SECURITY_KEYWORDS) {
+            AdminTopic::Security
+        } else if mentions(INTEGRATION_KEYWORDS) {
+            AdminTopic::Integration
+        } else {
+            AdminTopic::General
+        }
+    }
+
+    /// Whether the reply differs between SaaS and on-prem deployments
+    fn depends_on_deployment(self) -> bool {
+        matches!(
+            self,
+            AdminTopic::Setup | AdminTopic::Security | AdminTopic::Integration
+        )
+    }
+}
+
+/// Append the notes for `deployment` (SaaS notes first, then on-prem notes)
+fn push_deployment_notes(
+    response: &mut String,
+    templates: &AdminTemplates,
+    deployment: Option<DeploymentType>,
+    notes: (&str, &str),
+) {
+    match deployment {
+        Some(DeploymentType::Saas) => {
+            response.push_str(templates.saas_notes_heading);
+            response.push_str(notes.0);
+        }
+        Some(DeploymentType::OnPrem) => {
+            response.push_str(templates.on_prem_notes_heading);
+            response.push_str(notes.1);
+        }
+        None => {}
+    }
+}
+
+fn generate_admin_response(
+    query: &str,
+    tool_results: &[String],
+    links: &DocLinks,
+    deployment: Option<DeploymentType>,
+    language: Language,
+    role: UserRole,
+    _system_prompt: &str,
+) -> String {
     // In a real implementation, this would call an LLM
     // For now, generate a helpful template response
 
-    let query_lower = query.to_lowercase();
+    let topic = AdminTopic::detect(query);
     let has_docs = !tool_results.is_empty();
+    let templates = AdminTemplates::for_language(language);
 
     let mut response = String::new();
 
-    if query_lower.contains("setup") || query_lower.contains("install") {
-        response.push_str("## WatsonX Orchestrate Setup Guide\n\n");
-        response.push_str("Here's how to set up WatsonX Orchestrate:\n\n");
-        response.push_str("1. **Access the Admin Console**: Navigate to your WXO instance and log in with admin credentials.\n\n");
-        response.push_str("2. **Configure Identity Provider**: Set up SSO or local authentication under Settings > Security.\n\n");
-        response.push_str("3. **Create User Groups**: Define roles and permissions in Settings > Users & Teams.\n\n");
-        response.push_str("4. **Set Up Integrations**: Connect external services in Settings > Integrations.\n\n");
-    } else if query_lower.contains("user") || query_lower.contains("permission") {
-        response.push_str("## User Management\n\n");
-        response.push_str("To manage users in WatsonX Orchestrate:\n\n");
-        response.push_str("1. Go to **Settings > Users & Teams**\n");
-        response.push_str("2. Click **Add User** to invite new users\n");
-        response.push_str("3. Assign appropriate roles (Admin, Developer, User)\n");
-        response.push_str("4. Configure team memberships for collaboration\n\n");
-        response.push_str("**Tip**: Use groups to manage permissions at scale.\n");
-    } else if query_lower.contains("security") || query_lower.contains("authentication") {
-        response.push_str("## Security Configuration\n\n");
-        response.push_str("Security best practices for WatsonX Orchestrate:\n\n");
-        response.push_str("- Enable **Multi-Factor Authentication** (MFA) for all admin accounts\n");
-        response.push_str("- Configure **Session Timeouts** appropriately\n");
-        response.push_str("- Set up **Audit Logging** to track changes\n");
-        response.push_str("- Review **API Key** permissions regularly\n");
-        response.push_str("- Use **Least Privilege** principle for user roles\n");
-    } else if query_lower.contains("integration") {
-        response.push_str("## Integration Setup\n\n");
-        response.push_str("To configure integrations:\n\n");
-        response.push_str("1. Navigate to **Settings > Integrations**\n");
-        response.push_str("2. Select the integration type (Salesforce, ServiceNow, etc.)\n");
-        response.push_str("3. Provide the required credentials\n");
-        response.push_str("4. Configure sync settings and permissions\n");
-        response.push_str("5. Test the connection before enabling\n");
-    } else {
-        response.push_str("I'm here to help you with WatsonX Orchestrate administration.\n\n");
-        response.push_str("I can assist with:\n");
-        response.push_str("- Initial setup and configuration\n");
-        response.push_str("- User and team management\n");
-        response.push_str("- Security settings\n");
-        response.push_str("- Integration configuration\n");
-        response.push_str("- API key management\n\n");
-        response.push_str("What would you like help with?");
+    match topic {
+        AdminTopic::Setup => {
+            response.push_str(templates.setup_heading);
+            let [saas_step, on_prem_step, generic_step] = templates.setup_first_step;
+            match deployment {
+                Some(DeploymentType::Saas) => {
+                    response.push_str(templates.setup_intro_saas);
+                    response.push_str(saas_step);
+                }
+                Some(DeploymentType::OnPrem) => {
+                    response.push_str(templates.setup_intro_on_prem);
+                    response.push_str(on_prem_step);
+                }
+                None => {
+                    response.push_str(templates.deployment_question);
+                    response.push_str(generic_step);
+                }
+            }
+            response.push_str(templates.setup_steps);
+            push_deployment_notes(&mut response, templates, deployment, templates.setup_notes);
+            response.push_str(&links.quick_links_markdown("admin"));
+        }
+        AdminTopic::Users => {
+            response.push_str(templates.users);
+            response.push_str(&links.quick_links_markdown("admin"));
+        }
+        AdminTopic::Security => {
+            response.push_str(templates.security_heading);
+            if deployment.is_none() {
+                response.push_str(templates.deployment_question);
+            }
+            response.push_str(templates.security_practices);
+            push_deployment_notes(
+                &mut response,
+                templates,
+                deployment,
+                templates.security_notes,
+            );
+            response.push_str(&links.quick_links_markdown("admin"));
+        }
+        AdminTopic::Integration => {
+            response.push_str(templates.integration_heading);
+            if deployment.is_none() {
+                response.push_str(templates.deployment_question);
+            }
+            response.push_str(templates.integration_steps);
+            push_deployment_notes(
+                &mut response,
+                templates,
+                deployment,
+                templates.integration_notes,
+            );
+            response.push_str(&links.quick_links_markdown("admin"));
+        }
+        AdminTopic::General => {
+            response.push_str(templates.general);
+        }
+    }
+
+    // Developers also get the API/CLI route to the settings above
+    if topic != AdminTopic::General && role == UserRole::Developer {
+        response.push_str(templates.developer_notes);
     }
 
     if has_docs {
-        response.push_str("\n\n---\n\n**📚 Related Documentation:**\n");
-        response.push_str("I found some relevant documentation that might help. ");
-        response.push_str("Check the search results above for more details.");
+        response.push_str(templates.related_docs);
     }
 
     response
@@ -218,6 +494,7 @@ fn generate_admin_response(query: &str, tool_results: &[String], _system_prompt:
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::agents::awaiting_user_input;
     use crate::tools::create_tool_registry;
 
     #[test]
@@ -226,4 +503,206 @@ mod tests {
         let graph = AdminSetupAgent::build_graph(registry);
         assert!(graph.is_ok());
     }
+
+    #[test]
+    fn test_admin_response_uses_link_base_url() {
+        let links = DocLinks::with_base_url("https://mirror.example.com/wxo");
+        let response = generate_admin_response(
+            "How do I setup WXO?",
+            &[],
+            &links,
+            None,
+            Language::English,
+            UserRole::Admin,
+            "",
+        );
+        assert!(response.contains("](https://mirror.example.com/wxo/admin)"));
+        assert!(!response.contains("ibm.com"));
+    }
+
+    async fn run_admin_graph(query: &str) -> AgentState {
+        let registry = Arc::new(create_tool_registry());
+        let graph = AdminSetupAgent::build_graph(registry).unwrap();
+        let runner = GraphRunner::new(graph, RunnerConfig::default().max_iterations(10));
+
+        let state = AgentState::with_system_and_user(AgentType::AdminSetup.system_prompt(), query);
+        runner.invoke(state).await.unwrap()
+    }
+
+    #[test]
+    fn test_parse_deployment_type() {
+        assert_eq!(
+            DeploymentType::parse("on-prem"),
+            Some(DeploymentType::OnPrem)
+        );
+        assert_eq!(
+            DeploymentType::parse("Cloud Pak for Data"),
+            Some(DeploymentType::OnPrem)
+        );
+        assert_eq!(DeploymentType::parse("SaaS"), Some(DeploymentType::Saas));
+        assert_eq!(DeploymentType::parse("How do I setup WXO?"), None);
+    }
+
+    #[test]
+    fn test_admin_response_branches_on_deployment() {
+        let links = DocLinks::default();
+        let query = "How do I setup WXO?";
+
+        let on_prem = generate_admin_response(
+            query,
+            &[],
+            &links,
+            Some(DeploymentType::OnPrem),
+            Language::English,
+            UserRole::Admin,
+            "",
+        );
+        assert!(on_prem.contains("Helm chart"));
+        assert!(on_prem.contains("### On-Premises Notes"));
+        assert!(!on_prem.contains("Navigate to your WXO instance"));
+
+        let saas = generate_admin_response(
+            query,
+            &[],
+            &links,
+            Some(DeploymentType::Saas),
+            Language::English,
+            UserRole::Admin,
+            "",
+        );
+        assert!(saas.contains("IBM Cloud"));
+        assert!(!saas.contains("Helm"));
+
+        let unknown = generate_admin_response(
+            query,
+            &[],
+            &links,
+            None,
+            Language::English,
+            UserRole::Admin,
+            "",
+        );
+        assert!(unknown.contains("Are you on SaaS or on-premises?"));
+    }
+
+    #[test]
+    fn test_developer_gets_automation_notes() {
+        let links = DocLinks::default();
+        let render = |query: &str, role: UserRole| {
+            generate_admin_response(query, &[], &links, None, Language::English, role, "")
+        };
+
+        assert!(render("How do I set up SSO security?", UserRole::Developer)
+            .contains("### Automating This"));
+        assert!(!render("How do I set up SSO security?", UserRole::Admin)
+            .contains("### Automating This"));
+        assert!(!render("Hello", UserRole::Developer).contains("### Automating This"));
+    }
+
+    #[test]
+    fn test_admin_response_in_spanish() {
+        let links = DocLinks::default();
+        let response = generate_admin_response(
+            "¿Cómo configuro la seguridad?",
+            &[],
+            &links,
+            Some(DeploymentType::OnPrem),
+            Language::Spanish,
+            UserRole::Admin,
+            "",
+        );
+        assert!(response.starts_with("## Configuración de seguridad"));
+        assert!(response.contains("### Notas para instalaciones locales"));
+        assert!(!response.contains("Security best practices"));
+    }
+
+    #[tokio::test]
+    async fn test_deployment_type_read_from_context() {
+        let registry = Arc::new(create_tool_registry());
+        let graph = AdminSetupAgent::build_graph(registry).unwrap();
+        let runner = GraphRunner::new(graph, RunnerConfig::default().max_iterations(10));
+
+        let mut state = AgentState::with_system_and_user(
+            AgentType::AdminSetup.system_prompt(),
+            "How do I configure security?",
+        );
+        state.set_context("deployment_type", serde_json::json!("on-prem"));
+        let result = runner.invoke(state).await.unwrap();
+
+        let response = &result.last_assistant_message().unwrap().content;
+        assert!(response.contains("OpenShift routes"));
+        assert!(!response.contains("Are you on SaaS or on-premises?"));
+    }
+
+    #[tokio::test]
+    async fn test_deployment_question_awaits_input() {
+        assert!(awaiting_user_input(
+            &run_admin_graph("How do I configure security?").await
+        ));
+        assert!(!awaiting_user_input(
+            &run_admin_graph("How do I configure security on SaaS?").await
+        ));
+        assert!(!awaiting_user_input(
+            &run_admin_graph("How do I add users?").await
+        ));
+    }
+
+    /// Answers every question without asking anything back
+    struct StatementRenderer;
+
+    impl ResponseRenderer for StatementRenderer {
+        fn render(&self, _query: &str, _context: &RenderContext) -> String {
+            "Open the admin console.".to_string()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_renderer_decides_whether_to_await_input() {
+        let registry = Arc::new(create_tool_registry());
+        let options = GraphOptions::default()
+            .renderer_for(AgentType::AdminSetup, Arc::new(StatementRenderer));
+        let graph = AdminSetupAgent::build_graph_with_options(registry, options).unwrap();
+        let runner = GraphRunner::new(graph, RunnerConfig::default().max_iterations(10));
+
+        let state = AgentState::with_system_and_user(
+            AgentType::AdminSetup.system_prompt(),
+            "How do I configure security?",
+        );
+        assert!(!awaiting_user_input(&runner.invoke(state).await.unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_greeting_skips_doc_search() {
+        let result = run_admin_graph("hi there").await;
+        assert!(result.tool_calls.is_empty());
+        assert!(result.last_assistant_message().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_question_issues_doc_search() {
+        let result = run_admin_graph("how to create a skill").await;
+        assert!(crate::agents::tool_call_records(&result)
+            .iter()
+            .any(|call| call.name == "search_wxo_docs"));
+    }
+
+    #[tokio::test]
+    async fn test_reply_waits_for_tool_results() {
+        let result = run_admin_graph("How do I setup SSO for my team?").await;
+
+        // The search ran before the reply, so its results are in the reply
+        assert!(!result.has_pending_tool_calls());
+        assert!(result.messages.iter().any(|m| m.role == MessageRole::Tool));
+        let assistant_messages = result
+            .messages
+            .iter()
+            .filter(|m| m.role == MessageRole::Assistant)
+            .count();
+        assert_eq!(assistant_messages, 1);
+        assert!(result
+            .last_assistant_message()
+            .unwrap()
+            .content
+            .contains("Related Documentation"));
+    }
 }
@@ -2,34 +2,98 @@
 //!
 //! Provides optimization tips and best practices for WatsonX Orchestrate.
 
-use super::{route_by_tools, AnalyzeQueryNode, ExecuteToolsNode};
-use crate::state::AgentType;
+use super::layout::{Edge, GraphLayout, Wiring};
+use super::{
+    append_degraded_notice, append_followups, awaiting_tool_results, new_tool_call_id, observe,
+    promote_context, push_found_docs, queue_tool_call, record_relevant_docs, respond_with_llm,
+    route_by_tools, AnalyzeQueryNode, ExecuteToolsNode, GraphOptions, LlmClient, RenderContext,
+    ResponseRenderer, SearchGate, DEFAULT_TOOL_TIMEOUT,
+};
+use crate::state::{AgentType, Language};
 use oxidizedgraph::prelude::*;
 use std::sync::Arc;
 
 /// Agent for providing best practices guidance
 pub struct BestPracticesAgent;
 
+const WIRING: Wiring = Wiring {
+    entry: "analyze",
+    edges: &[
+        Edge::Fixed("analyze", "assess"),
+        Edge::Fixed("assess", "search_docs"),
+        Edge::Fixed("search_docs", "respond"),
+        Edge::Conditional(
+            "respond",
+            route_by_tools,
+            &["execute_tools", transitions::END],
+        ),
+        Edge::Fixed("execute_tools", "respond"),
+    ],
+};
+
 impl BestPracticesAgent {
     /// Build the agent graph for best practices coaching
     pub fn build_graph(tool_registry: Arc<ToolRegistry>) -> Result<CompiledGraph, GraphError> {
-        let system_prompt = AgentType::BestPractices.system_prompt().to_string();
+        Self::build_graph_with_options(tool_registry, GraphOptions::default())
+    }
 
-        GraphBuilder::new()
+    /// Build the agent graph with optional node observer and LLM
+    pub fn build_graph_with_options(
+        tool_registry: Arc<ToolRegistry>,
+        options: GraphOptions,
+    ) -> Result<CompiledGraph, GraphError> {
+        let system_prompt = options.system_prompt(AgentType::BestPractices);
+        let renderer = options.renderer(AgentType::BestPractices);
+        let GraphOptions {
+            observer,
+            llm,
+            min_keywords,
+            classifier,
+            tool_timeout,
+            ..
+        } = options;
+        let tool_timeout = tool_timeout.unwrap_or(DEFAULT_TOOL_TIMEOUT);
+        let gate = SearchGate::new(min_keywords);
+
+        let builder = GraphBuilder::new()
             .name("best_practices_agent")
             .description("Provides optimization tips and best practices")
-            .add_node(AnalyzeQueryNode::new("analyze"))
-            .add_node(AssessmentNode::new("assess"))
-            .add_node(BestPracticesSearchNode::new("search_docs", system_prompt.clone()))
-            .add_node(BestPracticesResponseNode::new("respond", system_prompt))
-            .add_node(ExecuteToolsNode::new("execute_tools", tool_registry))
-            .set_entry_point("analyze")
-            .add_edge("analyze", "assess")
-            .add_edge("assess", "search_docs")
-            .add_edge("search_docs", "respond")
-            .add_conditional_edge("respond", route_by_tools)
-            .add_edge("execute_tools", "respond")
-            .compile()
+            .add_node(observe(
+                AnalyzeQueryNode::new("analyze").with_classifier(classifier.unwrap_or_default()),
+                &observer,
+            ))
+            .add_node(observe(AssessmentNode::new("assess"), &observer))
+            .add_node(observe(
+                BestPracticesSearchNode::new("search_docs", system_prompt.clone()).with_gate(gate),
+                &observer,
+            ))
+            .add_node(observe(
+                BestPracticesResponseNode::new("respond", system_prompt)
+                    .with_llm(llm)
+                    .with_renderer(renderer),
+                &observer,
+            ))
+            .add_node(observe(
+                ExecuteToolsNode::new("execute_tools", tool_registry).with_timeout(tool_timeout),
+                &observer,
+            ));
+        WIRING.apply(builder).compile()
+    }
+
+    /// Describe the graph built by [`Self::build_graph`] for export
+    pub(crate) fn layout(tool_registry: Arc<ToolRegistry>) -> GraphLayout {
+        let system_prompt = AgentType::BestPractices.system_prompt().to_string();
+
+        GraphLayout::new("best_practices_agent")
+            .node(&AnalyzeQueryNode::new("analyze"))
+            .node(&AssessmentNode::new("assess"))
+            .node(&BestPracticesSearchNode::new(
+                "search_docs",
+                system_prompt.clone(),
+            ))
+            .node(&BestPracticesResponseNode::new("respond", system_prompt))
+            .node(&ExecuteToolsNode::new("execute_tools", tool_registry))
+            .wiring(&WIRING)
     }
 }
 
@@ -76,10 +140,23 @@ impl NodeExecutor for AssessmentNode {
     }
 }
 
+/// Keywords of cost and license questions, checked before the other topics
+/// since "how much does a workflow cost" is about cost, not workflow design
+const COST_KEYWORDS: &[&str] = &[
+    "cost",
+    "pricing",
+    "license",
+    "seat",
+    "consumption",
+    "budget",
+];
+
 fn identify_best_practices_topic(query: &str) -> &'static str {
     let query_lower = query.to_lowercase();
 
-    if query_lower.contains("workflow") || query_lower.contains("automation") {
+    let topic = if COST_KEYWORDS.iter().any(|kw| query_lower.contains(kw)) {
+        "cost_optimization"
+    } else if query_lower.contains("workflow") || query_lower.contains("automation") {
         "workflow_design"
     } else if query_lower.contains("performance") || query_lower.contains("speed") {
         "performance"
@@ -95,12 +172,15 @@ fn identify_best_practices_topic(query: &str) -> &'static str {
         "deployment"
     } else {
         "general"
-    }
+    };
+    tracing::debug!(query, topic, "Identified best practices topic");
+    topic
 }
 
 struct BestPracticesSearchNode {
     id: String,
     _system_prompt: String,
+    gate: SearchGate,
 }
 
 impl BestPracticesSearchNode {
@@ -108,8 +188,15 @@ impl BestPracticesSearchNode {
         Self {
             id: id.into(),
             _system_prompt: system_prompt,
+            gate: SearchGate::default(),
         }
     }
+
+    /// Only search for queries `gate` allows
+    fn with_gate(mut self, gate: SearchGate) -> Self {
+        self.gate = gate;
+        self
+    }
 }
 
 #[async_trait::async_trait]
@@ -139,7 +226,7 @@ impl NodeExecutor for BestPracticesSearchNode {
             (query, topic)
         };
 
-        if query.is_empty() {
+        if !self.gate.allows(&query) {
             return Ok(NodeOutput::cont());
         }
 
@@ -148,9 +235,20 @@ impl NodeExecutor for BestPracticesSearchNode {
                 .write()
                 .map_err(|e| NodeError::Other(format!("Failed to write state: {}", e)))?;
 
+            // Search the docs for the question itself
+            let tool_call = ToolCall {
+                id: new_tool_call_id(&guard),
+                name: "search_wxo_docs".to_string(),
+                arguments: serde_json::json!({
+                    "query": query,
+                    "limit": 5
+                }),
+            };
+            queue_tool_call(&mut guard, tool_call);
+
             // Search for best practices examples
             let tool_call = ToolCall {
-                id: uuid::Uuid::new_v4().to_string(),
+                id: new_tool_call_id(&guard),
                 name: "fetch_wxo_examples".to_string(),
                 arguments: serde_json::json!({
                     "topic": format!("{} best practices", topic.replace('_', " ")),
@@ -158,7 +256,7 @@ impl NodeExecutor for BestPracticesSearchNode {
                 }),
             };
 
-            guard.tool_calls.push(tool_call);
+            queue_tool_call(&mut guard, tool_call);
         }
 
         Ok(NodeOutput::cont())
@@ -168,6 +266,8 @@ impl NodeExecutor for BestPracticesSearchNode {
 struct BestPracticesResponseNode {
     id: String,
     system_prompt: String,
+    renderer: Arc<dyn ResponseRenderer>,
+    llm: Option<Arc<dyn LlmClient>>,
 }
 
 impl BestPracticesResponseNode {
@@ -175,7 +275,23 @@ impl BestPracticesResponseNode {
         Self {
             id: id.into(),
             system_prompt,
+            renderer: Arc::new(BestPracticesRenderer),
+            llm: None,
+        }
+    }
+
+    /// Generate replies with `llm` instead of the built-in template
+    fn with_llm(mut self, llm: Option<Arc<dyn LlmClient>>) -> Self {
+        self.llm = llm;
+        self
+    }
+
+    /// Render template replies with `renderer` instead of [`BestPracticesRenderer`]
+    fn with_renderer(mut self, renderer: Option<Arc<dyn ResponseRenderer>>) -> Self {
+        if let Some(renderer) = renderer {
+            self.renderer = renderer;
         }
+        self
     }
 }
 
@@ -190,20 +306,35 @@ impl NodeExecutor for BestPracticesResponseNode {
     }
 
     async fn execute(&self, state: SharedState) -> Result<NodeOutput, NodeError> {
+        if awaiting_tool_results(&state)? {
+            return Ok(NodeOutput::cont());
+        }
+
+        if let Some(llm) = &self.llm {
+            return respond_with_llm(
+                llm.as_ref(),
+                AgentType::BestPractices,
+                &self.system_prompt,
+                &state,
+            )
+            .await;
+        }
+
         let mut guard = state
             .write()
             .map_err(|e| NodeError::Other(format!("Failed to write state: {}", e)))?;
 
         let query = guard
             .get_context::<String>("original_query")
-                        .unwrap_or_default();
-
-        let topic = guard
-            .get_context::<String>("bp_topic")
-                        .unwrap_or_else(|| "general".to_string());
+            .unwrap_or_default();
+        let context = RenderContext::from_state(&guard);
+        record_relevant_docs(&mut guard, &context.tool_results);
 
-        let response = generate_best_practices_response(&query, &topic, &self.system_prompt);
+        let response = self.renderer.render(&query, &context);
+        let response = append_degraded_notice(&context, &response);
 
+        promote_context(&mut guard);
+        let response = append_followups(&mut guard, AgentType::BestPractices, &response);
         guard.add_assistant_message(&response);
         guard.mark_complete();
 
@@ -211,9 +342,33 @@ impl NodeExecutor for BestPracticesResponseNode {
     }
 }
 
+/// Default [`ResponseRenderer`] for the best practices agent
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BestPracticesRenderer;
+
+impl ResponseRenderer for BestPracticesRenderer {
+    fn render(&self, query: &str, context: &RenderContext) -> String {
+        let topic = context
+            .get::<String>("bp_topic")
+            .unwrap_or_else(|| "general".to_string());
+
+        generate_best_practices_response(
+            query,
+            &topic,
+            &context.tool_results,
+            context.max_results(),
+            context.language,
+            AgentType::BestPractices.system_prompt(),
+        )
+    }
+}
+
 fn generate_best_practices_response(
     _query: &str,
     topic: &str,
+    tool_results: &[String],
+    max_results: usize,
+    language: Language,
     _system_prompt: &str,
 ) -> String {
     let mut response = String::new();
@@ -378,6 +533,28 @@ fn generate_best_practices_response(
             response.push_str("- Create templates\n");
             response.push_str("- Review and improve regularly\n");
         }
+        "cost_optimization" => {
+            response.push_str("### Cost & Licensing Best Practices\n\n");
+            response.push_str("**1. Monitor Consumption**\n");
+            response.push_str("- Review usage reports in the admin console regularly\n");
+            response.push_str("- Track which skills and workflows consume the most\n");
+            response.push_str("- Set budget alerts before limits are reached\n\n");
+
+            response.push_str("**2. Right-Size Licenses**\n");
+            response.push_str("- Compare assigned seats with active users\n");
+            response.push_str("- Reclaim seats from inactive or departed users\n");
+            response.push_str("- Match plan tiers to actual usage patterns\n\n");
+
+            response.push_str("**3. Clean Up Unused Skills**\n");
+            response.push_str("- Find skills and workflows that haven't run recently\n");
+            response.push_str("- Archive or remove duplicates and experiments\n");
+            response.push_str("- Disable scheduled automations nobody relies on\n\n");
+
+            response.push_str("**4. Plan Ahead**\n");
+            response.push_str("- Estimate consumption before rolling out new automations\n");
+            response.push_str("- Review licensing at each renewal\n");
+            response.push_str("- Share cost reports with budget owners\n");
+        }
         _ => {
             response.push_str("### General Best Practices\n\n");
             response.push_str("**Start Simple**\n");
@@ -400,9 +577,11 @@ fn generate_best_practices_response(
             response.push_str("- Performance optimization\n");
             response.push_str("- Security\n");
             response.push_str("- Skill development\n");
+            response.push_str("- Cost and licensing\n");
         }
     }
 
+    push_found_docs(&mut response, tool_results, max_results, language);
     response.push_str("\n\n---\n\n");
     response.push_str("**💡 Need more specific advice?** Tell me about your use case and I can provide tailored recommendations.");
 
@@ -412,6 +591,7 @@ fn generate_best_practices_response(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::runner::{run_agent_with, RunOptions};
     use crate::tools::create_tool_registry;
 
     #[test]
@@ -435,5 +615,40 @@ mod tests {
             identify_best_practices_topic("What are security best practices?"),
             "security"
         );
+        for query in [
+            "How do I keep automation costs under control?",
+            "Are we paying for seats nobody uses?",
+            "What's the best way to track license consumption against our budget?",
+        ] {
+            assert_eq!(identify_best_practices_topic(query), "cost_optimization");
+        }
+
+        let response = generate_best_practices_response(
+            "",
+            "cost_optimization",
+            &[],
+            3,
+            Language::English,
+            "",
+        );
+        assert!(response.contains("Cost & Licensing Best Practices"));
+    }
+
+    #[tokio::test]
+    async fn test_found_docs_in_reply_and_sources() {
+        let response = run_agent_with(
+            AgentType::BestPractices,
+            None,
+            "What are security best practices?",
+            RunOptions::default().deterministic(true),
+        )
+        .await
+        .unwrap();
+
+        assert!(response.response.contains("[Security Best Practices]("));
+        assert!(response
+            .sources
+            .iter()
+            .any(|doc| doc.url == "https://www.ibm.com/docs/watsonx-orchestrate/security"));
     }
 }
@@ -0,0 +1,160 @@
+//! Documentation quick links
+//!
+//! Central table of the WatsonX Orchestrate documentation links that agents
+//! include in their responses, so URLs live in one place and can be pointed
+//! at a private docs mirror.
+
+/// Default base URL for WatsonX Orchestrate documentation
+pub const DEFAULT_DOCS_BASE_URL: &str = "https://www.ibm.com/docs/watsonx-orchestrate";
+
+/// Quick links per documentation category, as (label, path relative to the base URL)
+const LINK_TABLE: &[(&str, &[(&str, &str)])] = &[
+    (
+        "api",
+        &[
+            ("API Reference", "/api"),
+            ("Authentication Guide", "/api/auth"),
+        ],
+    ),
+    (
+        "admin",
+        &[
+            ("Admin Guide", "/admin"),
+            ("Security Configuration", "/security"),
+        ],
+    ),
+    (
+        "getting_started",
+        &[
+            ("Quick Start Guide", "/quickstart"),
+            ("Tutorial Videos", "/tutorials"),
+        ],
+    ),
+    (
+        "troubleshooting",
+        &[
+            ("Troubleshooting Guide", "/troubleshooting"),
+            ("Known Issues", "/known-issues"),
+        ],
+    ),
+    (
+        "release_notes",
+        &[
+            ("Latest Release Notes", "/release-notes"),
+            ("Roadmap", "/roadmap"),
+        ],
+    ),
+    (
+        "user",
+        &[("User Guide", "/user"), ("Skill Catalog", "/catalog")],
+    ),
+];
+
+/// A single documentation link
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocLink {
+    /// Link text
+    pub label: String,
+    /// Full URL
+    pub url: String,
+}
+
+/// Documentation quick links, resolved against a configurable base URL
+#[derive(Debug, Clone)]
+pub struct DocLinks {
+    base_url: String,
+}
+
+impl Default for DocLinks {
+    fn default() -> Self {
+        Self::with_base_url(DEFAULT_DOCS_BASE_URL)
+    }
+}
+
+impl DocLinks {
+    /// Use a custom docs base URL (e.g. a private mirror)
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Create links from the `WXORCA_DOCS_BASE_URL` environment variable,
+    /// falling back to the public IBM docs
+    pub fn from_env() -> Self {
+        std::env::var("WXORCA_DOCS_BASE_URL")
+            .map(Self::with_base_url)
+            .unwrap_or_default()
+    }
+
+    /// Get the base URL links are resolved against
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Get all categories that have quick links
+    pub fn categories() -> impl Iterator<Item = &'static str> {
+        LINK_TABLE.iter().map(|(category, _)| *category)
+    }
+
+    /// Get the quick links for a category (empty if the category is unknown)
+    pub fn links(&self, category: &str) -> Vec<DocLink> {
+        LINK_TABLE
+            .iter()
+            .find(|(c, _)| *c == category)
+            .map(|(_, links)| {
+                links
+                    .iter()
+                    .map(|(label, path)| DocLink {
+                        label: label.to_string(),
+                        url: format!("{}{}", self.base_url, path),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Render the quick links for a category as a markdown list
+    pub fn quick_links_markdown(&self, category: &str) -> String {
+        let mut out = String::from("**Quick Links:**\n");
+        for link in self.links(category) {
+            out.push_str(&format!("- [{}]({})\n", link.label, link.url));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_category_has_links() {
+        let links = DocLinks::default();
+        for category in DocLinks::categories() {
+            assert!(
+                !links.links(category).is_empty(),
+                "No links for category {}",
+                category
+            );
+        }
+    }
+
+    #[test]
+    fn test_base_url_override() {
+        let links = DocLinks::with_base_url("https://docs.internal.example.com/wxo/");
+        for category in DocLinks::categories() {
+            for link in links.links(category) {
+                assert!(link
+                    .url
+                    .starts_with("https://docs.internal.example.com/wxo/"));
+                assert!(!link.url.contains("ibm.com"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_unknown_category_has_no_links() {
+        assert!(DocLinks::default().links("nonexistent").is_empty());
+    }
+}
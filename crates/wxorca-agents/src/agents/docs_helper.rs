@@ -2,34 +2,117 @@
 //!
 //! Helps users navigate and understand WatsonX Orchestrate documentation.
 
-use super::{route_by_tools, AnalyzeQueryNode, ExecuteToolsNode};
-use crate::state::AgentType;
+use super::layout::{Edge, GraphLayout, Wiring};
+use super::{
+    append_followups, awaiting_tool_results, new_tool_call_id, observe, promote_context,
+    queue_tool_call, record_relevant_docs, respond_with_llm, route_by_tools, AnalyzeQueryNode,
+    DocLinks, ExecuteToolsNode, GraphOptions, LlmClient, RenderContext, ResponseRenderer,
+    SearchGate, DEFAULT_TOOL_TIMEOUT,
+};
+use crate::state::{AgentType, DocCategory, UserRole};
+use crate::tools::make_snippet;
 use oxidizedgraph::prelude::*;
 use std::sync::Arc;
 
 /// Agent for helping users with documentation
 pub struct DocsHelperAgent;
 
+const WIRING: Wiring = Wiring {
+    entry: "analyze",
+    edges: &[
+        Edge::Fixed("analyze", "categorize"),
+        Edge::Fixed("categorize", "search_docs"),
+        Edge::Fixed("search_docs", "execute_search"),
+        // Retry once without the category filter if nothing was found
+        Edge::Conditional(
+            "execute_search",
+            route_after_search,
+            &["broaden_search", "respond"],
+        ),
+        Edge::Fixed("broaden_search", "execute_search"),
+        Edge::Conditional(
+            "respond",
+            route_by_tools,
+            &["execute_tools", transitions::END],
+        ),
+        Edge::Fixed("execute_tools", "respond"),
+    ],
+};
+
 impl DocsHelperAgent {
     /// Build the agent graph for documentation help
     pub fn build_graph(tool_registry: Arc<ToolRegistry>) -> Result<CompiledGraph, GraphError> {
-        let system_prompt = AgentType::DocsHelper.system_prompt().to_string();
+        Self::build_graph_with_options(tool_registry, GraphOptions::default())
+    }
 
-        GraphBuilder::new()
+    /// Build the agent graph with optional node observer and LLM
+    pub fn build_graph_with_options(
+        tool_registry: Arc<ToolRegistry>,
+        options: GraphOptions,
+    ) -> Result<CompiledGraph, GraphError> {
+        let system_prompt = options.system_prompt(AgentType::DocsHelper);
+        let renderer = options.renderer(AgentType::DocsHelper);
+        let GraphOptions {
+            observer,
+            llm,
+            min_keywords,
+            classifier,
+            tool_timeout,
+            ..
+        } = options;
+        let tool_timeout = tool_timeout.unwrap_or(DEFAULT_TOOL_TIMEOUT);
+        let gate = SearchGate::new(min_keywords);
+
+        let builder = GraphBuilder::new()
             .name("docs_helper_agent")
             .description("Helps users navigate and understand WatsonX Orchestrate documentation")
-            .add_node(AnalyzeQueryNode::new("analyze"))
-            .add_node(DocsCategoryNode::new("categorize"))
-            .add_node(DocsSearchNode::new("search_docs", system_prompt.clone()))
-            .add_node(DocsResponseNode::new("respond", system_prompt))
-            .add_node(ExecuteToolsNode::new("execute_tools", tool_registry))
-            .set_entry_point("analyze")
-            .add_edge("analyze", "categorize")
-            .add_edge("categorize", "search_docs")
-            .add_edge("search_docs", "respond")
-            .add_conditional_edge("respond", route_by_tools)
-            .add_edge("execute_tools", "respond")
-            .compile()
+            .add_node(observe(
+                AnalyzeQueryNode::new("analyze").with_classifier(classifier.unwrap_or_default()),
+                &observer,
+            ))
+            .add_node(observe(DocsCategoryNode::new("categorize"), &observer))
+            .add_node(observe(
+                DocsSearchNode::new("search_docs", system_prompt.clone()).with_gate(gate),
+                &observer,
+            ))
+            .add_node(observe(
+                ExecuteToolsNode::new("execute_search", tool_registry.clone())
+                    .with_timeout(tool_timeout),
+                &observer,
+            ))
+            .add_node(observe(
+                DocsBroadenSearchNode::new("broaden_search"),
+                &observer,
+            ))
+            .add_node(observe(
+                DocsResponseNode::new("respond", system_prompt)
+                    .with_llm(llm)
+                    .with_renderer(renderer),
+                &observer,
+            ))
+            .add_node(observe(
+                ExecuteToolsNode::new("execute_tools", tool_registry).with_timeout(tool_timeout),
+                &observer,
+            ));
+        WIRING.apply(builder).compile()
+    }
+
+    /// Describe the graph built by [`Self::build_graph`] for export
+    pub(crate) fn layout(tool_registry: Arc<ToolRegistry>) -> GraphLayout {
+        let system_prompt = AgentType::DocsHelper.system_prompt().to_string();
+
+        GraphLayout::new("docs_helper_agent")
+            .node(&AnalyzeQueryNode::new("analyze"))
+            .node(&DocsCategoryNode::new("categorize"))
+            .node(&DocsSearchNode::new("search_docs", system_prompt.clone()))
+            .node(&ExecuteToolsNode::new(
+                "execute_search",
+                tool_registry.clone(),
+            ))
+            .node(&DocsBroadenSearchNode::new("broaden_search"))
+            .node(&DocsResponseNode::new("respond", system_prompt))
+            .node(&ExecuteToolsNode::new("execute_tools", tool_registry))
+            .wiring(&WIRING)
     }
 }
 
@@ -54,16 +137,21 @@ impl NodeExecutor for DocsCategoryNode {
     }
 
     async fn execute(&self, state: SharedState) -> Result<NodeOutput, NodeError> {
-        let query = {
+        let (query, role) = {
             let guard = state
                 .read()
                 .map_err(|e| NodeError::Other(format!("Failed to read state: {}", e)))?;
-            guard
-                .get_context::<String>("original_query")
-                                .unwrap_or_default()
+            (
+                guard
+                    .get_context::<String>("original_query")
+                    .unwrap_or_default(),
+                guard
+                    .get_context::<UserRole>("user_role")
+                    .unwrap_or_default(),
+            )
         };
 
-        let category = categorize_docs_request(&query);
+        let category = categorize_for_role(&query, role);
 
         {
             let mut guard = state
@@ -78,7 +166,7 @@ impl NodeExecutor for DocsCategoryNode {
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 struct DocsCategory {
-    primary: String,
+    primary: DocCategory,
     secondary: Option<String>,
     keywords: Vec<String>,
 }
@@ -87,23 +175,23 @@ fn categorize_docs_request(query: &str) -> DocsCategory {
     let query_lower = query.to_lowercase();
 
     let (primary, secondary) = if query_lower.contains("api") || query_lower.contains("endpoint") {
-        ("api", Some("reference"))
+        (DocCategory::Api, Some("reference"))
     } else if query_lower.contains("admin") || query_lower.contains("configure") {
-        ("admin", Some("setup"))
+        (DocCategory::Admin, Some("setup"))
     } else if query_lower.contains("start") || query_lower.contains("begin") {
-        ("getting_started", None)
+        (DocCategory::GettingStarted, None)
     } else if query_lower.contains("skill") {
-        ("user", Some("skills"))
+        (DocCategory::User, Some("skills"))
     } else if query_lower.contains("workflow") {
-        ("user", Some("workflows"))
+        (DocCategory::User, Some("workflows"))
     } else if query_lower.contains("integration") {
-        ("admin", Some("integrations"))
+        (DocCategory::Admin, Some("integrations"))
     } else if query_lower.contains("error") || query_lower.contains("troubleshoot") {
-        ("troubleshooting", None)
+        (DocCategory::Troubleshooting, None)
     } else if query_lower.contains("release") || query_lower.contains("new") {
-        ("release_notes", None)
+        (DocCategory::ReleaseNotes, None)
     } else {
-        ("user", None)
+        (DocCategory::User, None)
     };
 
     let keywords: Vec<String> = query_lower
@@ -113,16 +201,38 @@ fn categorize_docs_request(query: &str) -> DocsCategory {
         .take(5)
         .collect();
 
+    tracing::debug!(
+        query,
+        category = %primary,
+        subcategory = secondary,
+        "Categorized docs request"
+    );
+
     DocsCategory {
-        primary: primary.to_string(),
+        primary,
         secondary: secondary.map(|s| s.to_string()),
         keywords,
     }
 }
 
+/// Categorize a request, pointing admins at the admin docs and developers at
+/// the API reference when the query itself doesn't name a category
+fn categorize_for_role(query: &str, role: UserRole) -> DocsCategory {
+    let mut category = categorize_docs_request(query);
+    if category.primary == DocCategory::User && category.secondary.is_none() {
+        category.primary = match role {
+            UserRole::Admin => DocCategory::Admin,
+            UserRole::Developer => DocCategory::Api,
+            UserRole::EndUser => DocCategory::User,
+        };
+    }
+    category
+}
+
 struct DocsSearchNode {
     id: String,
     _system_prompt: String,
+    gate: SearchGate,
 }
 
 impl DocsSearchNode {
@@ -130,8 +240,15 @@ impl DocsSearchNode {
         Self {
             id: id.into(),
             _system_prompt: system_prompt,
+            gate: SearchGate::default(),
         }
     }
+
+    /// Only search for queries `gate` allows
+    fn with_gate(mut self, gate: SearchGate) -> Self {
+        self.gate = gate;
+        self
+    }
 }
 
 #[async_trait::async_trait]
@@ -157,7 +274,7 @@ impl NodeExecutor for DocsSearchNode {
             let category = guard
                 .get_context::<DocsCategory>("docs_category")
                                 .unwrap_or_else(|| DocsCategory {
-                    primary: "user".to_string(),
+                    primary: DocCategory::User,
                     secondary: None,
                     keywords: vec![],
                 });
@@ -165,7 +282,7 @@ impl NodeExecutor for DocsSearchNode {
             (query, category)
         };
 
-        if query.is_empty() {
+        if !self.gate.allows(&query) {
             return Ok(NodeOutput::cont());
         }
 
@@ -175,7 +292,7 @@ impl NodeExecutor for DocsSearchNode {
                 .map_err(|e| NodeError::Other(format!("Failed to write state: {}", e)))?;
 
             let tool_call = ToolCall {
-                id: uuid::Uuid::new_v4().to_string(),
+                id: new_tool_call_id(&guard),
                 name: "search_wxo_docs".to_string(),
                 arguments: serde_json::json!({
                     "query": query,
@@ -184,16 +301,91 @@ impl NodeExecutor for DocsSearchNode {
                 }),
             };
 
-            guard.tool_calls.push(tool_call);
+            queue_tool_call(&mut guard, tool_call);
         }
 
         Ok(NodeOutput::cont())
     }
 }
 
+/// Router that retries the docs search once without a category filter
+/// when the categorized search came back empty
+fn route_after_search(state: &AgentState) -> String {
+    let already_broadened = state
+        .get_context::<bool>("docs_search_broadened")
+        .unwrap_or(false);
+
+    let last_result_empty = state
+        .messages
+        .iter()
+        .rev()
+        .find(|m| m.role == MessageRole::Tool)
+        .and_then(|m| serde_json::from_str::<Vec<serde_json::Value>>(&m.content).ok())
+        .is_some_and(|docs| docs.is_empty());
+
+    if last_result_empty && !already_broadened {
+        "broaden_search".to_string()
+    } else {
+        "respond".to_string()
+    }
+}
+
+/// Node that re-issues the docs search without a category filter
+struct DocsBroadenSearchNode {
+    id: String,
+}
+
+impl DocsBroadenSearchNode {
+    fn new(id: impl Into<String>) -> Self {
+        Self { id: id.into() }
+    }
+}
+
+#[async_trait::async_trait]
+impl NodeExecutor for DocsBroadenSearchNode {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Retries the documentation search across all categories")
+    }
+
+    async fn execute(&self, state: SharedState) -> Result<NodeOutput, NodeError> {
+        let mut guard = state
+            .write()
+            .map_err(|e| NodeError::Other(format!("Failed to write state: {}", e)))?;
+
+        let query = guard
+            .get_context::<String>("original_query")
+            .unwrap_or_default();
+
+        guard.set_context("docs_search_broadened", serde_json::json!(true));
+
+        if query.is_empty() {
+            return Ok(NodeOutput::cont());
+        }
+
+        let tool_call = ToolCall {
+            id: new_tool_call_id(&guard),
+            name: "search_wxo_docs".to_string(),
+            arguments: serde_json::json!({
+                "query": query,
+                "limit": 5
+            }),
+        };
+
+        queue_tool_call(&mut guard, tool_call);
+
+        Ok(NodeOutput::cont())
+    }
+}
+
 struct DocsResponseNode {
     id: String,
     system_prompt: String,
+    renderer: Arc<dyn ResponseRenderer>,
+    llm: Option<Arc<dyn LlmClient>>,
 }
 
 impl DocsResponseNode {
@@ -201,7 +393,23 @@ impl DocsResponseNode {
         Self {
             id: id.into(),
             system_prompt,
+            renderer: Arc::new(DocsHelperRenderer::default()),
+            llm: None,
+        }
+    }
+
+    /// Generate replies with `llm` instead of the built-in template
+    fn with_llm(mut self, llm: Option<Arc<dyn LlmClient>>) -> Self {
+        self.llm = llm;
+        self
+    }
+
+    /// Render template replies with `renderer` instead of [`DocsHelperRenderer`]
+    fn with_renderer(mut self, renderer: Option<Arc<dyn ResponseRenderer>>) -> Self {
+        if let Some(renderer) = renderer {
+            self.renderer = renderer;
         }
+        self
     }
 }
 
@@ -216,32 +424,34 @@ impl NodeExecutor for DocsResponseNode {
     }
 
     async fn execute(&self, state: SharedState) -> Result<NodeOutput, NodeError> {
+        if awaiting_tool_results(&state)? {
+            return Ok(NodeOutput::cont());
+        }
+
+        if let Some(llm) = &self.llm {
+            return respond_with_llm(
+                llm.as_ref(),
+                AgentType::DocsHelper,
+                &self.system_prompt,
+                &state,
+            )
+            .await;
+        }
+
         let mut guard = state
             .write()
             .map_err(|e| NodeError::Other(format!("Failed to write state: {}", e)))?;
 
         let query = guard
             .get_context::<String>("original_query")
-                        .unwrap_or_default();
-
-        let category = guard
-            .get_context::<DocsCategory>("docs_category")
-                        .unwrap_or_else(|| DocsCategory {
-                primary: "user".to_string(),
-                secondary: None,
-                keywords: vec![],
-            });
-
-        let tool_results: Vec<String> = guard
-            .messages
-            .iter()
-            .filter(|m| m.role == MessageRole::Tool)
-            .map(|m| m.content.clone())
-            .collect();
+            .unwrap_or_default();
+        let context = RenderContext::from_state(&guard);
+        record_relevant_docs(&mut guard, &context.tool_results);
 
-        let response =
-            generate_docs_response(&query, &category, &tool_results, &self.system_prompt);
+        let response = self.renderer.render(&query, &context);
 
+        promote_context(&mut guard);
+        let response = append_followups(&mut guard, AgentType::DocsHelper, &response);
         guard.add_assistant_message(&response);
         guard.mark_complete();
 
@@ -249,10 +459,54 @@ impl NodeExecutor for DocsResponseNode {
     }
 }
 
+/// Default [`ResponseRenderer`] for the documentation helper
+#[derive(Debug, Clone)]
+pub struct DocsHelperRenderer {
+    doc_links: DocLinks,
+}
+
+impl DocsHelperRenderer {
+    /// Link to the docs at `doc_links`
+    pub fn new(doc_links: DocLinks) -> Self {
+        Self { doc_links }
+    }
+}
+
+impl Default for DocsHelperRenderer {
+    fn default() -> Self {
+        Self::new(DocLinks::from_env())
+    }
+}
+
+impl ResponseRenderer for DocsHelperRenderer {
+    fn render(&self, query: &str, context: &RenderContext) -> String {
+        let category = context
+            .get::<DocsCategory>("docs_category")
+            .unwrap_or_else(|| DocsCategory {
+                primary: DocCategory::User,
+                secondary: None,
+                keywords: vec![],
+            });
+
+        generate_docs_response(
+            query,
+            &category,
+            &context.tool_results,
+            context.all_tools_failed(),
+            context.max_results(),
+            &self.doc_links,
+            AgentType::DocsHelper.system_prompt(),
+        )
+    }
+}
+
 fn generate_docs_response(
-    _query: &str,
+    query: &str,
     category: &DocsCategory,
     tool_results: &[String],
+    search_failed: bool,
+    max_results: usize,
+    links: &DocLinks,
     _system_prompt: &str,
 ) -> String {
     let mut response = String::new();
@@ -260,101 +514,90 @@ fn generate_docs_response(
     response.push_str("## 📚 Documentation Guide\n\n");
 
     // Add category-specific documentation overview
-    match category.primary.as_str() {
-        "api" => {
+    match category.primary {
+        DocCategory::Api => {
             response.push_str("### API Documentation\n\n");
             response.push_str("The WatsonX Orchestrate API documentation covers:\n\n");
             response.push_str("- **Authentication**: How to obtain and use API tokens\n");
             response.push_str("- **Skills API**: Create, manage, and execute skills\n");
             response.push_str("- **Workflows API**: Manage workflow definitions\n");
             response.push_str("- **Users API**: User and team management\n\n");
-            response.push_str("**Quick Links:**\n");
-            response.push_str("- [API Reference](https://www.ibm.com/docs/watsonx-orchestrate/api)\n");
-            response.push_str("- [Authentication Guide](https://www.ibm.com/docs/watsonx-orchestrate/api/auth)\n");
+            response.push_str(&links.quick_links_markdown(category.primary.as_str()));
         }
-        "admin" => {
+        DocCategory::Admin => {
             response.push_str("### Administration Documentation\n\n");
             response.push_str("Admin documentation helps you:\n\n");
             response.push_str("- **Set up** your WXO environment\n");
             response.push_str("- **Configure** security and access control\n");
             response.push_str("- **Manage** users, teams, and permissions\n");
             response.push_str("- **Integrate** with external services\n\n");
-            response.push_str("**Quick Links:**\n");
-            response.push_str("- [Admin Guide](https://www.ibm.com/docs/watsonx-orchestrate/admin)\n");
-            response.push_str("- [Security Configuration](https://www.ibm.com/docs/watsonx-orchestrate/security)\n");
+            response.push_str(&links.quick_links_markdown(category.primary.as_str()));
         }
-        "getting_started" => {
+        DocCategory::GettingStarted => {
             response.push_str("### Getting Started\n\n");
             response.push_str("Welcome to WatsonX Orchestrate! Here's how to begin:\n\n");
             response.push_str("1. **First Steps**: Log in and explore the interface\n");
             response.push_str("2. **Try a Skill**: Use a pre-built skill from the catalog\n");
             response.push_str("3. **Create Your Own**: Build a simple custom skill\n");
             response.push_str("4. **Automate**: Combine skills into workflows\n\n");
-            response.push_str("**Quick Links:**\n");
-            response.push_str("- [Quick Start Guide](https://www.ibm.com/docs/watsonx-orchestrate/quickstart)\n");
-            response.push_str("- [Tutorial Videos](https://www.ibm.com/docs/watsonx-orchestrate/tutorials)\n");
+            response.push_str(&links.quick_links_markdown(category.primary.as_str()));
         }
-        "troubleshooting" => {
+        DocCategory::Troubleshooting => {
             response.push_str("### Troubleshooting Documentation\n\n");
             response.push_str("Find solutions for common issues:\n\n");
             response.push_str("- **Authentication Issues**: Login and access problems\n");
             response.push_str("- **Skill Errors**: Execution failures and debugging\n");
             response.push_str("- **Integration Problems**: Connection and sync issues\n");
             response.push_str("- **Performance**: Slow operations and timeouts\n\n");
-            response.push_str("**Quick Links:**\n");
-            response.push_str("- [Troubleshooting Guide](https://www.ibm.com/docs/watsonx-orchestrate/troubleshooting)\n");
-            response.push_str("- [Known Issues](https://www.ibm.com/docs/watsonx-orchestrate/known-issues)\n");
+            response.push_str(&links.quick_links_markdown(category.primary.as_str()));
         }
-        "release_notes" => {
+        DocCategory::ReleaseNotes => {
             response.push_str("### Release Notes\n\n");
             response.push_str("Stay up to date with WatsonX Orchestrate:\n\n");
             response.push_str("- **New Features**: Latest capabilities added\n");
             response.push_str("- **Improvements**: Enhancements to existing features\n");
             response.push_str("- **Bug Fixes**: Issues that have been resolved\n");
             response.push_str("- **Breaking Changes**: Updates that may require action\n\n");
-            response.push_str("**Quick Links:**\n");
-            response.push_str("- [Latest Release Notes](https://www.ibm.com/docs/watsonx-orchestrate/release-notes)\n");
-            response.push_str("- [Roadmap](https://www.ibm.com/docs/watsonx-orchestrate/roadmap)\n");
+            response.push_str(&links.quick_links_markdown(category.primary.as_str()));
         }
-        _ => {
+        DocCategory::User => {
             response.push_str("### User Documentation\n\n");
             response.push_str("User documentation helps you work effectively:\n\n");
             response.push_str("- **Skills**: Create and use automation skills\n");
             response.push_str("- **Workflows**: Build multi-step automations\n");
             response.push_str("- **Catalog**: Find pre-built integrations\n");
             response.push_str("- **AI Features**: Natural language interaction\n\n");
-            response.push_str("**Quick Links:**\n");
-            response.push_str("- [User Guide](https://www.ibm.com/docs/watsonx-orchestrate/user)\n");
-            response.push_str("- [Skill Catalog](https://www.ibm.com/docs/watsonx-orchestrate/catalog)\n");
+            response.push_str(&links.quick_links_markdown(category.primary.as_str()));
         }
     }
 
     // Include search results if available
-    if !tool_results.is_empty() {
+    if search_failed {
+        response.push_str("\n---\n\n⚠️ The documentation search is unavailable right now, ");
+        response.push_str("so these are general starting points rather than ");
+        response.push_str("matches for your question.\n");
+    } else if !tool_results.is_empty() {
         response.push_str("\n---\n\n### 🔍 Relevant Documentation Found\n\n");
         response.push_str("Based on your query, here are the most relevant docs:\n\n");
 
-        // Parse and format tool results
-        for result in tool_results {
-            if let Ok(docs) = serde_json::from_str::<Vec<serde_json::Value>>(result) {
-                for doc in docs.iter().take(3) {
-                    if let (Some(title), Some(url)) = (
-                        doc.get("title").and_then(|t| t.as_str()),
-                        doc.get("url").and_then(|u| u.as_str()),
-                    ) {
-                        response.push_str(&format!("- **[{}]({})**", title, url));
-                        if let Some(content) = doc.get("content").and_then(|c| c.as_str()) {
-                            let excerpt = if content.len() > 100 {
-                                format!("{}...", &content[..100])
-                            } else {
-                                content.to_string()
-                            };
-                            response.push_str(&format!("\n  _{}_", excerpt));
-                        }
-                        response.push_str("\n\n");
-                    }
-                }
+        // Parse and format tool results, up to `max_results` docs in all
+        let docs: Vec<serde_json::Value> = tool_results
+            .iter()
+            .filter_map(|result| serde_json::from_str::<Vec<serde_json::Value>>(result).ok())
+            .flatten()
+            .collect();
+        let linked = docs.iter().filter_map(|doc| {
+            let title = doc.get("title").and_then(|t| t.as_str())?;
+            let url = doc.get("url").and_then(|u| u.as_str())?;
+            Some((doc, title, url))
+        });
+        for (doc, title, url) in linked.take(max_results) {
+            response.push_str(&format!("- **[{}]({})**", title, url));
+            if let Some(content) = doc.get("content").and_then(|c| c.as_str()) {
+                let excerpt = make_snippet(content, query, 100);
+                response.push_str(&format!("\n  _{}_", excerpt));
             }
+            response.push_str("\n\n");
         }
     }
 
@@ -368,7 +611,7 @@ fn generate_docs_response(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::tools::create_tool_registry;
+    use crate::tools::{create_tool_registry, ToolErrorKind, ToolStatus};
 
     #[test]
     fn test_build_docs_graph() {
@@ -380,12 +623,136 @@ mod tests {
     #[test]
     fn test_categorize_api() {
         let category = categorize_docs_request("How do I use the API to create a skill?");
-        assert_eq!(category.primary, "api");
+        assert_eq!(category.primary, DocCategory::Api);
     }
 
     #[test]
     fn test_categorize_admin() {
         let category = categorize_docs_request("How do I configure SSO?");
-        assert_eq!(category.primary, "admin");
+        assert_eq!(category.primary, DocCategory::Admin);
+    }
+
+    #[test]
+    fn test_categorize_for_role() {
+        let query = "Where can I read about this?";
+        assert_eq!(
+            categorize_for_role(query, UserRole::EndUser).primary,
+            DocCategory::User
+        );
+        assert_eq!(
+            categorize_for_role(query, UserRole::Developer).primary,
+            DocCategory::Api
+        );
+        assert_eq!(
+            categorize_for_role(query, UserRole::Admin).primary,
+            DocCategory::Admin
+        );
+        // A category named in the query wins over the role
+        assert_eq!(
+            categorize_for_role("Where are the release notes?", UserRole::Admin).primary,
+            DocCategory::ReleaseNotes
+        );
+    }
+
+    #[test]
+    fn test_docs_response_uses_link_base_url() {
+        let links = DocLinks::with_base_url("https://mirror.example.com/wxo");
+        for &primary in DocCategory::all() {
+            let category = DocsCategory {
+                primary,
+                secondary: None,
+                keywords: vec![],
+            };
+            let response = generate_docs_response("", &category, &[], false, 3, &links, "");
+            assert!(response.contains("](https://mirror.example.com/wxo/"));
+            assert!(!response.contains("ibm.com"));
+        }
+    }
+
+    #[test]
+    fn test_docs_response_excerpt_with_multibyte_content() {
+        // The 100th byte falls inside 'é', which used to panic when sliced
+        let content = format!("{}é and more text after it", "a".repeat(99));
+        let docs = serde_json::json!([{
+            "title": "Accents",
+            "url": "https://example.com/accents",
+            "content": content,
+        }]);
+        let category = categorize_docs_request("api docs");
+        let response = generate_docs_response(
+            "",
+            &category,
+            &[docs.to_string()],
+            false,
+            3,
+            &DocLinks::default(),
+            "",
+        );
+
+        let excerpt = format!("_{}é…", "a".repeat(99));
+        assert!(response.contains(&excerpt));
+    }
+
+    #[test]
+    fn test_docs_response_capped_across_searches() {
+        let search = |titles: &[&str]| {
+            let docs: Vec<_> = titles
+                .iter()
+                .map(|title| serde_json::json!({"title": title, "url": "https://example.com"}))
+                .collect();
+            serde_json::json!(docs).to_string()
+        };
+        let tool_results = [search(&["One", "Two"]), search(&["Three", "Four"])];
+        let context = RenderContext {
+            tool_results: tool_results.to_vec(),
+            ..RenderContext::default()
+        };
+
+        let listed = |response: &str| response.matches("- **[").count();
+        let renderer = DocsHelperRenderer::default();
+        assert_eq!(listed(&renderer.render("skills", &context)), 3);
+
+        let terse = context
+            .clone()
+            .with_value("max_results_in_response", serde_json::json!(1));
+        let response = renderer.render("skills", &terse);
+        assert_eq!(listed(&response), 1);
+        assert!(response.contains("[One]"));
+
+        let detailed = context.with_value("max_results_in_response", serde_json::json!(5));
+        assert_eq!(listed(&renderer.render("skills", &detailed)), 4);
+    }
+
+    #[test]
+    fn test_failed_search_is_reported() {
+        let context = RenderContext {
+            tool_results: vec!["Tool error: [unavailable] Query failed".to_string()],
+            tool_statuses: vec![ToolStatus::Failed(ToolErrorKind::Unavailable)],
+            ..RenderContext::default()
+        };
+        assert!(context.all_tools_failed());
+
+        let response = DocsHelperRenderer::default().render("sso setup", &context);
+        assert!(response.contains("documentation search is unavailable"));
+        assert!(!response.contains("Relevant Documentation Found"));
+    }
+
+    #[tokio::test]
+    async fn test_miscategorized_query_falls_back_to_all_categories() {
+        // Categorized as "api", but no api doc matches these keywords
+        let query = "custom skills apis";
+        assert_eq!(categorize_docs_request(query).primary, DocCategory::Api);
+
+        let registry = Arc::new(create_tool_registry());
+        let graph = DocsHelperAgent::build_graph(registry).unwrap();
+        let runner = GraphRunner::new(graph, RunnerConfig::default().max_iterations(10));
+
+        let state =
+            AgentState::with_system_and_user(AgentType::DocsHelper.system_prompt(), query);
+        let result = runner.invoke(state).await.unwrap();
+
+        assert_eq!(result.get_context::<bool>("docs_search_broadened"), Some(true));
+        let response = &result.last_assistant_message().unwrap().content;
+        assert!(response.contains("Creating Custom Skills"));
     }
 }
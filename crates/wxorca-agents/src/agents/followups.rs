@@ -0,0 +1,181 @@
+//! Suggested follow-up questions
+//!
+//! Each reply ends with a few related questions picked from the topic the
+//! agent detected for the turn (the best practices topic, the diagnosed issue
+//! category, the docs category, ...), in the turn's language. The same
+//! questions are stored in the `suggestions` context key so the runner can
+//! return them as data.
+
+use super::templates::{FollowupTemplates, FOLLOWUPS};
+use crate::state::{AgentType, Language};
+use oxidizedgraph::prelude::*;
+
+/// Most follow-up questions suggested per reply
+const MAX_FOLLOWUPS: usize = 3;
+
+/// Suggest follow-up questions in `language` for a reply from `agent_type`
+/// about `topic`
+///
+/// `topic` is matched against each entry's keywords, so both a detected
+/// topic ("security") and free text ("set up SSO for my team") work. Topics
+/// that match nothing get the agent's general suggestions.
+pub fn suggest_followups(agent_type: AgentType, topic: &str, language: Language) -> Vec<String> {
+    let topic = topic.to_lowercase();
+
+    FOLLOWUPS
+        .iter()
+        .filter(|entry| entry.agent == agent_type)
+        .find(|entry| {
+            entry.keywords.is_empty()
+                || entry.keywords.iter().any(|keyword| topic.contains(keyword))
+        })
+        .map(|entry| {
+            entry
+                .questions(language)
+                .iter()
+                .take(MAX_FOLLOWUPS)
+                .map(|question| question.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Get the follow-up questions suggested for the turn's reply
+pub fn suggested_followups(state: &AgentState) -> Vec<String> {
+    state
+        .get_context::<Vec<String>>("suggestions")
+        .unwrap_or_default()
+}
+
+/// Pick follow-ups for the turn, record them in the `suggestions` context key
+/// and append them to `response`
+pub(crate) fn append_followups(
+    state: &mut AgentState,
+    agent_type: AgentType,
+    response: &str,
+) -> String {
+    let language = state
+        .get_context::<Language>("language")
+        .unwrap_or_default();
+    let topic = followup_topic(state, agent_type);
+    let suggestions = suggest_followups(agent_type, &topic, language);
+    state.set_context("suggestions", serde_json::json!(suggestions));
+    with_followups(response, &suggestions, language)
+}
+
+/// `response` followed by a list of `suggestions` under the localized
+/// heading, or unchanged if there are none
+pub(crate) fn with_followups(response: &str, suggestions: &[String], language: Language) -> String {
+    if suggestions.is_empty() {
+        return response.to_string();
+    }
+
+    let heading = FollowupTemplates::for_language(language).heading;
+    let mut response = response.trim_end().to_string();
+    response.push_str(&format!("\n\n{}\n\n", heading));
+    for question in suggestions {
+        response.push_str(&format!("- {}\n", question));
+    }
+    response
+}
+
+/// `response` without the follow-up list [`append_followups`] added to it
+pub(crate) fn strip_followups(response: &str, language: Language) -> &str {
+    let heading = FollowupTemplates::for_language(language).heading;
+    response
+        .split_once(&format!("\n\n{}\n\n", heading))
+        .map_or(response, |(reply, _)| reply)
+}
+
+/// The topic an agent detected for the turn, or the query itself for agents
+/// that don't classify one
+fn followup_topic(state: &AgentState, agent_type: AgentType) -> String {
+    let value = |key: &str, field: Option<&str>| {
+        let value = state.get_context::<serde_json::Value>(key)?;
+        let value = match field {
+            Some(field) => value.get(field)?.clone(),
+            None => value,
+        };
+        value.as_str().map(str::to_string)
+    };
+
+    let topic = match agent_type {
+        AgentType::BestPractices => value("bp_topic", None),
+        AgentType::Troubleshoot => value("diagnosis", Some("category")),
+        AgentType::DocsHelper => value("docs_category", Some("primary")),
+        AgentType::AdminSetup | AgentType::UsageAssistant => None,
+    };
+    topic
+        .or_else(|| state.get_context::<String>("original_query"))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggest_followups() {
+        let security = suggest_followups(AgentType::BestPractices, "security", Language::English);
+        assert!(security.contains(&"How do I set up RBAC?".to_string()));
+        assert!(security.contains(&"How do I rotate API keys?".to_string()));
+
+        for agent_type in AgentType::all() {
+            for language in [Language::English, Language::Spanish] {
+                let general = suggest_followups(*agent_type, "something unrelated", language);
+                assert!(
+                    (2..=MAX_FOLLOWUPS).contains(&general.len()),
+                    "{} {}",
+                    agent_type,
+                    language
+                );
+            }
+        }
+
+        assert_eq!(
+            suggest_followups(
+                AgentType::AdminSetup,
+                "How do I set up SSO for my team?",
+                Language::English
+            )[0],
+            "How do I map identity provider groups to roles?"
+        );
+        assert_eq!(
+            suggest_followups(
+                AgentType::UsageAssistant,
+                "¿Cómo comparto una habilidad?",
+                Language::Spanish
+            )[0],
+            "¿Cómo comparto una habilidad con mi equipo?"
+        );
+    }
+
+    #[test]
+    fn test_every_question_is_translated() {
+        for entry in FOLLOWUPS {
+            assert_eq!(entry.en.len(), entry.es.len(), "{:?}", entry.en);
+        }
+    }
+
+    #[test]
+    fn test_append_followups_uses_detected_topic() {
+        let mut state = AgentState::with_system_and_user("system", "Any security tips?");
+        state.set_context("original_query", serde_json::json!("Any security tips?"));
+        state.set_context("bp_topic", serde_json::json!("security"));
+
+        let response = append_followups(&mut state, AgentType::BestPractices, "Use RBAC.\n");
+
+        assert!(response.starts_with("Use RBAC.\n\n### Related Questions\n\n- "));
+        assert!(response.contains("- How do I rotate API keys?\n"));
+        assert_eq!(
+            suggested_followups(&state),
+            suggest_followups(AgentType::BestPractices, "security", Language::English)
+        );
+        assert_eq!(strip_followups(&response, Language::English), "Use RBAC.");
+
+        state.set_context("language", serde_json::json!(Language::Spanish));
+        let response = append_followups(&mut state, AgentType::BestPractices, "Usa RBAC.");
+        assert!(response.starts_with("Usa RBAC.\n\n### Preguntas relacionadas\n\n- "));
+        assert!(response.contains("- ¿Cómo roto las claves de API?\n"));
+    }
+}
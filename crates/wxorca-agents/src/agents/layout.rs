@@ -0,0 +1,216 @@
+//! Graphviz export of agent graphs
+//!
+//! A compiled graph can't be inspected, so each agent describes its topology
+//! with a [`GraphLayout`] next to its `build_graph`. Nodes are added from the
+//! real node types, so ids and descriptions match what runs, and the edges
+//! come from the same [`Wiring`] the graph is built with. Tests check that
+//! every transition a run takes is in the layout.
+
+use oxidizedgraph::prelude::*;
+
+/// Id of the implicit start node in the DOT output
+const START_NODE: &str = "__start__";
+
+/// Picks the node a conditional edge continues to
+pub(crate) type Router = fn(&AgentState) -> String;
+
+/// An edge of an agent graph
+pub(crate) enum Edge {
+    /// Always continue from the first node to the second
+    Fixed(&'static str, &'static str),
+    /// Continue from the node to whichever of the targets the router picks
+    Conditional(&'static str, Router, &'static [&'static str]),
+}
+
+/// Entry point and edges of an agent graph
+///
+/// Agents declare theirs once; [`Wiring::apply`] adds it to the graph and
+/// [`GraphLayout::wiring`] to the layout.
+pub(crate) struct Wiring {
+    pub(crate) entry: &'static str,
+    pub(crate) edges: &'static [Edge],
+}
+
+impl Wiring {
+    /// Set the entry point and add the edges to `builder`
+    pub(crate) fn apply(&self, builder: GraphBuilder) -> GraphBuilder {
+        let builder = builder.set_entry_point(self.entry);
+        self.edges
+            .iter()
+            .fold(builder, |builder, edge| match *edge {
+                Edge::Fixed(from, to) => builder.add_edge(from, to),
+                Edge::Conditional(from, router, _) => builder.add_conditional_edge(from, router),
+            })
+    }
+}
+
+/// Nodes and edges of an agent graph, renderable as Graphviz DOT
+#[derive(Debug, Clone, Default)]
+pub(crate) struct GraphLayout {
+    name: String,
+    nodes: Vec<(String, Option<String>)>,
+    entry: Option<String>,
+    edges: Vec<LayoutEdge>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct LayoutEdge {
+    from: String,
+    to: String,
+    conditional: bool,
+}
+
+impl GraphLayout {
+    pub(crate) fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Add a node, taking its id and description from the node itself
+    pub(crate) fn node(mut self, node: &dyn NodeExecutor) -> Self {
+        self.nodes.push((
+            node.id().to_string(),
+            node.description().map(str::to_string),
+        ));
+        self
+    }
+
+    pub(crate) fn entry(mut self, id: &str) -> Self {
+        self.entry = Some(id.to_string());
+        self
+    }
+
+    /// Add an unconditional edge
+    pub(crate) fn edge(mut self, from: &str, to: &str) -> Self {
+        self.edges.push(LayoutEdge {
+            from: from.to_string(),
+            to: to.to_string(),
+            conditional: false,
+        });
+        self
+    }
+
+    /// Add the possible targets of a conditional edge's router
+    pub(crate) fn conditional(mut self, from: &str, targets: &[&str]) -> Self {
+        self.edges.extend(targets.iter().map(|to| LayoutEdge {
+            from: from.to_string(),
+            to: to.to_string(),
+            conditional: true,
+        }));
+        self
+    }
+
+    /// Set the entry point and edges from `wiring`
+    pub(crate) fn wiring(self, wiring: &Wiring) -> Self {
+        let layout = self.entry(wiring.entry);
+        wiring
+            .edges
+            .iter()
+            .fold(layout, |layout, edge| match *edge {
+                Edge::Fixed(from, to) => layout.edge(from, to),
+                Edge::Conditional(from, _, targets) => layout.conditional(from, targets),
+            })
+    }
+
+    /// Ids of the declared nodes
+    pub(crate) fn node_ids(&self) -> impl Iterator<Item = &str> {
+        self.nodes.iter().map(|(id, _)| id.as_str())
+    }
+
+    /// Id of the entry node, if declared
+    pub(crate) fn entry_id(&self) -> Option<&str> {
+        self.entry.as_deref()
+    }
+
+    /// `(from, to)` pairs of every edge
+    pub(crate) fn edge_pairs(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.edges.iter().map(|e| (e.from.as_str(), e.to.as_str()))
+    }
+
+    /// Render as a Graphviz digraph, with conditional edges dashed
+    pub(crate) fn to_dot(&self) -> String {
+        let mut dot = format!("digraph \"{}\" {{\n", escape(&self.name));
+        dot.push_str("    rankdir=TB;\n");
+        dot.push_str("    node [shape=box, style=rounded];\n");
+
+        for (id, description) in &self.nodes {
+            let label = match description {
+                Some(description) => format!("{}\\n{}", escape(id), escape(description)),
+                None => escape(id),
+            };
+            dot.push_str(&format!("    \"{}\" [label=\"{}\"];\n", escape(id), label));
+        }
+
+        if let Some(entry) = &self.entry {
+            dot.push_str(&format!(
+                "    \"{}\" [shape=circle, label=\"start\"];\n",
+                START_NODE
+            ));
+            dot.push_str(&format!(
+                "    \"{}\" -> \"{}\";\n",
+                START_NODE,
+                escape(entry)
+            ));
+        }
+        if self.edges.iter().any(|e| e.to == transitions::END) {
+            dot.push_str(&format!(
+                "    \"{}\" [shape=doublecircle, label=\"end\"];\n",
+                transitions::END
+            ));
+        }
+
+        for edge in &self.edges {
+            let style = if edge.conditional {
+                " [style=dashed]"
+            } else {
+                ""
+            };
+            dot.push_str(&format!(
+                "    \"{}\" -> \"{}\"{};\n",
+                escape(&edge.from),
+                escape(&edge.to),
+                style
+            ));
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::AnalyzeQueryNode;
+
+    #[test]
+    fn test_layout_to_dot() {
+        let dot = GraphLayout::new("test_agent")
+            .node(&AnalyzeQueryNode::new("analyze"))
+            .entry("analyze")
+            .conditional("analyze", &["analyze", transitions::END])
+            .to_dot();
+
+        assert!(dot.starts_with("digraph \"test_agent\" {"));
+        assert!(dot.contains(
+            "\"analyze\" [label=\"analyze\\nAnalyzes the user's query to extract intent and key information\"];"
+        ));
+        assert!(dot.contains("\"__start__\" -> \"analyze\";"));
+        assert!(dot.contains(&format!(
+            "\"analyze\" -> \"{}\" [style=dashed];",
+            transitions::END
+        )));
+        assert!(dot.trim_end().ends_with('}'));
+    }
+
+    #[test]
+    fn test_escape_quotes() {
+        assert_eq!(escape(r#"say "hi""#), r#"say \"hi\""#);
+    }
+}
@@ -0,0 +1,191 @@
+//! LLM integration
+//!
+//! Response nodes build their replies from templates by default. When an
+//! [`LlmClient`] is configured they pass the system prompt, grounded in the
+//! user's context, and the conversation to the model instead.
+
+use super::{
+    append_degraded_notice, append_followups, grounded_system_prompt, promote_context,
+    record_relevant_docs, RenderContext,
+};
+use crate::state::AgentType;
+use oxidizedgraph::prelude::*;
+
+/// A chat model that response nodes can use to generate replies
+#[async_trait::async_trait]
+pub trait LlmClient: Send + Sync {
+    /// Generate the assistant's reply to the conversation so far
+    ///
+    /// `messages` excludes system messages; the agent's system prompt,
+    /// followed by what is known about the user's environment, is passed as
+    /// `system`.
+    async fn complete(&self, system: &str, messages: &[Message]) -> Result<String, NodeError>;
+}
+
+/// Generate the reply with `llm`, record it, and finish the turn
+pub(crate) async fn respond_with_llm(
+    llm: &dyn LlmClient,
+    agent_type: AgentType,
+    system_prompt: &str,
+    state: &SharedState,
+) -> Result<NodeOutput, NodeError> {
+    // Snapshot the conversation so the state isn't locked during the call
+    let (system, messages): (String, Vec<Message>) = {
+        let guard = state
+            .read()
+            .map_err(|e| NodeError::Other(format!("Failed to read state: {}", e)))?;
+        let messages = guard
+            .messages
+            .iter()
+            .filter(|m| m.role != MessageRole::System)
+            .cloned()
+            .collect();
+        (grounded_system_prompt(&guard, system_prompt), messages)
+    };
+
+    let response = llm.complete(&system, &messages).await?;
+
+    let mut guard = state
+        .write()
+        .map_err(|e| NodeError::Other(format!("Failed to write state: {}", e)))?;
+    let context = RenderContext::from_state(&guard);
+    record_relevant_docs(&mut guard, &context.tool_results);
+    let response = append_degraded_notice(&context, &response);
+    promote_context(&mut guard);
+    let response = append_followups(&mut guard, agent_type, &response);
+    guard.add_assistant_message(&response);
+    guard.mark_complete();
+
+    Ok(NodeOutput::finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::{build_agent_graph_with_options, GraphOptions};
+    use crate::runner::{run_agent_with, run_orchestrated_turn, run_turn, RunOptions};
+    use crate::state::{AgentType, PromptOverrides, WxorcaState};
+    use crate::tools::testing::DownTool;
+    use std::sync::{Arc, Mutex};
+
+    /// Records what it was asked and replies with a fixed string
+    #[derive(Default)]
+    struct FakeLlm {
+        calls: Mutex<Vec<(String, Vec<String>)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl LlmClient for FakeLlm {
+        async fn complete(&self, system: &str, messages: &[Message]) -> Result<String, NodeError> {
+            let contents = messages.iter().map(|m| m.content.clone()).collect();
+            self.calls
+                .lock()
+                .unwrap()
+                .push((system.to_string(), contents));
+            Ok("model reply".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_response_node_uses_llm() {
+        let llm = Arc::new(FakeLlm::default());
+        let options = GraphOptions::default().llm(llm.clone());
+
+        for &agent_type in AgentType::all() {
+            let graph = build_agent_graph_with_options(agent_type, options.clone()).unwrap();
+            let runner = GraphRunner::new(graph, RunnerConfig::default().max_iterations(10));
+            let state = AgentState::with_system_and_user(
+                agent_type.system_prompt(),
+                "How do I configure SSO?",
+            );
+
+            let result = runner.invoke(state).await.unwrap();
+            let reply = &result.last_assistant_message().unwrap().content;
+            assert!(reply.starts_with("model reply\n\n### Related Questions"));
+        }
+
+        let calls = llm.calls.lock().unwrap();
+        assert_eq!(calls.len(), AgentType::all().len());
+        let (system, messages) = &calls[0];
+        assert_eq!(system, AgentType::AdminSetup.system_prompt());
+        assert_eq!(
+            messages.first().map(String::as_str),
+            Some("How do I configure SSO?")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_response_node_uses_prompt_override() {
+        let llm = Arc::new(FakeLlm::default());
+        let prompts = PromptOverrides::new().with_prompt(AgentType::Troubleshoot, "Be brief.");
+        let options = GraphOptions::default()
+            .llm(llm.clone())
+            .prompts(Arc::new(prompts));
+
+        let graph = build_agent_graph_with_options(AgentType::Troubleshoot, options).unwrap();
+        let runner = GraphRunner::new(graph, RunnerConfig::default().max_iterations(10));
+        // The state carries the built-in prompt; the node must use the override
+        let state = AgentState::with_system_and_user(
+            AgentType::Troubleshoot.system_prompt(),
+            "My workflow is slow",
+        );
+        runner.invoke(state).await.unwrap();
+
+        let calls = llm.calls.lock().unwrap();
+        assert_eq!(calls[0].0, "Be brief.");
+    }
+
+    #[tokio::test]
+    async fn test_llm_prompt_is_grounded_in_user_context() {
+        let llm = Arc::new(FakeLlm::default());
+        let grounded = || {
+            let mut state = WxorcaState::new(AgentType::AdminSetup);
+            state.context.user_role = Some("admin".to_string());
+            state.context.wxo_version = Some("4.8".to_string());
+            state
+        };
+
+        let options = RunOptions::default().llm(llm.clone());
+        run_turn(&mut grounded(), "How do I configure SSO?", options.clone())
+            .await
+            .unwrap();
+        run_orchestrated_turn(&mut grounded(), "How do I configure SSO?", options)
+            .await
+            .unwrap();
+
+        let calls = llm.calls.lock().unwrap();
+        // The orchestrator may merge a second agent's reply
+        assert!(calls.len() >= 2);
+        assert!(calls[0]
+            .0
+            .starts_with(AgentType::AdminSetup.system_prompt()));
+        for (system, _) in calls.iter() {
+            assert!(system.contains("- Role: "));
+            assert!(system.contains("- WatsonX Orchestrate version: 4.8"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_llm_reply_notes_failed_lookups() {
+        let options = RunOptions::default()
+            .llm(Arc::new(FakeLlm::default()))
+            .tool_registry(Arc::new(
+                ToolRegistry::new().register(DownTool("search_wxo_docs")),
+            ));
+
+        let response = run_agent_with(
+            AgentType::AdminSetup,
+            None,
+            "How do I setup SSO for my team?",
+            options,
+        )
+        .await
+        .unwrap();
+
+        assert!(response.degraded);
+        assert!(response.response.starts_with("model reply\n\n---\n\n"));
+        assert!(response
+            .response
+            .contains("Live documentation lookup is temporarily unavailable"));
+    }
+}
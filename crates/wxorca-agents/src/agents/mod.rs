@@ -9,42 +9,331 @@
 
 mod admin_setup;
 mod best_practices;
+mod doc_links;
 mod docs_helper;
+mod followups;
+mod layout;
+mod llm;
+mod orchestrator;
+mod renderer;
+mod templates;
 mod troubleshoot;
 mod usage_assistant;
 
-pub use admin_setup::AdminSetupAgent;
-pub use best_practices::BestPracticesAgent;
-pub use docs_helper::DocsHelperAgent;
-pub use troubleshoot::TroubleshootAgent;
-pub use usage_assistant::UsageAssistantAgent;
+pub use admin_setup::{AdminSetupAgent, AdminSetupRenderer};
+pub use best_practices::{BestPracticesAgent, BestPracticesRenderer};
+pub use doc_links::{DocLink, DocLinks, DEFAULT_DOCS_BASE_URL};
+pub use docs_helper::{DocsHelperAgent, DocsHelperRenderer};
+pub use followups::{suggest_followups, suggested_followups};
+pub use llm::LlmClient;
+pub use orchestrator::{
+    build_orchestrator_graph, build_orchestrator_graph_with_options, orchestrated_agents,
+};
+pub use renderer::{RenderContext, ResponseRenderer, DEFAULT_MAX_RESULTS_IN_RESPONSE};
+pub use troubleshoot::{
+    DiagnosisRule, DiagnosisRules, Escalation, TroubleshootAgent, TroubleshootRenderer,
+};
+pub use usage_assistant::{UsageAssistantAgent, UsageAssistantRenderer};
 
-use crate::state::AgentType;
-use crate::tools::create_tool_registry;
+pub(crate) use followups::{append_followups, strip_followups, with_followups};
+pub(crate) use llm::respond_with_llm;
+
+pub(crate) use templates::CommonTemplates;
+
+use crate::state::{
+    with_prompt_block, AgentType, DocReference, Language, Message as WxorcaMessage,
+    PromptOverrides, WxoContext,
+};
+use crate::text::STOPWORDS;
+use crate::tools::{
+    create_tool_registry, shared_mock_tool_registry, shared_tool_registry, truncate_excerpt,
+    DocResult, RateLimiter, ToolErrorKind, ToolStatus,
+};
+use futures::stream::{self, StreamExt};
 use oxidizedgraph::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::Instrument;
 
 /// Build the agent graph for the specified agent type
 pub fn build_agent_graph(agent_type: AgentType) -> Result<CompiledGraph, GraphError> {
+    build_agent_graph_with_observer(agent_type, None)
+}
+
+/// Build the agent graph for the specified agent type, notifying `observer`
+/// as each node executes
+pub fn build_agent_graph_with_observer(
+    agent_type: AgentType,
+    observer: Option<NodeObserver>,
+) -> Result<CompiledGraph, GraphError> {
+    build_agent_graph_with_options(
+        agent_type,
+        GraphOptions {
+            observer,
+            ..GraphOptions::default()
+        },
+    )
+}
+
+/// Build the agent graph for the specified agent type with the given options
+pub fn build_agent_graph_with_options(
+    agent_type: AgentType,
+    options: GraphOptions,
+) -> Result<CompiledGraph, GraphError> {
+    let tool_registry = if let Some(tool_registry) = &options.tool_registry {
+        tool_registry.clone()
+    } else if options.deterministic || deterministic_from_env() {
+        shared_mock_tool_registry()
+    } else {
+        shared_tool_registry()
+    };
+
+    match agent_type {
+        AgentType::AdminSetup => AdminSetupAgent::build_graph_with_options(tool_registry, options),
+        AgentType::UsageAssistant => {
+            UsageAssistantAgent::build_graph_with_options(tool_registry, options)
+        }
+        AgentType::Troubleshoot => {
+            TroubleshootAgent::build_graph_with_options(tool_registry, options)
+        }
+        AgentType::BestPractices => {
+            BestPracticesAgent::build_graph_with_options(tool_registry, options)
+        }
+        AgentType::DocsHelper => DocsHelperAgent::build_graph_with_options(tool_registry, options),
+    }
+}
+
+/// Render the graph of the specified agent as Graphviz DOT
+///
+/// Conditional edges are dashed and point at every node their router can
+/// return. Pipe the output to `dot -Tpng` to draw it.
+pub fn export_agent_graph_dot(agent_type: AgentType) -> Result<String, GraphError> {
+    // Build the real graph first so an invalid graph is reported, not drawn
+    build_agent_graph(agent_type)?;
+    Ok(agent_graph_layout(agent_type).to_dot())
+}
+
+fn agent_graph_layout(agent_type: AgentType) -> layout::GraphLayout {
     let tool_registry = Arc::new(create_tool_registry());
 
     match agent_type {
-        AgentType::AdminSetup => AdminSetupAgent::build_graph(tool_registry),
-        AgentType::UsageAssistant => UsageAssistantAgent::build_graph(tool_registry),
-        AgentType::Troubleshoot => TroubleshootAgent::build_graph(tool_registry),
-        AgentType::BestPractices => BestPracticesAgent::build_graph(tool_registry),
-        AgentType::DocsHelper => DocsHelperAgent::build_graph(tool_registry),
+        AgentType::AdminSetup => AdminSetupAgent::layout(tool_registry),
+        AgentType::UsageAssistant => UsageAssistantAgent::layout(tool_registry),
+        AgentType::Troubleshoot => TroubleshootAgent::layout(tool_registry),
+        AgentType::BestPractices => BestPracticesAgent::layout(tool_registry),
+        AgentType::DocsHelper => DocsHelperAgent::layout(tool_registry),
+    }
+}
+
+/// Optional hooks used when building an agent graph
+#[derive(Clone, Default)]
+pub struct GraphOptions {
+    /// Notified as each node executes
+    pub observer: Option<NodeObserver>,
+    /// Model used by the response node instead of its built-in template
+    pub llm: Option<Arc<dyn LlmClient>>,
+    /// Renderers used by the response nodes instead of the agents' defaults,
+    /// by agent
+    pub renderers: HashMap<AgentType, Arc<dyn ResponseRenderer>>,
+    /// Serve tools from mock data without connecting to SurrealDB; also
+    /// enabled by the `WXORCA_DETERMINISTIC` environment variable
+    pub deterministic: bool,
+    /// Tools the agent calls instead of the built-in registry, e.g. one from
+    /// [`create_tool_registry_with`](crate::tools::create_tool_registry_with);
+    /// used even in deterministic mode
+    pub tool_registry: Option<Arc<ToolRegistry>>,
+    /// System prompts used instead of the built-in ones
+    pub prompts: Option<Arc<PromptOverrides>>,
+    /// Rules the troubleshooting agent diagnoses issues with instead of the
+    /// built-in ones
+    pub diagnosis_rules: Option<Arc<DiagnosisRules>>,
+    /// Meaningful keywords a query needs before the agents search for it,
+    /// instead of [`DEFAULT_MIN_KEYWORDS`]
+    pub min_keywords: Option<usize>,
+    /// Classifier the agents detect intents with instead of the built-in one
+    pub classifier: Option<Arc<IntentClassifier>>,
+    /// Time a single tool call may take, instead of [`DEFAULT_TOOL_TIMEOUT`]
+    pub tool_timeout: Option<Duration>,
+}
+
+impl GraphOptions {
+    /// Notify `observer` as each node executes
+    pub fn observer(mut self, observer: NodeObserver) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Generate replies with `llm`
+    pub fn llm(mut self, llm: Arc<dyn LlmClient>) -> Self {
+        self.llm = Some(llm);
+        self
+    }
+
+    /// Render `agent_type`'s template replies with `renderer`
+    pub fn renderer_for(
+        mut self,
+        agent_type: AgentType,
+        renderer: Arc<dyn ResponseRenderer>,
+    ) -> Self {
+        self.renderers.insert(agent_type, renderer);
+        self
+    }
+
+    /// Serve tools from mock data without connecting to SurrealDB
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Execute tool calls with `tool_registry`
+    pub fn tool_registry(mut self, tool_registry: Arc<ToolRegistry>) -> Self {
+        self.tool_registry = Some(tool_registry);
+        self
+    }
+
+    /// Prefer system prompts from `prompts` to the built-in ones
+    pub fn prompts(mut self, prompts: Arc<PromptOverrides>) -> Self {
+        self.prompts = Some(prompts);
+        self
+    }
+
+    /// Diagnose troubleshooting queries with `rules`
+    pub fn diagnosis_rules(mut self, rules: Arc<DiagnosisRules>) -> Self {
+        self.diagnosis_rules = Some(rules);
+        self
+    }
+
+    /// Only search for queries with at least `min_keywords` meaningful
+    /// keywords
+    pub fn min_keywords(mut self, min_keywords: usize) -> Self {
+        self.min_keywords = Some(min_keywords);
+        self
+    }
+
+    /// Detect intents with `classifier`
+    pub fn classifier(mut self, classifier: Arc<IntentClassifier>) -> Self {
+        self.classifier = Some(classifier);
+        self
+    }
+
+    /// Abandon tool calls that take longer than `timeout`
+    pub fn tool_timeout(mut self, timeout: Duration) -> Self {
+        self.tool_timeout = Some(timeout);
+        self
+    }
+
+    /// The renderer set for `agent_type`, if any
+    pub fn renderer(&self, agent_type: AgentType) -> Option<Arc<dyn ResponseRenderer>> {
+        self.renderers.get(&agent_type).cloned()
+    }
+
+    /// The system prompt for `agent_type`, with any override applied
+    pub fn system_prompt(&self, agent_type: AgentType) -> String {
+        match &self.prompts {
+            Some(prompts) => agent_type.system_prompt_or(prompts).to_string(),
+            None => agent_type.system_prompt().to_string(),
+        }
+    }
+}
+
+/// Environment variable that turns on deterministic mode for every run
+///
+/// Set it to `1` or `true` to get reproducible output for golden-file tests:
+/// tools use mock data instead of SurrealDB and tool call ids are numbered
+/// `call_1`, `call_2`, ... instead of random.
+pub const DETERMINISTIC_ENV: &str = "WXORCA_DETERMINISTIC";
+
+/// Whether [`DETERMINISTIC_ENV`] is set to `1` or `true`
+pub fn deterministic_from_env() -> bool {
+    std::env::var(DETERMINISTIC_ENV)
+        .map(|value| value == "1" || value.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// Id for a tool call about to be queued on `state`
+///
+/// In deterministic mode (the `deterministic` context key or
+/// [`DETERMINISTIC_ENV`]) ids count the turn's tool calls, after the
+/// `tool_call_offset` calls made earlier in the conversation, so a run always
+/// produces the same ids and ids never repeat within a conversation.
+/// Otherwise they are random UUIDs.
+pub(crate) fn new_tool_call_id(state: &AgentState) -> String {
+    let deterministic = state
+        .get_context::<bool>("deterministic")
+        .unwrap_or_else(deterministic_from_env);
+
+    if deterministic {
+        let offset = tool_call_offset(state);
+        format!("call_{}", offset + tool_call_records(state).len() + 1)
+    } else {
+        uuid::Uuid::new_v4().to_string()
+    }
+}
+
+/// Number of tool calls made before the current turn (or, for an
+/// orchestrated agent, before that agent ran)
+pub(crate) fn tool_call_offset(state: &AgentState) -> usize {
+    state.get_context::<usize>("tool_call_offset").unwrap_or(0)
+}
+
+/// Callback invoked with a node's id and description just before it executes
+pub type NodeObserver = Arc<dyn Fn(&str, Option<&str>) + Send + Sync>;
+
+/// Node wrapper that notifies an optional observer before delegating
+pub struct ObservedNode {
+    inner: Box<dyn NodeExecutor + Send + Sync>,
+    observer: Option<NodeObserver>,
+}
+
+/// Wrap a node so `observer` (if any) is notified when it executes
+pub fn observe(
+    node: impl NodeExecutor + Send + Sync + 'static,
+    observer: &Option<NodeObserver>,
+) -> ObservedNode {
+    ObservedNode {
+        inner: Box::new(node),
+        observer: observer.clone(),
+    }
+}
+
+#[async_trait::async_trait]
+impl NodeExecutor for ObservedNode {
+    fn id(&self) -> &str {
+        self.inner.id()
+    }
+
+    fn description(&self) -> Option<&str> {
+        self.inner.description()
+    }
+
+    async fn execute(&self, state: SharedState) -> Result<NodeOutput, NodeError> {
+        if let Some(observer) = &self.observer {
+            observer(self.inner.id(), self.inner.description());
+        }
+        self.inner.execute(state).await
     }
 }
 
 /// Common node for analyzing user queries
 pub struct AnalyzeQueryNode {
     id: String,
+    classifier: Arc<IntentClassifier>,
 }
 
 impl AnalyzeQueryNode {
     pub fn new(id: impl Into<String>) -> Self {
-        Self { id: id.into() }
+        Self {
+            id: id.into(),
+            classifier: Arc::new(IntentClassifier::default()),
+        }
+    }
+
+    /// Use a custom intent classifier (e.g. keyword sets for another language)
+    pub fn with_classifier(mut self, classifier: Arc<IntentClassifier>) -> Self {
+        self.classifier = classifier;
+        self
     }
 }
 
@@ -67,10 +356,37 @@ impl NodeExecutor for AnalyzeQueryNode {
         if let Some(last_msg) = guard.last_user_message() {
             let content = last_msg.content.clone();
 
-            // Simple keyword-based intent detection
-            let intent = detect_intent(&content);
+            // A follow-up like "yes, the SaaS one" continues the previous
+            // topic, so keep that topic's intent and keywords
+            let topic = previous_topic(&guard);
+            let (query, topic, intent) = match topic {
+                Some(topic) if is_followup(&content) => {
+                    let intent = self.classifier.classify_with_confidence(&topic);
+                    (format!("{} {}", topic, content), topic, intent)
+                }
+                _ => {
+                    // Simple keyword-based intent detection
+                    let intent = self.classifier.classify_with_confidence(&content);
+                    (content.clone(), content, intent)
+                }
+            };
+            let (intent, confidence) = (intent.0.to_string(), intent.1);
             guard.set_context("user_intent", serde_json::json!(intent));
-            guard.set_context("original_query", serde_json::json!(content));
+            guard.set_context("intent_confidence", serde_json::json!(confidence));
+            guard.set_context("original_query", serde_json::json!(query));
+            guard.set_context("conversation_topic", serde_json::json!(topic));
+
+            // Keep a language the client chose, otherwise detect it from
+            // the query
+            let language = match guard.get_context::<Language>("language") {
+                Some(language) => language,
+                None => {
+                    let detected = Language::detect(&query);
+                    guard.set_context("detected_language", serde_json::json!(detected));
+                    detected
+                }
+            };
+            guard.set_context("language", serde_json::json!(language));
 
             // Check if this needs tool usage
             let needs_tools = intent == "search" || intent == "validate" || intent == "example";
@@ -81,68 +397,491 @@ impl NodeExecutor for AnalyzeQueryNode {
     }
 }
 
-fn detect_intent(query: &str) -> &'static str {
-    let query_lower = query.to_lowercase();
+/// Words that open a message continuing the previous one
+const FOLLOWUP_STARTERS: &[&str] = &[
+    "yes",
+    "yeah",
+    "yep",
+    "no",
+    "nope",
+    "it",
+    "its",
+    "that",
+    "this",
+    "those",
+    "these",
+    "they",
+    "ok",
+    "okay",
+    "and",
+    "also",
+    "what about",
+];
 
-    // Check for "example" patterns first (more specific than "show me")
-    if query_lower.contains("example")
-        || query_lower.contains("sample")
-        || query_lower.contains("show me code")
-    {
-        return "example";
+/// Whether a message reads as a follow-up to the previous one
+///
+/// Messages opening with an answer or a pronoun ("yes, the SaaS one", "it
+/// still fails") depend on earlier context. A short message without one
+/// ("Configure SSO") is a new question.
+pub fn is_followup(message: &str) -> bool {
+    let message = message.trim().to_lowercase();
+
+    FOLLOWUP_STARTERS.iter().any(|starter| {
+        message
+            .strip_prefix(starter)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(|c: char| !c.is_alphanumeric()))
+    })
+}
+
+/// The topic of the conversation before the latest user message
+///
+/// Uses the `current_topic` context key if the caller carried one over from
+/// an earlier turn, otherwise the previous user message in the history.
+fn previous_topic(state: &AgentState) -> Option<String> {
+    state.get_context::<String>("current_topic").or_else(|| {
+        state
+            .messages
+            .iter()
+            .rev()
+            .filter(|m| m.role == MessageRole::User)
+            .nth(1)
+            .map(|m| m.content.clone())
+    })
+}
+
+/// Keyword-based intent classifier
+///
+/// Maps intent names to keyword lists. Intents are checked in the order they
+/// were added and the first one with a matching keyword wins; queries that
+/// match nothing are classified as `"general"`. Downstream routing expects the
+/// intent names used by the default classifier (`example`, `howto`,
+/// `troubleshoot`, `search`, `validate`, `advice`).
+#[derive(Debug, Clone)]
+pub struct IntentClassifier {
+    keywords: HashMap<String, Vec<String>>,
+    priority: Vec<String>,
+}
+
+impl IntentClassifier {
+    /// Create a classifier with no intents
+    pub fn empty() -> Self {
+        Self {
+            keywords: HashMap::new(),
+            priority: Vec::new(),
+        }
     }
 
-    if query_lower.contains("how do i")
-        || query_lower.contains("how to")
-        || query_lower.contains("show me")
+    /// Add an intent and its keywords, checked after previously added intents
+    pub fn with_intent<I, K>(mut self, intent: impl Into<String>, keywords: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<String>,
     {
-        return "howto";
+        let intent = intent.into();
+        let keywords = keywords
+            .into_iter()
+            .map(|k| k.into().to_lowercase())
+            .collect();
+
+        if self.keywords.insert(intent.clone(), keywords).is_none() {
+            self.priority.push(intent);
+        }
+        self
     }
 
-    if query_lower.contains("error")
-        || query_lower.contains("failed")
-        || query_lower.contains("not working")
-        || query_lower.contains("problem")
-    {
-        return "troubleshoot";
+    /// Classify a query into an intent
+    pub fn classify(&self, query: &str) -> &str {
+        self.classify_with_confidence(query).0
     }
 
-    if query_lower.contains("documentation")
-        || query_lower.contains("docs")
-        || query_lower.contains("where can i find")
-    {
-        return "search";
+    /// Classify a query into an intent, along with a 0.0–1.0 confidence
+    ///
+    /// Confidence grows with each of the intent's keywords the query
+    /// contains (see [`keyword_confidence`]) and drops when other intents
+    /// match too. Queries that match nothing are `"general"` with a
+    /// confidence of 0.0.
+    pub fn classify_with_confidence(&self, query: &str) -> (&str, f32) {
+        let query_lower = query.to_lowercase();
+        let intents = self.matching_intents(query);
+
+        let (intent, confidence) = match intents.first() {
+            Some(&intent) => {
+                let matched = self.keywords[intent]
+                    .iter()
+                    .filter(|kw| query_lower.contains(kw.as_str()))
+                    .map(String::as_str);
+                let mut confidence = keyword_confidence(matched);
+                if intents.len() > 1 {
+                    confidence *= COMPETING_INTENT_PENALTY;
+                }
+                (intent, confidence)
+            }
+            None => ("general", 0.0),
+        };
+        tracing::debug!(query, intent, confidence, "Detected intent");
+        (intent, confidence)
     }
 
-    if query_lower.contains("validate")
-        || query_lower.contains("check")
-        || query_lower.contains("correct")
-        || query_lower.contains("is this right")
-    {
-        return "validate";
+    /// Every intent whose keywords appear in a query, in priority order
+    pub fn matching_intents(&self, query: &str) -> Vec<&str> {
+        let query_lower = query.to_lowercase();
+
+        self.priority
+            .iter()
+            .filter(|intent| {
+                self.keywords
+                    .get(intent.as_str())
+                    .is_some_and(|kws| kws.iter().any(|kw| query_lower.contains(kw.as_str())))
+            })
+            .map(|intent| intent.as_str())
+            .collect()
     }
+}
 
-    if query_lower.contains("best practice")
-        || query_lower.contains("recommend")
-        || query_lower.contains("should i")
-    {
-        return "advice";
+impl Default for IntentClassifier {
+    fn default() -> Self {
+        Self::empty()
+            // Check for "example" patterns first (more specific than "show me")
+            .with_intent("example", ["example", "sample", "show me code"])
+            .with_intent("howto", ["how do i", "how to", "show me"])
+            .with_intent(
+                "troubleshoot",
+                ["error", "failed", "not working", "problem"],
+            )
+            .with_intent("search", ["documentation", "docs", "where can i find"])
+            .with_intent(
+                "validate",
+                ["validate", "check", "correct", "is this right"],
+            )
+            .with_intent("advice", ["best practice", "recommend", "should i"])
+    }
+}
+
+/// Confidence a matching multi-word keyword adds, e.g. "how do i"
+const PHRASE_KEYWORD_WEIGHT: f32 = 0.6;
+
+/// Confidence a matching single-word keyword adds, e.g. "check"
+const WORD_KEYWORD_WEIGHT: f32 = 0.4;
+
+/// Factor applied to an intent's confidence when other intents match too
+const COMPETING_INTENT_PENALTY: f32 = 0.75;
+
+/// Intent confidence below which [`route_by_intent`] skips the tools and
+/// asks what the user means; a single-word match with no competing intent
+/// clears it
+pub const INTENT_CONFIDENCE_THRESHOLD: f32 = 0.35;
+
+/// Confidence from the keywords a query matched, between 0.0 (none) and 1.0
+///
+/// Each keyword independently adds evidence, phrases more than single
+/// words, so two weak matches are stronger than one.
+pub(crate) fn keyword_confidence<'a>(matched: impl IntoIterator<Item = &'a str>) -> f32 {
+    let doubt: f32 = matched
+        .into_iter()
+        .map(|kw| {
+            if kw.trim().contains(' ') {
+                1.0 - PHRASE_KEYWORD_WEIGHT
+            } else {
+                1.0 - WORD_KEYWORD_WEIGHT
+            }
+        })
+        .product();
+    1.0 - doubt
+}
+
+/// Phrases that mark a query as a problem report, beyond the classifier's
+/// `troubleshoot` keywords
+const PROBLEM_KEYWORDS: &[&str] = &[
+    "fail",
+    "broken",
+    "doesn't work",
+    "does not work",
+    "can't",
+    "cannot",
+    "unable to",
+    "timeout",
+    "timing out",
+    "times out",
+    "stuck",
+    "crash",
+    "denied",
+    "unauthorized",
+    "forbidden",
+];
+
+/// Phrases that mark a query as being about setup or administration
+const ADMIN_KEYWORDS: &[&str] = &[
+    "configure",
+    "setup",
+    "set up",
+    "install",
+    "admin",
+    "sso",
+    "provision",
+    "tenant",
+    "permission",
+    "role",
+    "user management",
+    "integration",
+    "connect",
+];
+
+/// Pick the agent best suited to answer a query
+///
+/// Problem reports (including HTTP error codes like "401") go to the
+/// troubleshooter, advice requests to the best practices coach and
+/// documentation lookups to the docs helper, following the default
+/// [`IntentClassifier`]. Remaining queries go to the admin guide if they are
+/// about setup or administration, and to the usage assistant otherwise.
+pub fn classify_agent(query: &str) -> AgentType {
+    let query_lower = query.to_lowercase();
+    let mentions = |keywords: &[&str]| mentions_keyword(&query_lower, keywords);
+
+    match IntentClassifier::default().classify(query) {
+        "troubleshoot" => return AgentType::Troubleshoot,
+        _ if mentions(PROBLEM_KEYWORDS) || has_http_error_code(&query_lower) => {
+            return AgentType::Troubleshoot
+        }
+        "advice" => return AgentType::BestPractices,
+        "search" => return AgentType::DocsHelper,
+        _ => {}
+    }
+
+    if mentions(ADMIN_KEYWORDS) {
+        AgentType::AdminSetup
+    } else {
+        AgentType::UsageAssistant
+    }
+}
+
+/// Agents a query has signals for, starting with the [`classify_agent`] pick
+///
+/// Most queries have a single candidate. More than one, such as a problem
+/// report that also asks where the docs are, means the pick is uncertain.
+pub fn agent_candidates(query: &str) -> Vec<AgentType> {
+    let query_lower = query.to_lowercase();
+    let mentions = |keywords: &[&str]| mentions_keyword(&query_lower, keywords);
+    let intents = IntentClassifier::default().matching_intents(query);
+
+    let signals = [
+        (
+            AgentType::Troubleshoot,
+            intents.contains(&"troubleshoot")
+                || mentions(PROBLEM_KEYWORDS)
+                || has_http_error_code(&query_lower),
+        ),
+        (AgentType::BestPractices, intents.contains(&"advice")),
+        (AgentType::DocsHelper, intents.contains(&"search")),
+        (AgentType::AdminSetup, mentions(ADMIN_KEYWORDS)),
+    ];
+
+    let mut candidates = vec![classify_agent(query)];
+    for (agent_type, matched) in signals {
+        if matched && !candidates.contains(&agent_type) {
+            candidates.push(agent_type);
+        }
+    }
+    candidates
+}
+
+/// Whether any of `keywords` starts a word in `text`
+///
+/// Only the start is anchored, so "fail" still matches "failed" and
+/// "permission" matches "permissions", but "sso" doesn't match "lessons".
+fn mentions_keyword(text: &str, keywords: &[&str]) -> bool {
+    keywords.iter().any(|kw| {
+        text.match_indices(kw).any(|(i, _)| {
+            !text[..i]
+                .chars()
+                .next_back()
+                .is_some_and(char::is_alphanumeric)
+        })
+    })
+}
+
+/// Check for a 4xx/5xx HTTP status code as a standalone word
+fn has_http_error_code(query: &str) -> bool {
+    query
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| word.len() == 3 && word.starts_with(['4', '5']) && word.parse::<u16>().is_ok())
+}
+
+/// Default minimum number of meaningful keywords before a search is issued
+pub const DEFAULT_MIN_KEYWORDS: usize = 1;
+
+/// Count the words in a query that aren't stopwords
+pub fn meaningful_keyword_count(query: &str) -> usize {
+    query
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty() && !STOPWORDS.contains(w))
+        .count()
+}
+
+/// Decides whether a query has enough meaningful keywords to search for
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SearchGate {
+    min_keywords: usize,
+}
+
+impl SearchGate {
+    /// Require `min_keywords` meaningful keywords, or
+    /// [`DEFAULT_MIN_KEYWORDS`] when unset
+    pub(crate) fn new(min_keywords: Option<usize>) -> Self {
+        Self {
+            min_keywords: min_keywords.unwrap_or(DEFAULT_MIN_KEYWORDS),
+        }
     }
 
-    "general"
+    /// Whether `query` is worth searching for
+    pub(crate) fn allows(&self, query: &str) -> bool {
+        !query.is_empty() && meaningful_keyword_count(query) >= self.min_keywords
+    }
+}
+
+impl Default for SearchGate {
+    fn default() -> Self {
+        Self::new(None)
+    }
 }
 
 /// Common node for executing tools based on context
 pub struct ExecuteToolsNode {
     id: String,
     tool_registry: Arc<ToolRegistry>,
+    timeout: Duration,
+    rate_limiter: Option<Arc<RateLimiter>>,
 }
 
+/// Default time a single tool call may take before it is abandoned
+pub const DEFAULT_TOOL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Maximum number of tool calls from one pass that run at the same time
+const MAX_CONCURRENT_TOOL_CALLS: usize = 4;
+
+/// Times a call failing with a transient error is run again
+const MAX_TOOL_RETRIES: usize = 1;
+
 impl ExecuteToolsNode {
     pub fn new(id: impl Into<String>, tool_registry: Arc<ToolRegistry>) -> Self {
         Self {
             id: id.into(),
             tool_registry,
+            timeout: DEFAULT_TOOL_TIMEOUT,
+            rate_limiter: RateLimiter::shared(),
+        }
+    }
+
+    /// Abandon tool calls that take longer than `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Take a token from `rate_limiter` before each call, instead of from the
+    /// shared limiter configured by
+    /// [`TOOL_RATE_LIMIT_ENV`](crate::tools::TOOL_RATE_LIMIT_ENV); `None`
+    /// turns rate limiting off
+    pub fn with_rate_limiter(mut self, rate_limiter: Option<Arc<RateLimiter>>) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    /// Execute one call, returning it with its output, status and metrics
+    ///
+    /// Calls failing with a transient error (see
+    /// [`ToolErrorKind::is_transient`]) are retried up to `MAX_TOOL_RETRIES`
+    /// times.
+    async fn run_call(&self, call: ToolCall) -> (ToolCall, String, ToolStatus, ToolMetrics) {
+        let argument_bytes = call.arguments.to_string().len();
+        let span = tracing::info_span!(
+            "tool_call",
+            tool = %call.name,
+            call_id = %call.id,
+            argument_bytes
+        );
+
+        let started = Instant::now();
+        let mut retries = 0;
+        let (output, status) = loop {
+            let (output, status) = self.attempt_call(&call, &span).await;
+            match status {
+                ToolStatus::Failed(kind) if kind.is_transient() && retries < MAX_TOOL_RETRIES => {
+                    retries += 1;
+                    span.in_scope(|| tracing::debug!(error_kind = %kind, "Retrying tool call"));
+                }
+                _ => break (output, status),
+            }
+        };
+        let metrics = ToolMetrics {
+            tool: call.name.clone(),
+            call_id: call.id.clone(),
+            argument_bytes,
+            duration_ms: started.elapsed().as_millis() as u64,
+            success: !status.is_failure(),
+        };
+
+        span.in_scope(|| match status {
+            ToolStatus::Failed(ToolErrorKind::Timeout) => {
+                tracing::warn!(duration_ms = metrics.duration_ms, "Tool call timed out")
+            }
+            ToolStatus::Failed(kind) => tracing::warn!(
+                duration_ms = metrics.duration_ms,
+                error_kind = %kind,
+                "Tool call failed"
+            ),
+            _ => tracing::debug!(duration_ms = metrics.duration_ms, "Tool call succeeded"),
+        });
+
+        (call, output, status, metrics)
+    }
+
+    /// Run a call once, within the timeout, once the rate limiter allows it
+    async fn attempt_call(&self, call: &ToolCall, span: &tracing::Span) -> (String, ToolStatus) {
+        if let Some(limiter) = &self.rate_limiter {
+            match limiter.acquire().await {
+                Ok(waited) if !waited.is_zero() => span.in_scope(|| {
+                    tracing::debug!(waited_ms = waited.as_millis() as u64, "Tool call throttled")
+                }),
+                Ok(_) => {}
+                Err(wait) => {
+                    span.in_scope(|| {
+                        tracing::warn!(wait_ms = wait.as_millis() as u64, "Tool call rate limited")
+                    });
+                    return (
+                        format!(
+                            "Error: tool '{}' was rate limited ({} calls per second)",
+                            call.name,
+                            limiter.calls_per_second()
+                        ),
+                        ToolStatus::Failed(ToolErrorKind::RateLimited),
+                    );
+                }
+            }
+        }
+
+        let outcome = tokio::time::timeout(
+            self.timeout,
+            self.tool_registry.execute(call).instrument(span.clone()),
+        )
+        .await;
+
+        match outcome {
+            // ToolResult has content (success) or error fields
+            Ok(result) => {
+                let output = result.as_str().to_string();
+                let status = ToolStatus::from_output(&output, result.error.as_deref());
+                (output, status)
+            }
+            // A timed out call still gets a result so the response node can proceed
+            Err(_) => (
+                format!(
+                    "Error: tool '{}' timed out after {} ms",
+                    call.name,
+                    self.timeout.as_millis()
+                ),
+                ToolStatus::Failed(ToolErrorKind::Timeout),
+            ),
         }
     }
 }
@@ -159,21 +898,52 @@ impl NodeExecutor for ExecuteToolsNode {
 
     async fn execute(&self, state: SharedState) -> Result<NodeOutput, NodeError> {
         let pending_calls = {
-            let guard = state
-                .read()
-                .map_err(|e| NodeError::Other(format!("Failed to read state: {}", e)))?;
+            let mut guard = state
+                .write()
+                .map_err(|e| NodeError::Other(format!("Failed to write state: {}", e)))?;
+            // Count this pass so route_by_tools can stop a runaway loop
+            let iterations = tool_iterations(&guard) + 1;
+            guard.set_context("tool_iterations", serde_json::json!(iterations));
             guard.tool_calls.clone()
         };
 
-        for call in pending_calls {
-            let result = self.tool_registry.execute(&call).await;
+        // Run the calls concurrently; `buffered` yields outcomes in queue
+        // order, so results are recorded deterministically
+        let outcomes: Vec<(ToolCall, String, ToolStatus, ToolMetrics)> =
+            stream::iter(pending_calls)
+                .map(|call| self.run_call(call))
+                .buffered(MAX_CONCURRENT_TOOL_CALLS)
+                .collect()
+                .await;
 
+        {
             let mut guard = state
                 .write()
                 .map_err(|e| NodeError::Other(format!("Failed to write state: {}", e)))?;
 
-            // ToolResult has content (success) or error fields
-            guard.add_tool_result(&call.id, result.as_str());
+            let mut executed = guard
+                .get_context::<Vec<ToolCallRecord>>("executed_tool_calls")
+                .unwrap_or_default();
+            let mut all_metrics = tool_metrics(&guard);
+
+            for (call, output, status, metrics) in outcomes {
+                guard.add_tool_result(&call.id, &output);
+                let mut result = WxorcaMessage::tool_result(&call.id, &output);
+                result.tool_name = Some(call.name.clone());
+                record_turn_message(&mut guard, result);
+
+                // Record the call so callers can see what ran during the turn
+                executed.push(ToolCallRecord {
+                    name: call.name,
+                    arguments: call.arguments,
+                    result_summary: Some(summarize_tool_result(&output)),
+                    status: Some(status),
+                });
+                all_metrics.push(metrics);
+            }
+
+            guard.set_context("executed_tool_calls", serde_json::json!(executed));
+            guard.set_context("tool_metrics", serde_json::json!(all_metrics));
         }
 
         // Clear tool calls after execution
@@ -188,36 +958,1080 @@ impl NodeExecutor for ExecuteToolsNode {
     }
 }
 
+/// Maximum length of a tool result summary, in characters
+const TOOL_RESULT_SUMMARY_LEN: usize = 200;
+
+/// A tool call made (or queued) by an agent during a turn
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCallRecord {
+    /// Name of the tool
+    pub name: String,
+    /// Arguments passed to the tool
+    pub arguments: serde_json::Value,
+    /// Truncated tool output, if the call has been executed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result_summary: Option<String>,
+    /// Whether the call succeeded, found nothing or failed, if it has been
+    /// executed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<ToolStatus>,
+}
+
+fn summarize_tool_result(result: &str) -> String {
+    truncate_excerpt(result, TOOL_RESULT_SUMMARY_LEN)
+}
+
+/// Whether the turn ran tools and every call failed, so the reply was
+/// written without any live results
+pub fn tools_degraded(state: &AgentState) -> bool {
+    let statuses: Vec<ToolStatus> = tool_call_records(state)
+        .into_iter()
+        .filter_map(|record| record.status)
+        .collect();
+    !statuses.is_empty() && statuses.iter().all(ToolStatus::is_failure)
+}
+
+/// Add a notice that live lookups are down to a template reply whose tool
+/// calls all failed (see [`RenderContext::all_tools_failed`])
+///
+/// The docs helper's renderer explains a failed search itself, so only the
+/// other agents' templates use this; LLM replies get it for every agent.
+pub(crate) fn append_degraded_notice(context: &RenderContext, response: &str) -> String {
+    if !context.all_tools_failed() {
+        return response.to_string();
+    }
+
+    with_degraded_notice(response, context.language)
+}
+
+/// `response` followed by the notice that live lookups are down
+pub(crate) fn with_degraded_notice(response: &str, language: Language) -> String {
+    let notice = CommonTemplates::for_language(language).degraded_notice;
+    format!("{}\n\n---\n\n{}\n", response.trim_end(), notice)
+}
+
+/// `response` without the notice [`append_degraded_notice`] added to it, or
+/// `None` if it has none
+pub(crate) fn strip_degraded_notice(response: &str, language: Language) -> Option<&str> {
+    let notice = CommonTemplates::for_language(language).degraded_notice;
+    response
+        .trim_end()
+        .strip_suffix(notice)?
+        .strip_suffix("\n\n---\n\n")
+}
+
+/// Collect the tool calls executed during a run, followed by any still queued
+pub fn tool_call_records(state: &AgentState) -> Vec<ToolCallRecord> {
+    let mut records = state
+        .get_context::<Vec<ToolCallRecord>>("executed_tool_calls")
+        .unwrap_or_default();
+
+    records.extend(state.tool_calls.iter().map(|call| ToolCallRecord {
+        name: call.name.clone(),
+        arguments: call.arguments.clone(),
+        result_summary: None,
+        status: None,
+    }));
+
+    records
+}
+
+/// Queue `call` for the tool executor, recording it for the transcript
+pub(crate) fn queue_tool_call(state: &mut AgentState, call: ToolCall) {
+    record_turn_message(
+        state,
+        WxorcaMessage::tool_call(&call.id, &call.name, &call.arguments),
+    );
+    state.tool_calls.push(call);
+}
+
+fn record_turn_message(state: &mut AgentState, message: WxorcaMessage) {
+    let mut messages = turn_messages(state);
+    messages.push(message);
+    state.set_context("turn_messages", serde_json::json!(messages));
+}
+
+/// The tool calls and tool results of the turn, in order, as conversation
+/// messages
+///
+/// The runner adds them to the conversation ahead of the reply, so
+/// transcripts and exports show what the agent looked up.
+pub fn turn_messages(state: &AgentState) -> Vec<WxorcaMessage> {
+    state
+        .get_context::<Vec<WxorcaMessage>>("turn_messages")
+        .unwrap_or_default()
+}
+
+/// Maximum length of a doc reference excerpt, in characters
+const DOC_EXCERPT_LEN: usize = 100;
+
+/// Collect the documents returned by doc search tool results, most relevant
+/// first
+///
+/// Results that aren't search output are ignored. A document found by more
+/// than one search is listed once, with its highest relevance.
+pub(crate) fn doc_references(tool_results: &[String]) -> Vec<DocReference> {
+    let mut docs: Vec<DocReference> = Vec::new();
+
+    for doc in tool_results
+        .iter()
+        .filter_map(|result| serde_json::from_str::<Vec<DocResult>>(result).ok())
+        .flatten()
+    {
+        match docs.iter_mut().find(|d| d.url == doc.url) {
+            Some(existing) => existing.relevance = existing.relevance.max(doc.relevance),
+            None => docs.push(DocReference {
+                title: doc.title,
+                url: doc.url,
+                relevance: doc.relevance,
+                excerpt: (!doc.content.is_empty())
+                    .then(|| truncate_excerpt(&doc.content, DOC_EXCERPT_LEN)),
+            }),
+        }
+    }
+
+    docs.sort_by(|a, b| b.relevance.total_cmp(&a.relevance));
+    docs
+}
+
+/// Store the documents found in `tool_results` in the `relevant_docs`
+/// context key
+pub(crate) fn record_relevant_docs(state: &mut AgentState, tool_results: &[String]) {
+    let docs = doc_references(tool_results);
+    if !docs.is_empty() {
+        state.set_context("relevant_docs", serde_json::json!(docs));
+    }
+}
+
+/// Append a list of up to `max_results` of the docs found in
+/// `tool_results`, most relevant first, if there are any
+pub(crate) fn push_found_docs(
+    response: &mut String,
+    tool_results: &[String],
+    max_results: usize,
+    language: Language,
+) {
+    let docs = doc_references(tool_results);
+    if docs.is_empty() {
+        return;
+    }
+
+    response.push_str("\n\n");
+    response.push_str(CommonTemplates::for_language(language).found_docs_heading);
+    let items: Vec<String> = docs
+        .iter()
+        .take(max_results)
+        .map(|doc| format!("- [{}]({})", doc.title, doc.url))
+        .collect();
+    response.push_str(&items.join("\n"));
+}
+
+/// Get the documents the agent drew on during a run
+pub fn relevant_docs(state: &AgentState) -> Vec<DocReference> {
+    state
+        .get_context::<Vec<DocReference>>("relevant_docs")
+        .unwrap_or_default()
+}
+
+/// Collect the run's context worth keeping across turns into the
+/// `wxo_context` context key, for merging into the saved `WxoContext`
+pub(crate) fn promote_context(state: &mut AgentState) {
+    let mut context = WxoContext {
+        // The question the conversation is about, without the follow-ups
+        // appended to it, so the topic doesn't grow turn after turn
+        current_topic: state
+            .get_context::<String>("conversation_topic")
+            .or_else(|| state.get_context::<String>("original_query"))
+            .filter(|q| !q.is_empty()),
+        relevant_docs: relevant_docs(state),
+        deployment_type: state.get_context::<String>("deployment_type"),
+        ..WxoContext::default()
+    };
+    // Only the detected language is saved; `language` stays as the client
+    // set it, so detection doesn't override later turns
+    if let Some(language) = state.get_context::<Language>("detected_language") {
+        context
+            .metadata
+            .insert("detected_language".to_string(), language.code().into());
+    }
+    // Kept for support tooling reading saved conversations; forks drop
+    // these keys (see `TURN_METADATA_KEYS` in state.rs)
+    if let Some(escalation) = state.get_context::<Escalation>("escalation") {
+        context.metadata.insert(
+            "should_escalate".to_string(),
+            escalation.should_escalate.into(),
+        );
+        context
+            .metadata
+            .insert("support_tier".to_string(), escalation.support_tier.into());
+        context.metadata.insert(
+            "diagnosis_category".to_string(),
+            escalation.diagnosis_category.into(),
+        );
+    }
+    state.set_context("wxo_context", serde_json::json!(context));
+}
+
+/// Get the context a run promoted for the saved `WxoContext`
+pub fn promoted_context(state: &AgentState) -> WxoContext {
+    state
+        .get_context::<WxoContext>("wxo_context")
+        .unwrap_or_default()
+}
+
+/// Timing and outcome of a single tool invocation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolMetrics {
+    /// Name of the tool
+    pub tool: String,
+    /// ID of the tool call
+    pub call_id: String,
+    /// Size of the serialized arguments, in bytes
+    pub argument_bytes: usize,
+    /// Wall-clock time the tool took to run
+    pub duration_ms: u64,
+    /// Whether the tool returned a result rather than an error
+    pub success: bool,
+}
+
+/// Get the metrics of every tool executed during a run, in execution order
+pub fn tool_metrics(state: &AgentState) -> Vec<ToolMetrics> {
+    state
+        .get_context::<Vec<ToolMetrics>>("tool_metrics")
+        .unwrap_or_default()
+}
+
+/// Default cap on `execute_tools` passes per turn
+pub const DEFAULT_MAX_TOOL_ITERATIONS: usize = 5;
+
+/// Get how many times `execute_tools` has run during the current turn
+pub fn tool_iterations(state: &AgentState) -> usize {
+    state.get_context::<usize>("tool_iterations").unwrap_or(0)
+}
+
+/// `base` followed by the user context block the runner stored under
+/// `prompt_context`, matching the system message it sent for the turn
+pub(crate) fn grounded_system_prompt(state: &AgentState, base: &str) -> String {
+    with_prompt_block(
+        base,
+        state.get_context::<String>("prompt_context").as_deref(),
+    )
+}
+
+/// End the turn waiting for the user to answer a clarifying question
+///
+/// Use instead of `mark_complete` when the reply asks for details the agent
+/// can't go on without. Sets the `awaiting_user_input` context key, which
+/// the runner reports as [`AgentResponse::needs_input`](crate::runner::AgentResponse::needs_input).
+pub(crate) fn request_user_input(state: &mut AgentState) {
+    state.set_context("awaiting_user_input", serde_json::json!(true));
+}
+
+/// Whether the turn ended with a question for the user rather than complete
+pub fn awaiting_user_input(state: &AgentState) -> bool {
+    state
+        .get_context::<bool>("awaiting_user_input")
+        .unwrap_or(false)
+}
+
 /// Router function for deciding whether to use tools or respond directly
+///
+/// Ends the turn once `execute_tools` has run `max_tool_iterations` times
+/// (read from the context, defaulting to [`DEFAULT_MAX_TOOL_ITERATIONS`]),
+/// so a response node that keeps queuing calls can't loop regardless of the
+/// runner's own iteration cap.
 pub fn route_by_tools(state: &AgentState) -> String {
-    if state.has_pending_tool_calls() {
-        "execute_tools".to_string()
-    } else {
-        transitions::END.to_string()
+    if !state.has_pending_tool_calls() {
+        return transitions::END.to_string();
     }
+
+    let max_iterations = state
+        .get_context::<usize>("max_tool_iterations")
+        .unwrap_or(DEFAULT_MAX_TOOL_ITERATIONS);
+    if tool_iterations(state) >= max_iterations {
+        tracing::warn!(
+            max_iterations,
+            pending = state.tool_calls.len(),
+            "Tool iteration limit reached, ending turn"
+        );
+        return transitions::END.to_string();
+    }
+
+    "execute_tools".to_string()
+}
+
+/// Whether a response node should wait for its queued tool calls to run
+///
+/// True when calls are pending and [`route_by_tools`] will execute them. The
+/// node then continues without replying, and answers on the pass after
+/// `execute_tools` has added the results to the conversation, so the reply
+/// can use them. Response nodes check this before anything else.
+pub(crate) fn awaiting_tool_results(state: &SharedState) -> Result<bool, NodeError> {
+    let guard = state
+        .read()
+        .map_err(|e| NodeError::Other(format!("Failed to read state: {}", e)))?;
+    Ok(route_by_tools(&guard) == "execute_tools")
 }
 
 /// Router function based on user intent
+///
+/// Intents that need tools but were detected with less than
+/// [`INTENT_CONFIDENCE_THRESHOLD`] go to `clarify` rather than searching on a
+/// guess, so the agent can ask what the user means (see
+/// [`ClarifyIntentNode`]). Other tool intents go to `search_docs`, the rest
+/// to `respond`.
 pub fn route_by_intent(state: &AgentState) -> String {
-    if let Some(needs_tools) = state.get_context::<bool>("needs_tools") {
-        if needs_tools {
-            return "search_docs".to_string();
-        }
+    if !state.get_context::<bool>("needs_tools").unwrap_or(false) {
+        return "respond".to_string();
+    }
+    let confident = state
+        .get_context::<f32>("intent_confidence")
+        .is_none_or(|confidence| confidence >= INTENT_CONFIDENCE_THRESHOLD);
+    if confident {
+        "search_docs".to_string()
+    } else {
+        "clarify".to_string()
+    }
+}
+
+/// Asks the user what they're after when [`route_by_intent`] isn't
+/// confident enough in the detected intent to search on it
+pub(crate) struct ClarifyIntentNode {
+    id: String,
+}
+
+impl ClarifyIntentNode {
+    pub(crate) fn new(id: impl Into<String>) -> Self {
+        Self { id: id.into() }
+    }
+}
+
+#[async_trait::async_trait]
+impl NodeExecutor for ClarifyIntentNode {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Asks a clarifying question when the intent is uncertain")
+    }
+
+    async fn execute(&self, state: SharedState) -> Result<NodeOutput, NodeError> {
+        let mut guard = state
+            .write()
+            .map_err(|e| NodeError::Other(format!("Failed to write state: {}", e)))?;
+
+        let language = guard
+            .get_context::<Language>("language")
+            .unwrap_or_default();
+        promote_context(&mut guard);
+        guard.add_assistant_message(CommonTemplates::for_language(language).clarify_intent);
+        request_user_input(&mut guard);
+
+        Ok(NodeOutput::finish())
     }
-    "respond".to_string()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn detect_intent(query: &str) -> String {
+        IntentClassifier::default().classify(query).to_string()
+    }
+
     #[test]
     fn test_detect_intent() {
         assert_eq!(detect_intent("How do I create a skill?"), "howto");
         assert_eq!(detect_intent("I'm getting an error"), "troubleshoot");
         assert_eq!(detect_intent("Show me an example"), "example");
         assert_eq!(detect_intent("Is this config correct?"), "validate");
-        assert_eq!(detect_intent("What's the best practice for this?"), "advice");
+        assert_eq!(
+            detect_intent("What's the best practice for this?"),
+            "advice"
+        );
+        assert_eq!(detect_intent("Hello there"), "general");
+    }
+
+    #[test]
+    fn test_is_followup() {
+        assert!(is_followup("yes, the SaaS one"));
+        assert!(is_followup("It still times out after the change"));
+        assert!(is_followup("What about on-prem?"));
+        assert!(!is_followup("How do I create a skill for Salesforce?"));
+        // Short, but a question of its own
+        assert!(!is_followup("Configure SSO"));
+        assert!(is_followup("Yes"));
+        // Starts with "it" but isn't a follow-up
+        assert!(!is_followup("Italy deployments need more configuration"));
+        assert!(!is_followup("   "));
+    }
+
+    #[tokio::test]
+    async fn test_followup_keeps_previous_topic() {
+        let graph = build_agent_graph(AgentType::AdminSetup).unwrap();
+        let runner = GraphRunner::new(graph, RunnerConfig::default().max_iterations(10));
+        let mut state = AgentState::with_system_and_user(
+            AgentType::AdminSetup.system_prompt(),
+            "How do I setup WXO?",
+        );
+        state.add_assistant_message("Which deployment are you on?");
+        state.add_user_message("yes, the SaaS one");
+
+        let result = runner.invoke(state).await.unwrap();
+
+        assert_eq!(
+            result
+                .get_context::<String>("conversation_topic")
+                .as_deref(),
+            Some("How do I setup WXO?")
+        );
+        assert_eq!(
+            result.get_context::<String>("original_query").as_deref(),
+            Some("How do I setup WXO? yes, the SaaS one")
+        );
+        assert_eq!(
+            result.get_context::<String>("user_intent").as_deref(),
+            Some("howto")
+        );
+        let response = &result.last_assistant_message().unwrap().content;
+        assert!(response.contains("IBM Cloud"));
+    }
+
+    #[tokio::test]
+    async fn test_language_detected_from_query() {
+        let graph = build_agent_graph(AgentType::Troubleshoot).unwrap();
+        let runner = GraphRunner::new(graph, RunnerConfig::default().max_iterations(10));
+        let state = AgentState::with_system_and_user(
+            AgentType::Troubleshoot.system_prompt(),
+            "¿Por qué mi flujo de trabajo está tan lento?",
+        );
+
+        let result = runner.invoke(state).await.unwrap();
+
+        assert_eq!(
+            result.get_context::<Language>("language"),
+            Some(Language::Spanish)
+        );
+        let response = &result.last_assistant_message().unwrap().content;
+        assert!(response.contains("RENDIMIENTO"));
+    }
+
+    #[test]
+    fn test_meaningful_keyword_count() {
+        assert_eq!(meaningful_keyword_count("hi there"), 0);
+        assert_eq!(meaningful_keyword_count("Thanks!"), 0);
+        assert_eq!(meaningful_keyword_count("how to create a skill"), 2);
+        assert_eq!(meaningful_keyword_count(""), 0);
+    }
+
+    #[test]
+    fn test_intent_confidence() {
+        let classifier = IntentClassifier::default();
+
+        let (intent, weak) = classifier.classify_with_confidence("Is this correct?");
+        assert_eq!(intent, "validate");
+        let (intent, strong) =
+            classifier.classify_with_confidence("Can you check if this is correct?");
+        assert_eq!(intent, "validate");
+        assert!(strong > weak);
+        assert!(weak >= INTENT_CONFIDENCE_THRESHOLD);
+
+        // "check" also matches "validate", so the pick is less certain
+        let (intent, competing) = classifier.classify_with_confidence("Check the docs");
+        assert_eq!(intent, "search");
+        assert!(competing < INTENT_CONFIDENCE_THRESHOLD);
+
+        assert_eq!(
+            classifier.classify_with_confidence("Hello there"),
+            ("general", 0.0)
+        );
+    }
+
+    #[test]
+    fn test_low_confidence_intent_asks_for_clarification() {
+        let mut state = AgentState::new();
+        state.set_context("needs_tools", serde_json::json!(true));
+        assert_eq!(route_by_intent(&state), "search_docs");
+
+        state.set_context("intent_confidence", serde_json::json!(0.3));
+        assert_eq!(route_by_intent(&state), "clarify");
+        state.set_context("intent_confidence", serde_json::json!(0.64));
+        assert_eq!(route_by_intent(&state), "search_docs");
+
+        state.set_context("needs_tools", serde_json::json!(false));
+        state.set_context("intent_confidence", serde_json::json!(0.0));
+        assert_eq!(route_by_intent(&state), "respond");
+    }
+
+    #[test]
+    fn test_custom_intent_classifier() {
+        let classifier = IntentClassifier::empty()
+            .with_intent("example", ["exemple"])
+            .with_intent("howto", ["comment faire", "comment créer"])
+            .with_intent("troubleshoot", ["erreur", "échec"]);
+
+        assert_eq!(
+            classifier.classify("Comment créer une compétence ?"),
+            "howto"
+        );
+        assert_eq!(classifier.classify("J'ai une Erreur 401"), "troubleshoot");
+        assert_eq!(classifier.classify("Montre-moi un exemple"), "example");
+        assert_eq!(classifier.classify("How do I create a skill?"), "general");
+    }
+
+    #[tokio::test]
+    async fn test_graph_options_classifier() {
+        let classifier = IntentClassifier::empty().with_intent("troubleshoot", ["erreur"]);
+        let options = GraphOptions::default()
+            .deterministic(true)
+            .classifier(Arc::new(classifier));
+
+        for &agent_type in AgentType::all() {
+            let graph = build_agent_graph_with_options(agent_type, options.clone()).unwrap();
+            let runner = GraphRunner::new(graph, RunnerConfig::default().max_iterations(10));
+            let state = AgentState::with_system_and_user("system", "Erreur 401 sur mon workflow");
+            let result = runner.invoke(state).await.unwrap();
+            assert_eq!(
+                result.get_context::<String>("user_intent").as_deref(),
+                Some("troubleshoot"),
+                "{}",
+                agent_type
+            );
+        }
+    }
+
+    #[test]
+    fn test_classify_agent() {
+        let cases = [
+            (
+                "I'm getting a 401 when calling the API",
+                AgentType::Troubleshoot,
+            ),
+            ("My workflow keeps timing out", AgentType::Troubleshoot),
+            ("How do I configure SSO?", AgentType::AdminSetup),
+            (
+                "What are the best practices for skill design?",
+                AgentType::BestPractices,
+            ),
+            ("Where can I find the release notes?", AgentType::DocsHelper),
+            ("How do I create a workflow?", AgentType::UsageAssistant),
+            // "sso" inside another word isn't about SSO
+            (
+                "How do I share lessons with my team?",
+                AgentType::UsageAssistant,
+            ),
+        ];
+
+        for (query, expected) in cases {
+            assert_eq!(classify_agent(query), expected, "query: {}", query);
+        }
+    }
+
+    #[test]
+    fn test_agent_candidates() {
+        assert_eq!(
+            agent_candidates("How do I configure SSO?"),
+            vec![AgentType::AdminSetup]
+        );
+        assert_eq!(
+            agent_candidates("My skill fails, where can I find the docs?"),
+            vec![AgentType::Troubleshoot, AgentType::DocsHelper]
+        );
+        assert_eq!(
+            agent_candidates("Should I set up one tenant per team?"),
+            vec![AgentType::BestPractices, AgentType::AdminSetup]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_observer_sees_each_node() {
+        let seen = Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+        let recorder = seen.clone();
+        let observer: NodeObserver = Arc::new(move |id, _description| {
+            recorder.lock().unwrap().push(id.to_string());
+        });
+
+        let graph =
+            build_agent_graph_with_observer(AgentType::Troubleshoot, Some(observer)).unwrap();
+        let runner = GraphRunner::new(graph, RunnerConfig::default().max_iterations(10));
+        let state = AgentState::with_system_and_user(
+            AgentType::Troubleshoot.system_prompt(),
+            "My workflow keeps timing out",
+        );
+        runner.invoke(state).await.unwrap();
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.first().map(String::as_str), Some("analyze"));
+        assert!(seen.iter().any(|id| id == "respond"));
+    }
+
+    #[test]
+    fn test_doc_references() {
+        let first = serde_json::json!([
+            { "title": "Admin Guide", "content": "Set up SSO", "url": "https://example.com/admin",
+              "category": "admin", "relevance": 0.4 },
+            { "title": "API Reference", "content": "", "url": "https://example.com/api",
+              "category": "api", "relevance": 0.6 }
+        ]);
+        let second = serde_json::json!([
+            { "title": "Admin Guide", "content": "Set up SSO", "url": "https://example.com/admin",
+              "category": "admin", "relevance": 0.9 }
+        ]);
+        let results = vec![
+            first.to_string(),
+            "not a search result".to_string(),
+            second.to_string(),
+        ];
+
+        let docs = doc_references(&results);
+        assert_eq!(docs.len(), 2);
+        assert_eq!(docs[0].url, "https://example.com/admin");
+        assert_eq!(docs[0].relevance, 0.9);
+        assert_eq!(docs[0].excerpt.as_deref(), Some("Set up SSO"));
+        assert!(docs[1].excerpt.is_none());
+    }
+
+    #[test]
+    fn test_deterministic_tool_call_ids() {
+        let mut state = AgentState::with_system_and_user("system", "question");
+        state.set_context("deterministic", serde_json::json!(true));
+        assert_eq!(new_tool_call_id(&state), "call_1");
+
+        state.tool_calls.push(ToolCall {
+            id: new_tool_call_id(&state),
+            name: "search_wxo_docs".to_string(),
+            arguments: serde_json::json!({}),
+        });
+        assert_eq!(new_tool_call_id(&state), "call_2");
+
+        // Calls from earlier turns are skipped
+        state.set_context("tool_call_offset", serde_json::json!(4));
+        assert_eq!(new_tool_call_id(&state), "call_6");
+
+        state.set_context("deterministic", serde_json::json!(false));
+        assert_ne!(new_tool_call_id(&state), "call_2");
+    }
+
+    #[tokio::test]
+    async fn test_tool_metrics_recorded() {
+        let graph = build_agent_graph(AgentType::DocsHelper).unwrap();
+        let runner = GraphRunner::new(graph, RunnerConfig::default().max_iterations(10));
+        let state = AgentState::with_system_and_user(
+            AgentType::DocsHelper.system_prompt(),
+            "Where is the admin guide for configuring security?",
+        );
+        let result = runner.invoke(state).await.unwrap();
+
+        let metrics = tool_metrics(&result);
+        let search = metrics
+            .iter()
+            .find(|m| m.tool == "search_wxo_docs")
+            .expect("search_wxo_docs should have run");
+        assert!(!search.call_id.is_empty());
+        assert!(search.argument_bytes > 0);
+    }
+
+    #[tokio::test]
+    async fn test_min_keywords_option_skips_short_queries() {
+        let searches = |options: GraphOptions| async move {
+            let graph = build_agent_graph_with_options(AgentType::AdminSetup, options).unwrap();
+            let runner = GraphRunner::new(graph, RunnerConfig::default().max_iterations(10));
+            let state = AgentState::with_system_and_user(
+                AgentType::AdminSetup.system_prompt(),
+                "Configure SSO",
+            );
+            let result = runner.invoke(state).await.unwrap();
+            tool_call_records(&result)
+                .iter()
+                .filter(|r| r.name == "search_wxo_docs")
+                .count()
+        };
+
+        let options = GraphOptions::default().deterministic(true);
+        assert_eq!(searches(options.clone()).await, 1);
+        assert_eq!(searches(options.min_keywords(3)).await, 0);
+    }
+
+    /// Queues a tool call every time it runs
+    struct AlwaysQueueNode;
+
+    #[async_trait::async_trait]
+    impl NodeExecutor for AlwaysQueueNode {
+        fn id(&self) -> &str {
+            "respond"
+        }
+
+        fn description(&self) -> Option<&str> {
+            Some("Queues a tool call on every pass")
+        }
+
+        async fn execute(&self, state: SharedState) -> Result<NodeOutput, NodeError> {
+            let mut guard = state
+                .write()
+                .map_err(|e| NodeError::Other(format!("Failed to write state: {}", e)))?;
+            guard.tool_calls.push(ToolCall {
+                id: uuid::Uuid::new_v4().to_string(),
+                name: "search_wxo_docs".to_string(),
+                arguments: serde_json::json!({ "query": "again" }),
+            });
+            Ok(NodeOutput::cont())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_loop_terminates() {
+        let graph = GraphBuilder::new()
+            .name("looping_agent")
+            .add_node(AlwaysQueueNode)
+            .add_node(ExecuteToolsNode::new(
+                "execute_tools",
+                Arc::new(create_tool_registry()),
+            ))
+            .set_entry_point("respond")
+            .add_conditional_edge("respond", route_by_tools)
+            .add_edge("execute_tools", "respond")
+            .compile()
+            .unwrap();
+        // Leave the runner's own cap well above the tool limit
+        let runner = GraphRunner::new(graph, RunnerConfig::default().max_iterations(100));
+
+        let mut state = AgentState::with_system_and_user("system", "loop forever");
+        state.set_context("max_tool_iterations", serde_json::json!(3));
+        let result = runner.invoke(state).await.unwrap();
+
+        assert_eq!(tool_iterations(&result), 3);
+        assert!(result.has_pending_tool_calls());
+    }
+
+    /// A tool that takes far longer than the test timeout
+    struct SlowTool;
+
+    #[async_trait::async_trait]
+    impl Tool for SlowTool {
+        fn name(&self) -> &str {
+            "slow_tool"
+        }
+
+        fn description(&self) -> &str {
+            "Sleeps before answering"
+        }
+
+        fn parameters_schema(&self) -> serde_json::Value {
+            serde_json::json!({ "type": "object", "properties": {}, "required": [] })
+        }
+
+        async fn execute(&self, _arguments: serde_json::Value) -> Result<String, NodeError> {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok("too late".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_timeout_records_result() {
+        let registry = Arc::new(ToolRegistry::new().register(SlowTool));
+        let graph = GraphBuilder::new()
+            .name("slow_tools")
+            .add_node(
+                ExecuteToolsNode::new("execute_tools", registry)
+                    .with_timeout(Duration::from_millis(50)),
+            )
+            .set_entry_point("execute_tools")
+            .add_conditional_edge("execute_tools", route_by_tools)
+            .compile()
+            .unwrap();
+        let runner = GraphRunner::new(graph, RunnerConfig::default().max_iterations(10));
+
+        let mut state = AgentState::with_system_and_user("system", "run the slow tool");
+        state.tool_calls.push(ToolCall {
+            id: "call_1".to_string(),
+            name: "slow_tool".to_string(),
+            arguments: serde_json::json!({}),
+        });
+        let result = tokio::time::timeout(Duration::from_secs(5), runner.invoke(state))
+            .await
+            .expect("the graph should not hang")
+            .unwrap();
+
+        let tool_message = result
+            .messages
+            .iter()
+            .find(|m| m.role == MessageRole::Tool)
+            .expect("a timeout result should be recorded");
+        assert!(tool_message.content.contains("timed out"));
+        assert!(!tool_metrics(&result)[0].success);
+    }
+
+    /// A custom tool registered alongside the built-in ones
+    struct LicenseTool;
+
+    #[async_trait::async_trait]
+    impl Tool for LicenseTool {
+        fn name(&self) -> &str {
+            "check_license_status"
+        }
+
+        fn description(&self) -> &str {
+            "Checks the license status of a tenant"
+        }
+
+        fn parameters_schema(&self) -> serde_json::Value {
+            serde_json::json!({
+                "type": "object",
+                "properties": { "tenant": { "type": "string" } },
+                "required": ["tenant"]
+            })
+        }
+
+        async fn execute(&self, arguments: serde_json::Value) -> Result<String, NodeError> {
+            Ok(format!(
+                "{{\"tenant\": {}, \"status\": \"active\"}}",
+                arguments["tenant"]
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_tool_runs_through_execute_tools() {
+        let registry = Arc::new(crate::tools::create_tool_registry_with(vec![Box::new(
+            LicenseTool,
+        )]));
+        assert!(build_agent_graph_with_options(
+            AgentType::AdminSetup,
+            GraphOptions::default().tool_registry(registry.clone())
+        )
+        .is_ok());
+
+        let graph = GraphBuilder::new()
+            .name("custom_tools")
+            .add_node(ExecuteToolsNode::new("execute_tools", registry))
+            .set_entry_point("execute_tools")
+            .add_conditional_edge("execute_tools", route_by_tools)
+            .compile()
+            .unwrap();
+        let runner = GraphRunner::new(graph, RunnerConfig::default().max_iterations(10));
+
+        let mut state = AgentState::with_system_and_user("system", "is acme licensed?");
+        state.tool_calls.push(ToolCall {
+            id: "call_1".to_string(),
+            name: "check_license_status".to_string(),
+            arguments: serde_json::json!({ "tenant": "acme" }),
+        });
+        state.tool_calls.push(ToolCall {
+            id: "call_2".to_string(),
+            name: "validate_wxo_config".to_string(),
+            arguments: serde_json::json!({ "config_type": "skill", "config": {} }),
+        });
+        let result = runner.invoke(state).await.unwrap();
+
+        let tool_results: Vec<&str> = result
+            .messages
+            .iter()
+            .filter(|m| m.role == MessageRole::Tool)
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(tool_results.len(), 2);
+        assert_eq!(
+            tool_results[0],
+            "{\"tenant\": \"acme\", \"status\": \"active\"}"
+        );
+        assert!(tool_metrics(&result).iter().all(|metric| metric.success));
+    }
+
+    /// A tool that fails with `kind` until it has been called `failures` times
+    struct FlakyTool {
+        kind: ToolErrorKind,
+        failures: usize,
+        calls: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl Tool for FlakyTool {
+        fn name(&self) -> &str {
+            "flaky_tool"
+        }
+
+        fn description(&self) -> &str {
+            "Fails a set number of times before answering"
+        }
+
+        fn parameters_schema(&self) -> serde_json::Value {
+            serde_json::json!({ "type": "object", "properties": {}, "required": [] })
+        }
+
+        async fn execute(&self, _arguments: serde_json::Value) -> Result<String, NodeError> {
+            let call = self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            if call < self.failures {
+                return Err(self.kind.error("backend hiccup"));
+            }
+            Ok("[\"recovered\"]".to_string())
+        }
+    }
+
+    async fn run_flaky_tool(kind: ToolErrorKind, failures: usize) -> (AgentState, usize) {
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let tool = FlakyTool {
+            kind,
+            failures,
+            calls: calls.clone(),
+        };
+        let registry = Arc::new(ToolRegistry::new().register(tool));
+        let graph = GraphBuilder::new()
+            .name("flaky_tools")
+            .add_node(ExecuteToolsNode::new("execute_tools", registry))
+            .set_entry_point("execute_tools")
+            .add_conditional_edge("execute_tools", route_by_tools)
+            .compile()
+            .unwrap();
+        let runner = GraphRunner::new(graph, RunnerConfig::default().max_iterations(10));
+
+        let mut state = AgentState::with_system_and_user("system", "run the flaky tool");
+        state.tool_calls.push(ToolCall {
+            id: "call_1".to_string(),
+            name: "flaky_tool".to_string(),
+            arguments: serde_json::json!({}),
+        });
+        let result = runner.invoke(state).await.unwrap();
+        let calls = calls.load(std::sync::atomic::Ordering::SeqCst);
+        (result, calls)
+    }
+
+    #[tokio::test]
+    async fn test_transient_tool_errors_are_retried() {
+        let (result, calls) = run_flaky_tool(ToolErrorKind::Unavailable, 1).await;
+        assert_eq!(calls, 2);
+        let records = tool_call_records(&result);
+        assert_eq!(records[0].status, Some(ToolStatus::Success));
+        assert!(tool_metrics(&result)[0].success);
+
+        // Bad arguments fail the same way every time
+        let (result, calls) = run_flaky_tool(ToolErrorKind::InvalidArguments, 1).await;
+        assert_eq!(calls, 1);
+        let records = tool_call_records(&result);
+        assert_eq!(
+            records[0].status,
+            Some(ToolStatus::Failed(ToolErrorKind::InvalidArguments))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_calls_share_one_bucket() {
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let tool = FlakyTool {
+            kind: ToolErrorKind::Unavailable,
+            failures: 0,
+            calls: calls.clone(),
+        };
+        let registry = Arc::new(ToolRegistry::new().register(tool));
+        let limiter = Arc::new(RateLimiter::new(1).with_max_wait(Duration::from_millis(10)));
+        let graph = GraphBuilder::new()
+            .name("limited_tools")
+            .add_node(
+                ExecuteToolsNode::new("execute_tools", registry)
+                    .with_rate_limiter(Some(limiter.clone())),
+            )
+            .set_entry_point("execute_tools")
+            .add_conditional_edge("execute_tools", route_by_tools)
+            .compile()
+            .unwrap();
+        let runner = GraphRunner::new(graph, RunnerConfig::default().max_iterations(10));
+
+        let mut state = AgentState::with_system_and_user("system", "run the tool twice");
+        for id in ["call_1", "call_2"] {
+            state.tool_calls.push(ToolCall {
+                id: id.to_string(),
+                name: "flaky_tool".to_string(),
+                arguments: serde_json::json!({}),
+            });
+        }
+        let result = runner.invoke(state).await.unwrap();
+
+        // The bucket holds one call per second, and the second call may not
+        // wait that long
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+        let statuses: Vec<Option<ToolStatus>> = tool_call_records(&result)
+            .into_iter()
+            .map(|record| record.status)
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                Some(ToolStatus::Success),
+                Some(ToolStatus::Failed(ToolErrorKind::RateLimited))
+            ]
+        );
+    }
+
+    /// A tool that records how many of its calls overlap
+    #[derive(Clone, Default)]
+    struct OverlapTool {
+        active: Arc<std::sync::atomic::AtomicUsize>,
+        max_active: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl Tool for OverlapTool {
+        fn name(&self) -> &str {
+            "overlap_tool"
+        }
+
+        fn description(&self) -> &str {
+            "Echoes its label after a short delay"
+        }
+
+        fn parameters_schema(&self) -> serde_json::Value {
+            serde_json::json!({ "type": "object", "properties": {}, "required": [] })
+        }
+
+        async fn execute(&self, arguments: serde_json::Value) -> Result<String, NodeError> {
+            use std::sync::atomic::Ordering;
+
+            let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_active.fetch_max(active, Ordering::SeqCst);
+            // Later calls finish first, so ordering can't come from completion
+            let delay = 100 - 30 * arguments["order"].as_u64().unwrap_or(0);
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            self.active.fetch_sub(1, Ordering::SeqCst);
+            Ok(format!("result {}", arguments["order"]))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_calls_run_concurrently_in_order() {
+        let tool = OverlapTool::default();
+        let registry = Arc::new(ToolRegistry::new().register(tool.clone()));
+        let graph = GraphBuilder::new()
+            .name("overlap_tools")
+            .add_node(ExecuteToolsNode::new("execute_tools", registry))
+            .set_entry_point("execute_tools")
+            .add_conditional_edge("execute_tools", route_by_tools)
+            .compile()
+            .unwrap();
+        let runner = GraphRunner::new(graph, RunnerConfig::default().max_iterations(10));
+
+        let mut state = AgentState::with_system_and_user("system", "run the tools");
+        for order in 0..3 {
+            state.tool_calls.push(ToolCall {
+                id: format!("call_{}", order),
+                name: "overlap_tool".to_string(),
+                arguments: serde_json::json!({ "order": order }),
+            });
+        }
+        let result = runner.invoke(state).await.unwrap();
+
+        let outputs: Vec<&str> = result
+            .messages
+            .iter()
+            .filter(|m| m.role == MessageRole::Tool)
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(outputs, vec!["result 0", "result 1", "result 2"]);
+        let call_ids: Vec<String> = tool_metrics(&result)
+            .into_iter()
+            .map(|m| m.call_id)
+            .collect();
+        assert_eq!(call_ids, vec!["call_0", "call_1", "call_2"]);
+        assert!(tool.max_active.load(std::sync::atomic::Ordering::SeqCst) > 1);
     }
 
     #[test]
@@ -233,4 +2047,92 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_agent_graph_layouts_are_connected() {
+        for agent_type in AgentType::all() {
+            let layout = agent_graph_layout(*agent_type);
+            let ids: Vec<&str> = layout.node_ids().collect();
+            for (from, to) in layout.edge_pairs() {
+                assert!(
+                    ids.contains(&from),
+                    "{:?}: unknown node {}",
+                    agent_type,
+                    from
+                );
+                assert!(
+                    ids.contains(&to) || to == transitions::END,
+                    "{:?}: unknown node {}",
+                    agent_type,
+                    to
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_agent_graph_layouts_match_runs() {
+        let queries = [
+            "How do I configure SSO?",
+            "Show me an example of a Python skill",
+            "My workflow fails with error 401",
+            "Check the docs",
+            "What are the best practices for skill design?",
+            "Where is the API reference?",
+            "Validate my config",
+            "Hello",
+        ];
+
+        for &agent_type in AgentType::all() {
+            let layout = agent_graph_layout(agent_type);
+            let ids: Vec<&str> = layout.node_ids().collect();
+            let edges: Vec<(&str, &str)> = layout.edge_pairs().collect();
+
+            for query in queries {
+                let seen = Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+                let recorder = seen.clone();
+                let observer: NodeObserver = Arc::new(move |id, _description| {
+                    recorder.lock().unwrap().push(id.to_string());
+                });
+                let options = GraphOptions::default()
+                    .deterministic(true)
+                    .observer(observer);
+                let graph = build_agent_graph_with_options(agent_type, options).unwrap();
+                let runner = GraphRunner::new(graph, RunnerConfig::default().max_iterations(20));
+                let state = AgentState::with_system_and_user("system", query);
+                runner.invoke(state).await.unwrap();
+
+                let seen = seen.lock().unwrap();
+                assert_eq!(seen.first().map(String::as_str), layout.entry_id());
+                for id in seen.iter() {
+                    assert!(
+                        ids.contains(&id.as_str()),
+                        "{}: {} not in layout",
+                        agent_type,
+                        id
+                    );
+                }
+                for pair in seen.windows(2) {
+                    assert!(
+                        edges.contains(&(pair[0].as_str(), pair[1].as_str())),
+                        "{}: {} -> {} not in layout ({})",
+                        agent_type,
+                        pair[0],
+                        pair[1],
+                        query
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_export_agent_graph_dot() {
+        let dot = export_agent_graph_dot(AgentType::DocsHelper).unwrap();
+
+        assert!(dot.starts_with("digraph \"docs_helper_agent\""));
+        assert!(dot.contains("\"search_docs\" -> \"execute_search\";"));
+        assert!(dot.contains("\"execute_search\" -> \"broaden_search\" [style=dashed];"));
+        assert!(dot.contains("Retries the documentation search across all categories"));
+    }
 }
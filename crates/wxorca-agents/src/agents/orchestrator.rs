@@ -0,0 +1,408 @@
+//! Orchestrator
+//!
+//! Meta-agent that answers any question without the caller picking an agent.
+//! It finds the [`agent_candidates`] for the query and runs the specialized
+//! graph of the best one. When the pick is uncertain (more than one
+//! candidate), it runs the top two and merges their replies, each under a
+//! header naming the agent.
+
+use super::{
+    agent_candidates, awaiting_user_input, build_agent_graph_with_options, grounded_system_prompt,
+    is_followup, observe, previous_topic, promote_context, record_relevant_docs,
+    request_user_input, strip_degraded_notice, strip_followups, suggested_followups,
+    tool_call_offset, tool_call_records, tool_iterations, tool_metrics, turn_messages,
+    with_degraded_notice, with_followups, GraphOptions, ToolCallRecord,
+};
+use crate::state::{AgentType, Language};
+use oxidizedgraph::prelude::*;
+
+/// Most agents whose replies are merged into one
+const MAX_MERGED_AGENTS: usize = 2;
+
+/// Maximum graph iterations for each agent the orchestrator runs
+const AGENT_MAX_ITERATIONS: usize = 10;
+
+/// Build the orchestrator graph
+pub fn build_orchestrator_graph() -> Result<CompiledGraph, GraphError> {
+    build_orchestrator_graph_with_options(GraphOptions::default())
+}
+
+/// Build the orchestrator graph; `options` are passed on to every agent
+/// graph it runs
+pub fn build_orchestrator_graph_with_options(
+    options: GraphOptions,
+) -> Result<CompiledGraph, GraphError> {
+    let observer = options.observer.clone();
+
+    GraphBuilder::new()
+        .name("orchestrator")
+        .description("Dispatches each question to the best suited agent")
+        .add_node(observe(
+            OrchestrateNode::new("orchestrate", options),
+            &observer,
+        ))
+        .set_entry_point("orchestrate")
+        .add_edge("orchestrate", transitions::END)
+        .compile()
+}
+
+/// Get the agents whose replies make up the turn's response, primary first
+pub fn orchestrated_agents(state: &AgentState) -> Vec<AgentType> {
+    state
+        .get_context::<Vec<AgentType>>("orchestrated_agents")
+        .unwrap_or_default()
+}
+
+/// Runs the candidate agents' graphs on copies of the state and merges the
+/// results back
+struct OrchestrateNode {
+    id: String,
+    options: GraphOptions,
+}
+
+impl OrchestrateNode {
+    fn new(id: impl Into<String>, options: GraphOptions) -> Self {
+        Self {
+            id: id.into(),
+            options,
+        }
+    }
+
+    /// Run one agent's graph on a copy of `state` that uses its system prompt,
+    /// grounded in the user's context like the runner's
+    ///
+    /// `tool_call_offset` counts the calls made before this agent, so its
+    /// tool call ids don't repeat an earlier agent's.
+    async fn run_agent(
+        &self,
+        agent_type: AgentType,
+        state: &AgentState,
+        tool_call_offset: usize,
+    ) -> Result<AgentState, NodeError> {
+        let graph =
+            build_agent_graph_with_options(agent_type, self.options.clone()).map_err(|e| {
+                NodeError::Other(format!("Failed to build {} graph: {}", agent_type, e))
+            })?;
+
+        let mut agent_state = state.clone();
+        agent_state.set_context("tool_call_offset", serde_json::json!(tool_call_offset));
+        if let Some(system) = agent_state
+            .messages
+            .iter_mut()
+            .find(|m| m.role == MessageRole::System)
+        {
+            system.content = grounded_system_prompt(state, &self.options.system_prompt(agent_type));
+        }
+
+        GraphRunner::new(
+            graph,
+            RunnerConfig::default()
+                .max_iterations(AGENT_MAX_ITERATIONS)
+                .verbose(false),
+        )
+        .invoke(agent_state)
+        .await
+        .map_err(|e| NodeError::Other(format!("{} agent failed: {}", agent_type, e)))
+    }
+}
+
+#[async_trait::async_trait]
+impl NodeExecutor for OrchestrateNode {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Runs the best suited agent, or merges the top two when unsure")
+    }
+
+    async fn execute(&self, state: SharedState) -> Result<NodeOutput, NodeError> {
+        let initial = state
+            .read()
+            .map_err(|e| NodeError::Other(format!("Failed to read state: {}", e)))?
+            .clone();
+
+        let query = initial
+            .last_user_message()
+            .map(|m| m.content.clone())
+            .unwrap_or_default();
+        // Route a follow-up like "yes, the SaaS one" by the topic it continues
+        let query = match previous_topic(&initial) {
+            Some(topic) if is_followup(&query) => format!("{} {}", topic, query),
+            _ => query,
+        };
+
+        let mut agents = agent_candidates(&query);
+        agents.truncate(MAX_MERGED_AGENTS);
+        tracing::debug!(?agents, "Orchestrating turn");
+
+        let mut results = Vec::with_capacity(agents.len());
+        let mut offset = tool_call_offset(&initial);
+        for agent_type in &agents {
+            let result = self.run_agent(*agent_type, &initial, offset).await?;
+            offset += tool_call_records(&result).len();
+            results.push(result);
+        }
+
+        let merged = merge_results(&initial, &agents, results);
+        let mut guard = state
+            .write()
+            .map_err(|e| NodeError::Other(format!("Failed to write state: {}", e)))?;
+        *guard = merged;
+
+        Ok(NodeOutput::finish())
+    }
+}
+
+/// Combine the states returned by each agent into the turn's result
+///
+/// The first agent's state is the base. With more than one agent:
+/// - the reply is each agent's reply under a `# <agent name>` header,
+///   separated by rules, primary first
+/// - tool results and executed tool calls from later agents are added unless
+///   the same output, or a call with the same name and arguments, is already
+///   there; tool metrics and the recorded tool call and result messages
+///   keep every call, since each one ran
+/// - suggested follow-ups are combined, primary agent's first, and listed
+///   once at the end of the reply instead of after each agent's part
+/// - the escalation comes from the first agent that raised one
+/// - the turn awaits user input if any agent asked a clarifying question
+fn merge_results(
+    initial: &AgentState,
+    agents: &[AgentType],
+    results: Vec<AgentState>,
+) -> AgentState {
+    let turn_start = initial.messages.len();
+    let mut results = results.into_iter();
+    let Some(mut merged) = results.next() else {
+        return initial.clone();
+    };
+    // Each agent ran with its own system prompt; keep the conversation's
+    merged.messages[..turn_start].clone_from_slice(&initial.messages);
+
+    let language = merged
+        .get_context::<Language>("language")
+        .unwrap_or_default();
+    let mut replies = vec![last_reply(&merged, turn_start)];
+
+    let mut executed = merged
+        .get_context::<Vec<ToolCallRecord>>("executed_tool_calls")
+        .unwrap_or_default();
+    let mut metrics = tool_metrics(&merged);
+    let mut iterations = tool_iterations(&merged);
+    let mut suggestions = suggested_followups(&merged);
+    let mut transcript = turn_messages(&merged);
+
+    for result in results {
+        replies.push(last_reply(&result, turn_start));
+
+        for message in result.messages.iter().skip(turn_start) {
+            let duplicate = merged
+                .messages
+                .iter()
+                .any(|m| m.role == MessageRole::Tool && m.content == message.content);
+            if message.role == MessageRole::Tool && !duplicate {
+                merged.messages.push(message.clone());
+            }
+        }
+
+        for record in result
+            .get_context::<Vec<ToolCallRecord>>("executed_tool_calls")
+            .unwrap_or_default()
+        {
+            if !executed
+                .iter()
+                .any(|r| r.name == record.name && r.arguments == record.arguments)
+            {
+                executed.push(record);
+            }
+        }
+        metrics.extend(tool_metrics(&result));
+        transcript.extend(turn_messages(&result));
+        iterations += tool_iterations(&result);
+        for suggestion in suggested_followups(&result) {
+            if !suggestions.contains(&suggestion) {
+                suggestions.push(suggestion);
+            }
+        }
+
+        if awaiting_user_input(&result) {
+            request_user_input(&mut merged);
+        }
+
+        if merged
+            .get_context::<serde_json::Value>("escalation")
+            .is_none()
+        {
+            if let Some(escalation) = result.get_context::<serde_json::Value>("escalation") {
+                merged.set_context("escalation", escalation);
+            }
+        }
+    }
+
+    if agents.len() > 1 {
+        // Replace the primary reply with the merged one
+        if let Some(index) = merged.messages[turn_start..]
+            .iter()
+            .rposition(|m| m.role == MessageRole::Assistant)
+        {
+            merged.messages.remove(turn_start + index);
+        }
+        let mut degraded = false;
+        let reply = agents
+            .iter()
+            .zip(&replies)
+            .map(|(agent_type, reply)| {
+                let mut reply = strip_followups(reply, language).trim_end();
+                if let Some(stripped) = strip_degraded_notice(reply, language) {
+                    degraded = true;
+                    reply = stripped.trim_end();
+                }
+                format!("# {}\n\n{}", agent_type.display_name(), reply)
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n---\n\n");
+        let reply = if degraded {
+            with_degraded_notice(&reply, language)
+        } else {
+            reply
+        };
+        let reply = with_followups(&reply, &suggestions, language);
+        merged.add_assistant_message(&reply);
+
+        merged.set_context("executed_tool_calls", serde_json::json!(executed));
+        merged.set_context("tool_metrics", serde_json::json!(metrics));
+        merged.set_context("tool_iterations", serde_json::json!(iterations));
+        merged.set_context("suggestions", serde_json::json!(suggestions));
+        merged.set_context("turn_messages", serde_json::json!(transcript));
+
+        let tool_results: Vec<String> = merged.messages[turn_start..]
+            .iter()
+            .filter(|m| m.role == MessageRole::Tool)
+            .map(|m| m.content.clone())
+            .collect();
+        record_relevant_docs(&mut merged, &tool_results);
+        promote_context(&mut merged);
+    }
+
+    merged.set_context("orchestrated_agents", serde_json::json!(agents));
+    merged
+}
+
+/// The reply an agent added during the turn, if any
+fn last_reply(state: &AgentState, turn_start: usize) -> String {
+    state.messages[turn_start..]
+        .iter()
+        .rev()
+        .find(|m| m.role == MessageRole::Assistant)
+        .map(|m| m.content.clone())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::templates::CommonTemplates;
+
+    async fn orchestrate(query: &str) -> AgentState {
+        let graph =
+            build_orchestrator_graph_with_options(GraphOptions::default().deterministic(true))
+                .unwrap();
+        let runner = GraphRunner::new(graph, RunnerConfig::default().max_iterations(10));
+
+        let mut state = AgentState::with_system_and_user("system", query);
+        state.set_context("deterministic", serde_json::json!(true));
+        runner.invoke(state).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_single_candidate_runs_one_agent() {
+        let result = orchestrate("How do I configure SSO for my team?").await;
+
+        assert_eq!(orchestrated_agents(&result), vec![AgentType::AdminSetup]);
+        let reply = result.last_assistant_message().unwrap().content.clone();
+        assert!(!reply.starts_with(&format!("# {}", AgentType::AdminSetup.display_name())));
+        // The conversation keeps its own system prompt
+        assert_eq!(result.messages[0].content, "system");
+    }
+
+    #[tokio::test]
+    async fn test_uncertain_query_merges_two_agents() {
+        let result = orchestrate("My skill fails with an error, where can I find the docs?").await;
+
+        assert_eq!(
+            orchestrated_agents(&result),
+            vec![AgentType::Troubleshoot, AgentType::DocsHelper]
+        );
+        let reply = result.last_assistant_message().unwrap().content.clone();
+        assert!(reply.starts_with(&format!("# {}", AgentType::Troubleshoot.display_name())));
+        assert!(reply.contains(&format!(
+            "\n---\n\n# {}",
+            AgentType::DocsHelper.display_name()
+        )));
+        assert_eq!(
+            result
+                .messages
+                .iter()
+                .filter(|m| m.role == MessageRole::Assistant)
+                .count(),
+            1
+        );
+        // One list of follow-ups, combining both agents'
+        assert_eq!(reply.matches("### Related Questions").count(), 1);
+        let (_, followups) = reply.split_once("### Related Questions").unwrap();
+        for suggestion in suggested_followups(&result) {
+            assert!(followups.contains(&suggestion));
+        }
+
+        // Each agent's calls get their own ids
+        let ids: Vec<String> = turn_messages(&result)
+            .into_iter()
+            .filter(|m| m.is_tool_call())
+            .filter_map(|m| m.tool_call_id)
+            .collect();
+        assert!(ids.len() > 1);
+        for (i, id) in ids.iter().enumerate() {
+            assert!(!ids[..i].contains(id), "duplicate tool call id {}", id);
+        }
+
+        // A search both agents ran is only recorded once
+        let records = result
+            .get_context::<Vec<ToolCallRecord>>("executed_tool_calls")
+            .unwrap_or_default();
+        for (i, record) in records.iter().enumerate() {
+            assert!(!records[..i]
+                .iter()
+                .any(|r| r.name == record.name && r.arguments == record.arguments));
+        }
+    }
+
+    #[test]
+    fn test_merged_reply_has_one_degraded_notice() {
+        let initial = AgentState::with_system_and_user("system", "How do I set up a skill?");
+        let reply = |text: &str| {
+            let mut result = initial.clone();
+            let reply = with_degraded_notice(text, Language::English);
+            let reply = with_followups(&reply, &["Next?".to_string()], Language::English);
+            result.add_assistant_message(&reply);
+            result.set_context("suggestions", serde_json::json!(["Next?"]));
+            result
+        };
+        let agents = [AgentType::AdminSetup, AgentType::UsageAssistant];
+
+        let merged = merge_results(&initial, &agents, vec![reply("First"), reply("Second")]);
+
+        let reply = merged.last_assistant_message().unwrap().content.clone();
+        assert_eq!(reply.matches("temporarily unavailable").count(), 1);
+        assert!(reply.contains("First\n\n---\n\n# "));
+        let (body, _) = reply.split_once("### Related Questions").unwrap();
+        assert!(body
+            .trim_end()
+            .ends_with(CommonTemplates::for_language(Language::English).degraded_notice));
+    }
+
+    #[test]
+    fn test_orchestrator_graph_builds() {
+        assert!(build_orchestrator_graph().is_ok());
+    }
+}
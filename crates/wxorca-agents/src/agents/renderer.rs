@@ -0,0 +1,145 @@
+//! Pluggable response rendering
+//!
+//! Response nodes gather the turn's context and hand it to a
+//! [`ResponseRenderer`] to produce the reply. Each agent ships a default
+//! renderer with its built-in templates; pass another through
+//! [`GraphOptions::renderer_for`](super::GraphOptions::renderer_for) to change the
+//! wording of one agent without reimplementing its node.
+
+use super::tool_call_records;
+use crate::state::{Language, UserRole};
+use crate::tools::ToolStatus;
+use oxidizedgraph::prelude::*;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+
+/// Context keys copied into [`RenderContext::values`]
+const RENDER_CONTEXT_KEYS: &[&str] = &[
+    "user_intent",
+    "intent_confidence",
+    "deployment_type",
+    "diagnosis",
+    "bp_topic",
+    "docs_category",
+    "max_results_in_response",
+];
+
+/// Docs or examples listed in a reply unless the `max_results_in_response`
+/// context key says otherwise
+pub const DEFAULT_MAX_RESULTS_IN_RESPONSE: usize = 3;
+
+/// Turns a turn's context into the agent's reply
+pub trait ResponseRenderer: Send + Sync {
+    /// Render the reply to `query`
+    fn render(&self, query: &str, context: &RenderContext) -> String;
+
+    /// Whether the reply to `query` asks the user something the agent needs
+    /// to know before it can finish, e.g. which deployment they are on
+    fn asks_for_input(&self, _query: &str, _context: &RenderContext) -> bool {
+        false
+    }
+}
+
+/// What a [`ResponseRenderer`] knows about the turn
+#[derive(Debug, Clone, Default)]
+pub struct RenderContext {
+    /// Output of each tool run during the turn, in order
+    pub tool_results: Vec<String>,
+    /// Status of each tool call executed during the turn, in order
+    pub tool_statuses: Vec<ToolStatus>,
+    /// Language to reply in
+    pub language: Language,
+    /// Role of the user, for how much depth to go into
+    pub user_role: UserRole,
+    /// Values set by earlier nodes (`diagnosis`, `bp_topic`, ...), by context key
+    pub values: HashMap<String, serde_json::Value>,
+}
+
+impl RenderContext {
+    /// Snapshot the parts of `state` a renderer needs
+    pub(crate) fn from_state(state: &AgentState) -> Self {
+        let tool_results = state
+            .messages
+            .iter()
+            .filter(|m| m.role == MessageRole::Tool)
+            .map(|m| m.content.clone())
+            .collect();
+        let tool_statuses = tool_call_records(state)
+            .into_iter()
+            .filter_map(|record| record.status)
+            .collect();
+        let values = RENDER_CONTEXT_KEYS
+            .iter()
+            .filter_map(|key| {
+                state
+                    .get_context::<serde_json::Value>(key)
+                    .map(|value| (key.to_string(), value))
+            })
+            .collect();
+
+        Self {
+            tool_results,
+            tool_statuses,
+            language: state
+                .get_context::<Language>("language")
+                .unwrap_or_default(),
+            user_role: state
+                .get_context::<UserRole>("user_role")
+                .unwrap_or_default(),
+            values,
+        }
+    }
+
+    /// Set a context value
+    pub fn with_value(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
+        self.values.insert(key.into(), value);
+        self
+    }
+
+    /// Whether every tool call that ran failed, as opposed to finding nothing
+    pub fn all_tools_failed(&self) -> bool {
+        !self.tool_statuses.is_empty() && self.tool_statuses.iter().all(ToolStatus::is_failure)
+    }
+
+    /// How many docs or code examples to list in the reply
+    pub fn max_results(&self) -> usize {
+        self.get::<usize>("max_results_in_response")
+            .unwrap_or(DEFAULT_MAX_RESULTS_IN_RESPONSE)
+    }
+
+    /// Get a context value, if it is set and has the expected shape
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        self.values
+            .get(key)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_context_from_state() {
+        let mut state = AgentState::with_system_and_user("system", "¿Cómo configuro SSO?");
+        state.set_context("language", serde_json::json!(Language::Spanish));
+        state.set_context("bp_topic", serde_json::json!("security"));
+        state.set_context("original_query", serde_json::json!("not copied"));
+        state.add_tool_result("call_1", "[]");
+
+        let context = RenderContext::from_state(&state);
+
+        assert_eq!(context.tool_results, vec!["[]".to_string()]);
+        assert_eq!(context.language, Language::Spanish);
+        assert_eq!(
+            context.get::<String>("bp_topic").as_deref(),
+            Some("security")
+        );
+        assert_eq!(context.get::<usize>("bp_topic"), None);
+        assert!(!context.values.contains_key("original_query"));
+        assert_eq!(context.max_results(), DEFAULT_MAX_RESULTS_IN_RESPONSE);
+
+        state.set_context("max_results_in_response", serde_json::json!(1));
+        assert_eq!(RenderContext::from_state(&state).max_results(), 1);
+    }
+}
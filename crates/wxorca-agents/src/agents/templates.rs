@@ -0,0 +1,846 @@
+//! Localized response templates
+//!
+//! The template-based response nodes pick their wording from a per-language
+//! table. Adding a language means adding a constant for each table below and
+//! a match arm in its `for_language` lookup.
+
+use crate::state::{AgentType, Language};
+
+/// Wording used by the admin setup agent
+pub(crate) struct AdminTemplates {
+    /// Asked before detailed steps when the deployment type is unknown
+    pub deployment_question: &'static str,
+    pub saas_notes_heading: &'static str,
+    pub on_prem_notes_heading: &'static str,
+    pub setup_heading: &'static str,
+    pub setup_intro_saas: &'static str,
+    pub setup_intro_on_prem: &'static str,
+    /// First setup step, for SaaS, on-prem and unknown deployments
+    pub setup_first_step: [&'static str; 3],
+    /// Setup steps after the first
+    pub setup_steps: &'static str,
+    /// Setup notes as (SaaS, on-prem)
+    pub setup_notes: (&'static str, &'static str),
+    pub users: &'static str,
+    pub security_heading: &'static str,
+    pub security_practices: &'static str,
+    pub security_notes: (&'static str, &'static str),
+    pub integration_heading: &'static str,
+    pub integration_steps: &'static str,
+    pub integration_notes: (&'static str, &'static str),
+    /// Reply when the query doesn't match a known admin topic
+    pub general: &'static str,
+    /// Appended to topic replies for developers
+    pub developer_notes: &'static str,
+    /// Appended when documentation search results are available
+    pub related_docs: &'static str,
+}
+
+impl AdminTemplates {
+    pub fn for_language(language: Language) -> &'static Self {
+        match language {
+            Language::English => &ADMIN_EN,
+            Language::Spanish => &ADMIN_ES,
+        }
+    }
+}
+
+const ADMIN_EN: AdminTemplates = AdminTemplates {
+    deployment_question: "**Are you on SaaS or on-premises?** Some steps differ \
+        between IBM-hosted SaaS and self-managed (Cloud Pak for Data) installations, so let me \
+        know and I'll tailor the details. Here's the general outline in the meantime:\n\n",
+    saas_notes_heading: "### SaaS Notes\n\n",
+    on_prem_notes_heading: "### On-Premises Notes\n\n",
+    setup_heading: "## WatsonX Orchestrate Setup Guide\n\n",
+    setup_intro_saas: "Here's how to set up WatsonX Orchestrate SaaS:\n\n",
+    setup_intro_on_prem: "Here's how to set up WatsonX Orchestrate on-premises:\n\n",
+    setup_first_step: [
+        "1. **Access the Admin Console**: Open your WXO service instance from the IBM Cloud (or AWS) console and launch it with admin credentials.\n\n",
+        "1. **Install the Platform**: Deploy WXO on Cloud Pak for Data with the operator or Helm chart, confirm the pods are running (`oc get pods -n <namespace>`), then open the admin console from your cluster route.\n\n",
+        "1. **Access the Admin Console**: Navigate to your WXO instance and log in with admin credentials.\n\n",
+    ],
+    setup_steps: "2. **Configure Identity Provider**: Set up SSO or local authentication under Settings > Security.\n\n\
+        3. **Create User Groups**: Define roles and permissions in Settings > Users & Teams.\n\n\
+        4. **Set Up Integrations**: Connect external services in Settings > Integrations.\n\n",
+    setup_notes: (
+        "- Plans, regions and instance limits are managed from the IBM Cloud console\n\
+         - IBM applies upgrades and patches for you\n\n",
+        "- Size worker nodes and storage classes before installing\n\
+         - Plan upgrades with the Cloud Pak for Data release cadence\n\
+         - Back up the platform's persistent volumes regularly\n\n",
+    ),
+    users: "## User Management\n\n\
+        To manage users in WatsonX Orchestrate:\n\n\
+        1. Go to **Settings > Users & Teams**\n\
+        2. Click **Add User** to invite new users\n\
+        3. Assign appropriate roles (Admin, Developer, User)\n\
+        4. Configure team memberships for collaboration\n\n\
+        **Tip**: Use groups to manage permissions at scale.\n\n",
+    security_heading: "## Security Configuration\n\n",
+    security_practices: "Security best practices for WatsonX Orchestrate:\n\n\
+        - Enable **Multi-Factor Authentication** (MFA) for all admin accounts\n\
+        - Configure **Session Timeouts** appropriately\n\
+        - Set up **Audit Logging** to track changes\n\
+        - Review **API Key** permissions regularly\n\
+        - Use **Least Privilege** principle for user roles\n\n",
+    security_notes: (
+        "- Connect SSO through IBM Cloud IAM or your SAML identity provider\n\
+         - TLS and platform patching are handled by IBM\n\n",
+        "- Install your own TLS certificates on the OpenShift routes\n\
+         - Keep credentials in Kubernetes secrets and rotate them regularly\n\
+         - Restrict cluster access with network policies\n\n",
+    ),
+    integration_heading: "## Integration Setup\n\n",
+    integration_steps: "To configure integrations:\n\n\
+        1. Navigate to **Settings > Integrations**\n\
+        2. Select the integration type (Salesforce, ServiceNow, etc.)\n\
+        3. Provide the required credentials\n\
+        4. Configure sync settings and permissions\n\
+        5. Test the connection before enabling\n\n",
+    integration_notes: (
+        "- Allow-list the WXO egress IP ranges in the target service's firewall\n\n",
+        "- Make sure cluster egress (proxies, network policies) can reach the external service\n\
+         - Import the service's CA certificate if it uses a private CA\n\n",
+    ),
+    general: "I'm here to help you with WatsonX Orchestrate administration.\n\n\
+        I can assist with:\n\
+        - Initial setup and configuration\n\
+        - User and team management\n\
+        - Security settings\n\
+        - Integration configuration\n\
+        - API key management\n\n\
+        What would you like help with?",
+    developer_notes: "\n\n### Automating This\n\n\
+        Everything above can also be scripted: the WatsonX Orchestrate REST API covers users, \
+        security settings and connections, and the ADK CLI (`orchestrate connections`, \
+        `orchestrate tools import`) lets you keep the configuration in version control.",
+    related_docs: "\n\n---\n\n**📚 Related Documentation:**\n\
+        I found some relevant documentation that might help. \
+        Check the search results above for more details.",
+};
+
+const ADMIN_ES: AdminTemplates = AdminTemplates {
+    deployment_question: "**¿Usas SaaS o una instalación local?** Algunos pasos cambian \
+        entre SaaS alojado por IBM y las instalaciones autogestionadas (Cloud Pak for Data), así \
+        que avísame y ajustaré los detalles. Mientras tanto, este es el esquema general:\n\n",
+    saas_notes_heading: "### Notas para SaaS\n\n",
+    on_prem_notes_heading: "### Notas para instalaciones locales\n\n",
+    setup_heading: "## Guía de configuración de WatsonX Orchestrate\n\n",
+    setup_intro_saas: "Así se configura WatsonX Orchestrate SaaS:\n\n",
+    setup_intro_on_prem: "Así se configura WatsonX Orchestrate en una instalación local:\n\n",
+    setup_first_step: [
+        "1. **Accede a la consola de administración**: Abre tu instancia de WXO desde la consola de IBM Cloud (o AWS) e iníciala con credenciales de administrador.\n\n",
+        "1. **Instala la plataforma**: Despliega WXO en Cloud Pak for Data con el operador o el Helm chart, comprueba que los pods estén en ejecución (`oc get pods -n <namespace>`) y abre la consola de administración desde la ruta del clúster.\n\n",
+        "1. **Accede a la consola de administración**: Ve a tu instancia de WXO e inicia sesión con credenciales de administrador.\n\n",
+    ],
+    setup_steps: "2. **Configura el proveedor de identidad**: Configura SSO o la autenticación local en Settings > Security.\n\n\
+        3. **Crea grupos de usuarios**: Define roles y permisos en Settings > Users & Teams.\n\n\
+        4. **Configura las integraciones**: Conecta servicios externos en Settings > Integrations.\n\n",
+    setup_notes: (
+        "- Los planes, regiones y límites de instancia se gestionan desde la consola de IBM Cloud\n\
+         - IBM aplica las actualizaciones y parches por ti\n\n",
+        "- Dimensiona los nodos de trabajo y las clases de almacenamiento antes de instalar\n\
+         - Planifica las actualizaciones según el calendario de versiones de Cloud Pak for Data\n\
+         - Haz copias de seguridad periódicas de los volúmenes persistentes de la plataforma\n\n",
+    ),
+    users: "## Gestión de usuarios\n\n\
+        Para gestionar usuarios en WatsonX Orchestrate:\n\n\
+        1. Ve a **Settings > Users & Teams**\n\
+        2. Haz clic en **Add User** para invitar a nuevos usuarios\n\
+        3. Asigna los roles adecuados (Admin, Developer, User)\n\
+        4. Configura la pertenencia a equipos para colaborar\n\n\
+        **Consejo**: Usa grupos para gestionar permisos a escala.\n\n",
+    security_heading: "## Configuración de seguridad\n\n",
+    security_practices: "Buenas prácticas de seguridad para WatsonX Orchestrate:\n\n\
+        - Activa la **autenticación multifactor** (MFA) en todas las cuentas de administrador\n\
+        - Configura **tiempos de espera de sesión** adecuados\n\
+        - Activa el **registro de auditoría** para seguir los cambios\n\
+        - Revisa con regularidad los permisos de las **claves de API**\n\
+        - Aplica el principio de **mínimo privilegio** en los roles de usuario\n\n",
+    security_notes: (
+        "- Conecta SSO mediante IBM Cloud IAM o tu proveedor de identidad SAML\n\
+         - IBM se encarga de TLS y de los parches de la plataforma\n\n",
+        "- Instala tus propios certificados TLS en las rutas de OpenShift\n\
+         - Guarda las credenciales en secretos de Kubernetes y rótalas con regularidad\n\
+         - Restringe el acceso al clúster con políticas de red\n\n",
+    ),
+    integration_heading: "## Configuración de integraciones\n\n",
+    integration_steps: "Para configurar integraciones:\n\n\
+        1. Ve a **Settings > Integrations**\n\
+        2. Selecciona el tipo de integración (Salesforce, ServiceNow, etc.)\n\
+        3. Proporciona las credenciales necesarias\n\
+        4. Configura la sincronización y los permisos\n\
+        5. Prueba la conexión antes de activarla\n\n",
+    integration_notes: (
+        "- Añade los rangos de IP de salida de WXO a la lista de permitidos del firewall del servicio\n\n",
+        "- Comprueba que la salida del clúster (proxies, políticas de red) llega al servicio externo\n\
+         - Importa el certificado de la CA del servicio si usa una CA privada\n\n",
+    ),
+    general: "Estoy aquí para ayudarte con la administración de WatsonX Orchestrate.\n\n\
+        Puedo ayudarte con:\n\
+        - Instalación y configuración inicial\n\
+        - Gestión de usuarios y equipos\n\
+        - Ajustes de seguridad\n\
+        - Configuración de integraciones\n\
+        - Gestión de claves de API\n\n\
+        ¿Con qué necesitas ayuda?",
+    developer_notes: "\n\n### Automatización\n\n\
+        Todo lo anterior también se puede automatizar: la API REST de WatsonX Orchestrate cubre \
+        usuarios, ajustes de seguridad y conexiones, y la CLI del ADK (`orchestrate connections`, \
+        `orchestrate tools import`) te permite guardar la configuración en control de versiones.",
+    related_docs: "\n\n---\n\n**📚 Documentación relacionada:**\n\
+        He encontrado documentación que puede ayudarte. \
+        Revisa los resultados de búsqueda anteriores para más detalles.",
+};
+
+/// Wording for one troubleshooting category
+pub(crate) struct IssueText {
+    /// Category name shown in the analysis heading
+    pub label: &'static str,
+    pub likely_causes: &'static [&'static str],
+    pub suggested_checks: &'static [&'static str],
+    /// Category-specific advice closing the diagnosis
+    pub advice: &'static str,
+}
+
+/// Wording used by the troubleshooting agent
+pub(crate) struct TroubleshootTemplates {
+    pub analysis_heading: &'static str,
+    pub severity_label: &'static str,
+    /// Severity names as (high, medium, low)
+    pub severities: (&'static str, &'static str, &'static str),
+    pub likely_causes_heading: &'static str,
+    pub steps_heading: &'static str,
+    /// Text around the category name when the diagnosis is uncertain, as
+    /// (before, after)
+    pub hedge: (&'static str, &'static str),
+    pub authentication: IssueText,
+    pub performance: IssueText,
+    pub integration: IssueText,
+    pub execution: IssueText,
+    pub general: IssueText,
+    pub closing: &'static str,
+}
+
+impl TroubleshootTemplates {
+    pub fn for_language(language: Language) -> &'static Self {
+        match language {
+            Language::English => &TROUBLESHOOT_EN,
+            Language::Spanish => &TROUBLESHOOT_ES,
+        }
+    }
+
+    /// Wording for a built-in diagnosis category, or `None` for categories
+    /// added by custom rules
+    pub fn issue(&self, category: &str) -> Option<&IssueText> {
+        match category {
+            "authentication" => Some(&self.authentication),
+            "performance" => Some(&self.performance),
+            "integration" => Some(&self.integration),
+            "execution" => Some(&self.execution),
+            "general" => Some(&self.general),
+            _ => None,
+        }
+    }
+}
+
+const TROUBLESHOOT_EN: TroubleshootTemplates = TroubleshootTemplates {
+    analysis_heading: "## 🔍 Issue Analysis: ",
+    severity_label: "**Severity**: ",
+    severities: ("🔴 High", "🟡 Medium", "🟢 Low"),
+    likely_causes_heading: "### Likely Causes\n",
+    steps_heading: "### Troubleshooting Steps\n\n",
+    hedge: (
+        "It sounds like you might be hitting a problem with ",
+        ", but I'm not certain. If the steps below don't fit, tell me more about what you're seeing.",
+    ),
+    authentication: IssueText {
+        label: "AUTHENTICATION",
+        likely_causes: &[
+            "Expired credentials or tokens",
+            "Incorrect SSO configuration",
+            "User permissions not set correctly",
+            "API key revoked or expired",
+        ],
+        suggested_checks: &[
+            "Verify credentials are correct",
+            "Check token expiration",
+            "Review user permissions",
+            "Test SSO configuration",
+        ],
+        advice: "### Quick Fix Attempts\n\
+            1. Clear browser cache and cookies\n\
+            2. Try logging out and back in\n\
+            3. Check if your session has expired\n\
+            4. Verify your account is active\n\n\
+            **⚠️ If issues persist**, contact your administrator to verify your account permissions.",
+    },
+    performance: IssueText {
+        label: "PERFORMANCE",
+        likely_causes: &[
+            "High system load",
+            "Network latency",
+            "Large data volumes",
+            "Resource constraints",
+        ],
+        suggested_checks: &[
+            "Check system status page",
+            "Monitor network connectivity",
+            "Review workflow complexity",
+            "Check concurrent user count",
+        ],
+        advice: "### Quick Fix Attempts\n\
+            1. Refresh the page\n\
+            2. Check your internet connection\n\
+            3. Try a different browser\n\
+            4. Check the WXO status page for outages\n\n\
+            **💡 Tip**: If working with large datasets, try processing in smaller batches.",
+    },
+    integration: IssueText {
+        label: "INTEGRATION",
+        likely_causes: &[
+            "External service unavailable",
+            "Credentials expired",
+            "API rate limit exceeded",
+            "Configuration mismatch",
+        ],
+        suggested_checks: &[
+            "Verify external service status",
+            "Check integration credentials",
+            "Review API rate limits",
+            "Test connection settings",
+        ],
+        advice: "### Quick Fix Attempts\n\
+            1. Test the external service directly\n\
+            2. Re-authenticate the integration\n\
+            3. Check for API version changes\n\
+            4. Review integration logs\n\n\
+            **⚠️ Note**: External service issues are outside WXO control.",
+    },
+    execution: IssueText {
+        label: "EXECUTION",
+        likely_causes: &[
+            "Invalid input data",
+            "Missing required parameters",
+            "Skill configuration error",
+            "Dependency failure",
+        ],
+        suggested_checks: &[
+            "Review input data format",
+            "Check required parameters",
+            "Validate skill configuration",
+            "Check execution logs",
+        ],
+        advice: "### Quick Fix Attempts\n\
+            1. Verify input data format\n\
+            2. Check for required fields\n\
+            3. Review skill/workflow logs\n\
+            4. Test with simpler inputs\n\n\
+            **💡 Tip**: Use the validation tool to check your configuration.",
+    },
+    general: IssueText {
+        label: "GENERAL",
+        likely_causes: &[
+            "Configuration issue",
+            "User error",
+            "Temporary system issue",
+        ],
+        suggested_checks: &[
+            "Describe the issue in more detail",
+            "Check system status",
+            "Review recent changes",
+        ],
+        advice: "### Need More Information\n\
+            Could you provide more details about:\n\
+            - What exactly happened?\n\
+            - Any error messages shown?\n\
+            - When did this start?\n\
+            - Any recent changes?\n",
+    },
+    closing: "\n\n---\n\n\
+        **Still having issues?** I can help you escalate to IBM Support if needed.",
+};
+
+const TROUBLESHOOT_ES: TroubleshootTemplates = TroubleshootTemplates {
+    analysis_heading: "## 🔍 Análisis del problema: ",
+    severity_label: "**Gravedad**: ",
+    severities: ("🔴 Alta", "🟡 Media", "🟢 Baja"),
+    likely_causes_heading: "### Causas probables\n",
+    steps_heading: "### Pasos para resolverlo\n\n",
+    hedge: (
+        "Parece que podrías tener un problema de ",
+        ", aunque no estoy seguro. Si los pasos siguientes no encajan, cuéntame más sobre lo que ves.",
+    ),
+    authentication: IssueText {
+        label: "AUTENTICACIÓN",
+        likely_causes: &[
+            "Credenciales o tokens caducados",
+            "Configuración de SSO incorrecta",
+            "Permisos de usuario mal configurados",
+            "Clave de API revocada o caducada",
+        ],
+        suggested_checks: &[
+            "Verifica que las credenciales sean correctas",
+            "Comprueba la caducidad del token",
+            "Revisa los permisos del usuario",
+            "Prueba la configuración de SSO",
+        ],
+        advice: "### Soluciones rápidas\n\
+            1. Borra la caché y las cookies del navegador\n\
+            2. Cierra sesión y vuelve a iniciarla\n\
+            3. Comprueba si tu sesión ha caducado\n\
+            4. Verifica que tu cuenta esté activa\n\n\
+            **⚠️ Si el problema persiste**, pide a tu administrador que revise los permisos de tu cuenta.",
+    },
+    performance: IssueText {
+        label: "RENDIMIENTO",
+        likely_causes: &[
+            "Carga alta del sistema",
+            "Latencia de red",
+            "Grandes volúmenes de datos",
+            "Recursos insuficientes",
+        ],
+        suggested_checks: &[
+            "Consulta la página de estado del sistema",
+            "Supervisa la conectividad de red",
+            "Revisa la complejidad del flujo de trabajo",
+            "Comprueba el número de usuarios simultáneos",
+        ],
+        advice: "### Soluciones rápidas\n\
+            1. Recarga la página\n\
+            2. Comprueba tu conexión a internet\n\
+            3. Prueba con otro navegador\n\
+            4. Consulta la página de estado de WXO por si hay incidencias\n\n\
+            **💡 Consejo**: Si trabajas con grandes conjuntos de datos, procésalos en lotes más pequeños.",
+    },
+    integration: IssueText {
+        label: "INTEGRACIÓN",
+        likely_causes: &[
+            "Servicio externo no disponible",
+            "Credenciales caducadas",
+            "Límite de peticiones de la API superado",
+            "Configuración incoherente",
+        ],
+        suggested_checks: &[
+            "Verifica el estado del servicio externo",
+            "Comprueba las credenciales de la integración",
+            "Revisa los límites de peticiones de la API",
+            "Prueba la configuración de la conexión",
+        ],
+        advice: "### Soluciones rápidas\n\
+            1. Prueba el servicio externo directamente\n\
+            2. Vuelve a autenticar la integración\n\
+            3. Comprueba si ha cambiado la versión de la API\n\
+            4. Revisa los registros de la integración\n\n\
+            **⚠️ Nota**: Los problemas de servicios externos quedan fuera del control de WXO.",
+    },
+    execution: IssueText {
+        label: "EJECUCIÓN",
+        likely_causes: &[
+            "Datos de entrada no válidos",
+            "Faltan parámetros obligatorios",
+            "Error en la configuración de la habilidad",
+            "Fallo de una dependencia",
+        ],
+        suggested_checks: &[
+            "Revisa el formato de los datos de entrada",
+            "Comprueba los parámetros obligatorios",
+            "Valida la configuración de la habilidad",
+            "Consulta los registros de ejecución",
+        ],
+        advice: "### Soluciones rápidas\n\
+            1. Verifica el formato de los datos de entrada\n\
+            2. Comprueba los campos obligatorios\n\
+            3. Revisa los registros de la habilidad o del flujo de trabajo\n\
+            4. Prueba con entradas más sencillas\n\n\
+            **💡 Consejo**: Usa la herramienta de validación para comprobar tu configuración.",
+    },
+    general: IssueText {
+        label: "GENERAL",
+        likely_causes: &[
+            "Problema de configuración",
+            "Error de uso",
+            "Problema temporal del sistema",
+        ],
+        suggested_checks: &[
+            "Describe el problema con más detalle",
+            "Consulta el estado del sistema",
+            "Revisa los cambios recientes",
+        ],
+        advice: "### Necesito más información\n\
+            ¿Podrías darme más detalles sobre lo siguiente?\n\
+            - ¿Qué ocurrió exactamente?\n\
+            - ¿Apareció algún mensaje de error?\n\
+            - ¿Cuándo empezó?\n\
+            - ¿Hubo algún cambio reciente?\n",
+    },
+    closing: "\n\n---\n\n\
+        **¿Sigues teniendo problemas?** Puedo ayudarte a escalarlo al soporte de IBM si es necesario.",
+};
+
+/// Wording shared by every agent
+pub(crate) struct CommonTemplates {
+    /// Asked instead of searching when the detected intent is uncertain
+    pub clarify_intent: &'static str,
+    /// Appended when every live lookup failed
+    pub degraded_notice: &'static str,
+    /// Heads the list of docs the turn's searches found
+    pub found_docs_heading: &'static str,
+    /// Returned instead of running the agent when a message is flagged
+    pub refusal: &'static str,
+}
+
+impl CommonTemplates {
+    pub fn for_language(language: Language) -> &'static Self {
+        match language {
+            Language::English => &COMMON_EN,
+            Language::Spanish => &COMMON_ES,
+        }
+    }
+}
+
+const COMMON_EN: CommonTemplates = CommonTemplates {
+    clarify_intent: "I want to make sure I point you in the right direction. \
+        Are you looking for **documentation**, a **code example**, or a **check of your \
+        configuration**? A few more details about what you're trying to do will help.",
+    degraded_notice: "⚠️ Live documentation lookup is temporarily unavailable, so this answer is \
+        based on general guidance rather than the latest docs.",
+    found_docs_heading: "### 📚 Related Documentation\n\n",
+    refusal: "I'm sorry, but I can't help with that message. \
+        I'm happy to answer questions about setting up, using or troubleshooting \
+        WatsonX Orchestrate.",
+};
+
+const COMMON_ES: CommonTemplates = CommonTemplates {
+    clarify_intent: "Quiero asegurarme de orientarte bien. \
+        ¿Buscas **documentación**, un **ejemplo de código** o que **revise tu configuración**? \
+        Cuéntame un poco más sobre lo que intentas hacer.",
+    degraded_notice: "⚠️ La consulta de documentación en vivo no está disponible temporalmente, \
+        así que esta respuesta se basa en recomendaciones generales y no en la \
+        documentación más reciente.",
+    found_docs_heading: "### 📚 Documentación relacionada\n\n",
+    refusal: "Lo siento, pero no puedo ayudarte con ese mensaje. \
+        Con gusto respondo preguntas sobre cómo configurar, usar o solucionar problemas de \
+        WatsonX Orchestrate.",
+};
+
+/// Follow-up questions for one agent's topic, in every language
+pub(crate) struct FollowupEntry {
+    pub agent: AgentType,
+    /// Matched against detected topics (e.g. `security`) and free text in
+    /// any language; empty for the agent's fallback entry
+    pub keywords: &'static [&'static str],
+    pub en: &'static [&'static str],
+    pub es: &'static [&'static str],
+}
+
+impl FollowupEntry {
+    pub fn questions(&self, language: Language) -> &'static [&'static str] {
+        match language {
+            Language::English => self.en,
+            Language::Spanish => self.es,
+        }
+    }
+}
+
+/// Follow-up questions appended to replies, checked in order; an agent's
+/// entry without keywords is its fallback
+pub(crate) const FOLLOWUPS: &[FollowupEntry] = &[
+    FollowupEntry {
+        agent: AgentType::AdminSetup,
+        keywords: &["sso", "saml", "identity", "identidad"],
+        en: &[
+            "How do I map identity provider groups to roles?",
+            "How do I test SSO before enforcing it?",
+        ],
+        es: &[
+            "¿Cómo asigno los grupos del proveedor de identidad a roles?",
+            "¿Cómo pruebo el SSO antes de hacerlo obligatorio?",
+        ],
+    },
+    FollowupEntry {
+        agent: AgentType::AdminSetup,
+        keywords: &["user", "role", "permission", "rbac", "usuario", "permiso"],
+        en: &[
+            "How do I set up RBAC?",
+            "How do I audit who has admin access?",
+        ],
+        es: &[
+            "¿Cómo configuro RBAC?",
+            "¿Cómo audito quién tiene acceso de administrador?",
+        ],
+    },
+    FollowupEntry {
+        agent: AgentType::AdminSetup,
+        keywords: &[
+            "integration",
+            "connect",
+            "salesforce",
+            "servicenow",
+            "integraci",
+            "conect",
+        ],
+        en: &[
+            "How do I store integration credentials securely?",
+            "How do I monitor integration health?",
+        ],
+        es: &[
+            "¿Cómo guardo de forma segura las credenciales de las integraciones?",
+            "¿Cómo superviso el estado de las integraciones?",
+        ],
+    },
+    FollowupEntry {
+        agent: AgentType::AdminSetup,
+        keywords: &[],
+        en: &[
+            "How do I configure SSO?",
+            "How do I set up RBAC?",
+            "How do I connect my first integration?",
+        ],
+        es: &[
+            "¿Cómo configuro SSO?",
+            "¿Cómo configuro RBAC?",
+            "¿Cómo conecto mi primera integración?",
+        ],
+    },
+    FollowupEntry {
+        agent: AgentType::UsageAssistant,
+        keywords: &["workflow", "automation", "flujo", "automatizaci"],
+        en: &[
+            "How do I add error handling to a workflow?",
+            "How do I trigger a workflow on a schedule?",
+        ],
+        es: &[
+            "¿Cómo añado gestión de errores a un flujo de trabajo?",
+            "¿Cómo ejecuto un flujo de trabajo de forma programada?",
+        ],
+    },
+    FollowupEntry {
+        agent: AgentType::UsageAssistant,
+        keywords: &["skill", "catalog", "habilidad", "catálogo"],
+        en: &[
+            "How do I share a skill with my team?",
+            "How do I test a skill before publishing it?",
+        ],
+        es: &[
+            "¿Cómo comparto una habilidad con mi equipo?",
+            "¿Cómo pruebo una habilidad antes de publicarla?",
+        ],
+    },
+    FollowupEntry {
+        agent: AgentType::UsageAssistant,
+        keywords: &[],
+        en: &[
+            "How do I create my first skill?",
+            "How do I chain skills into a workflow?",
+        ],
+        es: &[
+            "¿Cómo creo mi primera habilidad?",
+            "¿Cómo encadeno habilidades en un flujo de trabajo?",
+        ],
+    },
+    FollowupEntry {
+        agent: AgentType::Troubleshoot,
+        keywords: &["authentication"],
+        en: &[
+            "How do I rotate API keys?",
+            "How do I check my SSO configuration?",
+        ],
+        es: &[
+            "¿Cómo roto las claves de API?",
+            "¿Cómo compruebo mi configuración de SSO?",
+        ],
+    },
+    FollowupEntry {
+        agent: AgentType::Troubleshoot,
+        keywords: &["performance"],
+        en: &[
+            "How do I set timeouts for slow skills?",
+            "How do I find which step of a workflow is slow?",
+        ],
+        es: &[
+            "¿Cómo configuro tiempos de espera para habilidades lentas?",
+            "¿Cómo encuentro qué paso de un flujo de trabajo es lento?",
+        ],
+    },
+    FollowupEntry {
+        agent: AgentType::Troubleshoot,
+        keywords: &["integration"],
+        en: &[
+            "How do I test an integration's connection?",
+            "How do I handle API rate limits?",
+        ],
+        es: &[
+            "¿Cómo pruebo la conexión de una integración?",
+            "¿Cómo gestiono los límites de frecuencia de la API?",
+        ],
+    },
+    FollowupEntry {
+        agent: AgentType::Troubleshoot,
+        keywords: &["execution"],
+        en: &[
+            "How do I read a skill's execution logs?",
+            "How do I add retries to a workflow step?",
+        ],
+        es: &[
+            "¿Cómo leo los registros de ejecución de una habilidad?",
+            "¿Cómo añado reintentos a un paso de un flujo de trabajo?",
+        ],
+    },
+    FollowupEntry {
+        agent: AgentType::Troubleshoot,
+        keywords: &[],
+        en: &[
+            "Where can I check the service status?",
+            "How do I contact IBM support?",
+        ],
+        es: &[
+            "¿Dónde puedo consultar el estado del servicio?",
+            "¿Cómo contacto con el soporte de IBM?",
+        ],
+    },
+    FollowupEntry {
+        agent: AgentType::BestPractices,
+        keywords: &["security"],
+        en: &[
+            "How do I set up RBAC?",
+            "How do I rotate API keys?",
+            "How do I audit access to sensitive skills?",
+        ],
+        es: &[
+            "¿Cómo configuro RBAC?",
+            "¿Cómo roto las claves de API?",
+            "¿Cómo audito el acceso a habilidades sensibles?",
+        ],
+    },
+    FollowupEntry {
+        agent: AgentType::BestPractices,
+        keywords: &["performance"],
+        en: &[
+            "How do I cache results between workflow steps?",
+            "How do I run workflow steps in parallel?",
+        ],
+        es: &[
+            "¿Cómo guardo en caché resultados entre pasos de un flujo de trabajo?",
+            "¿Cómo ejecuto pasos de un flujo de trabajo en paralelo?",
+        ],
+    },
+    FollowupEntry {
+        agent: AgentType::BestPractices,
+        keywords: &["cost"],
+        en: &[
+            "How do I track usage per team?",
+            "How do I find unused skills?",
+        ],
+        es: &[
+            "¿Cómo hago seguimiento del uso por equipo?",
+            "¿Cómo encuentro habilidades que no se usan?",
+        ],
+    },
+    FollowupEntry {
+        agent: AgentType::BestPractices,
+        keywords: &["workflow", "error_handling"],
+        en: &[
+            "How do I add error handling to a workflow?",
+            "How do I version workflows safely?",
+        ],
+        es: &[
+            "¿Cómo añado gestión de errores a un flujo de trabajo?",
+            "¿Cómo versiono flujos de trabajo de forma segura?",
+        ],
+    },
+    FollowupEntry {
+        agent: AgentType::BestPractices,
+        keywords: &["skill"],
+        en: &[
+            "How do I document a skill for other teams?",
+            "How do I test a skill before publishing it?",
+        ],
+        es: &[
+            "¿Cómo documento una habilidad para otros equipos?",
+            "¿Cómo pruebo una habilidad antes de publicarla?",
+        ],
+    },
+    FollowupEntry {
+        agent: AgentType::BestPractices,
+        keywords: &[],
+        en: &[
+            "How do I secure my deployment?",
+            "How do I keep workflows maintainable?",
+        ],
+        es: &[
+            "¿Cómo protejo mi despliegue?",
+            "¿Cómo mantengo los flujos de trabajo fáciles de mantener?",
+        ],
+    },
+    FollowupEntry {
+        agent: AgentType::DocsHelper,
+        keywords: &["api"],
+        en: &[
+            "How do I authenticate API requests?",
+            "Where are the API rate limits documented?",
+        ],
+        es: &[
+            "¿Cómo autentico las solicitudes a la API?",
+            "¿Dónde están documentados los límites de frecuencia de la API?",
+        ],
+    },
+    FollowupEntry {
+        agent: AgentType::DocsHelper,
+        keywords: &["admin"],
+        en: &[
+            "Where is the security configuration guide?",
+            "How do I configure SSO?",
+        ],
+        es: &[
+            "¿Dónde está la guía de configuración de seguridad?",
+            "¿Cómo configuro SSO?",
+        ],
+    },
+    FollowupEntry {
+        agent: AgentType::DocsHelper,
+        keywords: &["troubleshooting"],
+        en: &[
+            "Where are the known issues listed?",
+            "How do I contact IBM support?",
+        ],
+        es: &[
+            "¿Dónde se enumeran los problemas conocidos?",
+            "¿Cómo contacto con el soporte de IBM?",
+        ],
+    },
+    FollowupEntry {
+        agent: AgentType::DocsHelper,
+        keywords: &[],
+        en: &[
+            "Where is the quick start guide?",
+            "What changed in the latest release?",
+        ],
+        es: &[
+            "¿Dónde está la guía de inicio rápido?",
+            "¿Qué cambió en la última versión?",
+        ],
+    },
+];
+
+/// Heading of the follow-up list
+pub(crate) struct FollowupTemplates {
+    pub heading: &'static str,
+}
+
+impl FollowupTemplates {
+    pub fn for_language(language: Language) -> &'static Self {
+        match language {
+            Language::English => &FOLLOWUPS_EN,
+            Language::Spanish => &FOLLOWUPS_ES,
+        }
+    }
+}
+
+const FOLLOWUPS_EN: FollowupTemplates = FollowupTemplates {
+    heading: "### Related Questions",
+};
+
+const FOLLOWUPS_ES: FollowupTemplates = FollowupTemplates {
+    heading: "### Preguntas relacionadas",
+};
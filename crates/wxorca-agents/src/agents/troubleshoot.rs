@@ -2,44 +2,121 @@
 //!
 //! Helps users diagnose and resolve issues with WatsonX Orchestrate.
 
-use super::{route_by_tools, AnalyzeQueryNode, ExecuteToolsNode};
-use crate::state::AgentType;
+use super::layout::{Edge, GraphLayout, Wiring};
+use super::templates::TroubleshootTemplates;
+use super::{
+    append_degraded_notice, append_followups, awaiting_tool_results, keyword_confidence,
+    new_tool_call_id, observe, promote_context, push_found_docs, queue_tool_call,
+    record_relevant_docs, request_user_input, respond_with_llm, route_by_tools, AnalyzeQueryNode,
+    ExecuteToolsNode, GraphOptions, LlmClient, RenderContext, ResponseRenderer, SearchGate,
+    DEFAULT_TOOL_TIMEOUT,
+};
+use crate::state::{AgentType, DocCategory, Language};
+use anyhow::Context;
 use oxidizedgraph::prelude::*;
+use std::path::Path;
 use std::sync::Arc;
 
 /// Agent for troubleshooting WatsonX Orchestrate issues
 pub struct TroubleshootAgent;
 
+const WIRING: Wiring = Wiring {
+    entry: "analyze",
+    edges: &[
+        Edge::Fixed("analyze", "diagnose"),
+        Edge::Fixed("diagnose", "search_docs"),
+        Edge::Fixed("search_docs", "respond"),
+        Edge::Conditional(
+            "respond",
+            route_by_tools,
+            &["execute_tools", transitions::END],
+        ),
+        Edge::Fixed("execute_tools", "respond"),
+    ],
+};
+
 impl TroubleshootAgent {
     /// Build the agent graph for troubleshooting
     pub fn build_graph(tool_registry: Arc<ToolRegistry>) -> Result<CompiledGraph, GraphError> {
-        let system_prompt = AgentType::Troubleshoot.system_prompt().to_string();
+        Self::build_graph_with_options(tool_registry, GraphOptions::default())
+    }
 
-        GraphBuilder::new()
+    /// Build the agent graph with optional node observer and LLM
+    pub fn build_graph_with_options(
+        tool_registry: Arc<ToolRegistry>,
+        options: GraphOptions,
+    ) -> Result<CompiledGraph, GraphError> {
+        let system_prompt = options.system_prompt(AgentType::Troubleshoot);
+        let renderer = options.renderer(AgentType::Troubleshoot);
+        let GraphOptions {
+            observer,
+            llm,
+            diagnosis_rules,
+            min_keywords,
+            classifier,
+            tool_timeout,
+            ..
+        } = options;
+        let tool_timeout = tool_timeout.unwrap_or(DEFAULT_TOOL_TIMEOUT);
+        let gate = SearchGate::new(min_keywords);
+        let diagnosis_rules = diagnosis_rules.unwrap_or_default();
+
+        let builder = GraphBuilder::new()
             .name("troubleshoot_agent")
             .description("Diagnoses and resolves WatsonX Orchestrate issues")
-            .add_node(AnalyzeQueryNode::new("analyze"))
-            .add_node(DiagnoseNode::new("diagnose"))
-            .add_node(TroubleshootSearchNode::new("search_docs", system_prompt.clone()))
-            .add_node(TroubleshootResponseNode::new("respond", system_prompt))
-            .add_node(ExecuteToolsNode::new("execute_tools", tool_registry))
-            .set_entry_point("analyze")
-            .add_edge("analyze", "diagnose")
-            .add_edge("diagnose", "search_docs")
-            .add_edge("search_docs", "respond")
-            .add_conditional_edge("respond", route_by_tools)
-            .add_edge("execute_tools", "respond")
-            .compile()
+            .add_node(observe(
+                AnalyzeQueryNode::new("analyze").with_classifier(classifier.unwrap_or_default()),
+                &observer,
+            ))
+            .add_node(observe(
+                DiagnoseNode::new("diagnose", diagnosis_rules),
+                &observer,
+            ))
+            .add_node(observe(
+                TroubleshootSearchNode::new("search_docs", system_prompt.clone()).with_gate(gate),
+                &observer,
+            ))
+            .add_node(observe(
+                TroubleshootResponseNode::new("respond", system_prompt)
+                    .with_llm(llm)
+                    .with_renderer(renderer),
+                &observer,
+            ))
+            .add_node(observe(
+                ExecuteToolsNode::new("execute_tools", tool_registry).with_timeout(tool_timeout),
+                &observer,
+            ));
+        WIRING.apply(builder).compile()
+    }
+
+    /// Describe the graph built by [`Self::build_graph`] for export
+    pub(crate) fn layout(tool_registry: Arc<ToolRegistry>) -> GraphLayout {
+        let system_prompt = AgentType::Troubleshoot.system_prompt().to_string();
+
+        GraphLayout::new("troubleshoot_agent")
+            .node(&AnalyzeQueryNode::new("analyze"))
+            .node(&DiagnoseNode::new("diagnose", Arc::default()))
+            .node(&TroubleshootSearchNode::new(
+                "search_docs",
+                system_prompt.clone(),
+            ))
+            .node(&TroubleshootResponseNode::new("respond", system_prompt))
+            .node(&ExecuteToolsNode::new("execute_tools", tool_registry))
+            .wiring(&WIRING)
     }
 }
 
 struct DiagnoseNode {
     id: String,
+    rules: Arc<DiagnosisRules>,
 }
 
 impl DiagnoseNode {
-    fn new(id: impl Into<String>) -> Self {
-        Self { id: id.into() }
+    fn new(id: impl Into<String>, rules: Arc<DiagnosisRules>) -> Self {
+        Self {
+            id: id.into(),
+            rules,
+        }
     }
 }
 
@@ -54,22 +131,43 @@ impl NodeExecutor for DiagnoseNode {
     }
 
     async fn execute(&self, state: SharedState) -> Result<NodeOutput, NodeError> {
-        let query = {
+        let (query, language, earlier_queries) = {
             let guard = state
                 .read()
                 .map_err(|e| NodeError::Other(format!("Failed to read state: {}", e)))?;
-            guard
+            let query = guard
                 .get_context::<String>("original_query")
-                                .unwrap_or_default()
+                                .unwrap_or_default();
+            let language = guard
+                .get_context::<Language>("language")
+                .unwrap_or_default();
+
+            // User messages from previous turns
+            let mut user_messages: Vec<String> = guard
+                .messages
+                .iter()
+                .filter(|m| m.role == MessageRole::User)
+                .map(|m| m.content.clone())
+                .collect();
+            user_messages.pop();
+
+            (query, language, user_messages)
         };
 
-        let diagnosis = diagnose_issue(&query);
+        let diagnosis = diagnose_issue(&self.rules, &query, language);
+        let recurring = diagnosis.category != "general"
+            && earlier_queries.iter().any(|q| {
+                classify_issue(&self.rules, q)
+                    .is_some_and(|rule| rule.category == diagnosis.category)
+            });
+        let escalation = Escalation::for_diagnosis(&diagnosis, recurring);
 
         {
             let mut guard = state
                 .write()
                 .map_err(|e| NodeError::Other(format!("Failed to write state: {}", e)))?;
             guard.set_context("diagnosis", serde_json::json!(diagnosis));
+            guard.set_context("escalation", serde_json::json!(escalation));
         }
 
         Ok(NodeOutput::cont())
@@ -80,115 +178,243 @@ impl NodeExecutor for DiagnoseNode {
 struct Diagnosis {
     category: String,
     severity: String,
+    /// How sure the diagnosis is, from 0.0 (no rule matched) to 1.0
+    #[serde(default)]
+    confidence: f32,
     likely_causes: Vec<String>,
     suggested_checks: Vec<String>,
 }
 
-fn diagnose_issue(query: &str) -> Diagnosis {
-    let query_lower = query.to_lowercase();
+/// Escalation data for support tooling, stored in the `escalation` context key
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Escalation {
+    /// Whether the issue should be handed to human support
+    pub should_escalate: bool,
+    /// Suggested support tier (`tier_1` self-service through `tier_3`)
+    pub support_tier: String,
+    /// Category from the diagnosis (e.g. `authentication`)
+    pub diagnosis_category: String,
+}
 
-    if query_lower.contains("authentication")
-        || query_lower.contains("login")
-        || query_lower.contains("access denied")
-        || query_lower.contains("401")
-    {
-        Diagnosis {
-            category: "authentication".to_string(),
-            severity: "high".to_string(),
-            likely_causes: vec![
-                "Expired credentials or tokens".to_string(),
-                "Incorrect SSO configuration".to_string(),
-                "User permissions not set correctly".to_string(),
-                "API key revoked or expired".to_string(),
-            ],
-            suggested_checks: vec![
-                "Verify credentials are correct".to_string(),
-                "Check token expiration".to_string(),
-                "Review user permissions".to_string(),
-                "Test SSO configuration".to_string(),
-            ],
-        }
-    } else if query_lower.contains("timeout")
-        || query_lower.contains("slow")
-        || query_lower.contains("performance")
-    {
-        Diagnosis {
-            category: "performance".to_string(),
-            severity: "medium".to_string(),
-            likely_causes: vec![
-                "High system load".to_string(),
-                "Network latency".to_string(),
-                "Large data volumes".to_string(),
-                "Resource constraints".to_string(),
-            ],
-            suggested_checks: vec![
-                "Check system status page".to_string(),
-                "Monitor network connectivity".to_string(),
-                "Review workflow complexity".to_string(),
-                "Check concurrent user count".to_string(),
-            ],
-        }
-    } else if query_lower.contains("integration")
-        || query_lower.contains("connection")
-        || query_lower.contains("api")
-    {
-        Diagnosis {
-            category: "integration".to_string(),
-            severity: "medium".to_string(),
-            likely_causes: vec![
-                "External service unavailable".to_string(),
-                "Credentials expired".to_string(),
-                "API rate limit exceeded".to_string(),
-                "Configuration mismatch".to_string(),
-            ],
-            suggested_checks: vec![
-                "Verify external service status".to_string(),
-                "Check integration credentials".to_string(),
-                "Review API rate limits".to_string(),
-                "Test connection settings".to_string(),
-            ],
-        }
-    } else if query_lower.contains("skill")
-        || query_lower.contains("workflow")
-        || query_lower.contains("failed")
-    {
-        Diagnosis {
-            category: "execution".to_string(),
-            severity: "medium".to_string(),
-            likely_causes: vec![
-                "Invalid input data".to_string(),
-                "Missing required parameters".to_string(),
-                "Skill configuration error".to_string(),
-                "Dependency failure".to_string(),
-            ],
-            suggested_checks: vec![
-                "Review input data format".to_string(),
-                "Check required parameters".to_string(),
-                "Validate skill configuration".to_string(),
-                "Check execution logs".to_string(),
-            ],
+impl Escalation {
+    /// Escalate high-severity issues and issues whose category already came
+    /// up earlier in the conversation
+    fn for_diagnosis(diagnosis: &Diagnosis, recurring: bool) -> Self {
+        let high_severity = diagnosis.severity == "high";
+        let support_tier = match (high_severity, recurring) {
+            (true, true) => "tier_3",
+            (true, false) | (false, true) => "tier_2",
+            (false, false) => "tier_1",
+        };
+
+        Self {
+            should_escalate: high_severity || recurring,
+            support_tier: support_tier.to_string(),
+            diagnosis_category: diagnosis.category.clone(),
         }
-    } else {
-        Diagnosis {
-            category: "general".to_string(),
-            severity: "low".to_string(),
-            likely_causes: vec![
-                "Configuration issue".to_string(),
-                "User error".to_string(),
-                "Temporary system issue".to_string(),
-            ],
-            suggested_checks: vec![
-                "Describe the issue in more detail".to_string(),
-                "Check system status".to_string(),
-                "Review recent changes".to_string(),
-            ],
+    }
+}
+
+/// Built-in issue categories as (category, severity, query keywords in every
+/// supported language), checked in order; see [`DiagnosisRules::default`]
+const ISSUE_CATEGORIES: &[(&str, &str, &[&str])] = &[
+    (
+        "authentication",
+        "high",
+        &[
+            "authentication",
+            "login",
+            "access denied",
+            "401",
+            "autenticación",
+            "iniciar sesión",
+            "acceso denegado",
+        ],
+    ),
+    (
+        "performance",
+        "medium",
+        &[
+            "timeout",
+            "slow",
+            "performance",
+            "lento",
+            "rendimiento",
+            "tiempo de espera",
+        ],
+    ),
+    (
+        "integration",
+        "medium",
+        &[
+            "integration",
+            "connection",
+            "api",
+            "integración",
+            "conexión",
+        ],
+    ),
+    (
+        "execution",
+        "medium",
+        &[
+            "skill",
+            "workflow",
+            "failed",
+            "habilidad",
+            "flujo de trabajo",
+            "falla",
+            "falló",
+        ],
+    ),
+];
+
+/// Diagnosis confidence below which the reply hedges, asking the user to say
+/// more if the category doesn't fit
+const DIAGNOSIS_CONFIDENCE_THRESHOLD: f32 = 0.5;
+
+/// Severities a [`DiagnosisRule`] can have
+const SEVERITIES: &[&str] = &["high", "medium", "low"];
+
+/// One troubleshooting category and the queries it applies to
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DiagnosisRule {
+    /// Category reported in the diagnosis (e.g. `authentication`)
+    pub category: String,
+    /// Query keywords that select this rule, matched case-insensitively
+    pub keywords: Vec<String>,
+    /// `high`, `medium` or `low`; high-severity issues escalate
+    pub severity: String,
+    /// Likely causes; leave empty to use the built-in wording for the
+    /// category in the user's language
+    #[serde(default)]
+    pub causes: Vec<String>,
+    /// Suggested checks; leave empty to use the built-in wording, as for
+    /// `causes`
+    #[serde(default)]
+    pub checks: Vec<String>,
+}
+
+/// The rules the troubleshooting agent diagnoses issues with
+///
+/// Rules are checked in order and the first one with a keyword in the query
+/// wins; queries matching none are a low-severity `general` issue. The
+/// default is the built-in rule set. Support staff can keep their own set in
+/// a JSON file (an object with a `rules` array) and load it with
+/// [`DiagnosisRules::load`].
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DiagnosisRules {
+    pub rules: Vec<DiagnosisRule>,
+}
+
+impl Default for DiagnosisRules {
+    fn default() -> Self {
+        let rules = ISSUE_CATEGORIES
+            .iter()
+            .map(|&(category, severity, keywords)| DiagnosisRule {
+                category: category.to_string(),
+                keywords: keywords.iter().map(|kw| kw.to_string()).collect(),
+                severity: severity.to_string(),
+                causes: Vec::new(),
+                checks: Vec::new(),
+            })
+            .collect();
+
+        Self { rules }
+    }
+}
+
+impl DiagnosisRules {
+    /// Parse a rule set from JSON, rejecting rules that could never match or
+    /// have an unknown severity
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let rules: Self = serde_json::from_str(json).context("Invalid diagnosis rules")?;
+
+        for rule in &rules.rules {
+            if !SEVERITIES.contains(&rule.severity.as_str()) {
+                anyhow::bail!(
+                    "Diagnosis rule {} has unknown severity {} (expected one of: {})",
+                    rule.category,
+                    rule.severity,
+                    SEVERITIES.join(", ")
+                );
+            }
+            if rule.keywords.iter().all(|kw| kw.trim().is_empty()) {
+                anyhow::bail!("Diagnosis rule {} has no keywords", rule.category);
+            }
         }
+
+        Ok(rules)
+    }
+
+    /// Load a rule set from a JSON file
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::from_json(&json).with_context(|| format!("Failed to load {}", path.display()))
+    }
+}
+
+/// Get the rule for the issue described in `query`, if any matches
+fn classify_issue<'a>(rules: &'a DiagnosisRules, query: &str) -> Option<&'a DiagnosisRule> {
+    let query_lower = query.to_lowercase();
+
+    rules
+        .rules
+        .iter()
+        .find(|rule| matched_keywords(rule, &query_lower).next().is_some())
+}
+
+/// The keywords of `rule` that appear in a lowercased query
+fn matched_keywords<'a>(
+    rule: &'a DiagnosisRule,
+    query_lower: &'a str,
+) -> impl Iterator<Item = &'a str> {
+    rule.keywords
+        .iter()
+        .filter(|kw| !kw.trim().is_empty())
+        .filter(move |kw| query_lower.contains(&kw.to_lowercase()))
+        .map(String::as_str)
+}
+
+fn diagnose_issue(rules: &DiagnosisRules, query: &str, language: Language) -> Diagnosis {
+    let rule = classify_issue(rules, query);
+    let category = rule.map_or("general", |rule| rule.category.as_str());
+    let severity = rule.map_or("low", |rule| rule.severity.as_str());
+    let query_lower = query.to_lowercase();
+    let confidence = rule.map_or(0.0, |rule| {
+        keyword_confidence(matched_keywords(rule, &query_lower))
+    });
+    tracing::debug!(
+        query,
+        category,
+        severity,
+        confidence,
+        %language,
+        "Diagnosed issue"
+    );
+    let templates = TroubleshootTemplates::for_language(language);
+    let text = templates.issue(category).unwrap_or(&templates.general);
+    let to_strings = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+    let or_builtin = |items: Option<&Vec<String>>, builtin: &[&str]| match items {
+        Some(items) if !items.is_empty() => items.clone(),
+        _ => to_strings(builtin),
+    };
+
+    Diagnosis {
+        category: category.to_string(),
+        severity: severity.to_string(),
+        confidence,
+        likely_causes: or_builtin(rule.map(|rule| &rule.causes), text.likely_causes),
+        suggested_checks: or_builtin(rule.map(|rule| &rule.checks), text.suggested_checks),
     }
 }
 
 struct TroubleshootSearchNode {
     id: String,
     _system_prompt: String,
+    gate: SearchGate,
 }
 
 impl TroubleshootSearchNode {
@@ -196,8 +422,15 @@ impl TroubleshootSearchNode {
         Self {
             id: id.into(),
             _system_prompt: system_prompt,
+            gate: SearchGate::default(),
         }
     }
+
+    /// Only search for queries `gate` allows
+    fn with_gate(mut self, gate: SearchGate) -> Self {
+        self.gate = gate;
+        self
+    }
 }
 
 #[async_trait::async_trait]
@@ -228,7 +461,7 @@ impl NodeExecutor for TroubleshootSearchNode {
             (query, category)
         };
 
-        if query.is_empty() {
+        if !self.gate.allows(&query) {
             return Ok(NodeOutput::cont());
         }
 
@@ -238,16 +471,16 @@ impl NodeExecutor for TroubleshootSearchNode {
                 .map_err(|e| NodeError::Other(format!("Failed to write state: {}", e)))?;
 
             let tool_call = ToolCall {
-                id: uuid::Uuid::new_v4().to_string(),
+                id: new_tool_call_id(&guard),
                 name: "search_wxo_docs".to_string(),
                 arguments: serde_json::json!({
                     "query": format!("{} {}", diagnosis_category, query),
-                    "category": "troubleshooting",
+                    "category": DocCategory::Troubleshooting,
                     "limit": 5
                 }),
             };
 
-            guard.tool_calls.push(tool_call);
+            queue_tool_call(&mut guard, tool_call);
         }
 
         Ok(NodeOutput::cont())
@@ -257,6 +490,8 @@ impl NodeExecutor for TroubleshootSearchNode {
 struct TroubleshootResponseNode {
     id: String,
     system_prompt: String,
+    renderer: Arc<dyn ResponseRenderer>,
+    llm: Option<Arc<dyn LlmClient>>,
 }
 
 impl TroubleshootResponseNode {
@@ -264,8 +499,24 @@ impl TroubleshootResponseNode {
         Self {
             id: id.into(),
             system_prompt,
+            renderer: Arc::new(TroubleshootRenderer),
+            llm: None,
         }
     }
+
+    /// Generate replies with `llm` instead of the built-in template
+    fn with_llm(mut self, llm: Option<Arc<dyn LlmClient>>) -> Self {
+        self.llm = llm;
+        self
+    }
+
+    /// Render template replies with `renderer` instead of [`TroubleshootRenderer`]
+    fn with_renderer(mut self, renderer: Option<Arc<dyn ResponseRenderer>>) -> Self {
+        if let Some(renderer) = renderer {
+            self.renderer = renderer;
+        }
+        self
+    }
 }
 
 #[async_trait::async_trait]
@@ -279,111 +530,132 @@ impl NodeExecutor for TroubleshootResponseNode {
     }
 
     async fn execute(&self, state: SharedState) -> Result<NodeOutput, NodeError> {
+        if awaiting_tool_results(&state)? {
+            return Ok(NodeOutput::cont());
+        }
+
+        if let Some(llm) = &self.llm {
+            return respond_with_llm(
+                llm.as_ref(),
+                AgentType::Troubleshoot,
+                &self.system_prompt,
+                &state,
+            )
+            .await;
+        }
+
         let mut guard = state
             .write()
             .map_err(|e| NodeError::Other(format!("Failed to write state: {}", e)))?;
 
         let query = guard
             .get_context::<String>("original_query")
-                        .unwrap_or_default();
+            .unwrap_or_default();
+        let context = RenderContext::from_state(&guard);
+        record_relevant_docs(&mut guard, &context.tool_results);
+
+        let response = self.renderer.render(&query, &context);
+        let response = append_degraded_notice(&context, &response);
 
-        let diagnosis = guard
+        promote_context(&mut guard);
+        let response = append_followups(&mut guard, AgentType::Troubleshoot, &response);
+        guard.add_assistant_message(&response);
+        // Issues no rule recognized get a request for more details
+        let unrecognized = guard
             .get_context::<Diagnosis>("diagnosis")
-                        .unwrap_or_else(|| Diagnosis {
+            .is_none_or(|diagnosis| diagnosis.category == "general");
+        if unrecognized {
+            request_user_input(&mut guard);
+        } else {
+            guard.mark_complete();
+        }
+
+        Ok(NodeOutput::finish())
+    }
+}
+
+/// Default [`ResponseRenderer`] for the troubleshooting agent
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TroubleshootRenderer;
+
+impl ResponseRenderer for TroubleshootRenderer {
+    fn render(&self, query: &str, context: &RenderContext) -> String {
+        let diagnosis = context
+            .get::<Diagnosis>("diagnosis")
+            .unwrap_or_else(|| Diagnosis {
                 category: "general".to_string(),
                 severity: "low".to_string(),
+                confidence: 0.0,
                 likely_causes: vec![],
                 suggested_checks: vec![],
             });
 
-        let response = generate_troubleshoot_response(&query, &diagnosis, &self.system_prompt);
-
-        guard.add_assistant_message(&response);
-        guard.mark_complete();
-
-        Ok(NodeOutput::finish())
+        generate_troubleshoot_response(
+            query,
+            &diagnosis,
+            &context.tool_results,
+            context.max_results(),
+            context.language,
+            AgentType::Troubleshoot.system_prompt(),
+        )
     }
 }
 
 fn generate_troubleshoot_response(
     _query: &str,
     diagnosis: &Diagnosis,
+    tool_results: &[String],
+    max_results: usize,
+    language: Language,
     _system_prompt: &str,
 ) -> String {
+    let templates = TroubleshootTemplates::for_language(language);
     let mut response = String::new();
 
-    response.push_str(&format!(
-        "## 🔍 Issue Analysis: {}\n\n",
-        diagnosis.category.to_uppercase()
-    ));
+    let (text, label) = match templates.issue(&diagnosis.category) {
+        Some(text) => (text, text.label.to_string()),
+        // Categories from custom rules have no built-in wording, so name
+        // them as-is
+        None => (
+            &templates.general,
+            diagnosis.category.replace('_', " ").to_uppercase(),
+        ),
+    };
+    response.push_str(&format!("{}{}\n\n", templates.analysis_heading, label));
+
+    // A match on a single weak keyword may be the wrong category
+    if diagnosis.category != "general" && diagnosis.confidence < DIAGNOSIS_CONFIDENCE_THRESHOLD {
+        let (before, after) = templates.hedge;
+        response.push_str(&format!("{}{}{}\n\n", before, label.to_lowercase(), after));
+    }
 
+    let (high, medium, low) = templates.severities;
     response.push_str(&format!(
-        "**Severity**: {}\n\n",
+        "{}{}\n\n",
+        templates.severity_label,
         match diagnosis.severity.as_str() {
-            "high" => "🔴 High",
-            "medium" => "🟡 Medium",
-            _ => "🟢 Low",
+            "high" => high,
+            "medium" => medium,
+            _ => low,
         }
     ));
 
-    response.push_str("### Likely Causes\n");
+    response.push_str(templates.likely_causes_heading);
     for cause in &diagnosis.likely_causes {
         response.push_str(&format!("- {}\n", cause));
     }
     response.push('\n');
 
-    response.push_str("### Troubleshooting Steps\n\n");
+    response.push_str(templates.steps_heading);
     for (i, check) in diagnosis.suggested_checks.iter().enumerate() {
         response.push_str(&format!("{}. {}\n", i + 1, check));
     }
     response.push('\n');
 
     // Add category-specific advice
-    match diagnosis.category.as_str() {
-        "authentication" => {
-            response.push_str("### Quick Fix Attempts\n");
-            response.push_str("1. Clear browser cache and cookies\n");
-            response.push_str("2. Try logging out and back in\n");
-            response.push_str("3. Check if your session has expired\n");
-            response.push_str("4. Verify your account is active\n\n");
-            response.push_str("**⚠️ If issues persist**, contact your administrator to verify your account permissions.");
-        }
-        "performance" => {
-            response.push_str("### Quick Fix Attempts\n");
-            response.push_str("1. Refresh the page\n");
-            response.push_str("2. Check your internet connection\n");
-            response.push_str("3. Try a different browser\n");
-            response.push_str("4. Check the WXO status page for outages\n\n");
-            response.push_str("**💡 Tip**: If working with large datasets, try processing in smaller batches.");
-        }
-        "integration" => {
-            response.push_str("### Quick Fix Attempts\n");
-            response.push_str("1. Test the external service directly\n");
-            response.push_str("2. Re-authenticate the integration\n");
-            response.push_str("3. Check for API version changes\n");
-            response.push_str("4. Review integration logs\n\n");
-            response.push_str("**⚠️ Note**: External service issues are outside WXO control.");
-        }
-        "execution" => {
-            response.push_str("### Quick Fix Attempts\n");
-            response.push_str("1. Verify input data format\n");
-            response.push_str("2. Check for required fields\n");
-            response.push_str("3. Review skill/workflow logs\n");
-            response.push_str("4. Test with simpler inputs\n\n");
-            response.push_str("**💡 Tip**: Use the validation tool to check your configuration.");
-        }
-        _ => {
-            response.push_str("### Need More Information\n");
-            response.push_str("Could you provide more details about:\n");
-            response.push_str("- What exactly happened?\n");
-            response.push_str("- Any error messages shown?\n");
-            response.push_str("- When did this start?\n");
-            response.push_str("- Any recent changes?\n");
-        }
-    }
-
-    response.push_str("\n\n---\n\n");
-    response.push_str("**Still having issues?** I can help you escalate to IBM Support if needed.");
+    response.push_str(text.advice);
+    push_found_docs(&mut response, tool_results, max_results, language);
+    response.push_str(templates.closing);
 
     response
 }
@@ -391,6 +663,8 @@ fn generate_troubleshoot_response(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::agents::awaiting_user_input;
+    use crate::runner::{run_agent_with, RunOptions};
     use crate::tools::create_tool_registry;
 
     #[test]
@@ -402,14 +676,221 @@ mod tests {
 
     #[test]
     fn test_diagnose_authentication() {
-        let diagnosis = diagnose_issue("I can't login, getting access denied");
+        let diagnosis = diagnose_issue(
+            &DiagnosisRules::default(),
+            "I can't login, getting access denied",
+            Language::English,
+        );
         assert_eq!(diagnosis.category, "authentication");
         assert_eq!(diagnosis.severity, "high");
     }
 
     #[test]
     fn test_diagnose_performance() {
-        let diagnosis = diagnose_issue("The workflow is running very slow");
+        let diagnosis = diagnose_issue(
+            &DiagnosisRules::default(),
+            "The workflow is running very slow",
+            Language::English,
+        );
         assert_eq!(diagnosis.category, "performance");
     }
+
+    #[test]
+    fn test_diagnosis_confidence() {
+        let rules = DiagnosisRules::default();
+        let strong = diagnose_issue(
+            &rules,
+            "I can't login, getting access denied",
+            Language::English,
+        );
+        let weak = diagnose_issue(&rules, "The workflow is slow", Language::English);
+        assert_eq!(weak.category, "performance");
+        assert!(strong.confidence > weak.confidence);
+        assert!(strong.confidence >= DIAGNOSIS_CONFIDENCE_THRESHOLD);
+        assert_eq!(
+            diagnose_issue(&rules, "Hmm", Language::English).confidence,
+            0.0
+        );
+
+        let hedge = "It sounds like you might be hitting a problem with performance";
+        let response = generate_troubleshoot_response("", &weak, &[], 3, Language::English, "");
+        assert!(response.contains(hedge));
+        let response = generate_troubleshoot_response("", &strong, &[], 3, Language::English, "");
+        assert!(!response.contains("It sounds like"));
+    }
+
+    #[test]
+    fn test_diagnose_in_spanish() {
+        let diagnosis = diagnose_issue(
+            &DiagnosisRules::default(),
+            "Recibo acceso denegado al iniciar sesión",
+            Language::Spanish,
+        );
+        assert_eq!(diagnosis.category, "authentication");
+        assert_eq!(diagnosis.severity, "high");
+        assert!(diagnosis
+            .likely_causes
+            .contains(&"Configuración de SSO incorrecta".to_string()));
+
+        let response =
+            generate_troubleshoot_response("", &diagnosis, &[], 3, Language::Spanish, "");
+        assert!(response.starts_with("## 🔍 Análisis del problema: AUTENTICACIÓN"));
+        assert!(response.contains("**Gravedad**: 🔴 Alta"));
+        assert!(response.contains("### Soluciones rápidas"));
+    }
+
+    #[tokio::test]
+    async fn test_custom_diagnosis_rules() {
+        let query = "My invoice shows the wrong plan";
+        let default = diagnose_issue(&DiagnosisRules::default(), query, Language::English);
+        assert_eq!(default.category, "general");
+
+        let rules = DiagnosisRules::from_json(
+            r#"{"rules": [{
+                "category": "billing",
+                "keywords": ["invoice", "charged"],
+                "severity": "high",
+                "causes": ["Plan changed mid-cycle"]
+            }]}"#,
+        )
+        .unwrap();
+        let diagnosis = diagnose_issue(&rules, query, Language::English);
+        assert_eq!(diagnosis.category, "billing");
+        assert_eq!(diagnosis.likely_causes, vec!["Plan changed mid-cycle"]);
+        // No checks given, so the general ones are used
+        assert!(!diagnosis.suggested_checks.is_empty());
+
+        let options = GraphOptions::default().diagnosis_rules(Arc::new(rules));
+        let graph =
+            TroubleshootAgent::build_graph_with_options(Arc::new(create_tool_registry()), options)
+                .unwrap();
+        let runner = GraphRunner::new(graph, RunnerConfig::default().max_iterations(10));
+        let state =
+            AgentState::with_system_and_user(AgentType::Troubleshoot.system_prompt(), query);
+        let result = runner.invoke(state).await.unwrap();
+
+        let escalation = result.get_context::<Escalation>("escalation").unwrap();
+        assert_eq!(escalation.diagnosis_category, "billing");
+        assert!(escalation.should_escalate);
+        let response = &result.last_assistant_message().unwrap().content;
+        assert!(response.starts_with("## 🔍 Issue Analysis: BILLING"));
+
+        assert!(DiagnosisRules::from_json(
+            r#"{"rules": [{"category": "billing", "keywords": ["invoice"], "severity": "urgent"}]}"#
+        )
+        .is_err());
+    }
+
+    async fn run_troubleshoot(state: AgentState) -> AgentState {
+        let registry = Arc::new(create_tool_registry());
+        let graph = TroubleshootAgent::build_graph(registry).unwrap();
+        let runner = GraphRunner::new(graph, RunnerConfig::default().max_iterations(10));
+        runner.invoke(state).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_high_severity_escalates() {
+        let state = AgentState::with_system_and_user(
+            AgentType::Troubleshoot.system_prompt(),
+            "Users get access denied on login",
+        );
+        let escalation = run_troubleshoot(state)
+            .await
+            .get_context::<Escalation>("escalation")
+            .unwrap();
+
+        assert!(escalation.should_escalate);
+        assert_eq!(escalation.support_tier, "tier_2");
+        assert_eq!(escalation.diagnosis_category, "authentication");
+    }
+
+    #[tokio::test]
+    async fn test_unrecognized_issue_awaits_details() {
+        let state = AgentState::with_system_and_user(
+            AgentType::Troubleshoot.system_prompt(),
+            "Something is wrong",
+        );
+        assert!(awaiting_user_input(&run_troubleshoot(state).await));
+
+        let state = AgentState::with_system_and_user(
+            AgentType::Troubleshoot.system_prompt(),
+            "Users get access denied on login",
+        );
+        assert!(!awaiting_user_input(&run_troubleshoot(state).await));
+    }
+
+    #[tokio::test]
+    async fn test_recurring_category_escalates() {
+        let mut state = AgentState::with_system_and_user(
+            AgentType::Troubleshoot.system_prompt(),
+            "My workflow is slow",
+        );
+        let escalation = run_troubleshoot(state.clone())
+            .await
+            .get_context::<Escalation>("escalation")
+            .unwrap();
+        assert!(!escalation.should_escalate);
+        assert_eq!(escalation.support_tier, "tier_1");
+
+        state.add_assistant_message("Check the system status page.");
+        state.add_user_message("It still times out");
+        let escalation = run_troubleshoot(state)
+            .await
+            .get_context::<Escalation>("escalation")
+            .unwrap();
+        assert!(escalation.should_escalate);
+        assert_eq!(escalation.diagnosis_category, "performance");
+    }
+
+    /// Appends a support portal link to the default troubleshooting reply
+    struct PortalRenderer;
+
+    impl ResponseRenderer for PortalRenderer {
+        fn render(&self, query: &str, context: &RenderContext) -> String {
+            format!(
+                "{}\n\nStill stuck? Open a ticket at https://support.example.com",
+                TroubleshootRenderer.render(query, context)
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_renderer_replaces_default() {
+        let registry = Arc::new(create_tool_registry());
+        let options =
+            GraphOptions::default().renderer_for(AgentType::Troubleshoot, Arc::new(PortalRenderer));
+        let graph = TroubleshootAgent::build_graph_with_options(registry, options).unwrap();
+        let runner = GraphRunner::new(graph, RunnerConfig::default().max_iterations(10));
+
+        let state = AgentState::with_system_and_user(
+            AgentType::Troubleshoot.system_prompt(),
+            "Users get access denied on login",
+        );
+        let result = runner.invoke(state).await.unwrap();
+
+        let response = &result.last_assistant_message().unwrap().content;
+        assert!(response.starts_with("## 🔍 Issue Analysis: "));
+        let (rendered, _followups) = response.split_once("\n\n### Related Questions").unwrap();
+        assert!(rendered.ends_with("https://support.example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_found_docs_in_reply_and_sources() {
+        let response = run_agent_with(
+            AgentType::Troubleshoot,
+            None,
+            "My skill execution fails with an error",
+            RunOptions::default().deterministic(true),
+        )
+        .await
+        .unwrap();
+
+        assert!(response
+            .response
+            .contains("[Troubleshooting Common Issues]("));
+        assert!(response
+            .sources
+            .iter()
+            .any(|doc| doc.url == "https://www.ibm.com/docs/watsonx-orchestrate/troubleshooting"));
+    }
 }
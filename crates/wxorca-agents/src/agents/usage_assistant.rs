@@ -2,48 +2,125 @@
 //!
 //! Helps users understand and effectively use WatsonX Orchestrate features.
 
-use super::{route_by_tools, AnalyzeQueryNode, ExecuteToolsNode};
-use crate::state::AgentType;
+use super::layout::{Edge, GraphLayout, Wiring};
+use super::{
+    append_degraded_notice, append_followups, awaiting_tool_results, new_tool_call_id, observe,
+    promote_context, queue_tool_call, record_relevant_docs, respond_with_llm, route_by_intent,
+    route_by_tools, AnalyzeQueryNode, ClarifyIntentNode, ExecuteToolsNode, GraphOptions, LlmClient,
+    RenderContext, ResponseRenderer, SearchGate, DEFAULT_TOOL_TIMEOUT,
+};
+use crate::state::{AgentType, DocCategory, UserRole};
 use oxidizedgraph::prelude::*;
 use std::sync::Arc;
 
 /// Agent for helping users with WatsonX Orchestrate features
 pub struct UsageAssistantAgent;
 
+const WIRING: Wiring = Wiring {
+    entry: "analyze",
+    edges: &[
+        Edge::Conditional(
+            "analyze",
+            route_after_analyze,
+            &["clarify", "fetch_examples", "search_docs"],
+        ),
+        Edge::Fixed("search_docs", "respond"),
+        Edge::Fixed("fetch_examples", "respond"),
+        Edge::Fixed("clarify", transitions::END),
+        Edge::Conditional(
+            "respond",
+            route_by_tools,
+            &["execute_tools", transitions::END],
+        ),
+        Edge::Fixed("execute_tools", "respond"),
+    ],
+};
+
+/// Route on the intent, asking first when the intent is uncertain
+fn route_after_analyze(state: &AgentState) -> String {
+    if route_by_intent(state) == "clarify" {
+        return "clarify".to_string();
+    }
+    if let Some(intent) = state.get_context::<String>("user_intent") {
+        if intent == "example" {
+            return "fetch_examples".to_string();
+        }
+    }
+    "search_docs".to_string()
+}
+
 impl UsageAssistantAgent {
     /// Build the agent graph for usage assistance
     pub fn build_graph(tool_registry: Arc<ToolRegistry>) -> Result<CompiledGraph, GraphError> {
-        let system_prompt = AgentType::UsageAssistant.system_prompt().to_string();
+        Self::build_graph_with_options(tool_registry, GraphOptions::default())
+    }
 
-        GraphBuilder::new()
+    /// Build the agent graph with optional node observer and LLM
+    pub fn build_graph_with_options(
+        tool_registry: Arc<ToolRegistry>,
+        options: GraphOptions,
+    ) -> Result<CompiledGraph, GraphError> {
+        let system_prompt = options.system_prompt(AgentType::UsageAssistant);
+        let renderer = options.renderer(AgentType::UsageAssistant);
+        let GraphOptions {
+            observer,
+            llm,
+            min_keywords,
+            classifier,
+            tool_timeout,
+            ..
+        } = options;
+        let tool_timeout = tool_timeout.unwrap_or(DEFAULT_TOOL_TIMEOUT);
+        let gate = SearchGate::new(min_keywords);
+
+        let builder = GraphBuilder::new()
             .name("usage_assistant_agent")
             .description("Helps users understand and use WatsonX Orchestrate features")
-            .add_node(AnalyzeQueryNode::new("analyze"))
-            .add_node(UsageSearchNode::new("search_docs", system_prompt.clone()))
-            .add_node(ExampleFetchNode::new("fetch_examples"))
-            .add_node(UsageResponseNode::new("respond", system_prompt))
-            .add_node(ExecuteToolsNode::new("execute_tools", tool_registry))
-            .set_entry_point("analyze")
-            // Analyze -> conditional routing based on intent
-            .add_conditional_edge("analyze", |state| {
-                if let Some(intent) = state.get_context::<String>("user_intent") {
-                    if intent == "example" {
-                        return "fetch_examples".to_string();
-                    }
-                }
-                "search_docs".to_string()
-            })
-            .add_edge("search_docs", "respond")
-            .add_edge("fetch_examples", "respond")
-            .add_conditional_edge("respond", route_by_tools)
-            .add_edge("execute_tools", "respond")
-            .compile()
+            .add_node(observe(
+                AnalyzeQueryNode::new("analyze").with_classifier(classifier.unwrap_or_default()),
+                &observer,
+            ))
+            .add_node(observe(
+                UsageSearchNode::new("search_docs", system_prompt.clone()).with_gate(gate),
+                &observer,
+            ))
+            .add_node(observe(
+                ExampleFetchNode::new("fetch_examples").with_gate(gate),
+                &observer,
+            ))
+            .add_node(observe(ClarifyIntentNode::new("clarify"), &observer))
+            .add_node(observe(
+                UsageResponseNode::new("respond", system_prompt)
+                    .with_llm(llm)
+                    .with_renderer(renderer),
+                &observer,
+            ))
+            .add_node(observe(
+                ExecuteToolsNode::new("execute_tools", tool_registry).with_timeout(tool_timeout),
+                &observer,
+            ));
+        WIRING.apply(builder).compile()
+    }
+
+    /// Describe the graph built by [`Self::build_graph`] for export
+    pub(crate) fn layout(tool_registry: Arc<ToolRegistry>) -> GraphLayout {
+        let system_prompt = AgentType::UsageAssistant.system_prompt().to_string();
+
+        GraphLayout::new("usage_assistant_agent")
+            .node(&AnalyzeQueryNode::new("analyze"))
+            .node(&UsageSearchNode::new("search_docs", system_prompt.clone()))
+            .node(&ExampleFetchNode::new("fetch_examples"))
+            .node(&ClarifyIntentNode::new("clarify"))
+            .node(&UsageResponseNode::new("respond", system_prompt))
+            .node(&ExecuteToolsNode::new("execute_tools", tool_registry))
+            .wiring(&WIRING)
     }
 }
 
 struct UsageSearchNode {
     id: String,
     _system_prompt: String,
+    gate: SearchGate,
 }
 
 impl UsageSearchNode {
@@ -51,8 +128,15 @@ impl UsageSearchNode {
         Self {
             id: id.into(),
             _system_prompt: system_prompt,
+            gate: SearchGate::default(),
         }
     }
+
+    /// Only search for queries `gate` allows
+    fn with_gate(mut self, gate: SearchGate) -> Self {
+        self.gate = gate;
+        self
+    }
 }
 
 #[async_trait::async_trait]
@@ -66,16 +150,21 @@ impl NodeExecutor for UsageSearchNode {
     }
 
     async fn execute(&self, state: SharedState) -> Result<NodeOutput, NodeError> {
-        let query = {
+        let (query, role) = {
             let guard = state
                 .read()
                 .map_err(|e| NodeError::Other(format!("Failed to read state: {}", e)))?;
-            guard
-                .get_context::<String>("original_query")
-                                .unwrap_or_default()
+            (
+                guard
+                    .get_context::<String>("original_query")
+                    .unwrap_or_default(),
+                guard
+                    .get_context::<UserRole>("user_role")
+                    .unwrap_or_default(),
+            )
         };
 
-        if query.is_empty() {
+        if !self.gate.allows(&query) {
             return Ok(NodeOutput::cont());
         }
 
@@ -85,16 +174,29 @@ impl NodeExecutor for UsageSearchNode {
                 .map_err(|e| NodeError::Other(format!("Failed to write state: {}", e)))?;
 
             let tool_call = ToolCall {
-                id: uuid::Uuid::new_v4().to_string(),
+                id: new_tool_call_id(&guard),
                 name: "search_wxo_docs".to_string(),
                 arguments: serde_json::json!({
                     "query": query,
-                    "category": "user",
+                    "category": DocCategory::User,
                     "limit": 5
                 }),
             };
 
-            guard.tool_calls.push(tool_call);
+            queue_tool_call(&mut guard, tool_call);
+
+            // Developers get code alongside the docs
+            if role == UserRole::Developer {
+                let tool_call = ToolCall {
+                    id: new_tool_call_id(&guard),
+                    name: "fetch_wxo_examples".to_string(),
+                    arguments: serde_json::json!({
+                        "topic": query,
+                        "limit": 3
+                    }),
+                };
+                queue_tool_call(&mut guard, tool_call);
+            }
         }
 
         Ok(NodeOutput::cont())
@@ -103,11 +205,21 @@ impl NodeExecutor for UsageSearchNode {
 
 struct ExampleFetchNode {
     id: String,
+    gate: SearchGate,
 }
 
 impl ExampleFetchNode {
     fn new(id: impl Into<String>) -> Self {
-        Self { id: id.into() }
+        Self {
+            id: id.into(),
+            gate: SearchGate::default(),
+        }
+    }
+
+    /// Only fetch examples for queries `gate` allows
+    fn with_gate(mut self, gate: SearchGate) -> Self {
+        self.gate = gate;
+        self
     }
 }
 
@@ -131,7 +243,7 @@ impl NodeExecutor for ExampleFetchNode {
                                 .unwrap_or_default()
         };
 
-        if query.is_empty() {
+        if !self.gate.allows(&query) {
             return Ok(NodeOutput::cont());
         }
 
@@ -141,7 +253,7 @@ impl NodeExecutor for ExampleFetchNode {
                 .map_err(|e| NodeError::Other(format!("Failed to write state: {}", e)))?;
 
             let tool_call = ToolCall {
-                id: uuid::Uuid::new_v4().to_string(),
+                id: new_tool_call_id(&guard),
                 name: "fetch_wxo_examples".to_string(),
                 arguments: serde_json::json!({
                     "topic": query,
@@ -149,7 +261,7 @@ impl NodeExecutor for ExampleFetchNode {
                 }),
             };
 
-            guard.tool_calls.push(tool_call);
+            queue_tool_call(&mut guard, tool_call);
         }
 
         Ok(NodeOutput::cont())
@@ -159,6 +271,8 @@ impl NodeExecutor for ExampleFetchNode {
 struct UsageResponseNode {
     id: String,
     system_prompt: String,
+    renderer: Arc<dyn ResponseRenderer>,
+    llm: Option<Arc<dyn LlmClient>>,
 }
 
 impl UsageResponseNode {
@@ -166,7 +280,23 @@ impl UsageResponseNode {
         Self {
             id: id.into(),
             system_prompt,
+            renderer: Arc::new(UsageAssistantRenderer),
+            llm: None,
+        }
+    }
+
+    /// Generate replies with `llm` instead of the built-in template
+    fn with_llm(mut self, llm: Option<Arc<dyn LlmClient>>) -> Self {
+        self.llm = llm;
+        self
+    }
+
+    /// Render template replies with `renderer` instead of [`UsageAssistantRenderer`]
+    fn with_renderer(mut self, renderer: Option<Arc<dyn ResponseRenderer>>) -> Self {
+        if let Some(renderer) = renderer {
+            self.renderer = renderer;
         }
+        self
     }
 }
 
@@ -181,23 +311,35 @@ impl NodeExecutor for UsageResponseNode {
     }
 
     async fn execute(&self, state: SharedState) -> Result<NodeOutput, NodeError> {
+        if awaiting_tool_results(&state)? {
+            return Ok(NodeOutput::cont());
+        }
+
+        if let Some(llm) = &self.llm {
+            return respond_with_llm(
+                llm.as_ref(),
+                AgentType::UsageAssistant,
+                &self.system_prompt,
+                &state,
+            )
+            .await;
+        }
+
         let mut guard = state
             .write()
             .map_err(|e| NodeError::Other(format!("Failed to write state: {}", e)))?;
 
         let query = guard
             .get_context::<String>("original_query")
-                        .unwrap_or_default();
-
-        let tool_results: Vec<String> = guard
-            .messages
-            .iter()
-            .filter(|m| m.role == MessageRole::Tool)
-            .map(|m| m.content.clone())
-            .collect();
+            .unwrap_or_default();
+        let context = RenderContext::from_state(&guard);
+        record_relevant_docs(&mut guard, &context.tool_results);
 
-        let response = generate_usage_response(&query, &tool_results, &self.system_prompt);
+        let response = self.renderer.render(&query, &context);
+        let response = append_degraded_notice(&context, &response);
 
+        promote_context(&mut guard);
+        let response = append_followups(&mut guard, AgentType::UsageAssistant, &response);
         guard.add_assistant_message(&response);
         guard.mark_complete();
 
@@ -205,18 +347,101 @@ impl NodeExecutor for UsageResponseNode {
     }
 }
 
-fn generate_usage_response(query: &str, tool_results: &[String], _system_prompt: &str) -> String {
+/// Default [`ResponseRenderer`] for the usage assistant
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UsageAssistantRenderer;
+
+impl ResponseRenderer for UsageAssistantRenderer {
+    fn render(&self, query: &str, context: &RenderContext) -> String {
+        generate_usage_response(
+            query,
+            &context.tool_results,
+            context.user_role,
+            context
+                .get::<usize>("max_results_in_response")
+                .unwrap_or(DEFAULT_CODE_EXAMPLES),
+            AgentType::UsageAssistant.system_prompt(),
+        )
+    }
+}
+
+/// Code examples included in a developer's reply unless the
+/// `max_results_in_response` context key says otherwise. Fewer than the
+/// docs default, since each example is a full code block.
+const DEFAULT_CODE_EXAMPLES: usize = 2;
+
+/// Append the steps for creating a skill, at the depth suited to `role`
+fn push_skill_creation(response: &mut String, role: UserRole) {
+    response.push_str("### Creating a Skill\n");
+    match role {
+        UserRole::EndUser => {
+            response.push_str("1. Click **+ New Skill** in the skill catalog\n");
+            response.push_str("2. Choose a skill type (API, Custom, Pre-built)\n");
+            response.push_str("3. Define inputs and outputs\n");
+            response.push_str("4. Test your skill before publishing\n\n");
+        }
+        UserRole::Developer => {
+            response.push_str("1. Write the skill as a Python tool or describe your API with an OpenAPI spec\n");
+            response.push_str("2. Import it with the ADK CLI: `orchestrate tools import -k python -f my_tool.py` (or `-k openapi -f spec.yaml`)\n");
+            response.push_str("3. Try it against a local environment before importing it into your tenant\n");
+            response.push_str("4. Keep the tool source in version control and re-import on change\n\n");
+        }
+        UserRole::Admin => {
+            response.push_str("1. Import the skill's OpenAPI spec through the Skills API or the ADK CLI (`orchestrate tools import -k openapi -f spec.yaml`)\n");
+            response.push_str("2. Set up the connection and credentials the skill calls with\n");
+            response.push_str("3. Limit who can use it with role-based access control\n");
+            response.push_str("4. Publish it to the catalog for your teams\n\n");
+        }
+    }
+}
+
+/// Append up to `max_results` of the code examples found by
+/// `fetch_wxo_examples`, if any
+fn push_code_examples(response: &mut String, tool_results: &[String], max_results: usize) {
+    let examples: Vec<serde_json::Value> = tool_results
+        .iter()
+        .filter_map(|result| serde_json::from_str::<Vec<serde_json::Value>>(result).ok())
+        .flatten()
+        .filter(|example| example.get("code").is_some())
+        .take(max_results)
+        .collect();
+    if examples.is_empty() {
+        return;
+    }
+
+    response.push_str("\n\n### Code Examples\n");
+    for example in examples {
+        let field = |name: &str| {
+            example
+                .get(name)
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+        };
+        response.push_str(&format!(
+            "\n**{}**\n\n```{}\n{}\n```\n",
+            field("title"),
+            field("language"),
+            field("code").trim_end()
+        ));
+    }
+}
+
+/// Reply to a usage question, in UI terms for end users, with code for
+/// developers and with API/CLI detail for admins
+fn generate_usage_response(
+    query: &str,
+    tool_results: &[String],
+    role: UserRole,
+    max_results: usize,
+    _system_prompt: &str,
+) -> String {
     let query_lower = query.to_lowercase();
     let mut response = String::new();
 
     if query_lower.contains("skill") {
         response.push_str("## Working with Skills\n\n");
         response.push_str("Skills are the building blocks of WatsonX Orchestrate. Here's how to work with them:\n\n");
-        response.push_str("### Creating a Skill\n");
-        response.push_str("1. Click **+ New Skill** in the skill catalog\n");
-        response.push_str("2. Choose a skill type (API, Custom, Pre-built)\n");
-        response.push_str("3. Define inputs and outputs\n");
-        response.push_str("4. Test your skill before publishing\n\n");
+        push_skill_creation(&mut response, role);
         response.push_str("### Using Skills\n");
         response.push_str("- Type naturally: \"Send an email to John about the meeting\"\n");
         response.push_str("- WXO will find and execute the right skill\n");
@@ -230,6 +455,10 @@ fn generate_usage_response(query: &str, tool_results: &[String], _system_prompt:
         response.push_str("4. **Test**: Run the workflow with test data\n");
         response.push_str("5. **Deploy**: Publish for your team to use\n\n");
         response.push_str("**💡 Pro Tip**: Start simple and add complexity gradually.");
+        if role != UserRole::EndUser {
+            response.push_str("\n\nWorkflows can also be exported and imported through the API, ");
+            response.push_str("so you can keep them in version control and promote them between environments.");
+        }
     } else if query_lower.contains("catalog") {
         response.push_str("## Skill Catalog\n\n");
         response.push_str("The catalog contains all available skills:\n\n");
@@ -254,6 +483,10 @@ fn generate_usage_response(query: &str, tool_results: &[String], _system_prompt:
         response.push_str("What would you like to learn about?");
     }
 
+    if role == UserRole::Developer {
+        push_code_examples(&mut response, tool_results, max_results);
+    }
+
     if !tool_results.is_empty() {
         response.push_str("\n\n---\n\n**📋 Additional Resources:**\n");
         response.push_str("I found some relevant information. Check the details above.");
@@ -265,6 +498,7 @@ fn generate_usage_response(query: &str, tool_results: &[String], _system_prompt:
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::agents::{awaiting_user_input, tool_call_records};
     use crate::tools::create_tool_registry;
 
     #[test]
@@ -273,4 +507,113 @@ mod tests {
         let graph = UsageAssistantAgent::build_graph(registry);
         assert!(graph.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_uncertain_intent_asks_for_clarification() {
+        let registry = Arc::new(create_tool_registry());
+        let graph = UsageAssistantAgent::build_graph(registry).unwrap();
+        let runner = GraphRunner::new(graph, RunnerConfig::default().max_iterations(10));
+
+        // "check" matches both `search` and `validate`
+        let state = AgentState::with_system_and_user(
+            AgentType::UsageAssistant.system_prompt(),
+            "Check the docs",
+        );
+        let result = runner.invoke(state).await.unwrap();
+
+        assert!(awaiting_user_input(&result));
+        assert!(tool_call_records(&result).is_empty());
+        assert!(result
+            .last_assistant_message()
+            .unwrap()
+            .content
+            .contains("**code example**"));
+    }
+
+    #[test]
+    fn test_response_depth_follows_role() {
+        let query = "How do I create a skill?";
+        let examples = vec![serde_json::json!([{
+            "title": "Create a Custom Skill",
+            "language": "python",
+            "code": "skill = Skill(name=\"greet\")",
+            "tags": ["skill"]
+        }])
+        .to_string()];
+
+        let end_user = generate_usage_response(query, &examples, UserRole::EndUser, 3, "");
+        assert!(end_user.contains("**+ New Skill**"));
+        assert!(!end_user.contains("```"));
+
+        let developer = generate_usage_response(query, &examples, UserRole::Developer, 3, "");
+        assert!(developer.contains("orchestrate tools import"));
+        assert!(developer.contains("```python\nskill = Skill(name=\"greet\")\n```"));
+
+        let admin = generate_usage_response(query, &examples, UserRole::Admin, 3, "");
+        assert!(admin.contains("role-based access control"));
+        assert!(!admin.contains("```"));
+    }
+
+    #[test]
+    fn test_code_examples_capped() {
+        let examples = vec![serde_json::json!([
+            {"title": "First", "language": "python", "code": "first()"},
+            {"title": "Second", "language": "python", "code": "second()"},
+        ])
+        .to_string()];
+
+        let mut response = String::new();
+        push_code_examples(&mut response, &examples, 1);
+        assert!(response.contains("**First**"));
+        assert!(!response.contains("**Second**"));
+
+        let mut response = String::new();
+        push_code_examples(&mut response, &examples, 3);
+        assert!(response.contains("**Second**"));
+    }
+
+    #[test]
+    fn test_developer_code_examples_default_to_two() {
+        let examples = vec![serde_json::json!([
+            {"title": "First", "language": "python", "code": "first()"},
+            {"title": "Second", "language": "python", "code": "second()"},
+            {"title": "Third", "language": "python", "code": "third()"},
+        ])
+        .to_string()];
+        let context = RenderContext {
+            tool_results: examples,
+            user_role: UserRole::Developer,
+            ..Default::default()
+        };
+
+        let reply = UsageAssistantRenderer.render("How do I create a skill?", &context);
+        assert!(reply.contains("**Second**"));
+        assert!(!reply.contains("**Third**"));
+
+        let context = context.with_value("max_results_in_response", serde_json::json!(3));
+        let reply = UsageAssistantRenderer.render("How do I create a skill?", &context);
+        assert!(reply.contains("**Third**"));
+    }
+
+    #[tokio::test]
+    async fn test_developer_gets_code_examples() {
+        let graph = UsageAssistantAgent::build_graph_with_options(
+            Arc::new(crate::tools::create_mock_tool_registry()),
+            GraphOptions::default(),
+        )
+        .unwrap();
+        let runner = GraphRunner::new(graph, RunnerConfig::default().max_iterations(10));
+
+        let mut state = AgentState::with_system_and_user("system", "How do I create a skill?");
+        state.set_context("user_role", serde_json::json!(UserRole::Developer));
+        let result = runner.invoke(state).await.unwrap();
+
+        let names: Vec<String> = super::super::tool_call_records(&result)
+            .into_iter()
+            .map(|record| record.name)
+            .collect();
+        assert_eq!(names, vec!["search_wxo_docs", "fetch_wxo_examples"]);
+        let reply = &result.last_assistant_message().unwrap().content;
+        assert!(reply.contains("### Code Examples"));
+    }
 }
@@ -3,25 +3,51 @@
 //! Provides a CLI interface for interacting with WXOrca agents.
 //! Used by the backend server via subprocess communication.
 
-use anyhow::Result;
-use clap::{Parser, ValueEnum};
+use anyhow::{Context, Result};
+use clap::{ArgGroup, Parser, ValueEnum};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tracing_subscriber::EnvFilter;
+use wxorca_agents::cache::DEFAULT_CACHE_SIZE;
+use wxorca_agents::db::DbConfig;
 use wxorca_agents::prelude::*;
+use wxorca_agents::tools::NO_MOCK_FALLBACK_ENV;
 
 #[derive(Parser)]
 #[command(name = "wxorca-cli")]
 #[command(about = "WXOrca - AI-powered guide for IBM WatsonX Orchestrate")]
+#[command(group(ArgGroup::new("file_output").args(["input_file", "export_markdown"])))]
 struct Cli {
-    /// The type of agent to use
-    #[arg(short, long)]
-    agent: AgentTypeArg,
+    /// The type of agent to use, by name or by menu number (1-5)
+    #[arg(
+        short,
+        long,
+        value_parser = parse_agent_arg,
+        required_unless_present_any = ["export_markdown", "auto_agent", "list_agents", "graph"]
+    )]
+    agent: Option<AgentTypeArg>,
+
+    /// Pick the agent for each message based on its content
+    #[arg(long, conflicts_with = "agent")]
+    auto_agent: bool,
 
     /// Session ID for conversation persistence
     #[arg(short, long)]
     session: Option<String>,
 
+    /// What's known about the user's environment, as a JSON object with
+    /// `user_role`, `wxo_version`, `deployment_type` and `metadata`
+    #[arg(long, value_name = "JSON", value_parser = parse_context_arg)]
+    context: Option<WxoContext>,
+
+    /// Resume `--session` conversations from the database and save each
+    /// turn, appending only the new messages
+    #[arg(long, conflicts_with = "input_file")]
+    persist: bool,
+
     /// Single message to process (if not provided, enters interactive mode)
     #[arg(short, long)]
     message: Option<String>,
@@ -33,6 +59,84 @@ struct Cli {
     /// Enable verbose logging
     #[arg(short, long)]
     verbose: bool,
+
+    /// Include the tool calls made during the turn in the response
+    #[arg(long)]
+    show_tool_calls: bool,
+
+    /// Stream node progress as the agent runs (text format only)
+    #[arg(long)]
+    stream: bool,
+
+    /// Format of the agent's reply
+    #[arg(long, default_value = "markdown")]
+    response_format: ResponseFormatArg,
+
+    /// Remove emoji from plain-text replies
+    #[arg(long)]
+    strip_emoji: bool,
+
+    /// Maximum number of replies kept in the response cache
+    #[arg(long, default_value_t = DEFAULT_CACHE_SIZE)]
+    cache_size: usize,
+
+    /// Always run the agent instead of reusing cached replies
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Fail doc and example lookups when SurrealDB can't be searched instead
+    /// of answering from mock data (same as `WXORCA_NO_MOCK_FALLBACK=1`)
+    #[arg(long)]
+    no_mock_fallback: bool,
+
+    /// Most docs or code examples listed in a reply (default: 3)
+    #[arg(long, value_name = "N")]
+    max_results: Option<usize>,
+
+    /// Directory of `<agent>.txt` files (e.g. `troubleshoot.txt`) that
+    /// replace the built-in system prompts
+    #[arg(long, value_name = "DIR")]
+    prompts_dir: Option<PathBuf>,
+
+    /// JSON file of rules the troubleshooting agent diagnoses issues with
+    #[arg(long, value_name = "FILE")]
+    diagnosis_rules: Option<PathBuf>,
+
+    /// JSON file of moderation rules that replace the built-in blocklist
+    #[arg(long, value_name = "FILE")]
+    blocklist: Option<PathBuf>,
+
+    /// Process a JSONL file of input messages and output an array of responses
+    #[arg(long, conflicts_with = "message")]
+    input_file: Option<PathBuf>,
+
+    /// Export a stored conversation as a Markdown transcript
+    #[arg(long, value_name = "SESSION_ID", conflicts_with_all = ["message", "input_file"])]
+    export_markdown: Option<String>,
+
+    /// Describe each agent and the names accepted for it, then exit
+    #[arg(long, conflicts_with_all = ["agent", "message", "input_file", "export_markdown"])]
+    list_agents: bool,
+
+    /// Print an agent's graph as Graphviz DOT (pipe to `dot -Tpng`), then exit
+    #[arg(
+        long,
+        value_name = "AGENT",
+        value_parser = parse_agent_arg,
+        conflicts_with_all = ["agent", "message", "input_file", "export_markdown", "list_agents"]
+    )]
+    graph: Option<AgentTypeArg>,
+
+    /// Recompute the term statistics used to rank doc searches, then exit
+    #[arg(
+        long,
+        conflicts_with_all = ["agent", "message", "input_file", "export_markdown", "list_agents", "graph"]
+    )]
+    rebuild_search_index: bool,
+
+    /// Write batch responses or the exported transcript to this file instead of stdout
+    #[arg(long, requires = "file_output")]
+    output_file: Option<PathBuf>,
 }
 
 #[derive(Clone, ValueEnum)]
@@ -56,19 +160,76 @@ impl From<AgentTypeArg> for AgentType {
     }
 }
 
+impl From<AgentType> for AgentTypeArg {
+    fn from(agent_type: AgentType) -> Self {
+        match agent_type {
+            AgentType::AdminSetup => AgentTypeArg::AdminSetup,
+            AgentType::UsageAssistant => AgentTypeArg::Usage,
+            AgentType::Troubleshoot => AgentTypeArg::Troubleshoot,
+            AgentType::BestPractices => AgentTypeArg::BestPractices,
+            AgentType::DocsHelper => AgentTypeArg::Docs,
+        }
+    }
+}
+
+/// Parse an agent name, or its number in `AgentType::all()` for menu-driven
+/// callers
+fn parse_agent_arg(value: &str) -> Result<AgentTypeArg, String> {
+    if value.trim().parse::<usize>().is_ok() {
+        return value.parse::<AgentType>().map(Into::into);
+    }
+    AgentTypeArg::from_str(value, true)
+}
+
+/// The `--context` JSON; unknown fields are rejected rather than ignored
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ContextArg {
+    user_role: Option<String>,
+    wxo_version: Option<String>,
+    deployment_type: Option<String>,
+    #[serde(default)]
+    metadata: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Parse the `--context` JSON into the context a new conversation starts with
+fn parse_context_arg(value: &str) -> Result<WxoContext, String> {
+    let arg: ContextArg =
+        serde_json::from_str(value).map_err(|e| format!("Invalid context JSON: {}", e))?;
+    if let Some(ref role) = arg.user_role {
+        role.parse::<UserRole>()?;
+    }
+
+    Ok(WxoContext {
+        user_role: arg.user_role,
+        wxo_version: arg.wxo_version,
+        deployment_type: arg.deployment_type,
+        metadata: arg.metadata,
+        ..WxoContext::default()
+    })
+}
+
 #[derive(Clone, ValueEnum)]
 enum OutputFormat {
     Json,
     Text,
 }
 
-#[derive(Serialize)]
-struct AgentResponse {
-    session_id: String,
-    agent_type: String,
-    response: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    error: Option<String>,
+#[derive(Clone, ValueEnum)]
+enum ResponseFormatArg {
+    Markdown,
+    PlainText,
+    Html,
+}
+
+impl From<ResponseFormatArg> for ResponseFormat {
+    fn from(arg: ResponseFormatArg) -> Self {
+        match arg {
+            ResponseFormatArg::Markdown => ResponseFormat::Markdown,
+            ResponseFormatArg::PlainText => ResponseFormat::PlainText,
+            ResponseFormatArg::Html => ResponseFormat::Html,
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -78,9 +239,33 @@ struct InputMessage {
     session_id: Option<String>,
 }
 
+/// An agent as described by `--list-agents`
+#[derive(Serialize)]
+struct AgentInfo {
+    agent_type: AgentType,
+    display_name: &'static str,
+    description: &'static str,
+    aliases: &'static [&'static str],
+}
+
+impl From<AgentType> for AgentInfo {
+    fn from(agent_type: AgentType) -> Self {
+        Self {
+            agent_type,
+            display_name: agent_type.display_name(),
+            description: agent_type.description(),
+            aliases: agent_type.aliases(),
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
+    // Read when the tool registries are built, before any agent runs
+    if cli.no_mock_fallback {
+        std::env::set_var(NO_MOCK_FALLBACK_ENV, "1");
+    }
 
     // Initialize logging
     if cli.verbose {
@@ -89,16 +274,78 @@ async fn main() -> Result<()> {
             .init();
     }
 
-    let agent_type: AgentType = cli.agent.into();
+    if cli.list_agents {
+        return list_agents(&cli.format);
+    }
+
+    if let Some(agent) = cli.graph {
+        print!("{}", export_agent_graph_dot(agent.into())?);
+        return Ok(());
+    }
+
+    if let Some(ref session_id) = cli.export_markdown {
+        let transcript = export_markdown(session_id).await?;
+        return write_output(cli.output_file.as_deref(), &transcript);
+    }
+
+    if cli.rebuild_search_index {
+        return rebuild_search_index(&cli.format).await;
+    }
 
-    if let Some(message) = cli.message {
+    let mut options = run_options(&cli);
+    if let Some(ref dir) = cli.prompts_dir {
+        options = options.prompts(Arc::new(PromptOverrides::load_dir(dir)?));
+    }
+    if let Some(ref path) = cli.diagnosis_rules {
+        options = options.diagnosis_rules(Arc::new(DiagnosisRules::load(path)?));
+    }
+    if let Some(ref path) = cli.blocklist {
+        options = options.blocklist(Arc::new(Blocklist::load(path)?));
+    }
+    let context = cli.context.clone().unwrap_or_default();
+    let db = if cli.persist {
+        Some(Database::connect(&DbConfig::from_env()).await?)
+    } else {
+        None
+    };
+    // Search the database conversations are saved to rather than a second connection
+    if let Some(ref db) = db {
+        options = options.tool_registry(Arc::new(create_tool_registry_for(db)));
+    }
+    // Without an agent (--auto-agent), each message picks its own
+    let agent: Option<AgentType> = cli.agent.map(Into::into);
+
+    if let Some(ref input_file) = cli.input_file {
+        // Batch mode
+        let responses = process_batch(
+            agent,
+            cli.session.as_deref(),
+            &context,
+            input_file,
+            &options,
+        )
+        .await?;
+        let output = serde_json::to_string_pretty(&responses)?;
+        write_output(cli.output_file.as_deref(), &output)?;
+    } else if let Some(message) = cli.message {
         // Single message mode
-        let response = process_message(&agent_type, cli.session.as_deref(), &message).await?;
-        output_response(&response, &cli.format)?;
+        let agent_type = agent.unwrap_or_else(|| classify_agent(&message));
+        let response = respond(
+            agent_type,
+            cli.session.as_deref(),
+            &context,
+            db.as_ref(),
+            &message,
+            options,
+        )
+        .await;
+        output_response(&response, &cli.format, cli.verbose)?;
     } else {
         // Interactive mode (read from stdin)
         let stdin = io::stdin();
         let mut stdout = io::stdout();
+        let mut shell = Shell::new(agent, context);
+        shell.db = db;
 
         for line in stdin.lock().lines() {
             let line = line?;
@@ -106,6 +353,15 @@ async fn main() -> Result<()> {
                 continue;
             }
 
+            // Slash commands go to stderr so JSON output stays one response per line
+            if line.trim_start().starts_with('/') {
+                match parse_shell_command(&line) {
+                    Ok(command) => eprintln!("{}", shell.run_command(command)),
+                    Err(e) => eprintln!("{}", e),
+                }
+                continue;
+            }
+
             // Try to parse as JSON, otherwise use as plain text
             let (message, session_id) = if let Ok(input) = serde_json::from_str::<InputMessage>(&line)
             {
@@ -114,8 +370,8 @@ async fn main() -> Result<()> {
                 (line, cli.session.clone())
             };
 
-            let response = process_message(&agent_type, session_id.as_deref(), &message).await?;
-            output_response(&response, &cli.format)?;
+            let response = shell.respond(&message, session_id, options.clone()).await;
+            output_response(&response, &cli.format, cli.verbose)?;
             stdout.flush()?;
         }
     }
@@ -123,113 +379,344 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-async fn process_message(
-    agent_type: &AgentType,
+/// Help shown by `/help` in interactive mode
+const SHELL_HELP: &str = "Commands:
+  /agent <type>  Switch agent, by name or menu number (1-5)
+  /reset         Clear the conversation and start over
+  /help          Show this help";
+
+/// A slash command typed in interactive mode
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ShellCommand {
+    Reset,
+    Agent(AgentType),
+    Help,
+}
+
+/// Parse a line starting with `/` as a [`ShellCommand`]
+fn parse_shell_command(line: &str) -> Result<ShellCommand, String> {
+    let mut words = line.trim().trim_start_matches('/').split_whitespace();
+    let name = words.next().unwrap_or_default();
+    let argument = words.next();
+
+    match (name, argument) {
+        ("reset", None) => Ok(ShellCommand::Reset),
+        ("help", None) => Ok(ShellCommand::Help),
+        ("agent", Some(agent)) => parse_agent_arg(agent)
+            .map(|arg| ShellCommand::Agent(arg.into()))
+            .map_err(|e| format!("Unknown agent '{}': {}", agent, e)),
+        ("agent", None) => Err("Usage: /agent <type>".to_string()),
+        _ => Err(format!(
+            "Unknown command '{}'; type /help for the list",
+            line.trim()
+        )),
+    }
+}
+
+/// The agent and conversation of an interactive session
+///
+/// Messages continue the same conversation until `/reset` or a message for
+/// another session.
+struct Shell {
+    /// Agent for every message, or `None` to pick one per message
+    agent: Option<AgentType>,
+    /// Context each new conversation starts with
+    context: WxoContext,
+    /// Where conversations are resumed from and saved, with `--persist`
+    db: Option<Database>,
+    state: Option<WxorcaState>,
+}
+
+impl Shell {
+    fn new(agent: Option<AgentType>, context: WxoContext) -> Self {
+        Self {
+            agent,
+            context,
+            db: None,
+            state: None,
+        }
+    }
+
+    /// Apply a command, returning the text to show the user
+    fn run_command(&mut self, command: ShellCommand) -> String {
+        match command {
+            ShellCommand::Reset => {
+                self.state = None;
+                "Conversation cleared".to_string()
+            }
+            ShellCommand::Agent(agent_type) => {
+                self.agent = Some(agent_type);
+                if let Some(state) = self.state.as_mut() {
+                    state.agent_type = agent_type;
+                }
+                format!("Switched to {}", agent_type.display_name())
+            }
+            ShellCommand::Help => SHELL_HELP.to_string(),
+        }
+    }
+
+    /// Run one turn of the current conversation, or of a new one if
+    /// `session_id` names a different session
+    async fn respond(
+        &mut self,
+        message: &str,
+        session_id: Option<String>,
+        options: RunOptions,
+    ) -> AgentResponse {
+        let agent_type = self.agent.unwrap_or_else(|| classify_agent(message));
+        let same_session = |state: &WxorcaState| {
+            session_id
+                .as_ref()
+                .is_none_or(|sid| *sid == state.session_id)
+        };
+        let mut state = match self.state.take() {
+            Some(state) if same_session(&state) => state,
+            _ => {
+                let db = self.db.as_ref();
+                match resume_state(agent_type, session_id, &self.context, db).await {
+                    Ok(state) => state,
+                    Err(e) => return AgentResponse::failed("", agent_type, e.to_string()),
+                }
+            }
+        };
+        state.agent_type = agent_type;
+
+        let response = run_checked_turn(&mut state, message, options).await;
+        let response = save_turn(self.db.as_ref(), &mut state, response).await;
+        self.state = Some(state);
+        response
+    }
+}
+
+/// Start a conversation, seeded with the `--context` given on the command line
+fn new_state(
+    agent_type: AgentType,
+    session_id: Option<String>,
+    context: &WxoContext,
+) -> WxorcaState {
+    let mut state = match session_id {
+        Some(sid) => WxorcaState::with_session_id(agent_type, sid),
+        None => WxorcaState::new(agent_type),
+    };
+    state.context = context.clone();
+    state
+}
+
+/// Load the session's saved conversation if there is a database to load
+/// it from, or start a new one
+async fn resume_state(
+    agent_type: AgentType,
+    session_id: Option<String>,
+    context: &WxoContext,
+    db: Option<&Database>,
+) -> Result<WxorcaState, WxorcaError> {
+    if let (Some(db), Some(sid)) = (db, session_id.as_deref()) {
+        let loaded = db
+            .load_conversation(sid)
+            .await
+            .map_err(WxorcaError::Database)?;
+        if let Some(mut state) = loaded {
+            state.agent_type = agent_type;
+            return Ok(state);
+        }
+    }
+    Ok(new_state(agent_type, session_id, context))
+}
+
+/// Save the turn's new messages if there is a database, reporting a failure
+/// in the response's `error`
+async fn save_turn(
+    db: Option<&Database>,
+    state: &mut WxorcaState,
+    mut response: AgentResponse,
+) -> AgentResponse {
+    // Refused messages never reach the conversation
+    if !response.flagged.is_empty() {
+        return response;
+    }
+    if let Some(db) = db {
+        if let Err(e) = db.save_turn(state).await {
+            response.error = Some(format!("Failed to save conversation: {}", e));
+        }
+    }
+    response
+}
+
+/// Run one turn, reporting a failure in the response's `error`
+async fn respond(
+    agent_type: AgentType,
     session_id: Option<&str>,
+    context: &WxoContext,
+    db: Option<&Database>,
     message: &str,
-) -> Result<AgentResponse> {
-    // Build the agent graph
-    let graph = match build_agent_graph(*agent_type) {
-        Ok(g) => g,
+    options: RunOptions,
+) -> AgentResponse {
+    let session = session_id.map(String::from);
+    let mut state = match resume_state(agent_type, session, context, db).await {
+        Ok(state) => state,
         Err(e) => {
-            return Ok(AgentResponse {
-                session_id: session_id.unwrap_or("").to_string(),
-                agent_type: agent_type.to_string(),
-                response: String::new(),
-                error: Some(format!("Failed to build agent graph: {}", e)),
-            });
+            return AgentResponse::failed(session_id.unwrap_or(""), agent_type, e.to_string())
         }
     };
 
-    // Create or restore state
-    let mut state = if let Some(sid) = session_id {
-        WxorcaState::with_session_id(*agent_type, sid)
-    } else {
-        WxorcaState::new(*agent_type)
-    };
+    let response = run_checked_turn(&mut state, message, options).await;
+    save_turn(db, &mut state, response).await
+}
+
+/// Run one turn on `state` if it passes validation, reporting a failure in
+/// the response's `error`
+async fn run_checked_turn(
+    state: &mut WxorcaState,
+    message: &str,
+    options: RunOptions,
+) -> AgentResponse {
+    if let Err(violations) = state.validate() {
+        let error = format!("Invalid conversation state: {}", violations.join("; "));
+        return AgentResponse::failed(&state.session_id, state.agent_type, error);
+    }
+
+    match run_turn(state, message, options).await {
+        Ok(response) => response,
+        Err(e) => AgentResponse::failed(&state.session_id, state.agent_type, e.to_string()),
+    }
+}
 
-    // Add the user message
-    state.add_user_message(message);
-
-    // Convert to AgentState for the runner
-    let agent_state = convert_to_agent_state(&state);
-
-    // Run the graph
-    let runner = GraphRunner::new(
-        graph,
-        RunnerConfig::default()
-            .max_iterations(10)
-            .verbose(false),
-    );
-
-    match runner.invoke(agent_state).await {
-        Ok(result_state) => {
-            // Extract the assistant's response
-            let response = result_state
-                .last_assistant_message()
-                .map(|m| m.content.clone())
-                .unwrap_or_else(|| "I apologize, but I couldn't generate a response.".to_string());
-
-            Ok(AgentResponse {
-                session_id: state.session_id.clone(),
-                agent_type: agent_type.to_string(),
-                response,
-                error: None,
-            })
+/// Run every message in a JSONL file, keeping one conversation per session id
+///
+/// Without an `agent`, each session's agent is picked from its first message.
+/// Lines that fail are reported in their response's `error` rather than
+/// aborting the batch.
+async fn process_batch(
+    agent: Option<AgentType>,
+    default_session: Option<&str>,
+    context: &WxoContext,
+    path: &Path,
+    options: &RunOptions,
+) -> Result<Vec<AgentResponse>> {
+    let input = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let mut sessions: HashMap<String, WxorcaState> = HashMap::new();
+    let mut responses = Vec::new();
+
+    for (i, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
         }
-        Err(e) => Ok(AgentResponse {
-            session_id: state.session_id.clone(),
-            agent_type: agent_type.to_string(),
-            response: String::new(),
-            error: Some(format!("Agent execution failed: {}", e)),
-        }),
+
+        let input = match serde_json::from_str::<InputMessage>(line) {
+            Ok(input) => input,
+            Err(e) => {
+                let session_id = default_session.unwrap_or("");
+                let agent_type = agent.unwrap_or_default();
+                let error = format!("Invalid input on line {}: {}", i + 1, e);
+                responses.push(AgentResponse::failed(session_id, agent_type, error));
+                continue;
+            }
+        };
+
+        let session_id = input
+            .session_id
+            .or_else(|| default_session.map(String::from));
+        let agent_type = agent.unwrap_or_else(|| classify_agent(&input.message));
+        let mut state = match session_id {
+            Some(sid) => match sessions.remove(&sid) {
+                Some(state) => state,
+                None => new_state(agent_type, Some(sid), context),
+            },
+            None => new_state(agent_type, None, context),
+        };
+
+        let response = run_checked_turn(&mut state, &input.message, options.clone()).await;
+        sessions.insert(state.session_id.clone(), state);
+        responses.push(response);
     }
+
+    Ok(responses)
 }
 
-fn convert_to_agent_state(wxorca_state: &WxorcaState) -> AgentState {
-    // Use with_system_and_user if we have a user message, otherwise just create with system
-    let system_prompt = wxorca_state.agent_type.system_prompt();
+/// Load a conversation from the database and render it as Markdown
+async fn export_markdown(session_id: &str) -> Result<String> {
+    let db = Database::connect(&DbConfig::from_env()).await?;
+    let state = db
+        .load_conversation(session_id)
+        .await?
+        .with_context(|| format!("No conversation found for session {}", session_id))?;
 
-    let mut agent_state = if let Some(first_user_msg) = wxorca_state.messages.iter().find(|m| m.role == WxorcaMessageRole::User) {
-        AgentState::with_system_and_user(system_prompt, &first_user_msg.content)
-    } else {
-        let mut state = AgentState::new();
-        state.messages.push(oxidizedgraph::prelude::Message::system(system_prompt));
-        state
-    };
+    Ok(state.to_markdown_transcript())
+}
 
-    // Add remaining messages (skip the first user message as it's already added)
-    let mut skip_first_user = true;
-    for msg in &wxorca_state.messages {
-        match msg.role {
-            WxorcaMessageRole::User => {
-                if skip_first_user {
-                    skip_first_user = false;
-                    continue;
-                }
-                agent_state.add_user_message(&msg.content);
-            }
-            WxorcaMessageRole::Assistant => agent_state.add_assistant_message(&msg.content),
-            WxorcaMessageRole::System => {
-                // System messages are added via the initial state
-                agent_state.messages.push(oxidizedgraph::prelude::Message::system(&msg.content));
-            }
-            WxorcaMessageRole::Tool => {
-                if let Some(ref tool_call_id) = msg.tool_call_id {
-                    agent_state.add_tool_result(tool_call_id, &msg.content);
-                }
+/// Rebuild the doc search index and print its size
+async fn rebuild_search_index(format: &OutputFormat) -> Result<()> {
+    let db = Database::connect(&DbConfig::from_env()).await?;
+    db.migrate().await?;
+    let stats = db.rebuild_search_index().await?;
+
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&stats)?),
+        OutputFormat::Text => println!(
+            "Indexed {} docs ({} distinct terms)",
+            stats.doc_count, stats.term_count
+        ),
+    }
+    Ok(())
+}
+
+/// Print every agent with its description and accepted names
+fn list_agents(format: &OutputFormat) -> Result<()> {
+    let agents: Vec<AgentInfo> = AgentType::all().iter().copied().map(Into::into).collect();
+
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&agents)?),
+        OutputFormat::Text => {
+            for agent in &agents {
+                println!("{} ({})", agent.display_name, agent.aliases.join(", "));
+                println!("  {}\n", agent.description);
             }
         }
     }
+    Ok(())
+}
+
+/// Write `output` to `path`, or to stdout if no path is given
+fn write_output(path: Option<&Path>, output: &str) -> Result<()> {
+    match path {
+        Some(path) => std::fs::write(path, output)
+            .with_context(|| format!("Failed to write {}", path.display())),
+        None => {
+            println!("{}", output);
+            Ok(())
+        }
+    }
+}
 
-    // Set context
-    agent_state.set_context("agent_type", serde_json::json!(wxorca_state.agent_type));
-    agent_state.set_context("session_id", serde_json::json!(wxorca_state.session_id));
+fn run_options(cli: &Cli) -> RunOptions {
+    let mut options = RunOptions::default()
+        .show_tool_calls(cli.show_tool_calls)
+        .response_format(cli.response_format.clone().into())
+        .strip_emoji(cli.strip_emoji);
 
-    agent_state
+    if let Some(max) = cli.max_results {
+        options = options.max_results_in_response(max);
+    }
+    if !cli.no_cache && cli.cache_size > 0 {
+        options = options.cache(Arc::new(ResponseCache::new(cli.cache_size)));
+    }
+
+    // Print each node as it runs when streaming
+    if cli.stream && matches!(cli.format, OutputFormat::Text) {
+        options = options.observer(Arc::new(|id: &str, description: Option<&str>| {
+            let mut stdout = io::stdout();
+            let _ = writeln!(stdout, "[{}] {}", id, description.unwrap_or(""));
+            let _ = stdout.flush();
+        }));
+    }
+
+    options
 }
 
-fn output_response(response: &AgentResponse, format: &OutputFormat) -> Result<()> {
+/// Print a response; in text format, `verbose` also prints its timing to stderr
+fn output_response(response: &AgentResponse, format: &OutputFormat, verbose: bool) -> Result<()> {
     match format {
         OutputFormat::Json => {
             println!("{}", serde_json::to_string(response)?);
@@ -239,8 +726,184 @@ fn output_response(response: &AgentResponse, format: &OutputFormat) -> Result<()
                 eprintln!("Error: {}", error);
             } else {
                 println!("{}", response.response);
+                for call in response.tool_calls.iter().flatten() {
+                    match call.status {
+                        Some(status) => {
+                            println!("[tool] {} {} -> {}", call.name, call.arguments, status)
+                        }
+                        None => println!("[tool] {} {}", call.name, call.arguments),
+                    }
+                }
+            }
+            if verbose {
+                eprintln!("[timing] {} ms", response.duration_ms);
+                for timing in &response.node_timings {
+                    eprintln!("[timing]   {} {} ms", timing.node, timing.duration_ms);
+                }
             }
         }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_agents_flag() {
+        let cli = Cli::try_parse_from(["wxorca-cli", "--list-agents", "--format", "text"]).unwrap();
+        assert!(cli.list_agents);
+        assert!(Cli::try_parse_from(["wxorca-cli", "--list-agents", "--agent", "docs"]).is_err());
+
+        let info = AgentInfo::from(AgentType::DocsHelper);
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["agent_type"], "docs-helper");
+        assert!(json["aliases"].as_array().unwrap().contains(&"docs".into()));
+    }
+
+    #[test]
+    fn test_context_flag() {
+        let cli = Cli::try_parse_from([
+            "wxorca-cli",
+            "--agent",
+            "admin",
+            "--context",
+            r#"{"user_role": "admin", "wxo_version": "3.2", "deployment_type": "on-prem", "metadata": {"team": "ops"}}"#,
+        ])
+        .unwrap();
+        let context = cli.context.unwrap();
+        assert_eq!(context.role(), Some(UserRole::Admin));
+        assert_eq!(context.wxo_version.as_deref(), Some("3.2"));
+        assert_eq!(context.deployment_type.as_deref(), Some("on-prem"));
+        assert_eq!(context.metadata["team"], "ops");
+
+        let state = new_state(AgentType::AdminSetup, Some("s".to_string()), &context);
+        assert_eq!(state.context.wxo_version.as_deref(), Some("3.2"));
+
+        let unknown = parse_context_arg(r#"{"user_role": "admin", "version": "3.2"}"#);
+        assert!(unknown.unwrap_err().contains("unknown field `version`"));
+        assert!(parse_context_arg(r#"{"user_role": "owner"}"#).is_err());
+        assert!(parse_context_arg("not json").is_err());
+    }
+
+    #[test]
+    fn test_agent_by_menu_number() {
+        let cli = Cli::try_parse_from(["wxorca-cli", "--agent", "3", "-m", "help"]).unwrap();
+        assert!(matches!(cli.agent, Some(AgentTypeArg::Troubleshoot)));
+        let cli = Cli::try_parse_from(["wxorca-cli", "--agent", "docs", "-m", "help"]).unwrap();
+        assert!(matches!(cli.agent, Some(AgentTypeArg::Docs)));
+        assert!(Cli::try_parse_from(["wxorca-cli", "--agent", "6", "-m", "help"]).is_err());
+    }
+
+    #[test]
+    fn test_graph_flag() {
+        let cli = Cli::try_parse_from(["wxorca-cli", "--graph", "troubleshoot"]).unwrap();
+        assert!(matches!(cli.graph, Some(AgentTypeArg::Troubleshoot)));
+        assert!(Cli::try_parse_from(["wxorca-cli", "--graph", "docs", "--agent", "docs"]).is_err());
+    }
+
+    #[test]
+    fn test_rebuild_search_index_flag() {
+        let cli = Cli::try_parse_from(["wxorca-cli", "--rebuild-search-index"]).unwrap();
+        assert!(cli.rebuild_search_index);
+        assert!(
+            Cli::try_parse_from(["wxorca-cli", "--rebuild-search-index", "-m", "help"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_shell_command() {
+        assert_eq!(parse_shell_command("/reset"), Ok(ShellCommand::Reset));
+        assert_eq!(parse_shell_command(" /help "), Ok(ShellCommand::Help));
+        assert_eq!(
+            parse_shell_command("/agent troubleshoot"),
+            Ok(ShellCommand::Agent(AgentType::Troubleshoot))
+        );
+        assert_eq!(
+            parse_shell_command("/agent 5"),
+            Ok(ShellCommand::Agent(AgentType::DocsHelper))
+        );
+        assert!(parse_shell_command("/agent").is_err());
+        assert!(parse_shell_command("/agent nobody").is_err());
+        assert!(parse_shell_command("/quit").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_shell_commands_change_conversation() {
+        let mut shell = Shell::new(Some(AgentType::Troubleshoot), WxoContext::default());
+        shell
+            .respond("My workflow is slow", None, RunOptions::default())
+            .await;
+        let session_id = shell.state.as_ref().unwrap().session_id.clone();
+
+        shell.run_command(ShellCommand::Agent(AgentType::BestPractices));
+        let response = shell
+            .respond("How should I structure it?", None, RunOptions::default())
+            .await;
+        assert_eq!(response.session_id, session_id);
+        let state = shell.state.as_ref().unwrap();
+        assert_eq!(state.agent_type, AgentType::BestPractices);
+        let user_messages = state
+            .messages
+            .iter()
+            .filter(|m| m.role == WxorcaMessageRole::User)
+            .count();
+        assert_eq!(user_messages, 2);
+
+        shell.run_command(ShellCommand::Reset);
+        assert!(shell.state.is_none());
+        assert!(shell
+            .run_command(ShellCommand::Help)
+            .contains("/agent <type>"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_state_is_not_run() {
+        let response = respond(
+            AgentType::DocsHelper,
+            Some(""),
+            &WxoContext::default(),
+            None,
+            "Where are the docs?",
+            RunOptions::default(),
+        )
+        .await;
+        assert!(response.error.unwrap().contains("session_id is empty"));
+        assert!(response.response.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_batch_keeps_sessions_and_reports_bad_lines() {
+        let path = std::env::temp_dir().join(format!("wxorca-batch-{}.jsonl", std::process::id()));
+        std::fs::write(
+            &path,
+            concat!(
+                r#"{"message": "My workflow is slow", "session_id": "a"}"#,
+                "\n",
+                "not json\n",
+                r#"{"message": "It still times out", "session_id": "a"}"#,
+                "\n",
+            ),
+        )
+        .unwrap();
+
+        let responses = process_batch(
+            Some(AgentType::Troubleshoot),
+            None,
+            &WxoContext::default(),
+            &path,
+            &RunOptions::default(),
+        )
+        .await
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(responses.len(), 3);
+        assert!(responses[0].error.is_none());
+        assert!(responses[1].error.as_deref().unwrap().contains("line 2"));
+        assert_eq!(responses[2].session_id, "a");
+        // The second turn saw the first, so the repeated issue escalates
+        assert!(responses[2].escalation.as_ref().unwrap().should_escalate);
+    }
+}
@@ -0,0 +1,339 @@
+//! Agent response caching
+//!
+//! FAQ-style questions ("how do I create a skill?") produce the same reply
+//! every time. A [`ResponseCache`] passed in
+//! [`RunOptions`](crate::runner::RunOptions) lets the runner skip the graph
+//! for questions it has already answered in the same [`CacheScope`].
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use crate::agents::{Escalation, ToolCallRecord};
+use crate::state::{AgentType, DocReference, Language, UserRole};
+use crate::tools::ToolStatus;
+
+/// Default number of responses kept by the CLI
+pub const DEFAULT_CACHE_SIZE: usize = 128;
+
+/// Read-only documentation lookups; replies built from their successful
+/// results are cached by default
+pub const CACHEABLE_TOOLS: &[&str] = &["search_wxo_docs", "fetch_wxo_examples"];
+
+/// Everything besides the agent and question that shapes a reply
+///
+/// Replies are only reused within the same scope, so an answer tailored to
+/// an admin on SaaS isn't replayed to a developer running on-premises.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CacheScope {
+    /// The user's role, if known
+    pub role: Option<UserRole>,
+    /// The deployment type (SaaS or on-premises), if known
+    pub deployment_type: Option<String>,
+    /// The reply language, if set rather than detected from the question
+    pub language: Option<Language>,
+    /// Cap on the search results listed in the reply
+    pub max_results_in_response: Option<usize>,
+    /// Meaningful keywords a question needs before the agent searches
+    pub min_keywords: Option<usize>,
+    /// System prompt sent with the question, including the user context
+    pub system_prompt: String,
+}
+
+/// Cache key: the agent, the scope and the question with case and spacing
+/// normalized
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    agent_type: AgentType,
+    scope: CacheScope,
+    query: String,
+}
+
+impl CacheKey {
+    fn new(agent_type: AgentType, scope: &CacheScope, query: &str) -> Self {
+        Self {
+            agent_type,
+            scope: scope.clone(),
+            query: normalize_query(query),
+        }
+    }
+}
+
+/// Lowercase a query and collapse its whitespace
+fn normalize_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A reply stored in the cache, before response-format rendering
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    /// The assistant's Markdown reply
+    pub response: String,
+    /// Escalation data produced alongside the reply
+    pub escalation: Option<Escalation>,
+    /// Documents the reply cites
+    pub sources: Vec<DocReference>,
+    /// Follow-up questions suggested with the reply
+    pub suggestions: Vec<String>,
+    /// Whether the reply asks the user a clarifying question
+    pub needs_input: bool,
+}
+
+#[derive(Default)]
+struct Entries {
+    map: HashMap<CacheKey, CachedResponse>,
+    /// Keys from least to most recently used
+    order: VecDeque<CacheKey>,
+}
+
+impl Entries {
+    fn touch(&mut self, key: &CacheKey) {
+        if let Some(pos) = self.order.iter().position(|k| k == key) {
+            let key = self.order.remove(pos).expect("position is in bounds");
+            self.order.push_back(key);
+        }
+    }
+}
+
+/// In-memory LRU cache of agent replies keyed by agent, scope and normalized
+/// query
+pub struct ResponseCache {
+    capacity: usize,
+    cache_tool_responses: bool,
+    cacheable_tools: Vec<String>,
+    entries: Mutex<Entries>,
+}
+
+impl ResponseCache {
+    /// Create a cache holding up to `capacity` responses
+    ///
+    /// Responses from turns that executed tools are only cached when every
+    /// call was a successful [`CACHEABLE_TOOLS`] lookup, since other tools
+    /// depend on live state; see [`ResponseCache::cache_tool_responses`].
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            cache_tool_responses: false,
+            cacheable_tools: CACHEABLE_TOOLS.iter().map(|t| t.to_string()).collect(),
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    /// Also cache responses from turns that executed any tools
+    pub fn cache_tool_responses(mut self, cache: bool) -> Self {
+        self.cache_tool_responses = cache;
+        self
+    }
+
+    /// Replace the tools whose successful results may be cached
+    pub fn cacheable_tools(mut self, tools: &[&str]) -> Self {
+        self.cacheable_tools = tools.iter().map(|t| t.to_string()).collect();
+        self
+    }
+
+    /// Whether a response whose turn executed `calls` may be cached
+    pub fn allows_tool_calls(&self, calls: &[ToolCallRecord]) -> bool {
+        self.cache_tool_responses
+            || calls.iter().all(|call| {
+                call.status == Some(ToolStatus::Success)
+                    && self.cacheable_tools.iter().any(|tool| *tool == call.name)
+            })
+    }
+
+    /// Look up the cached reply for `query`, marking it recently used
+    pub fn get(
+        &self,
+        agent_type: AgentType,
+        scope: &CacheScope,
+        query: &str,
+    ) -> Option<CachedResponse> {
+        let key = CacheKey::new(agent_type, scope, query);
+        let mut entries = self.entries.lock().ok()?;
+        let cached = entries.map.get(&key).cloned()?;
+        entries.touch(&key);
+        Some(cached)
+    }
+
+    /// Store the reply for `query`, evicting the least recently used entry
+    /// if the cache is full
+    pub fn insert(
+        &self,
+        agent_type: AgentType,
+        scope: &CacheScope,
+        query: &str,
+        response: CachedResponse,
+    ) {
+        if self.capacity == 0 {
+            return;
+        }
+        let key = CacheKey::new(agent_type, scope, query);
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+
+        if entries.map.insert(key.clone(), response).is_some() {
+            entries.touch(&key);
+            return;
+        }
+        entries.order.push_back(key);
+        while entries.order.len() > self.capacity {
+            if let Some(oldest) = entries.order.pop_front() {
+                entries.map.remove(&oldest);
+            }
+        }
+    }
+
+    /// Number of cached responses
+    pub fn len(&self) -> usize {
+        self.entries.lock().map(|e| e.map.len()).unwrap_or(0)
+    }
+
+    /// Whether the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(text: &str) -> CachedResponse {
+        CachedResponse {
+            response: text.to_string(),
+            escalation: None,
+            sources: Vec::new(),
+            suggestions: Vec::new(),
+            needs_input: false,
+        }
+    }
+
+    #[test]
+    fn test_query_is_normalized() {
+        let cache = ResponseCache::new(4);
+        let scope = CacheScope::default();
+        cache.insert(
+            AgentType::UsageAssistant,
+            &scope,
+            "How do I create a skill?",
+            reply("a"),
+        );
+
+        let hit = cache.get(
+            AgentType::UsageAssistant,
+            &scope,
+            "  how do I   CREATE a skill? ",
+        );
+        assert_eq!(hit.unwrap().response, "a");
+        assert!(cache
+            .get(AgentType::AdminSetup, &scope, "How do I create a skill?")
+            .is_none());
+    }
+
+    #[test]
+    fn test_replies_are_scoped() {
+        let cache = ResponseCache::new(4);
+        let admin = CacheScope {
+            role: Some(UserRole::Admin),
+            deployment_type: Some("saas".to_string()),
+            ..CacheScope::default()
+        };
+        cache.insert(
+            AgentType::AdminSetup,
+            &admin,
+            "How do I set up SSO?",
+            reply("a"),
+        );
+
+        assert!(cache
+            .get(AgentType::AdminSetup, &admin, "How do I set up SSO?")
+            .is_some());
+        for scope in [
+            CacheScope::default(),
+            CacheScope {
+                role: Some(UserRole::Developer),
+                ..admin.clone()
+            },
+            CacheScope {
+                deployment_type: Some("on-prem".to_string()),
+                ..admin.clone()
+            },
+            CacheScope {
+                language: Some(Language::Spanish),
+                ..admin.clone()
+            },
+            CacheScope {
+                max_results_in_response: Some(1),
+                ..admin.clone()
+            },
+            CacheScope {
+                min_keywords: Some(3),
+                ..admin.clone()
+            },
+            CacheScope {
+                system_prompt: "Be brief.".to_string(),
+                ..admin.clone()
+            },
+        ] {
+            assert!(cache
+                .get(AgentType::AdminSetup, &scope, "How do I set up SSO?")
+                .is_none());
+        }
+    }
+
+    #[test]
+    fn test_least_recently_used_is_evicted() {
+        let cache = ResponseCache::new(2);
+        let scope = CacheScope::default();
+        cache.insert(AgentType::DocsHelper, &scope, "one", reply("1"));
+        cache.insert(AgentType::DocsHelper, &scope, "two", reply("2"));
+        // Using "one" makes "two" the eviction candidate
+        cache.get(AgentType::DocsHelper, &scope, "one");
+        cache.insert(AgentType::DocsHelper, &scope, "three", reply("3"));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(AgentType::DocsHelper, &scope, "one").is_some());
+        assert!(cache.get(AgentType::DocsHelper, &scope, "two").is_none());
+        assert!(cache.get(AgentType::DocsHelper, &scope, "three").is_some());
+    }
+
+    fn call(name: &str, status: Option<ToolStatus>) -> ToolCallRecord {
+        ToolCallRecord {
+            name: name.to_string(),
+            arguments: serde_json::json!({}),
+            result_summary: None,
+            status,
+        }
+    }
+
+    #[test]
+    fn test_only_read_only_lookups_are_cacheable() {
+        let cache = ResponseCache::new(4);
+        assert!(cache.allows_tool_calls(&[]));
+        assert!(cache.allows_tool_calls(&[
+            call("search_wxo_docs", Some(ToolStatus::Success)),
+            call("fetch_wxo_examples", Some(ToolStatus::Success)),
+        ]));
+        assert!(!cache.allows_tool_calls(&[call("search_wxo_docs", Some(ToolStatus::Empty))]));
+        assert!(!cache.allows_tool_calls(&[call("search_wxo_docs", None)]));
+        assert!(!cache.allows_tool_calls(&[call("validate_wxo_config", Some(ToolStatus::Success))]));
+
+        let cache = ResponseCache::new(4).cache_tool_responses(true);
+        assert!(cache.allows_tool_calls(&[call("validate_wxo_config", None)]));
+    }
+
+    #[test]
+    fn test_zero_capacity_stores_nothing() {
+        let cache = ResponseCache::new(0);
+        cache.insert(
+            AgentType::DocsHelper,
+            &CacheScope::default(),
+            "one",
+            reply("1"),
+        );
+        assert!(cache.is_empty());
+    }
+}
@@ -6,19 +6,181 @@
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
 use surrealdb::{
-    engine::remote::ws::{Client, Ws},
+    engine::any::{self, Any},
     opt::auth::Root,
     sql::Thing,
     Surreal,
 };
 
-use crate::state::{AgentType, Message, WxorcaState};
+use crate::embedding::Embedder;
+use crate::redact::{redact_json, redact_pii};
+use crate::state::{
+    migrate_state, AgentType, ConversationSummary, DocCategory, Message, WxoContext, WxorcaState,
+    STATE_SCHEMA_VERSION, SUMMARY_PREVIEW_CHARS,
+};
+use crate::tools::{document_frequencies, text_similarity, truncate_excerpt, TermStats};
+
+/// A schema change applied by [`Database::migrate`]
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    /// Schema version after this migration; versions increase by one
+    pub version: u32,
+    /// What the migration changes
+    pub description: &'static str,
+    /// SurrealQL statements to run
+    pub sql: &'static str,
+}
+
+/// Every schema migration, oldest first
+///
+/// Append new migrations to the end; never edit one that has shipped.
+/// Definitions use `IF NOT EXISTS` so databases set up before versioning
+/// existed adopt the history without errors.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Create conversations, wxo_docs and feedback tables",
+        sql: r#"
+            DEFINE TABLE IF NOT EXISTS conversations SCHEMAFULL;
+            DEFINE FIELD IF NOT EXISTS session_id ON conversations TYPE string;
+            DEFINE FIELD IF NOT EXISTS agent_type ON conversations TYPE string;
+            DEFINE FIELD IF NOT EXISTS messages ON conversations TYPE array;
+            DEFINE FIELD IF NOT EXISTS created_at ON conversations TYPE datetime DEFAULT time::now();
+            DEFINE FIELD IF NOT EXISTS updated_at ON conversations TYPE datetime DEFAULT time::now();
+            DEFINE INDEX IF NOT EXISTS idx_session ON conversations FIELDS session_id UNIQUE;
+
+            DEFINE TABLE IF NOT EXISTS wxo_docs SCHEMAFULL;
+            DEFINE FIELD IF NOT EXISTS title ON wxo_docs TYPE string;
+            DEFINE FIELD IF NOT EXISTS content ON wxo_docs TYPE string;
+            DEFINE FIELD IF NOT EXISTS category ON wxo_docs TYPE string;
+            DEFINE FIELD IF NOT EXISTS url ON wxo_docs TYPE option<string>;
+            DEFINE FIELD IF NOT EXISTS embedding ON wxo_docs TYPE array DEFAULT [];
+            DEFINE FIELD IF NOT EXISTS created_at ON wxo_docs TYPE datetime DEFAULT time::now();
+            DEFINE INDEX IF NOT EXISTS idx_category ON wxo_docs FIELDS category;
+
+            DEFINE TABLE IF NOT EXISTS feedback SCHEMAFULL;
+            DEFINE FIELD IF NOT EXISTS session_id ON feedback TYPE string;
+            DEFINE FIELD IF NOT EXISTS message_id ON feedback TYPE option<string>;
+            DEFINE FIELD IF NOT EXISTS rating ON feedback TYPE int;
+            DEFINE FIELD IF NOT EXISTS comment ON feedback TYPE option<string>;
+            DEFINE FIELD IF NOT EXISTS created_at ON feedback TYPE datetime DEFAULT time::now();
+            DEFINE INDEX IF NOT EXISTS idx_feedback_session ON feedback FIELDS session_id;
+        "#,
+    },
+    Migration {
+        version: 2,
+        description: "Store context, iteration and completion with conversations",
+        sql: r#"
+            DEFINE FIELD IF NOT EXISTS context ON conversations FLEXIBLE TYPE object DEFAULT {};
+            DEFINE FIELD IF NOT EXISTS iteration ON conversations TYPE int DEFAULT 0;
+            DEFINE FIELD IF NOT EXISTS is_complete ON conversations TYPE bool DEFAULT false;
+            UPDATE conversations SET context = {} WHERE context IS NONE;
+            UPDATE conversations SET iteration = 0 WHERE iteration IS NONE;
+            UPDATE conversations SET is_complete = false WHERE is_complete IS NONE;
+        "#,
+    },
+    Migration {
+        version: 3,
+        description: "Create wxo_examples table",
+        sql: r#"
+            DEFINE TABLE IF NOT EXISTS wxo_examples SCHEMAFULL;
+            DEFINE FIELD IF NOT EXISTS title ON wxo_examples TYPE string;
+            DEFINE FIELD IF NOT EXISTS description ON wxo_examples TYPE string;
+            DEFINE FIELD IF NOT EXISTS language ON wxo_examples TYPE string;
+            DEFINE FIELD IF NOT EXISTS code ON wxo_examples TYPE string;
+            DEFINE FIELD IF NOT EXISTS tags ON wxo_examples TYPE array<string> DEFAULT [];
+            DEFINE FIELD IF NOT EXISTS created_at ON wxo_examples TYPE datetime DEFAULT time::now();
+            DEFINE INDEX IF NOT EXISTS idx_example_language ON wxo_examples FIELDS language;
+        "#,
+    },
+    Migration {
+        version: 4,
+        description: "Add full-text index over conversation messages",
+        sql: r#"
+            DEFINE ANALYZER IF NOT EXISTS conversation_text TOKENIZERS blank, class FILTERS lowercase;
+            DEFINE INDEX IF NOT EXISTS idx_message_content ON conversations
+                FIELDS messages.*.content SEARCH ANALYZER conversation_text BM25;
+        "#,
+    },
+    Migration {
+        version: 5,
+        description: "Track the session a conversation was forked from",
+        sql: r#"
+            DEFINE FIELD IF NOT EXISTS parent_session_id ON conversations TYPE option<string>;
+            DEFINE INDEX IF NOT EXISTS idx_parent_session ON conversations FIELDS parent_session_id;
+        "#,
+    },
+    Migration {
+        version: 6,
+        description: "Record the state schema version of each conversation",
+        sql: r#"
+            DEFINE FIELD IF NOT EXISTS schema_version ON conversations TYPE int DEFAULT 0;
+        "#,
+    },
+    Migration {
+        version: 7,
+        description: "Store per-term document frequencies for search ranking",
+        sql: r#"
+            DEFINE TABLE IF NOT EXISTS doc_terms SCHEMAFULL;
+            DEFINE FIELD IF NOT EXISTS term ON doc_terms TYPE string;
+            DEFINE FIELD IF NOT EXISTS doc_freq ON doc_terms TYPE int;
+            DEFINE INDEX IF NOT EXISTS idx_doc_term ON doc_terms FIELDS term UNIQUE;
+
+            DEFINE TABLE IF NOT EXISTS doc_index SCHEMAFULL;
+            DEFINE FIELD IF NOT EXISTS doc_count ON doc_index TYPE int;
+            DEFINE FIELD IF NOT EXISTS term_count ON doc_index TYPE int;
+            DEFINE FIELD IF NOT EXISTS built_at ON doc_index TYPE datetime DEFAULT time::now();
+        "#,
+    },
+    Migration {
+        version: 8,
+        description: "Store agent_type as a plain string instead of a JSON-encoded one",
+        sql: r#"
+            UPDATE conversations SET agent_type = string::replace(agent_type, '"', '')
+                WHERE string::contains(agent_type, '"');
+        "#,
+    },
+    Migration {
+        version: 9,
+        description: "Make documentation URLs unique, keeping one record per URL",
+        sql: r#"
+            FOR $page IN (
+                SELECT url, array::group(id) AS ids FROM wxo_docs
+                WHERE url IS NOT NONE GROUP BY url
+            ) {
+                DELETE array::slice($page.ids, 1);
+            };
+            DEFINE INDEX IF NOT EXISTS idx_doc_url ON wxo_docs FIELDS url UNIQUE;
+        "#,
+    },
+];
+
+/// Times a read query is retried after a transient failure
+const QUERY_RETRIES: u32 = 2;
+
+/// Delay before the first retry of a read query; doubles after each retry
+const QUERY_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Default number of most recently updated conversations
+/// `find_similar_conversations` compares against
+pub const DEFAULT_SIMILAR_CONVERSATION_WINDOW: usize = 500;
+
+/// Migrations newer than `version`, in the order to apply them
+fn pending_migrations(version: u32) -> impl Iterator<Item = &'static Migration> {
+    MIGRATIONS.iter().filter(move |m| m.version > version)
+}
 
 /// Database client wrapper for WXOrca
 #[derive(Clone)]
 pub struct Database {
-    client: Surreal<Client>,
+    client: Surreal<Any>,
+    redact_messages: bool,
+    validate_on_load: bool,
+    similar_conversation_window: usize,
 }
 
 /// A conversation record stored in the database
@@ -26,9 +188,23 @@ pub struct Database {
 pub struct ConversationRecord {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<Thing>,
+    /// [`STATE_SCHEMA_VERSION`] the conversation was saved with; 0 for
+    /// conversations saved before versioning
+    #[serde(default)]
+    pub schema_version: u32,
     pub session_id: String,
     pub agent_type: AgentType,
     pub messages: Vec<Message>,
+    /// Records saved before these fields existed load with defaults
+    #[serde(default)]
+    pub context: WxoContext,
+    #[serde(default)]
+    pub iteration: usize,
+    #[serde(default)]
+    pub is_complete: bool,
+    /// Session this conversation was forked from, if it is a branch
+    #[serde(default)]
+    pub parent_session_id: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -48,6 +224,48 @@ pub struct DocRecord {
     pub created_at: DateTime<Utc>,
 }
 
+/// A curated code example for `FetchExamplesTool`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExampleRecord {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Thing>,
+    pub title: String,
+    pub description: String,
+    pub language: String,
+    pub code: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ExampleRecord {
+    /// Whether the example matches a topic: the whole topic or any of its
+    /// words appears in the title, description, or a tag (case-insensitive)
+    pub fn matches_topic(&self, topic: &str) -> bool {
+        let title_lower = self.title.to_lowercase();
+        let desc_lower = self.description.to_lowercase();
+        let tags_lower: Vec<String> = self.tags.iter().map(|t| t.to_lowercase()).collect();
+
+        Self::topic_terms(topic).iter().any(|term| {
+            title_lower.contains(term.as_str())
+                || desc_lower.contains(term.as_str())
+                || tags_lower.iter().any(|t| t.contains(term.as_str()))
+        })
+    }
+
+    /// The lowercased topic followed by each of its distinct words
+    fn topic_terms(topic: &str) -> Vec<String> {
+        let topic = topic.to_lowercase();
+        let mut terms = vec![topic.clone()];
+        for word in topic.split_whitespace() {
+            if !terms.iter().any(|term| term == word) {
+                terms.push(word.to_string());
+            }
+        }
+        terms
+    }
+}
+
 /// User feedback record
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FeedbackRecord {
@@ -60,9 +278,257 @@ pub struct FeedbackRecord {
     pub created_at: DateTime<Utc>,
 }
 
+/// Which feedback [`Database::query_feedback`] returns
+///
+/// Unset fields don't filter, so the default matches all feedback.
+#[derive(Debug, Clone, Default)]
+pub struct FeedbackFilter {
+    /// Only feedback for this session
+    pub session_id: Option<String>,
+    /// Only ratings of at least this
+    pub min_rating: Option<i32>,
+    /// Only ratings of at most this
+    pub max_rating: Option<i32>,
+    /// Only feedback created after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Return at most this many records
+    pub limit: Option<usize>,
+    /// Skip this many records, for paging
+    pub offset: usize,
+}
+
+impl FeedbackFilter {
+    /// Only feedback for `session_id`
+    pub fn session_id(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = Some(session_id.into());
+        self
+    }
+
+    /// Only ratings of at least `rating`
+    pub fn min_rating(mut self, rating: i32) -> Self {
+        self.min_rating = Some(rating);
+        self
+    }
+
+    /// Only ratings of at most `rating`
+    pub fn max_rating(mut self, rating: i32) -> Self {
+        self.max_rating = Some(rating);
+        self
+    }
+
+    /// Only feedback created after `since`
+    pub fn since(mut self, since: DateTime<Utc>) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    /// Return the page of at most `limit` records starting at `offset`
+    pub fn page(mut self, limit: usize, offset: usize) -> Self {
+        self.limit = Some(limit);
+        self.offset = offset;
+        self
+    }
+
+    /// Build the SurrealQL query for this filter, newest feedback first
+    fn to_query(&self) -> String {
+        let conditions: Vec<&str> = [
+            (self.session_id.is_some(), "session_id = $session_id"),
+            (self.min_rating.is_some(), "rating >= $min_rating"),
+            (self.max_rating.is_some(), "rating <= $max_rating"),
+            (self.since.is_some(), "created_at > $since"),
+        ]
+        .into_iter()
+        .filter_map(|(set, condition)| set.then_some(condition))
+        .collect();
+
+        let mut query = "SELECT * FROM feedback".to_string();
+        if !conditions.is_empty() {
+            query.push_str(" WHERE ");
+            query.push_str(&conditions.join(" AND "));
+        }
+        query.push_str(" ORDER BY created_at DESC");
+        if self.limit.is_some() {
+            query.push_str(" LIMIT $limit");
+        }
+        if self.offset > 0 {
+            query.push_str(" START $offset");
+        }
+        query
+    }
+}
+
+/// Minimum feedback rating for a session to count as highly rated
+pub const HIGH_RATING: i32 = 4;
+
+/// Feedback statistics for an agent over a time window
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RatingStats {
+    /// Number of ratings
+    pub count: usize,
+    /// Mean rating, or `None` without any ratings
+    pub mean: Option<f64>,
+    /// Number of ratings of 1 through 5, at index `rating - 1`
+    pub histogram: [usize; 5],
+}
+
+impl RatingStats {
+    /// Build stats from `(rating, count)` pairs, ignoring ratings outside 1–5
+    fn from_counts(counts: &[(i32, usize)]) -> Self {
+        let mut stats = Self::default();
+        let mut total = 0;
+
+        for &(rating, count) in counts {
+            if !(1..=5).contains(&rating) {
+                continue;
+            }
+            stats.histogram[(rating - 1) as usize] += count;
+            stats.count += count;
+            total += i64::from(rating) * count as i64;
+        }
+
+        stats.mean = (stats.count > 0).then(|| total as f64 / stats.count as f64);
+        stats
+    }
+}
+
+/// Size of the search index built by [`Database::rebuild_search_index`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexStats {
+    /// Number of documents indexed
+    pub doc_count: usize,
+    /// Number of distinct terms across those documents
+    pub term_count: usize,
+}
+
+/// The search index built by [`Database::rebuild_search_index`], from
+/// [`Database::load_search_index`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchIndex {
+    /// Number of documents indexed
+    pub doc_count: usize,
+    /// When the index was built
+    pub built_at: DateTime<Utc>,
+    /// Number of documents each word appears in
+    pub vocabulary: HashMap<String, usize>,
+}
+
+impl SearchIndex {
+    /// Stats for `terms`, matched to indexed words the way search scoring
+    /// does (see [`TermStats::matching`])
+    pub fn term_stats(&self, terms: &[String], fuzzy: bool) -> TermStats {
+        TermStats::matching(self.doc_count, terms, &self.vocabulary, fuzzy)
+    }
+}
+
+/// Conversation counts for dashboards, from
+/// [`Database::get_conversation_stats`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConversationStats {
+    /// Number of conversations
+    pub total: usize,
+    /// Conversations per agent type; agents without any are omitted
+    pub per_agent: HashMap<AgentType, usize>,
+    /// Conversations marked complete
+    pub completed: usize,
+    /// Conversations still in progress
+    pub in_progress: usize,
+    /// Mean number of messages per conversation, or `None` without any
+    /// conversations
+    pub avg_messages: Option<f64>,
+}
+
+/// Everything WXOrca stores, for backups and moving data between
+/// environments
+///
+/// Serializes to JSON, so an export can be written to a file and imported
+/// elsewhere with [`Database::import_all`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DbExport {
+    /// Schema version of the database the data came from
+    pub schema_version: u32,
+    pub exported_at: DateTime<Utc>,
+    #[serde(default)]
+    pub conversations: Vec<ConversationRecord>,
+    #[serde(default)]
+    pub docs: Vec<DocRecord>,
+    #[serde(default)]
+    pub examples: Vec<ExampleRecord>,
+    #[serde(default)]
+    pub feedback: Vec<FeedbackRecord>,
+}
+
+impl DbExport {
+    /// Serialize as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize export")
+    }
+
+    /// Parse an export written by [`DbExport::to_json`]
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).context("Failed to parse export")
+    }
+}
+
+/// How [`Database::import_all`] treats data already in the database
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    /// Keep existing data and add the export to it
+    ///
+    /// Records are matched by natural key: conversations by session ID (the
+    /// more recently updated copy wins) and docs by URL (the export wins).
+    /// Docs without a URL, examples and feedback are skipped if an identical
+    /// record already exists.
+    Merge,
+    /// Delete all existing data first, leaving only the export
+    Replace,
+}
+
+/// RocksDB directory used when `SURREAL_BACKEND=rocksdb` and `SURREAL_PATH`
+/// is unset
+pub const DEFAULT_ROCKSDB_PATH: &str = "wxorca.db";
+
+/// Storage engine a [`Database`] connects to
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DbBackend {
+    /// A SurrealDB server over WebSocket, at `host:port`
+    #[default]
+    Remote,
+    /// An embedded in-memory database, gone when the process exits; handy
+    /// for tests and demos
+    Memory,
+    /// An embedded database persisted to a RocksDB directory
+    ///
+    /// Needs this crate's `rocksdb` feature, which enables SurrealDB's
+    /// `kv-rocksdb` engine; connecting fails otherwise.
+    RocksDb(PathBuf),
+}
+
+impl DbBackend {
+    /// Parse a backend name as used in `SURREAL_BACKEND`
+    ///
+    /// `rocksdb` stores its data at `path`.
+    pub fn from_name(name: &str, path: impl Into<PathBuf>) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "remote" | "ws" => Some(DbBackend::Remote),
+            "memory" | "mem" => Some(DbBackend::Memory),
+            "rocksdb" => Some(DbBackend::RocksDb(path.into())),
+            _ => None,
+        }
+    }
+
+    /// Whether the database runs inside this process
+    pub fn is_embedded(&self) -> bool {
+        !matches!(self, DbBackend::Remote)
+    }
+}
+
 /// Configuration for database connection
 #[derive(Debug, Clone)]
 pub struct DbConfig {
+    /// Storage engine; `host`, `port`, `username` and `password` only apply
+    /// to [`DbBackend::Remote`]
+    pub backend: DbBackend,
     pub host: String,
     pub port: u16,
     pub username: String,
@@ -74,6 +540,7 @@ pub struct DbConfig {
 impl Default for DbConfig {
     fn default() -> Self {
         Self {
+            backend: DbBackend::Remote,
             host: "localhost".to_string(),
             port: 8000,
             username: "root".to_string(),
@@ -85,9 +552,34 @@ impl Default for DbConfig {
 }
 
 impl DbConfig {
+    /// Config for an embedded in-memory database
+    pub fn memory() -> Self {
+        Self {
+            backend: DbBackend::Memory,
+            ..Self::default()
+        }
+    }
+
+    /// Config for an embedded database stored in the RocksDB directory `path`
+    pub fn rocksdb(path: impl Into<PathBuf>) -> Self {
+        Self {
+            backend: DbBackend::RocksDb(path.into()),
+            ..Self::default()
+        }
+    }
+
     /// Create config from environment variables
+    ///
+    /// `SURREAL_BACKEND` picks the engine (`remote`, `memory` or `rocksdb`,
+    /// default `remote`); `rocksdb` stores data at `SURREAL_PATH`.
     pub fn from_env() -> Self {
+        let path =
+            std::env::var("SURREAL_PATH").unwrap_or_else(|_| DEFAULT_ROCKSDB_PATH.to_string());
         Self {
+            backend: std::env::var("SURREAL_BACKEND")
+                .ok()
+                .and_then(|name| DbBackend::from_name(&name, path))
+                .unwrap_or_default(),
             host: std::env::var("SURREAL_HOST").unwrap_or_else(|_| "localhost".to_string()),
             port: std::env::var("SURREAL_PORT")
                 .ok()
@@ -104,22 +596,44 @@ impl DbConfig {
     pub fn url(&self) -> String {
         format!("{}:{}", self.host, self.port)
     }
+
+    /// Get the endpoint for the configured backend, with its scheme
+    pub fn endpoint(&self) -> String {
+        match &self.backend {
+            DbBackend::Remote => format!("ws://{}", self.url()),
+            DbBackend::Memory => "mem://".to_string(),
+            DbBackend::RocksDb(path) => format!("rocksdb://{}", path.display()),
+        }
+    }
 }
 
 impl Database {
     /// Connect to SurrealDB with the given configuration
+    ///
+    /// Embedded backends need no server or credentials; everything else
+    /// works the same as against a remote server.
     pub async fn connect(config: &DbConfig) -> Result<Self> {
-        let client = Surreal::new::<Ws>(&config.url())
-            .await
-            .context("Failed to connect to SurrealDB")?;
+        #[cfg(not(feature = "rocksdb"))]
+        if let DbBackend::RocksDb(path) = &config.backend {
+            anyhow::bail!(
+                "Cannot open the RocksDB database at {}: wxorca-agents was built without the `rocksdb` feature",
+                path.display()
+            );
+        }
 
-        client
-            .signin(Root {
-                username: &config.username,
-                password: &config.password,
-            })
+        let client = any::connect(config.endpoint())
             .await
-            .context("Failed to authenticate with SurrealDB")?;
+            .with_context(|| format!("Failed to connect to SurrealDB at {}", config.endpoint()))?;
+
+        if !config.backend.is_embedded() {
+            client
+                .signin(Root {
+                    username: &config.username,
+                    password: &config.password,
+                })
+                .await
+                .context("Failed to authenticate with SurrealDB")?;
+        }
 
         client
             .use_ns(&config.namespace)
@@ -127,72 +641,272 @@ impl Database {
             .await
             .context("Failed to select namespace and database")?;
 
-        Ok(Self { client })
+        Ok(Self::from_client(client))
+    }
+
+    /// Connect to SurrealDB, retrying with exponential backoff
+    ///
+    /// Useful when the database may still be starting up. Each new connection
+    /// is checked with [`Database::ping`], so one that drops straight away
+    /// counts as a failed attempt. The delay doubles after each failed
+    /// attempt; the last error is returned if every attempt fails.
+    pub async fn connect_with_retry(
+        config: &DbConfig,
+        max_attempts: u32,
+        initial_backoff: Duration,
+    ) -> Result<Self> {
+        Self::retry_connect(
+            &config.endpoint(),
+            max_attempts,
+            initial_backoff,
+            || async {
+                let db = Self::connect(config).await?;
+                db.ping().await?;
+                Ok(db)
+            },
+        )
+        .await
+    }
+
+    /// Run `connect` up to `max_attempts` times, doubling the delay from
+    /// `initial_backoff` after each failure, and return the last error if
+    /// none succeeds
+    async fn retry_connect<T, F, Fut>(
+        endpoint: &str,
+        max_attempts: u32,
+        initial_backoff: Duration,
+        connect: F,
+    ) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        let max_attempts = max_attempts.max(1);
+        let mut backoff = initial_backoff;
+        let mut attempt = 1;
+
+        loop {
+            tracing::debug!(
+                "Connecting to SurrealDB at {} (attempt {}/{})",
+                endpoint,
+                attempt,
+                max_attempts
+            );
+
+            match connect().await {
+                Ok(db) => return Ok(db),
+                Err(e) if attempt >= max_attempts => {
+                    tracing::error!(
+                        "Giving up connecting to SurrealDB after {} attempts: {:#}",
+                        attempt,
+                        e
+                    );
+                    return Err(e);
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "SurrealDB connection attempt {}/{} failed, retrying in {:?}: {:#}",
+                        attempt,
+                        max_attempts,
+                        backoff,
+                        e
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff = backoff.saturating_mul(2);
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Send a read query built by `run`, retrying transient failures (see
+    /// [`is_transient`]) up to [`QUERY_RETRIES`] times
+    ///
+    /// Other errors are returned straight away, wrapped with `what`. `run`
+    /// should `check()` the response, so errors raised by a statement (such
+    /// as a timeout) are retried too rather than surfacing on `take`.
+    async fn query_with_retry<T, F, Fut>(what: &str, run: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: std::future::Future<Output = surrealdb::Result<T>>,
+    {
+        let mut backoff = QUERY_RETRY_BACKOFF;
+        let mut retries = 0;
+
+        loop {
+            match run().await {
+                Ok(response) => return Ok(response),
+                Err(e) if retries < QUERY_RETRIES && is_transient(&e) => {
+                    tracing::warn!("{}, retrying in {:?}: {}", what, backoff, e);
+                    tokio::time::sleep(backoff).await;
+                    backoff = backoff.saturating_mul(2);
+                    retries += 1;
+                }
+                Err(e) => return Err(anyhow::Error::new(e).context(what.to_string())),
+            }
+        }
+    }
+
+    /// Check that the connection is still alive
+    ///
+    /// Issues a trivial query and succeeds only if the round-trip does, so
+    /// health checks can detect a dropped WebSocket connection and reconnect
+    /// with [`Database::connect_with_retry`].
+    pub async fn ping(&self) -> Result<()> {
+        let mut result = self
+            .client
+            .query("RETURN true")
+            .await
+            .context("Failed to ping SurrealDB")?;
+
+        let alive: Option<bool> = result.take(0).context("Failed to read ping response")?;
+        if alive != Some(true) {
+            anyhow::bail!("Unexpected ping response from SurrealDB: {:?}", alive);
+        }
+
+        Ok(())
+    }
+
+    /// Wrap an already connected client (e.g. the one `SearchDocsTool` uses)
+    pub fn from_client(client: Surreal<Any>) -> Self {
+        Self {
+            client,
+            redact_messages: false,
+            validate_on_load: false,
+            similar_conversation_window: DEFAULT_SIMILAR_CONVERSATION_WINDOW,
+        }
+    }
+
+    /// Mask emails, API keys and tokens in message content before saving
+    /// conversations (see [`redact_pii`])
+    pub fn with_pii_redaction(mut self, redact: bool) -> Self {
+        self.redact_messages = redact;
+        self
+    }
+
+    /// Reject loaded conversations that fail [`WxorcaState::validate`]
+    pub fn with_state_validation(mut self, validate: bool) -> Self {
+        self.validate_on_load = validate;
+        self
+    }
+
+    /// Compare against this many of the most recently updated conversations
+    /// in `find_similar_conversations`, instead of
+    /// [`DEFAULT_SIMILAR_CONVERSATION_WINDOW`]
+    pub fn with_similar_conversation_window(mut self, window: usize) -> Self {
+        self.similar_conversation_window = window;
+        self
+    }
+
+    /// Get the underlying SurrealDB client, e.g. to share it with `SearchDocsTool`
+    pub fn client(&self) -> &Surreal<Any> {
+        &self.client
     }
 
     /// Initialize the database schema
+    ///
+    /// Equivalent to [`migrate`](Database::migrate), kept for existing callers.
     pub async fn init_schema(&self) -> Result<()> {
-        // Conversations table
-        self.client
-            .query(
-                r#"
-                DEFINE TABLE IF NOT EXISTS conversations SCHEMAFULL;
-                DEFINE FIELD session_id ON conversations TYPE string;
-                DEFINE FIELD agent_type ON conversations TYPE string;
-                DEFINE FIELD messages ON conversations TYPE array;
-                DEFINE FIELD created_at ON conversations TYPE datetime DEFAULT time::now();
-                DEFINE FIELD updated_at ON conversations TYPE datetime DEFAULT time::now();
-                DEFINE INDEX idx_session ON conversations FIELDS session_id UNIQUE;
-                "#,
-            )
-            .await
-            .context("Failed to create conversations table")?;
+        self.migrate().await.map(|_| ())
+    }
 
-        // Documentation table for RAG
+    /// Bring the schema up to date, returning the resulting version
+    ///
+    /// Applies each entry of [`MIGRATIONS`] newer than the version recorded in
+    /// the `schema_version` table, in order. Each migration and its version
+    /// record commit together, so a failed migration is retried on the next
+    /// run.
+    pub async fn migrate(&self) -> Result<u32> {
         self.client
             .query(
                 r#"
-                DEFINE TABLE IF NOT EXISTS wxo_docs SCHEMAFULL;
-                DEFINE FIELD title ON wxo_docs TYPE string;
-                DEFINE FIELD content ON wxo_docs TYPE string;
-                DEFINE FIELD category ON wxo_docs TYPE string;
-                DEFINE FIELD url ON wxo_docs TYPE option<string>;
-                DEFINE FIELD embedding ON wxo_docs TYPE array DEFAULT [];
-                DEFINE FIELD created_at ON wxo_docs TYPE datetime DEFAULT time::now();
-                DEFINE INDEX idx_category ON wxo_docs FIELDS category;
+                DEFINE TABLE IF NOT EXISTS schema_version SCHEMAFULL;
+                DEFINE FIELD IF NOT EXISTS version ON schema_version TYPE int;
+                DEFINE FIELD IF NOT EXISTS description ON schema_version TYPE string;
+                DEFINE FIELD IF NOT EXISTS applied_at ON schema_version TYPE datetime DEFAULT time::now();
                 "#,
             )
             .await
-            .context("Failed to create wxo_docs table")?;
+            .context("Failed to create schema_version table")?;
 
-        // Feedback table
-        self.client
-            .query(
-                r#"
-                DEFINE TABLE IF NOT EXISTS feedback SCHEMAFULL;
-                DEFINE FIELD session_id ON feedback TYPE string;
-                DEFINE FIELD message_id ON feedback TYPE option<string>;
-                DEFINE FIELD rating ON feedback TYPE int;
-                DEFINE FIELD comment ON feedback TYPE option<string>;
-                DEFINE FIELD created_at ON feedback TYPE datetime DEFAULT time::now();
-                DEFINE INDEX idx_feedback_session ON feedback FIELDS session_id;
-                "#,
-            )
+        let mut version = self.schema_version().await?;
+        for migration in pending_migrations(version) {
+            tracing::info!(
+                version = migration.version,
+                "Applying schema migration: {}",
+                migration.description
+            );
+            self.client
+                .query(format!(
+                    "BEGIN TRANSACTION;\n{}\nCREATE schema_version CONTENT {{ version: $version, description: $description }};\nCOMMIT TRANSACTION;",
+                    migration.sql
+                ))
+                .bind(("version", migration.version))
+                .bind(("description", migration.description))
+                .await
+                .and_then(|response| response.check())
+                .with_context(|| {
+                    format!("Failed to apply schema migration {}", migration.version)
+                })?;
+            version = migration.version;
+        }
+
+        Ok(version)
+    }
+
+    /// Get the latest applied schema version, or 0 for a new database
+    pub async fn schema_version(&self) -> Result<u32> {
+        #[derive(Deserialize)]
+        struct VersionRow {
+            version: u32,
+        }
+
+        let mut result = self
+            .client
+            .query("SELECT version FROM schema_version ORDER BY version DESC LIMIT 1")
             .await
-            .context("Failed to create feedback table")?;
+            .context("Failed to read schema version")?;
+        let rows: Vec<VersionRow> = result.take(0)?;
 
-        Ok(())
+        Ok(rows.first().map(|row| row.version).unwrap_or(0))
     }
 
     // ==================== Conversation Operations ====================
 
+    /// `context` as stored, with its topic and metadata redacted if the
+    /// database was created [`with_pii_redaction`](Database::with_pii_redaction)
+    fn stored_context(&self, context: &WxoContext) -> WxoContext {
+        let mut context = context.clone();
+        if self.redact_messages {
+            context.current_topic = context.current_topic.as_deref().map(redact_pii);
+            context.metadata.values_mut().for_each(redact_json);
+        }
+        context
+    }
+
     /// Save or update a conversation
+    ///
+    /// Message content, the current topic and context metadata are redacted
+    /// first if the database was created
+    /// [`with_pii_redaction`](Database::with_pii_redaction).
     pub async fn save_conversation(&self, state: &WxorcaState) -> Result<()> {
+        let mut messages = state.messages.clone();
+        if self.redact_messages {
+            for message in &mut messages {
+                message.content = redact_pii(&message.content);
+            }
+        }
+
         let record = ConversationRecord {
             id: None,
+            schema_version: STATE_SCHEMA_VERSION,
             session_id: state.session_id.clone(),
             agent_type: state.agent_type,
-            messages: state.messages.clone(),
+            messages,
+            context: self.stored_context(&state.context),
+            iteration: state.iteration,
+            is_complete: state.is_complete,
+            parent_session_id: state.parent_session_id.clone(),
             created_at: state.created_at,
             updated_at: state.updated_at,
         };
@@ -202,24 +916,38 @@ impl Database {
             .query(
                 r#"
                 UPDATE conversations SET
+                    schema_version = $schema_version,
                     agent_type = $agent_type,
                     messages = $messages,
+                    context = $context,
+                    iteration = $iteration,
+                    is_complete = $is_complete,
                     updated_at = time::now()
                 WHERE session_id = $session_id;
 
                 IF (SELECT * FROM conversations WHERE session_id = $session_id).len() == 0 {
                     CREATE conversations SET
+                        schema_version = $schema_version,
                         session_id = $session_id,
                         agent_type = $agent_type,
                         messages = $messages,
+                        context = $context,
+                        iteration = $iteration,
+                        is_complete = $is_complete,
+                        parent_session_id = $parent_session_id,
                         created_at = $created_at,
                         updated_at = time::now()
                 };
                 "#,
             )
+            .bind(("schema_version", record.schema_version))
             .bind(("session_id", record.session_id.clone()))
-            .bind(("agent_type", serde_json::to_string(&record.agent_type)?))
+            .bind(("agent_type", record.agent_type))
             .bind(("messages", record.messages.clone()))
+            .bind(("context", record.context.clone()))
+            .bind(("iteration", record.iteration))
+            .bind(("is_complete", record.is_complete))
+            .bind(("parent_session_id", record.parent_session_id.clone()))
             .bind(("created_at", record.created_at))
             .await
             .context("Failed to save conversation")?;
@@ -227,23 +955,121 @@ impl Database {
         Ok(())
     }
 
-    /// Load a conversation by session ID
-    pub async fn load_conversation(&self, session_id: &str) -> Result<Option<WxorcaState>> {
-        let session_id = session_id.to_string();
+    /// Append messages to a saved conversation without rewriting the ones
+    /// already stored
+    ///
+    /// The append is a single update, so concurrent turns on the same
+    /// session can't overwrite each other's messages. Message content is
+    /// redacted as in [`save_conversation`](Database::save_conversation).
+    pub async fn append_messages(&self, session_id: &str, new_msgs: &[Message]) -> Result<()> {
+        if new_msgs.is_empty() {
+            return Ok(());
+        }
+
+        let mut messages = new_msgs.to_vec();
+        if self.redact_messages {
+            for message in &mut messages {
+                message.content = redact_pii(&message.content);
+            }
+        }
+
         let mut result = self
             .client
-            .query("SELECT * FROM conversations WHERE session_id = $session_id")
-            .bind(("session_id", session_id))
+            .query(
+                r#"
+                UPDATE conversations SET
+                    messages += $messages,
+                    updated_at = time::now()
+                WHERE session_id = $session_id
+                RETURN VALUE session_id
+                "#,
+            )
+            .bind(("session_id", session_id.to_string()))
+            .bind(("messages", messages))
             .await
-            .context("Failed to query conversation")?;
+            .context("Failed to append messages")?;
 
-        let records: Vec<ConversationRecord> = result.take(0)?;
+        let updated: Vec<String> = result.take(0)?;
+        if updated.is_empty() {
+            anyhow::bail!("No conversation found for session {}", session_id);
+        }
+        Ok(())
+    }
+
+    /// Save the turn's changes to a conversation
+    ///
+    /// Messages past [`WxorcaState::persisted_messages`] are appended with
+    /// [`append_messages`](Database::append_messages) and the rest of the
+    /// state is updated in place. A conversation that was never saved, or
+    /// whose saved messages were rewritten (e.g. by truncation), is saved in
+    /// full.
+    pub async fn save_turn(&self, state: &mut WxorcaState) -> Result<()> {
+        let persisted = state.persisted_messages;
+        if persisted == 0 || persisted > state.messages.len() {
+            self.save_conversation(state).await?;
+            state.persisted_messages = state.messages.len();
+            return Ok(());
+        }
+
+        self.append_messages(&state.session_id, &state.messages[persisted..])
+            .await?;
+        state.persisted_messages = state.messages.len();
+
+        self.client
+            .query(
+                r#"
+                UPDATE conversations SET
+                    agent_type = $agent_type,
+                    context = $context,
+                    iteration = $iteration,
+                    is_complete = $is_complete,
+                    updated_at = time::now()
+                WHERE session_id = $session_id
+                "#,
+            )
+            .bind(("session_id", state.session_id.clone()))
+            .bind(("agent_type", state.agent_type))
+            .bind(("context", self.stored_context(&state.context)))
+            .bind(("iteration", state.iteration))
+            .bind(("is_complete", state.is_complete))
+            .await
+            .context("Failed to save conversation")?;
+
+        Ok(())
+    }
+
+    /// Load a conversation by session ID
+    ///
+    /// Conversations saved by older versions are upgraded with
+    /// [`migrate_state`]. With [`with_state_validation`](Database::with_state_validation), a
+    /// stored conversation that violates the state's invariants is an error.
+    pub async fn load_conversation(&self, session_id: &str) -> Result<Option<WxorcaState>> {
+        let mut result = Self::query_with_retry("Failed to query conversation", || async {
+            self.client
+                .query("SELECT * OMIT id FROM conversations WHERE session_id = $session_id")
+                .bind(("session_id", session_id.to_string()))
+                .await?
+                .check()
+        })
+        .await?;
+
+        // Read the raw record so conversations saved by older versions can
+        // be upgraded before deserializing
+        let records: Vec<serde_json::Value> = result.take(0)?;
 
         if let Some(record) = records.into_iter().next() {
-            let mut state = WxorcaState::with_session_id(record.agent_type, record.session_id);
-            state.messages = record.messages;
-            state.created_at = record.created_at;
-            state.updated_at = record.updated_at;
+            let mut state = migrate_state(record)
+                .with_context(|| format!("Failed to load conversation {}", session_id))?;
+            state.persisted_messages = state.messages.len();
+            if self.validate_on_load {
+                if let Err(violations) = state.validate() {
+                    anyhow::bail!(
+                        "Conversation {} is inconsistent: {}",
+                        state.session_id,
+                        violations.join("; ")
+                    );
+                }
+            }
             Ok(Some(state))
         } else {
             Ok(None)
@@ -262,68 +1088,456 @@ impl Database {
         Ok(())
     }
 
+    /// Delete conversations last updated before `cutoff`, along with their
+    /// feedback, returning how many conversations were deleted
+    pub async fn delete_conversations_older_than(&self, cutoff: DateTime<Utc>) -> Result<usize> {
+        // One transaction, so no feedback is orphaned and conversations
+        // updated while purging are either kept or deleted with theirs
+        let mut result = self
+            .client
+            .query(
+                r#"
+                BEGIN TRANSACTION;
+                DELETE feedback WHERE session_id IN (
+                    SELECT VALUE session_id FROM conversations WHERE updated_at < $cutoff
+                );
+                DELETE conversations WHERE updated_at < $cutoff RETURN BEFORE;
+                COMMIT TRANSACTION;
+                "#,
+            )
+            .bind(("cutoff", cutoff))
+            .await
+            .context("Failed to delete expired conversations")?
+            .check()
+            .context("Failed to delete expired conversations")?;
+
+        let expired: Vec<ConversationRecord> = result.take(1)?;
+        if !expired.is_empty() {
+            tracing::info!(count = expired.len(), %cutoff, "Deleted expired conversations");
+        }
+        Ok(expired.len())
+    }
+
+    /// Delete conversations not updated in the last `retention_days` days
+    pub async fn purge_expired(&self, retention_days: u32) -> Result<usize> {
+        self.delete_conversations_older_than(retention_cutoff(Utc::now(), retention_days))
+            .await
+    }
+
     /// List recent conversations
     pub async fn list_conversations(&self, limit: usize) -> Result<Vec<ConversationRecord>> {
+        let (records, _) = self.list_conversations_paged(limit, 0).await?;
+        Ok(records)
+    }
+
+    /// List the conversations forked from `session_id`, oldest first
+    pub async fn list_branches(&self, session_id: &str) -> Result<Vec<ConversationRecord>> {
+        let session_id = session_id.to_string();
         let mut result = self
             .client
-            .query("SELECT * FROM conversations ORDER BY updated_at DESC LIMIT $limit")
-            .bind(("limit", limit))
+            .query(
+                "SELECT * FROM conversations WHERE parent_session_id = $session_id ORDER BY created_at ASC",
+            )
+            .bind(("session_id", session_id))
             .await
-            .context("Failed to list conversations")?;
+            .context("Failed to list conversation branches")?;
 
         let records: Vec<ConversationRecord> = result.take(0)?;
         Ok(records)
     }
 
-    // ==================== Documentation Operations ====================
+    /// List a page of conversations, newest first, along with the total count
+    pub async fn list_conversations_paged(
+        &self,
+        limit: usize,
+        offset: usize,
+    ) -> Result<(Vec<ConversationRecord>, usize)> {
+        let mut result = Self::query_with_retry("Failed to list conversations", || async {
+            self.client
+                .query(
+                    r#"
+                    SELECT * FROM conversations ORDER BY updated_at DESC LIMIT $limit START $offset;
+                    SELECT count() AS total FROM conversations GROUP ALL;
+                    "#,
+                )
+                .bind(("limit", limit))
+                .bind(("offset", offset))
+                .await?
+                .check()
+        })
+        .await?;
 
-    /// Add a documentation record
-    pub async fn add_doc(&self, doc: &DocRecord) -> Result<Thing> {
-        let doc = doc.clone();
-        let created: Option<DocRecord> = self
-            .client
-            .create("wxo_docs")
-            .content(doc)
-            .await
-            .context("Failed to add documentation")?;
+        #[derive(Deserialize)]
+        struct CountRow {
+            total: usize,
+        }
 
-        created
-            .and_then(|d| d.id)
-            .ok_or_else(|| anyhow::anyhow!("Failed to get created doc ID"))
+        let records: Vec<ConversationRecord> = result.take(0)?;
+        let counts: Vec<CountRow> = result.take(1)?;
+        let total = counts.into_iter().next().map(|r| r.total).unwrap_or(0);
+
+        Ok((records, total))
     }
 
-    /// Search documentation by text query (simple contains search)
-    pub async fn search_docs(&self, query: &str, limit: usize) -> Result<Vec<DocRecord>> {
-        let query = query.to_string();
+    /// List a page of conversation previews, most recently updated first
+    ///
+    /// Only the summary fields are computed and returned by the database, so
+    /// full message arrays are never transferred.
+    pub async fn list_conversation_summaries(
+        &self,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<ConversationSummary>> {
         let mut result = self
             .client
             .query(
                 r#"
-                SELECT * FROM wxo_docs
-                WHERE content CONTAINS $query OR title CONTAINS $query
-                LIMIT $limit
+                SELECT
+                    session_id,
+                    agent_type,
+                    array::len(messages) AS message_count,
+                    messages[WHERE role = 'user'][0].content AS first_user_message,
+                    updated_at AS last_updated,
+                    is_complete
+                FROM conversations
+                ORDER BY last_updated DESC
+                LIMIT $limit START $offset
                 "#,
             )
-            .bind(("query", query))
             .bind(("limit", limit))
+            .bind(("offset", offset))
             .await
-            .context("Failed to search documentation")?;
+            .context("Failed to list conversation summaries")?;
 
-        let records: Vec<DocRecord> = result.take(0)?;
-        Ok(records)
+        let mut summaries: Vec<ConversationSummary> = result.take(0)?;
+        for summary in &mut summaries {
+            summary.first_user_message = summary
+                .first_user_message
+                .take()
+                .map(|m| truncate_excerpt(&m, SUMMARY_PREVIEW_CHARS));
+        }
+
+        Ok(summaries)
+    }
+
+    /// Find other conversations that open with nearly the same question as
+    /// `session_id`, most similar first
+    ///
+    /// Compares first user messages by text similarity ([`text_similarity`],
+    /// the cosine of their word counts, ignoring stopwords), not embeddings,
+    /// so the same question asked in different words won't match. Only the
+    /// most recently updated conversations are compared, see
+    /// [`with_similar_conversation_window`](Database::with_similar_conversation_window).
+    /// Returns the session ids scoring at least `threshold`, from 0.0 to
+    /// 1.0. Fails if there is no conversation for `session_id`; one without
+    /// a user message matches nothing.
+    pub async fn find_similar_conversations(
+        &self,
+        session_id: &str,
+        threshold: f32,
+    ) -> Result<Vec<String>> {
+        let mut result = self
+            .client
+            .query(
+                r#"
+                SELECT session_id, messages[WHERE role = 'user'][0].content AS first_user_message
+                FROM conversations WHERE session_id = $session_id;
+                SELECT session_id, messages[WHERE role = 'user'][0].content AS first_user_message,
+                    updated_at
+                FROM conversations WHERE session_id != $session_id
+                ORDER BY updated_at DESC LIMIT $limit;
+                "#,
+            )
+            .bind(("session_id", session_id.to_string()))
+            .bind(("limit", self.similar_conversation_window))
+            .await
+            .context("Failed to load conversations for comparison")?;
+
+        #[derive(Deserialize)]
+        struct OpeningRow {
+            session_id: String,
+            first_user_message: Option<String>,
+        }
+
+        let target: Option<OpeningRow> = result.take(0)?;
+        let target =
+            target.with_context(|| format!("No conversation found for session {}", session_id))?;
+        let Some(ref question) = target.first_user_message else {
+            return Ok(Vec::new());
+        };
+
+        let rows: Vec<OpeningRow> = result.take(1)?;
+        let mut similar: Vec<(f32, &str)> = rows
+            .iter()
+            .filter_map(|row| {
+                let score = text_similarity(question, row.first_user_message.as_deref()?);
+                (score >= threshold).then_some((score, row.session_id.as_str()))
+            })
+            .collect();
+        similar.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        Ok(similar
+            .into_iter()
+            .map(|(_, session_id)| session_id.to_string())
+            .collect())
+    }
+
+    /// Find conversations where any message contains `needle`, most recently
+    /// updated first
+    ///
+    /// Uses the full-text index on message content, so matching is by
+    /// case-insensitive words rather than raw substrings.
+    pub async fn search_conversations_by_text(
+        &self,
+        needle: &str,
+        limit: usize,
+    ) -> Result<Vec<ConversationRecord>> {
+        let needle = needle.trim().to_string();
+        if needle.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut result = self
+            .client
+            .query(
+                r#"
+                SELECT * FROM conversations
+                WHERE messages.*.content @@ $needle
+                ORDER BY updated_at DESC
+                LIMIT $limit
+                "#,
+            )
+            .bind(("needle", needle))
+            .bind(("limit", limit))
+            .await
+            .context("Failed to search conversations")?;
+
+        let records: Vec<ConversationRecord> = result.take(0)?;
+        Ok(records)
+    }
+
+    // ==================== Documentation Operations ====================
+
+    /// Add a documentation record
+    pub async fn add_doc(&self, doc: &DocRecord) -> Result<Thing> {
+        let doc = doc.clone();
+        let created: Option<DocRecord> = self
+            .client
+            .create("wxo_docs")
+            .content(doc)
+            .await
+            .context("Failed to add documentation")?;
+
+        created
+            .and_then(|d| d.id)
+            .ok_or_else(|| anyhow::anyhow!("Failed to get created doc ID"))
+    }
+
+    /// Add a documentation record, first embedding its title and content
+    /// with `embedder`
+    pub async fn add_doc_with_embedding(
+        &self,
+        mut doc: DocRecord,
+        embedder: &dyn Embedder,
+    ) -> Result<Thing> {
+        doc.embedding = embedder
+            .embed(&format!("{}\n\n{}", doc.title, doc.content))
+            .await
+            .context("Failed to embed documentation")?;
+        self.add_doc(&doc).await
+    }
+
+    /// Add many documentation records in one query, returning their IDs in
+    /// the order given
+    ///
+    /// The records are inserted by a single statement, so if any of them is
+    /// rejected none are added.
+    pub async fn bulk_add_docs(&self, docs: &[DocRecord]) -> Result<Vec<Thing>> {
+        if docs.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut result = self
+            .client
+            .query("INSERT INTO wxo_docs $docs")
+            .bind(("docs", docs.to_vec()))
+            .await
+            .context("Failed to bulk add documentation")?;
+        let created: Vec<DocRecord> = result
+            .take(0)
+            .context("Failed to bulk add documentation; no records were added")?;
+
+        if created.len() != docs.len() {
+            anyhow::bail!(
+                "Bulk add created {} of {} documentation records",
+                created.len(),
+                docs.len()
+            );
+        }
+        let ids = created
+            .into_iter()
+            .map(|d| d.id)
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| anyhow::anyhow!("Failed to get created doc IDs"))?;
+
+        tracing::info!(count = ids.len(), "Added documentation records");
+        Ok(ids)
+    }
+
+    /// Get a documentation record by ID
+    pub async fn get_doc_by_id(&self, id: &Thing) -> Result<Option<DocRecord>> {
+        let mut result = self
+            .client
+            .query("SELECT * FROM $id")
+            .bind(("id", id.clone()))
+            .await
+            .context("Failed to get documentation")?;
+
+        let records: Vec<DocRecord> = result.take(0)?;
+        Ok(records.into_iter().next())
+    }
+
+    /// Replace the contents of an existing documentation record
+    ///
+    /// `doc.id` is ignored in favour of `id`. Fails if no record has that ID.
+    pub async fn update_doc(&self, id: &Thing, doc: &DocRecord) -> Result<()> {
+        let doc = DocRecord {
+            id: None,
+            ..doc.clone()
+        };
+        let mut result = self
+            .client
+            .query("UPDATE $id CONTENT $doc")
+            .bind(("id", id.clone()))
+            .bind(("doc", doc))
+            .await
+            .context("Failed to update documentation")?;
+
+        let updated: Vec<DocRecord> = result.take(0)?;
+        if updated.is_empty() {
+            anyhow::bail!("Documentation record {} not found", id);
+        }
+        Ok(())
+    }
+
+    /// Delete a documentation record. Fails if no record has that ID.
+    pub async fn delete_doc(&self, id: &Thing) -> Result<()> {
+        let mut result = self
+            .client
+            .query("DELETE $id RETURN BEFORE")
+            .bind(("id", id.clone()))
+            .await
+            .context("Failed to delete documentation")?;
+
+        let deleted: Vec<DocRecord> = result.take(0)?;
+        if deleted.is_empty() {
+            anyhow::bail!("Documentation record {} not found", id);
+        }
+        Ok(())
+    }
+
+    /// Update the documentation record with the same URL as `doc`, or add
+    /// it if there is none, returning the record's ID
+    ///
+    /// Used to sync docs from upstream, where the URL identifies a page. A
+    /// single `UPSERT`, so concurrent syncs can't add the same page twice.
+    pub async fn upsert_doc_by_url(&self, doc: &DocRecord) -> Result<Thing> {
+        let Some(url) = doc.url.clone() else {
+            anyhow::bail!(
+                "Cannot upsert documentation \"{}\" without a URL",
+                doc.title
+            );
+        };
+        let doc = DocRecord {
+            id: None,
+            ..doc.clone()
+        };
+
+        let mut result = self
+            .client
+            .query("UPSERT wxo_docs CONTENT $doc WHERE url = $url")
+            .bind(("doc", doc))
+            .bind(("url", url))
+            .await
+            .context("Failed to upsert documentation")?;
+        let upserted: Vec<DocRecord> = result.take(0)?;
+
+        upserted
+            .into_iter()
+            .next()
+            .and_then(|d| d.id)
+            .ok_or_else(|| anyhow::anyhow!("Failed to get upserted documentation ID"))
+    }
+
+    /// Search documentation by text query (simple contains search)
+    pub async fn search_docs(&self, query: &str, limit: usize) -> Result<Vec<DocRecord>> {
+        let mut result = Self::query_with_retry("Failed to search documentation", || async {
+            self.client
+                .query(
+                    r#"
+                    SELECT * FROM wxo_docs
+                    WHERE content CONTAINS $query OR title CONTAINS $query
+                    LIMIT $limit
+                    "#,
+                )
+                .bind(("query", query.to_string()))
+                .bind(("limit", limit))
+                .await?
+                .check()
+        })
+        .await?;
+
+        let records: Vec<DocRecord> = result.take(0)?;
+        Ok(records)
+    }
+
+    /// Search documentation by cosine similarity to a query embedding
+    ///
+    /// Returns the most similar records first. Records whose embedding has a
+    /// different dimension than the query (including empty embeddings) are
+    /// skipped. If `min_score` is set, records scoring below it are dropped.
+    pub async fn search_docs_by_embedding(
+        &self,
+        query_embedding: &[f32],
+        limit: usize,
+        min_score: Option<f32>,
+    ) -> Result<Vec<DocRecord>> {
+        if query_embedding.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut result = self
+            .client
+            .query(
+                r#"
+                SELECT *, vector::similarity::cosine(embedding, $embedding) AS score
+                FROM wxo_docs
+                WHERE array::len(embedding) = array::len($embedding)
+                    AND vector::similarity::cosine(embedding, $embedding) >= $min_score
+                ORDER BY score DESC
+                LIMIT $limit
+                "#,
+            )
+            .bind(("embedding", query_embedding.to_vec()))
+            .bind(("min_score", min_score.unwrap_or(-1.0)))
+            .bind(("limit", limit))
+            .await
+            .context("Failed to search documentation by embedding")?;
+
+        let records: Vec<DocRecord> = result.take(0)?;
+        Ok(records)
     }
 
     /// Search documentation by category
     pub async fn search_docs_by_category(
         &self,
-        category: &str,
+        category: DocCategory,
         limit: usize,
     ) -> Result<Vec<DocRecord>> {
-        let category = category.to_string();
         let mut result = self
             .client
             .query("SELECT * FROM wxo_docs WHERE category = $category LIMIT $limit")
-            .bind(("category", category))
+            .bind(("category", category.as_str()))
             .bind(("limit", limit))
             .await
             .context("Failed to search documentation by category")?;
@@ -332,8 +1546,11 @@ impl Database {
         Ok(records)
     }
 
-    /// Get all documentation categories
-    pub async fn get_doc_categories(&self) -> Result<Vec<String>> {
+    /// Get all documentation categories in use
+    ///
+    /// Stored categories that aren't a known [`DocCategory`] are logged and
+    /// skipped, since searches can never match them.
+    pub async fn get_doc_categories(&self) -> Result<Vec<DocCategory>> {
         let mut result = self
             .client
             .query("SELECT DISTINCT category FROM wxo_docs")
@@ -346,7 +1563,204 @@ impl Database {
         }
 
         let rows: Vec<CategoryRow> = result.take(0)?;
-        Ok(rows.into_iter().map(|r| r.category).collect())
+        Ok(rows
+            .into_iter()
+            .filter_map(|r| {
+                let category = DocCategory::all()
+                    .iter()
+                    .find(|c| c.as_str() == r.category)
+                    .copied();
+                if category.is_none() {
+                    tracing::warn!("Ignoring unknown documentation category: {}", r.category);
+                }
+                category
+            })
+            .collect())
+    }
+
+    /// Recompute the per-term document frequencies used to rank searches
+    ///
+    /// Scans every doc and replaces the `doc_terms` table and the
+    /// `doc_index:stats` record in one transaction. Run it after adding or
+    /// changing docs; until the first rebuild, search terms are weighted
+    /// equally.
+    pub async fn rebuild_search_index(&self) -> Result<IndexStats> {
+        let mut result = self
+            .client
+            .query("SELECT title, content FROM wxo_docs")
+            .await
+            .context("Failed to read documentation for indexing")?;
+
+        #[derive(Deserialize)]
+        struct DocText {
+            title: String,
+            content: String,
+        }
+
+        #[derive(Serialize)]
+        struct TermRow {
+            term: String,
+            doc_freq: usize,
+        }
+
+        let docs: Vec<DocText> = result.take(0)?;
+        let doc_freqs =
+            document_frequencies(docs.iter().map(|d| (d.title.as_str(), d.content.as_str())));
+        let stats = IndexStats {
+            doc_count: docs.len(),
+            term_count: doc_freqs.len(),
+        };
+        let terms: Vec<TermRow> = doc_freqs
+            .into_iter()
+            .map(|(term, doc_freq)| TermRow { term, doc_freq })
+            .collect();
+
+        self.client
+            .query(
+                r#"
+                BEGIN TRANSACTION;
+                DELETE doc_terms;
+                INSERT INTO doc_terms $terms;
+                UPSERT doc_index:stats CONTENT {
+                    doc_count: $doc_count,
+                    term_count: $term_count,
+                    built_at: time::now()
+                };
+                COMMIT TRANSACTION;
+                "#,
+            )
+            .bind(("terms", terms))
+            .bind(("doc_count", stats.doc_count))
+            .bind(("term_count", stats.term_count))
+            .await
+            .context("Failed to rebuild search index")?
+            .check()
+            .context("Failed to rebuild search index")?;
+
+        tracing::info!(
+            docs = stats.doc_count,
+            terms = stats.term_count,
+            "Rebuilt search index"
+        );
+        Ok(stats)
+    }
+
+    /// When the search index was last built, or `None` if it has never been
+    /// built
+    ///
+    /// Cheap to check before reusing a [`SearchIndex`] loaded earlier.
+    pub async fn search_index_built_at(&self) -> Result<Option<DateTime<Utc>>> {
+        #[derive(Deserialize)]
+        struct BuiltAt {
+            built_at: DateTime<Utc>,
+        }
+
+        let mut result = self
+            .client
+            .query("SELECT built_at FROM doc_index:stats")
+            .await
+            .context("Failed to load search index statistics")?;
+        let rows: Vec<BuiltAt> = result.take(0)?;
+        Ok(rows.into_iter().next().map(|row| row.built_at))
+    }
+
+    /// Load the whole search index vocabulary, or `None` if the index has
+    /// never been built
+    pub async fn load_search_index(&self) -> Result<Option<SearchIndex>> {
+        #[derive(Deserialize)]
+        struct IndexRow {
+            doc_count: usize,
+            built_at: DateTime<Utc>,
+        }
+
+        #[derive(Deserialize)]
+        struct TermRow {
+            term: String,
+            doc_freq: usize,
+        }
+
+        let mut result = self
+            .client
+            .query(
+                r#"
+                SELECT doc_count, built_at FROM doc_index:stats;
+                SELECT term, doc_freq FROM doc_terms;
+                "#,
+            )
+            .await
+            .context("Failed to load search index")?;
+
+        let index: Vec<IndexRow> = result.take(0)?;
+        let Some(index) = index.into_iter().next() else {
+            return Ok(None);
+        };
+        let rows: Vec<TermRow> = result.take(1)?;
+        Ok(Some(SearchIndex {
+            doc_count: index.doc_count,
+            built_at: index.built_at,
+            vocabulary: rows.into_iter().map(|r| (r.term, r.doc_freq)).collect(),
+        }))
+    }
+
+    // ==================== Example Operations ====================
+
+    /// Add a code example
+    pub async fn add_example(&self, example: &ExampleRecord) -> Result<Thing> {
+        let example = example.clone();
+        let created: Option<ExampleRecord> = self
+            .client
+            .create("wxo_examples")
+            .content(example)
+            .await
+            .context("Failed to add example")?;
+
+        created
+            .and_then(|e| e.id)
+            .ok_or_else(|| anyhow::anyhow!("Failed to get created example ID"))
+    }
+
+    /// Search code examples by topic, optionally restricted to a language
+    ///
+    /// Topic matching follows [`ExampleRecord::matches_topic`].
+    pub async fn search_examples(
+        &self,
+        topic: &str,
+        language: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<ExampleRecord>> {
+        let terms = ExampleRecord::topic_terms(topic);
+        // Tags are searched joined by newlines, which no topic word contains
+        let term_matches: Vec<String> = (0..terms.len())
+            .map(|i| {
+                format!(
+                    "string::contains(string::lowercase(title), $term{0}) \
+                     OR string::contains(string::lowercase(description), $term{0}) \
+                     OR string::contains(string::lowercase(array::join(tags, \"\\n\")), $term{0})",
+                    i
+                )
+            })
+            .collect();
+
+        let mut conditions = vec![format!("({})", term_matches.join(" OR "))];
+        if language.is_some() {
+            conditions.push("string::lowercase(language) = $language".to_string());
+        }
+        let sql = format!(
+            "SELECT * FROM wxo_examples WHERE {} ORDER BY created_at LIMIT $limit",
+            conditions.join(" AND ")
+        );
+
+        let mut query = self.client.query(sql).bind(("limit", limit));
+        for (i, term) in terms.into_iter().enumerate() {
+            query = query.bind((format!("term{}", i), term));
+        }
+        if let Some(language) = language {
+            query = query.bind(("language", language.to_lowercase()));
+        }
+
+        let mut result = query.await.context("Failed to search examples")?;
+        let records: Vec<ExampleRecord> = result.take(0)?;
+        Ok(records)
     }
 
     // ==================== Feedback Operations ====================
@@ -363,16 +1777,39 @@ impl Database {
         Ok(())
     }
 
-    /// Get feedback for a session
+    /// Get feedback for a session, newest first
     pub async fn get_session_feedback(&self, session_id: &str) -> Result<Vec<FeedbackRecord>> {
-        let session_id = session_id.to_string();
-        let mut result = self
-            .client
-            .query("SELECT * FROM feedback WHERE session_id = $session_id ORDER BY created_at DESC")
-            .bind(("session_id", session_id))
+        self.query_feedback(FeedbackFilter::default().session_id(session_id))
             .await
-            .context("Failed to get session feedback")?;
+            .context("Failed to get session feedback")
+    }
+
+    /// Get the feedback matching `filter`, newest first
+    ///
+    /// E.g. `FeedbackFilter::default().max_rating(1)` finds every 1-star
+    /// rating across sessions and agents.
+    pub async fn query_feedback(&self, filter: FeedbackFilter) -> Result<Vec<FeedbackRecord>> {
+        let mut query = self.client.query(filter.to_query());
+        if let Some(session_id) = filter.session_id {
+            query = query.bind(("session_id", session_id));
+        }
+        if let Some(min_rating) = filter.min_rating {
+            query = query.bind(("min_rating", min_rating));
+        }
+        if let Some(max_rating) = filter.max_rating {
+            query = query.bind(("max_rating", max_rating));
+        }
+        if let Some(since) = filter.since {
+            query = query.bind(("since", since));
+        }
+        if let Some(limit) = filter.limit {
+            query = query.bind(("limit", limit));
+        }
+        if filter.offset > 0 {
+            query = query.bind(("offset", filter.offset));
+        }
 
+        let mut result = query.await.context("Failed to query feedback")?;
         let records: Vec<FeedbackRecord> = result.take(0)?;
         Ok(records)
     }
@@ -390,7 +1827,7 @@ impl Database {
                 )
                 "#,
             )
-            .bind(("agent_type", serde_json::to_string(&agent_type)?))
+            .bind(("agent_type", agent_type))
             .await
             .context("Failed to get agent rating")?;
 
@@ -402,26 +1839,1380 @@ impl Database {
         let rows: Vec<AvgRow> = result.take(0)?;
         Ok(rows.into_iter().next().and_then(|r| r.avg_rating))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Get rating statistics for an agent's feedback created after `since`
+    pub async fn get_agent_rating_stats(
+        &self,
+        agent_type: AgentType,
+        since: DateTime<Utc>,
+    ) -> Result<RatingStats> {
+        let mut result = self
+            .client
+            .query(
+                r#"
+                SELECT rating, count() AS count FROM feedback
+                WHERE created_at > $since AND session_id IN (
+                    SELECT VALUE session_id FROM conversations WHERE agent_type = $agent_type
+                )
+                GROUP BY rating
+                "#,
+            )
+            .bind(("agent_type", agent_type))
+            .bind(("since", since))
+            .await
+            .context("Failed to get agent rating stats")?;
 
-    // Integration tests would require a running SurrealDB instance
-    // These are placeholder tests for the type system
+        #[derive(Deserialize)]
+        struct CountRow {
+            rating: i32,
+            count: usize,
+        }
 
-    #[test]
-    fn test_db_config_default() {
-        let config = DbConfig::default();
-        assert_eq!(config.host, "localhost");
-        assert_eq!(config.port, 8000);
-        assert_eq!(config.namespace, "wxorca");
+        let rows: Vec<CountRow> = result.take(0)?;
+        let counts: Vec<(i32, usize)> = rows.into_iter().map(|r| (r.rating, r.count)).collect();
+        Ok(RatingStats::from_counts(&counts))
     }
 
-    #[test]
-    fn test_db_config_url() {
-        let config = DbConfig::default();
-        assert_eq!(config.url(), "localhost:8000");
+    /// Get aggregate conversation counts
+    ///
+    /// Counting is done by the database in a single round trip, so no
+    /// conversation records are transferred.
+    pub async fn get_conversation_stats(&self) -> Result<ConversationStats> {
+        let mut result = self
+            .client
+            .query(
+                r#"
+                SELECT
+                    count() AS total,
+                    count(is_complete = true) AS completed,
+                    math::mean(array::len(messages)) AS avg_messages
+                FROM conversations GROUP ALL;
+                SELECT agent_type, count() AS count FROM conversations GROUP BY agent_type;
+                "#,
+            )
+            .await
+            .context("Failed to get conversation stats")?;
+
+        #[derive(Deserialize)]
+        struct TotalsRow {
+            total: usize,
+            completed: usize,
+            avg_messages: Option<f64>,
+        }
+
+        #[derive(Deserialize)]
+        struct AgentRow {
+            agent_type: AgentType,
+            count: usize,
+        }
+
+        let totals: Vec<TotalsRow> = result.take(0)?;
+        let agents: Vec<AgentRow> = result.take(1)?;
+
+        let Some(totals) = totals.into_iter().next() else {
+            return Ok(ConversationStats::default());
+        };
+        Ok(ConversationStats {
+            total: totals.total,
+            per_agent: agents
+                .into_iter()
+                .map(|r| (r.agent_type, r.count))
+                .collect(),
+            completed: totals.completed,
+            in_progress: totals.total.saturating_sub(totals.completed),
+            avg_messages: totals.avg_messages,
+        })
+    }
+
+    /// Count how many highly rated sessions each documentation URL appeared in
+    ///
+    /// Only sessions with feedback rated `min_rating` or higher are counted.
+    /// Docs that never appeared in such a session are omitted.
+    pub async fn get_doc_feedback_stats(&self, min_rating: i32) -> Result<HashMap<String, usize>> {
+        let mut result = self
+            .client
+            .query(
+                r#"
+                SELECT VALUE url FROM wxo_docs WHERE url != NONE;
+                SELECT VALUE messages FROM conversations
+                WHERE session_id IN (
+                    SELECT VALUE session_id FROM feedback WHERE rating >= $min_rating
+                );
+                "#,
+            )
+            .bind(("min_rating", min_rating))
+            .await
+            .context("Failed to get documentation feedback stats")?;
+
+        let urls: Vec<String> = result.take(0)?;
+        let conversations: Vec<Vec<Message>> = result.take(1)?;
+        Ok(count_doc_appearances(&urls, &conversations))
+    }
+
+    // ==================== Backup Operations ====================
+
+    /// Collect every conversation, doc, example and feedback record
+    pub async fn export_all(&self) -> Result<DbExport> {
+        let schema_version = self.schema_version().await?;
+        let mut result = self
+            .client
+            .query(
+                r#"
+                SELECT * FROM conversations ORDER BY created_at;
+                SELECT * FROM wxo_docs ORDER BY created_at;
+                SELECT * FROM wxo_examples ORDER BY created_at;
+                SELECT * FROM feedback ORDER BY created_at;
+                "#,
+            )
+            .await
+            .context("Failed to export data")?;
+
+        let export = DbExport {
+            schema_version,
+            exported_at: Utc::now(),
+            conversations: result.take(0)?,
+            docs: result.take(1)?,
+            examples: result.take(2)?,
+            feedback: result.take(3)?,
+        };
+        tracing::info!(
+            conversations = export.conversations.len(),
+            docs = export.docs.len(),
+            examples = export.examples.len(),
+            feedback = export.feedback.len(),
+            "Exported data"
+        );
+        Ok(export)
+    }
+
+    /// Write an export into this database
+    ///
+    /// Record IDs from the export are dropped and new ones generated, so IDs
+    /// from another environment can't collide with existing records. Fails
+    /// if the export comes from a newer schema than this database has.
+    /// A [`ImportMode::Replace`] import runs in one transaction, so if it
+    /// fails the database is left as it was. A [`ImportMode::Merge`] import
+    /// writes records one statement at a time and may leave part of the
+    /// export written.
+    pub async fn import_all(&self, export: &DbExport, mode: ImportMode) -> Result<()> {
+        let schema_version = self.schema_version().await?;
+        if export.schema_version > schema_version {
+            anyhow::bail!(
+                "Export is from schema version {}, but this database is at version {}; run migrations first",
+                export.schema_version,
+                schema_version
+            );
+        }
+
+        match mode {
+            ImportMode::Replace => self.replace_all(export).await?,
+            ImportMode::Merge => self.merge_all(export).await?,
+        }
+
+        tracing::info!(
+            ?mode,
+            conversations = export.conversations.len(),
+            docs = export.docs.len(),
+            examples = export.examples.len(),
+            feedback = export.feedback.len(),
+            "Imported data"
+        );
+        Ok(())
+    }
+
+    /// Delete all data and write `export` in its place, in one transaction
+    async fn replace_all(&self, export: &DbExport) -> Result<()> {
+        let conversations: Vec<ConversationRecord> = export
+            .conversations
+            .iter()
+            .map(|conversation| ConversationRecord {
+                id: None,
+                ..conversation.clone()
+            })
+            .collect();
+        // The last copy of a page wins, as when merging
+        let mut docs: Vec<DocRecord> = Vec::new();
+        for doc in &export.docs {
+            if doc.url.is_some() {
+                docs.retain(|d| d.url != doc.url);
+            }
+            docs.push(DocRecord {
+                id: None,
+                ..doc.clone()
+            });
+        }
+        let examples: Vec<ExampleRecord> = export
+            .examples
+            .iter()
+            .map(|example| ExampleRecord {
+                id: None,
+                ..example.clone()
+            })
+            .collect();
+        let feedback: Vec<FeedbackRecord> = export
+            .feedback
+            .iter()
+            .map(|feedback| FeedbackRecord {
+                id: None,
+                ..feedback.clone()
+            })
+            .collect();
+
+        self.client
+            .query(
+                r#"
+                BEGIN TRANSACTION;
+                DELETE conversations;
+                DELETE wxo_docs;
+                DELETE wxo_examples;
+                DELETE feedback;
+                FOR $record IN $conversations { CREATE conversations CONTENT $record; };
+                FOR $record IN $docs { CREATE wxo_docs CONTENT $record; };
+                FOR $record IN $examples { CREATE wxo_examples CONTENT $record; };
+                FOR $record IN $feedback { CREATE feedback CONTENT $record; };
+                COMMIT TRANSACTION;
+                "#,
+            )
+            .bind(("conversations", conversations))
+            .bind(("docs", docs))
+            .bind(("examples", examples))
+            .bind(("feedback", feedback))
+            .await
+            .context("Failed to replace data with import")?
+            .check()
+            .context("Failed to replace data with import")?;
+        Ok(())
+    }
+
+    /// Add `export` to the existing data, matching records as described for
+    /// [`ImportMode::Merge`]
+    async fn merge_all(&self, export: &DbExport) -> Result<()> {
+        for conversation in &export.conversations {
+            self.import_conversation(conversation).await?;
+        }
+        for doc in &export.docs {
+            let doc = DocRecord {
+                id: None,
+                ..doc.clone()
+            };
+            if doc.url.is_some() {
+                self.upsert_doc_by_url(&doc).await?;
+            } else if !self.doc_exists(&doc).await? {
+                self.add_doc(&doc).await?;
+            }
+        }
+        for example in &export.examples {
+            let example = ExampleRecord {
+                id: None,
+                ..example.clone()
+            };
+            if !self.example_exists(&example).await? {
+                self.add_example(&example).await?;
+            }
+        }
+        for feedback in &export.feedback {
+            let feedback = FeedbackRecord {
+                id: None,
+                ..feedback.clone()
+            };
+            if !self.feedback_exists(&feedback).await? {
+                self.submit_feedback(&feedback).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write an exported conversation as stored, keeping the existing one if
+    /// it was updated more recently
+    async fn import_conversation(&self, record: &ConversationRecord) -> Result<()> {
+        self.client
+            .query(
+                r#"
+                IF (SELECT * FROM conversations WHERE session_id = $session_id).len() == 0 {
+                    CREATE conversations SET
+                        schema_version = $schema_version,
+                        session_id = $session_id,
+                        agent_type = $agent_type,
+                        messages = $messages,
+                        context = $context,
+                        iteration = $iteration,
+                        is_complete = $is_complete,
+                        parent_session_id = $parent_session_id,
+                        created_at = $created_at,
+                        updated_at = $updated_at
+                } ELSE {
+                    UPDATE conversations SET
+                        schema_version = $schema_version,
+                        agent_type = $agent_type,
+                        messages = $messages,
+                        context = $context,
+                        iteration = $iteration,
+                        is_complete = $is_complete,
+                        parent_session_id = $parent_session_id,
+                        updated_at = $updated_at
+                    WHERE session_id = $session_id AND updated_at < $updated_at
+                };
+                "#,
+            )
+            .bind(("schema_version", record.schema_version))
+            .bind(("session_id", record.session_id.clone()))
+            .bind(("agent_type", record.agent_type))
+            .bind(("messages", record.messages.clone()))
+            .bind(("context", record.context.clone()))
+            .bind(("iteration", record.iteration))
+            .bind(("is_complete", record.is_complete))
+            .bind(("parent_session_id", record.parent_session_id.clone()))
+            .bind(("created_at", record.created_at))
+            .bind(("updated_at", record.updated_at))
+            .await
+            .with_context(|| format!("Failed to import conversation {}", record.session_id))?;
+
+        Ok(())
+    }
+
+    async fn doc_exists(&self, doc: &DocRecord) -> Result<bool> {
+        let mut result = self
+            .client
+            .query(
+                "SELECT VALUE id FROM wxo_docs WHERE title = $title AND content = $content LIMIT 1",
+            )
+            .bind(("title", doc.title.clone()))
+            .bind(("content", doc.content.clone()))
+            .await
+            .context("Failed to look up documentation")?;
+        let ids: Vec<Thing> = result.take(0)?;
+        Ok(!ids.is_empty())
+    }
+
+    async fn example_exists(&self, example: &ExampleRecord) -> Result<bool> {
+        let mut result = self
+            .client
+            .query(
+                "SELECT VALUE id FROM wxo_examples WHERE title = $title AND code = $code LIMIT 1",
+            )
+            .bind(("title", example.title.clone()))
+            .bind(("code", example.code.clone()))
+            .await
+            .context("Failed to look up example")?;
+        let ids: Vec<Thing> = result.take(0)?;
+        Ok(!ids.is_empty())
+    }
+
+    /// Feedback is identified by session, message and time; feedback on the
+    /// whole session (no message) only matches other session feedback
+    async fn feedback_exists(&self, feedback: &FeedbackRecord) -> Result<bool> {
+        let query = if feedback.message_id.is_some() {
+            r#"
+            SELECT VALUE id FROM feedback
+            WHERE session_id = $session_id
+                AND message_id = $message_id
+                AND created_at = $created_at
+            LIMIT 1
+            "#
+        } else {
+            r#"
+            SELECT VALUE id FROM feedback
+            WHERE session_id = $session_id
+                AND message_id IS NONE
+                AND created_at = $created_at
+            LIMIT 1
+            "#
+        };
+        let mut result = self
+            .client
+            .query(query)
+            .bind(("session_id", feedback.session_id.clone()))
+            .bind(("message_id", feedback.message_id.clone()))
+            .bind(("created_at", feedback.created_at))
+            .await
+            .context("Failed to look up feedback")?;
+        let ids: Vec<Thing> = result.take(0)?;
+        Ok(!ids.is_empty())
+    }
+}
+
+/// Count the conversations each URL appears in
+///
+/// A doc appears in a conversation if its whole URL occurs in any message,
+/// e.g. a search result or a link in the assistant's reply (see
+/// [`mentions_url`]).
+fn count_doc_appearances(
+    urls: &[String],
+    conversations: &[Vec<Message>],
+) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+
+    for messages in conversations {
+        for url in urls {
+            if messages.iter().any(|m| mentions_url(&m.content, url)) {
+                *counts.entry(url.clone()).or_insert(0) += 1;
+            }
+        }
+    }
+
+    counts
+}
+
+/// Whether `text` contains `url` as a whole URL rather than as the start of
+/// a longer one
+///
+/// `https://example.com/api` is mentioned by `See https://example.com/api.`
+/// or `(https://example.com/api#auth)`, but not by
+/// `https://example.com/api-reference` or `https://example.com/api/v2`.
+fn mentions_url(text: &str, url: &str) -> bool {
+    if url.is_empty() {
+        return false;
+    }
+
+    text.match_indices(url).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let mut after = text[start + url.len()..].chars();
+        let continues_url = |c: char| c.is_alphanumeric() || "/-_~%+".contains(c);
+
+        let starts_here = !before.is_some_and(continues_url);
+        let ends_here = match after.next() {
+            None => true,
+            // Sentence punctuation ends the URL; `.html` doesn't
+            Some('.' | ',') => !after.next().is_some_and(continues_url),
+            Some(c) => !continues_url(c),
+        };
+        starts_here && ends_here
+    })
+}
+
+/// Whether a query that failed with `err` may succeed if sent again
+///
+/// Dropped or not-yet-established connections, query timeouts and
+/// transaction conflicts are transient. Everything else, such as parse or
+/// schema errors, fails the same way every time.
+pub fn is_transient(err: &surrealdb::Error) -> bool {
+    use surrealdb::error::{Api, Db};
+
+    matches!(
+        err,
+        surrealdb::Error::Api(Api::Ws(_) | Api::Http(_) | Api::ConnectionUninitialised)
+            | surrealdb::Error::Db(Db::QueryTimedout | Db::TxRetryable)
+    )
+}
+
+/// The oldest `updated_at` kept when retaining `retention_days` days of
+/// conversations
+fn retention_cutoff(now: DateTime<Utc>, retention_days: u32) -> DateTime<Utc> {
+    now - chrono::Duration::days(i64::from(retention_days))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::{run_turn, RunOptions};
+
+    async fn memory_db() -> Database {
+        let db = Database::connect(&DbConfig::memory()).await.unwrap();
+        db.init_schema().await.unwrap();
+        db
+    }
+
+    #[tokio::test]
+    async fn test_memory_backend_applies_migrations() {
+        let db = memory_db().await;
+
+        db.ping().await.unwrap();
+        assert_eq!(db.schema_version().await.unwrap(), MIGRATIONS.len() as u32);
+        // Already up to date
+        assert_eq!(db.migrate().await.unwrap(), MIGRATIONS.len() as u32);
+    }
+
+    #[tokio::test]
+    async fn test_migration_unquotes_legacy_agent_type() {
+        let db = memory_db().await;
+        db.client
+            .query(
+                r#"
+                CREATE conversations CONTENT {
+                    session_id: "legacy",
+                    agent_type: "\"troubleshoot\"",
+                    messages: []
+                };
+                DELETE schema_version WHERE version = 8;
+                "#,
+            )
+            .await
+            .and_then(|response| response.check())
+            .unwrap();
+
+        assert_eq!(db.migrate().await.unwrap(), MIGRATIONS.len() as u32);
+        let loaded = db.load_conversation("legacy").await.unwrap().unwrap();
+        assert_eq!(loaded.agent_type, AgentType::Troubleshoot);
+    }
+
+    #[tokio::test]
+    async fn test_migration_dedupes_doc_urls() {
+        let db = memory_db().await;
+        db.client
+            .query(
+                r#"
+                REMOVE INDEX idx_doc_url ON wxo_docs;
+                DELETE schema_version WHERE version = 9;
+                CREATE wxo_docs CONTENT { title: "A", content: "", category: "usage", url: "u" };
+                CREATE wxo_docs CONTENT { title: "B", content: "", category: "usage", url: "u" };
+                CREATE wxo_docs CONTENT { title: "C", content: "", category: "usage" };
+                CREATE wxo_docs CONTENT { title: "D", content: "", category: "usage" };
+                "#,
+            )
+            .await
+            .and_then(|response| response.check())
+            .unwrap();
+
+        assert_eq!(db.migrate().await.unwrap(), MIGRATIONS.len() as u32);
+        let mut result = db
+            .client
+            .query("SELECT VALUE url FROM wxo_docs")
+            .await
+            .unwrap();
+        let mut urls: Vec<Option<String>> = result.take(0).unwrap();
+        urls.sort();
+        assert_eq!(urls, vec![None, None, Some("u".to_string())]);
+
+        // The index now rejects a second record for the page
+        let duplicate = db
+            .client
+            .query(
+                r#"
+                CREATE wxo_docs CONTENT { title: "E", content: "", category: "usage", url: "u" }
+                "#,
+            )
+            .await
+            .and_then(|response| response.check());
+        assert!(duplicate.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_upsert_doc_by_url() {
+        let db = memory_db().await;
+        let mut doc = DocRecord {
+            id: None,
+            title: "SSO setup".to_string(),
+            content: "Old steps".to_string(),
+            category: "admin".to_string(),
+            url: Some("https://example.com/sso".to_string()),
+            embedding: Vec::new(),
+            created_at: Utc::now(),
+        };
+
+        let id = db.upsert_doc_by_url(&doc).await.unwrap();
+        doc.content = "New steps".to_string();
+        assert_eq!(db.upsert_doc_by_url(&doc).await.unwrap(), id);
+
+        let stored = db.get_doc_by_id(&id).await.unwrap().unwrap();
+        assert_eq!(stored.content, "New steps");
+        let mut result = db
+            .client
+            .query("SELECT VALUE id FROM wxo_docs")
+            .await
+            .unwrap();
+        let ids: Vec<Thing> = result.take(0).unwrap();
+        assert_eq!(ids, vec![id]);
+
+        doc.url = None;
+        assert!(db.upsert_doc_by_url(&doc).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_search_docs_by_embedding() {
+        let db = memory_db().await;
+        let doc = |title: &str, embedding: Vec<f32>| DocRecord {
+            id: None,
+            title: title.to_string(),
+            content: "Steps".to_string(),
+            category: "admin".to_string(),
+            url: None,
+            embedding,
+            created_at: Utc::now(),
+        };
+        db.bulk_add_docs(&[
+            doc("orthogonal", vec![0.0, 1.0]),
+            doc("exact", vec![1.0, 0.0]),
+            doc("close", vec![0.8, 0.6]),
+            doc("unembedded", Vec::new()),
+            doc("other model", vec![1.0, 0.0, 0.0]),
+        ])
+        .await
+        .unwrap();
+
+        let titles = |docs: Vec<DocRecord>| docs.into_iter().map(|d| d.title).collect::<Vec<_>>();
+        let all = db
+            .search_docs_by_embedding(&[1.0, 0.0], 10, None)
+            .await
+            .unwrap();
+        assert_eq!(titles(all), vec!["exact", "close", "orthogonal"]);
+
+        let similar = db
+            .search_docs_by_embedding(&[1.0, 0.0], 10, Some(0.5))
+            .await
+            .unwrap();
+        assert_eq!(titles(similar), vec!["exact", "close"]);
+
+        let top = db
+            .search_docs_by_embedding(&[1.0, 0.0], 1, None)
+            .await
+            .unwrap();
+        assert_eq!(titles(top), vec!["exact"]);
+
+        assert!(db
+            .search_docs_by_embedding(&[], 10, None)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delete_missing_doc_fails() {
+        let db = memory_db().await;
+        let doc = DocRecord {
+            id: None,
+            title: "SSO setup".to_string(),
+            content: "Steps".to_string(),
+            category: "admin".to_string(),
+            url: Some("https://example.com/sso".to_string()),
+            embedding: Vec::new(),
+            created_at: Utc::now(),
+        };
+
+        let id = db.upsert_doc_by_url(&doc).await.unwrap();
+        db.delete_doc(&id).await.unwrap();
+        assert!(db.get_doc_by_id(&id).await.unwrap().is_none());
+        assert!(db.delete_doc(&id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_redaction_covers_context() {
+        let db = memory_db().await.with_pii_redaction(true);
+
+        let mut state = WxorcaState::with_session_id(AgentType::AdminSetup, "redacted");
+        state.add_user_message("Add jane@example.com as an admin");
+        state.context.current_topic = Some("Add jane@example.com as an admin".to_string());
+        state
+            .context
+            .metadata
+            .insert("api_key".to_string(), serde_json::json!("abc123"));
+        db.save_conversation(&state).await.unwrap();
+
+        let loaded = db.load_conversation("redacted").await.unwrap().unwrap();
+        assert_eq!(
+            loaded.context.current_topic.as_deref(),
+            Some("Add [REDACTED_EMAIL] as an admin")
+        );
+        assert_eq!(loaded.context.metadata["api_key"], "[REDACTED]");
+    }
+
+    #[cfg(not(feature = "rocksdb"))]
+    #[tokio::test]
+    async fn test_rocksdb_backend_needs_feature() {
+        let err = Database::connect(&DbConfig::rocksdb("unused.db"))
+            .await
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("`rocksdb` feature"));
+    }
+
+    #[tokio::test]
+    async fn test_memory_backend_round_trips_conversation() {
+        let db = memory_db().await;
+
+        let mut state = WxorcaState::with_session_id(AgentType::Troubleshoot, "session-1");
+        state.add_user_message("My skill fails");
+        state.add_assistant_message("Which error do you see?");
+        db.save_conversation(&state).await.unwrap();
+
+        let fork = state.fork_at(0);
+        db.save_conversation(&fork).await.unwrap();
+
+        let loaded = db.load_conversation("session-1").await.unwrap().unwrap();
+        assert_eq!(loaded.agent_type, AgentType::Troubleshoot);
+        assert_eq!(loaded.messages.len(), 2);
+        assert_eq!(loaded.messages[1].content, "Which error do you see?");
+        assert!(db.load_conversation("missing").await.unwrap().is_none());
+
+        let branches = db.list_branches("session-1").await.unwrap();
+        assert_eq!(branches.len(), 1);
+        assert_eq!(branches[0].session_id, fork.session_id);
+    }
+
+    #[tokio::test]
+    async fn test_escalation_survives_save_and_restore() {
+        let db = memory_db().await;
+        let mut state = WxorcaState::with_session_id(AgentType::Troubleshoot, "session-1");
+        run_turn(
+            &mut state,
+            "Users get access denied on login",
+            RunOptions::default().deterministic(true),
+        )
+        .await
+        .unwrap();
+        db.save_turn(&mut state).await.unwrap();
+
+        let loaded = db.load_conversation("session-1").await.unwrap().unwrap();
+        let metadata = &loaded.context.metadata;
+        assert_eq!(metadata["should_escalate"], serde_json::json!(true));
+        assert_eq!(metadata["support_tier"], serde_json::json!("tier_2"));
+        assert_eq!(
+            metadata["diagnosis_category"],
+            serde_json::json!("authentication")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_similar_conversations() {
+        let db = memory_db().await;
+        for (session_id, question) in [
+            ("original", "How do I configure SSO?"),
+            ("repeat", "how do I configure SSO"),
+            ("related", "How do I configure SSO for my whole team?"),
+            ("workflows", "How do I configure workflows?"),
+            ("other", "Workflow retry policies"),
+        ] {
+            let mut state = WxorcaState::with_session_id(AgentType::AdminSetup, session_id);
+            state.add_user_message(question);
+            db.save_conversation(&state).await.unwrap();
+        }
+        db.save_conversation(&WxorcaState::with_session_id(
+            AgentType::AdminSetup,
+            "empty",
+        ))
+        .await
+        .unwrap();
+
+        let similar = db
+            .find_similar_conversations("original", 0.6)
+            .await
+            .unwrap();
+        assert_eq!(similar, vec!["repeat", "related"]);
+        let duplicates = db
+            .find_similar_conversations("original", 0.99)
+            .await
+            .unwrap();
+        assert_eq!(duplicates, vec!["repeat"]);
+
+        assert!(db
+            .find_similar_conversations("empty", 0.0)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(db.find_similar_conversations("missing", 0.5).await.is_err());
+
+        // "empty" was updated last, so it's the only one compared
+        let recent_only = db
+            .clone()
+            .with_similar_conversation_window(1)
+            .find_similar_conversations("original", 0.0)
+            .await
+            .unwrap();
+        assert!(recent_only.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_save_turn_appends_new_messages() {
+        let db = memory_db().await;
+
+        let mut state = WxorcaState::with_session_id(AgentType::Troubleshoot, "session-1");
+        state.add_user_message("My skill fails");
+        db.save_turn(&mut state).await.unwrap();
+        assert_eq!(state.persisted_messages, 1);
+
+        state.add_assistant_message("Which error do you see?");
+        state.add_user_message("A timeout");
+        state.iteration = 2;
+        db.save_turn(&mut state).await.unwrap();
+        assert_eq!(state.persisted_messages, 3);
+
+        let loaded = db.load_conversation("session-1").await.unwrap().unwrap();
+        let contents: Vec<&str> = loaded.messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(
+            contents,
+            vec!["My skill fails", "Which error do you see?", "A timeout"]
+        );
+        assert_eq!(loaded.iteration, 2);
+        assert_eq!(loaded.persisted_messages, 3);
+
+        assert!(db
+            .append_messages("missing", &[Message::user("Hello")])
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_conversation_stats() {
+        let db = memory_db().await;
+        assert_eq!(
+            db.get_conversation_stats().await.unwrap(),
+            ConversationStats::default()
+        );
+
+        // One conversation answered, two waiting on the user
+        let mut message_count = 0;
+        for (session_id, agent_type, message) in [
+            ("session-1", AgentType::Troubleshoot, "Something is wrong"),
+            (
+                "session-2",
+                AgentType::Troubleshoot,
+                "Users get access denied on login",
+            ),
+            (
+                "session-3",
+                AgentType::AdminSetup,
+                "How do I configure security?",
+            ),
+        ] {
+            let mut state = WxorcaState::with_session_id(agent_type, session_id);
+            run_turn(
+                &mut state,
+                message,
+                RunOptions::default().deterministic(true),
+            )
+            .await
+            .unwrap();
+            db.save_turn(&mut state).await.unwrap();
+            message_count += state.messages.len();
+        }
+
+        let stats = db.get_conversation_stats().await.unwrap();
+        assert_eq!(stats.total, 3);
+        assert_eq!(stats.per_agent[&AgentType::Troubleshoot], 2);
+        assert_eq!(stats.per_agent[&AgentType::AdminSetup], 1);
+        assert!(!stats.per_agent.contains_key(&AgentType::DocsHelper));
+        assert_eq!((stats.completed, stats.in_progress), (1, 2));
+        let avg_messages = stats.avg_messages.unwrap();
+        assert!((avg_messages - message_count as f64 / 3.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_delete_conversations_older_than() {
+        let db = memory_db().await;
+        for session_id in ["old", "new"] {
+            let mut state = WxorcaState::with_session_id(AgentType::AdminSetup, session_id);
+            state.add_user_message("How do I configure SSO?");
+            db.save_conversation(&state).await.unwrap();
+            db.submit_feedback(&FeedbackRecord {
+                id: None,
+                session_id: session_id.to_string(),
+                message_id: None,
+                rating: 4,
+                comment: None,
+                created_at: Utc::now(),
+            })
+            .await
+            .unwrap();
+        }
+        let cutoff = Utc::now() - chrono::Duration::days(30);
+        db.client
+            .query("UPDATE conversations SET updated_at = $at WHERE session_id = 'old'")
+            .bind(("at", cutoff - chrono::Duration::days(1)))
+            .await
+            .and_then(|response| response.check())
+            .unwrap();
+
+        assert_eq!(db.delete_conversations_older_than(cutoff).await.unwrap(), 1);
+        assert!(db.load_conversation("old").await.unwrap().is_none());
+        assert!(db.get_session_feedback("old").await.unwrap().is_empty());
+        assert!(db.load_conversation("new").await.unwrap().is_some());
+        assert_eq!(db.get_session_feedback("new").await.unwrap().len(), 1);
+
+        assert_eq!(db.delete_conversations_older_than(cutoff).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_import_all() {
+        let source = memory_db().await;
+        let mut state = WxorcaState::with_session_id(AgentType::AdminSetup, "imported");
+        state.add_user_message("How do I configure SSO?");
+        source.save_conversation(&state).await.unwrap();
+        source
+            .submit_feedback(&FeedbackRecord {
+                id: None,
+                session_id: "imported".to_string(),
+                message_id: None,
+                rating: 5,
+                comment: None,
+                created_at: Utc::now(),
+            })
+            .await
+            .unwrap();
+        let export = source.export_all().await.unwrap();
+
+        let db = memory_db().await;
+        db.save_conversation(&WxorcaState::with_session_id(
+            AgentType::Troubleshoot,
+            "existing",
+        ))
+        .await
+        .unwrap();
+
+        // Session feedback isn't duplicated by importing twice
+        for _ in 0..2 {
+            db.import_all(&export, ImportMode::Merge).await.unwrap();
+        }
+        assert_eq!(db.get_session_feedback("imported").await.unwrap().len(), 1);
+        assert!(db.load_conversation("existing").await.unwrap().is_some());
+
+        // A replace that fails part way leaves the data as it was
+        let mut broken = export.clone();
+        broken.conversations.push(export.conversations[0].clone());
+        assert!(db.import_all(&broken, ImportMode::Replace).await.is_err());
+        assert!(db.load_conversation("existing").await.unwrap().is_some());
+        assert_eq!(db.get_session_feedback("imported").await.unwrap().len(), 1);
+
+        db.import_all(&export, ImportMode::Replace).await.unwrap();
+        assert!(db.load_conversation("existing").await.unwrap().is_none());
+        assert!(db.load_conversation("imported").await.unwrap().is_some());
+        assert_eq!(db.get_session_feedback("imported").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_import_updates_parent_session() {
+        let db = memory_db().await;
+        let mut state = WxorcaState::with_session_id(AgentType::AdminSetup, "child");
+        state.add_user_message("How do I configure SSO?");
+        db.save_conversation(&state).await.unwrap();
+
+        // The exported copy was forked from "parent" and saved later
+        let source = memory_db().await;
+        state.parent_session_id = Some("parent".to_string());
+        state.add_assistant_message("Start in the admin console.");
+        source.save_conversation(&state).await.unwrap();
+        let export = source.export_all().await.unwrap();
+
+        db.import_all(&export, ImportMode::Merge).await.unwrap();
+        let loaded = db.load_conversation("child").await.unwrap().unwrap();
+        assert_eq!(loaded.messages.len(), 2);
+        assert_eq!(loaded.parent_session_id.as_deref(), Some("parent"));
+        assert_eq!(db.list_branches("parent").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_query_feedback() {
+        let db = memory_db().await;
+        let now = Utc::now();
+        for (i, (session_id, rating)) in [("a", 1), ("a", 5), ("b", 1), ("b", 3), ("c", 1)]
+            .into_iter()
+            .enumerate()
+        {
+            db.submit_feedback(&FeedbackRecord {
+                id: None,
+                session_id: session_id.to_string(),
+                message_id: None,
+                rating,
+                comment: None,
+                created_at: now - chrono::Duration::minutes(i as i64),
+            })
+            .await
+            .unwrap();
+        }
+
+        let one_star = db
+            .query_feedback(FeedbackFilter::default().max_rating(1))
+            .await
+            .unwrap();
+        let sessions: Vec<&str> = one_star.iter().map(|f| f.session_id.as_str()).collect();
+        assert_eq!(sessions, vec!["a", "b", "c"]);
+
+        let page = db
+            .query_feedback(FeedbackFilter::default().max_rating(1).page(1, 1))
+            .await
+            .unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].session_id, "b");
+
+        let recent_good = db
+            .query_feedback(
+                FeedbackFilter::default()
+                    .min_rating(3)
+                    .since(now - chrono::Duration::minutes(10)),
+            )
+            .await
+            .unwrap();
+        assert_eq!(recent_good.len(), 2);
+
+        assert_eq!(db.get_session_feedback("a").await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_search_index() {
+        let db = memory_db().await;
+        let terms = vec!["skill".to_string(), "webhook".to_string()];
+        assert!(db.load_search_index().await.unwrap().is_none());
+        assert!(db.search_index_built_at().await.unwrap().is_none());
+
+        let doc = |title: &str, content: &str| DocRecord {
+            id: None,
+            title: title.to_string(),
+            content: content.to_string(),
+            category: "usage".to_string(),
+            url: None,
+            embedding: Vec::new(),
+            created_at: Utc::now(),
+        };
+        db.bulk_add_docs(&[
+            doc("Skill basics", "Create a skill"),
+            doc("Webhooks", "Trigger a skill from a webhook"),
+        ])
+        .await
+        .unwrap();
+
+        let stats = db.rebuild_search_index().await.unwrap();
+        assert_eq!(stats.doc_count, 2);
+        assert_eq!(stats.term_count, 8);
+
+        let index = db.load_search_index().await.unwrap().unwrap();
+        assert_eq!(index.doc_count, 2);
+        assert_eq!(index.vocabulary["skill"], 2);
+        assert_eq!(index.vocabulary["webhook"], 1);
+        assert_eq!(
+            db.search_index_built_at().await.unwrap(),
+            Some(index.built_at)
+        );
+        let term_stats = index.term_stats(&terms, false);
+        assert_eq!(term_stats.doc_count, 2);
+        assert_eq!(term_stats.doc_freqs["skill"], 2);
+        assert_eq!(term_stats.doc_freqs["webhook"], 1);
+
+        // Prefixes count the documents of the words they match
+        let prefixes = vec!["sk".to_string(), "web".to_string()];
+        let term_stats = index.term_stats(&prefixes, false);
+        assert_eq!(term_stats.doc_freqs["sk"], 2);
+        assert_eq!(term_stats.doc_freqs["web"], 1);
+
+        // Rebuilding replaces the previous terms
+        assert_eq!(db.rebuild_search_index().await.unwrap(), stats);
+    }
+
+    #[test]
+    fn test_feedback_filter_query() {
+        assert_eq!(
+            FeedbackFilter::default().to_query(),
+            "SELECT * FROM feedback ORDER BY created_at DESC"
+        );
+        assert_eq!(
+            FeedbackFilter::default()
+                .session_id("s")
+                .min_rating(2)
+                .page(10, 20)
+                .to_query(),
+            "SELECT * FROM feedback WHERE session_id = $session_id AND rating >= $min_rating \
+             ORDER BY created_at DESC LIMIT $limit START $offset"
+        );
+    }
+
+    #[test]
+    fn test_db_backend_endpoints() {
+        assert_eq!(DbConfig::default().endpoint(), "ws://localhost:8000");
+        assert_eq!(DbConfig::memory().endpoint(), "mem://");
+        assert_eq!(
+            DbConfig::rocksdb("data/wxorca").endpoint(),
+            "rocksdb://data/wxorca"
+        );
+        assert_eq!(
+            DbBackend::from_name("RocksDB", "local.db"),
+            Some(DbBackend::RocksDb(PathBuf::from("local.db")))
+        );
+        assert_eq!(DbBackend::from_name("mem", ""), Some(DbBackend::Memory));
+        assert_eq!(DbBackend::from_name("sqlite", ""), None);
+        assert!(!DbBackend::Remote.is_embedded());
+    }
+
+    #[test]
+    fn test_migrations_are_ordered() {
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version as usize, i + 1);
+            assert!(!migration.sql.trim().is_empty());
+        }
+
+        let all: Vec<u32> = pending_migrations(0).map(|m| m.version).collect();
+        assert_eq!(all.len(), MIGRATIONS.len());
+        let rest: Vec<u32> = pending_migrations(2).map(|m| m.version).collect();
+        assert_eq!(rest, vec![3, 4, 5, 6, 7, 8, 9]);
+        assert_eq!(pending_migrations(MIGRATIONS.len() as u32).count(), 0);
+    }
+
+    #[test]
+    fn test_db_config_default() {
+        let config = DbConfig::default();
+        assert_eq!(config.host, "localhost");
+        assert_eq!(config.port, 8000);
+        assert_eq!(config.namespace, "wxorca");
+    }
+
+    #[test]
+    fn test_db_config_url() {
+        let config = DbConfig::default();
+        assert_eq!(config.url(), "localhost:8000");
+    }
+
+    #[test]
+    fn test_example_matches_topic() {
+        let example = ExampleRecord {
+            id: None,
+            title: "Salesforce Integration".to_string(),
+            description: "Sync leads from Salesforce".to_string(),
+            language: "json".to_string(),
+            code: "{}".to_string(),
+            tags: vec!["CRM".to_string()],
+            created_at: Utc::now(),
+        };
+
+        assert!(example.matches_topic("salesforce"));
+        assert!(example.matches_topic("crm sync"));
+        assert!(!example.matches_topic("python skill"));
+    }
+
+    #[tokio::test]
+    async fn test_search_examples() {
+        let db = memory_db().await;
+        for (title, language, tags) in [
+            ("Salesforce Integration", "json", vec!["CRM"]),
+            ("Python Skill", "Python", vec!["skill"]),
+            ("Skill Manifest", "yaml", vec!["skill", "manifest"]),
+            ("Slack Alerts", "json", vec!["notifications"]),
+        ] {
+            let example = ExampleRecord {
+                id: None,
+                title: title.to_string(),
+                description: format!("{} example", title),
+                language: language.to_string(),
+                code: "{}".to_string(),
+                tags: tags.into_iter().map(String::from).collect(),
+                created_at: Utc::now(),
+            };
+            db.add_example(&example).await.unwrap();
+        }
+
+        let titles = |records: Vec<ExampleRecord>| -> Vec<String> {
+            records.into_iter().map(|r| r.title).collect()
+        };
+
+        let found = db.search_examples("crm sync", None, 10).await.unwrap();
+        assert_eq!(titles(found), vec!["Salesforce Integration"]);
+
+        let found = db.search_examples("SKILL", None, 10).await.unwrap();
+        assert_eq!(titles(found), vec!["Python Skill", "Skill Manifest"]);
+        let found = db.search_examples("skill", None, 1).await.unwrap();
+        assert_eq!(titles(found), vec!["Python Skill"]);
+        let found = db
+            .search_examples("skill", Some("PYTHON"), 10)
+            .await
+            .unwrap();
+        assert_eq!(titles(found), vec!["Python Skill"]);
+
+        assert!(db
+            .search_examples("teams", None, 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_rating_stats_from_counts() {
+        let stats = RatingStats::from_counts(&[(5, 3), (4, 1), (1, 2), (0, 7)]);
+        assert_eq!(stats.count, 6);
+        assert_eq!(stats.histogram, [2, 0, 0, 1, 3]);
+        assert_eq!(stats.mean, Some(21.0 / 6.0));
+
+        assert_eq!(RatingStats::from_counts(&[]).mean, None);
+    }
+
+    #[tokio::test]
+    async fn test_retry_connect() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        // Succeeds once the database comes up
+        let attempts = &AtomicU32::new(0);
+        let result = Database::retry_connect("mem://", 3, Duration::ZERO, move || async move {
+            match attempts.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => anyhow::bail!("connection refused"),
+                _ => Ok(42),
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 42);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        // Gives up after the last attempt with its error
+        let attempts = &AtomicU32::new(0);
+        let result: Result<u32> =
+            Database::retry_connect("mem://", 3, Duration::ZERO, move || async move {
+                let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+                anyhow::bail!("attempt {} refused", attempt)
+            })
+            .await;
+        assert_eq!(result.unwrap_err().to_string(), "attempt 3 refused");
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        // The real connection succeeds on the first attempt
+        let db = Database::connect_with_retry(&DbConfig::memory(), 3, Duration::ZERO).await;
+        assert!(db.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_query_with_retry() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+        use surrealdb::error::Api;
+
+        // A dropped connection is retried until the query goes through
+        let attempts = &AtomicU32::new(0);
+        let result = Database::query_with_retry("Failed to query", move || async move {
+            match attempts.fetch_add(1, Ordering::SeqCst) {
+                0 => Err(surrealdb::Error::Api(Api::Ws("connection reset".into()))),
+                _ => Ok(42),
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 42);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+        // A query error fails straight away
+        let attempts = &AtomicU32::new(0);
+        let result: Result<u32> =
+            Database::query_with_retry("Failed to query", move || async move {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(surrealdb::Error::Api(Api::Query("Parse error".into())))
+            })
+            .await;
+        assert!(result.unwrap_err().to_string().contains("Failed to query"));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+        // Transient failures give up after the last retry
+        let attempts = &AtomicU32::new(0);
+        let result: Result<u32> =
+            Database::query_with_retry("Failed to query", move || async move {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(surrealdb::Error::Api(Api::ConnectionUninitialised))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), QUERY_RETRIES + 1);
+
+        // An error raised by a statement fails the query, not the later `take`
+        let db = memory_db().await;
+        let attempts = &AtomicU32::new(0);
+        let result = Database::query_with_retry("Failed to query", || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            db.client.query("THROW 'statement failed'").await?.check()
+        })
+        .await;
+        let err = result.err().unwrap();
+        assert!(format!("{:#}", err).contains("statement failed"));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_retention_cutoff() {
+        let now = Utc::now();
+        assert_eq!(retention_cutoff(now, 0), now);
+        assert_eq!(retention_cutoff(now, 30), now - chrono::Duration::days(30));
+    }
+
+    #[test]
+    fn test_count_doc_appearances() {
+        let urls = vec![
+            "https://example.com/admin".to_string(),
+            "https://example.com/api".to_string(),
+        ];
+        let conversations = vec![
+            vec![
+                Message::user("How do I set up SSO?"),
+                Message::assistant("See [Admin Guide](https://example.com/admin)"),
+            ],
+            vec![Message::assistant(
+                "https://example.com/admin and https://example.com/admin again",
+            )],
+        ];
+
+        let counts = count_doc_appearances(&urls, &conversations);
+        assert_eq!(counts.get("https://example.com/admin"), Some(&2));
+        assert!(!counts.contains_key("https://example.com/api"));
+
+        // Longer URLs starting with a doc's URL don't count for it
+        let conversations = vec![vec![Message::assistant(
+            "See https://example.com/api-reference and https://example.com/admin/users",
+        )]];
+        assert!(count_doc_appearances(&urls, &conversations).is_empty());
+    }
+
+    #[test]
+    fn test_mentions_url() {
+        let url = "https://example.com/api";
+        for text in [
+            "https://example.com/api",
+            "See https://example.com/api.",
+            "[API](https://example.com/api)",
+            "\"url\": \"https://example.com/api\"",
+            "https://example.com/api#auth",
+        ] {
+            assert!(mentions_url(text, url), "{}", text);
+        }
+        for text in [
+            "https://example.com/api-reference",
+            "https://example.com/api/v2",
+            "https://example.com/api.html",
+            "https://example.com/apis",
+        ] {
+            assert!(!mentions_url(text, url), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_legacy_conversation_record_defaults() {
+        // Records saved before context/iteration/is_complete were persisted
+        let record: ConversationRecord = serde_json::from_value(serde_json::json!({
+            "session_id": "legacy-session",
+            "agent_type": "troubleshoot",
+            "messages": [],
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-01T00:00:00Z"
+        }))
+        .unwrap();
+
+        assert!(record.context.user_role.is_none());
+        assert_eq!(record.iteration, 0);
+        assert!(!record.is_complete);
+    }
+
+    #[test]
+    fn test_export_round_trips_through_json() {
+        let created_at: DateTime<Utc> = "2024-01-01T00:00:00Z".parse().unwrap();
+        let export = DbExport {
+            schema_version: MIGRATIONS.len() as u32,
+            exported_at: created_at,
+            docs: vec![DocRecord {
+                id: None,
+                title: "Admin Guide".to_string(),
+                content: "Setting up SSO".to_string(),
+                category: "admin".to_string(),
+                url: Some("https://example.com/admin".to_string()),
+                embedding: vec![0.5, 0.25],
+                created_at,
+            }],
+            feedback: vec![FeedbackRecord {
+                id: None,
+                session_id: "session-1".to_string(),
+                message_id: None,
+                rating: 5,
+                comment: Some("Helpful".to_string()),
+                created_at,
+            }],
+            ..DbExport::default()
+        };
+
+        let restored = DbExport::from_json(&export.to_json().unwrap()).unwrap();
+        assert_eq!(restored.schema_version, export.schema_version);
+        assert_eq!(restored.docs[0].url, export.docs[0].url);
+        assert_eq!(restored.docs[0].embedding, vec![0.5, 0.25]);
+        assert_eq!(restored.feedback[0].comment.as_deref(), Some("Helpful"));
+        assert!(restored.conversations.is_empty());
+
+        // Exports with sections missing still load
+        let partial =
+            DbExport::from_json(r#"{"schema_version": 1, "exported_at": "2024-01-01T00:00:00Z"}"#)
+                .unwrap();
+        assert!(partial.docs.is_empty());
     }
 }
@@ -0,0 +1,104 @@
+//! Embedding generation for documentation records
+//!
+//! [`Database::search_docs_by_embedding`](crate::db::Database::search_docs_by_embedding)
+//! only finds records whose `embedding` is populated. An [`Embedder`] computes
+//! that vector at ingestion time; real models (watsonx.ai, OpenAI) are
+//! provided by the caller, and [`HashEmbedder`] covers tests and local runs.
+
+use anyhow::Result;
+
+/// Default vector size produced by [`HashEmbedder`]
+pub const DEFAULT_HASH_DIMENSIONS: usize = 64;
+
+/// A model that turns text into an embedding vector
+#[async_trait::async_trait]
+pub trait Embedder: Send + Sync {
+    /// Embed `text`
+    ///
+    /// Every call must return vectors of the same dimension, since similarity
+    /// search skips records whose dimension differs from the query's.
+    async fn embed(&self, text: &str) -> Result<Vec<f32>>;
+}
+
+/// Deterministic embedder that hashes words into a fixed number of buckets
+///
+/// Texts sharing words get similar vectors, which is enough to exercise
+/// vector search without a model. It has no notion of meaning.
+#[derive(Debug, Clone)]
+pub struct HashEmbedder {
+    dimensions: usize,
+}
+
+impl HashEmbedder {
+    /// Create an embedder producing vectors of `dimensions` values
+    pub fn new(dimensions: usize) -> Self {
+        Self {
+            dimensions: dimensions.max(1),
+        }
+    }
+}
+
+impl Default for HashEmbedder {
+    fn default() -> Self {
+        Self::new(DEFAULT_HASH_DIMENSIONS)
+    }
+}
+
+#[async_trait::async_trait]
+impl Embedder for HashEmbedder {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let mut vector = vec![0.0f32; self.dimensions];
+        for word in text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+        {
+            let hash = fnv1a(&word.to_lowercase());
+            vector[(hash % self.dimensions as u64) as usize] += 1.0;
+        }
+
+        // Normalize so document length doesn't affect similarity
+        let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|v| *v /= norm);
+        }
+        Ok(vector)
+    }
+}
+
+/// FNV-1a hash, stable across platforms and Rust versions
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cosine(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    #[tokio::test]
+    async fn test_hash_embedder_is_deterministic() {
+        let embedder = HashEmbedder::new(32);
+        let first = embedder.embed("Configure SSO for admins").await.unwrap();
+        let second = embedder.embed("configure sso for ADMINS").await.unwrap();
+
+        assert_eq!(first.len(), 32);
+        assert_eq!(first, second);
+        assert!((cosine(&first, &first) - 1.0).abs() < 1e-5);
+    }
+
+    #[tokio::test]
+    async fn test_shared_words_are_more_similar() {
+        let embedder = HashEmbedder::default();
+        let query = embedder.embed("configure SSO").await.unwrap();
+        let related = embedder.embed("How to configure SSO login").await.unwrap();
+        let unrelated = embedder.embed("Workflow retry policies").await.unwrap();
+
+        assert!(cosine(&query, &related) > cosine(&query, &unrelated));
+        assert!(embedder.embed("").await.unwrap().iter().all(|v| *v == 0.0));
+    }
+}
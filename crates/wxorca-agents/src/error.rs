@@ -0,0 +1,98 @@
+//! Library error type
+//!
+//! [`WxorcaError`] lets embedders tell a graph that failed to build from a
+//! turn that failed while running, without matching on error messages.
+
+use oxidizedgraph::prelude::*;
+
+/// Errors returned by the runner and other library entry points
+#[derive(Debug, thiserror::Error)]
+pub enum WxorcaError {
+    /// The agent graph could not be built
+    #[error("Failed to build agent graph: {0}")]
+    GraphBuild(#[source] GraphError),
+
+    /// The graph failed while running a turn for a reason other than a
+    /// failing node, e.g. hitting the iteration limit
+    #[error("Agent execution failed: {0}")]
+    Execution(#[source] GraphError),
+
+    /// A tool or node failed
+    #[error("Tool error: {0}")]
+    Tool(#[from] NodeError),
+
+    /// A database operation failed
+    #[error(transparent)]
+    Database(anyhow::Error),
+}
+
+impl WxorcaError {
+    /// Categorize a failed graph run
+    ///
+    /// A [`NodeError`] anywhere in the error's source chain becomes
+    /// [`WxorcaError::Tool`]; anything else is [`WxorcaError::Execution`].
+    pub fn from_run(error: GraphError) -> Self {
+        let source: &(dyn std::error::Error + 'static) = &error;
+        let node_error = std::iter::successors(Some(source), |e| e.source())
+            .find_map(|e| e.downcast_ref::<NodeError>())
+            .map(|e| match e {
+                NodeError::ToolError(message) => NodeError::ToolError(message.clone()),
+                other => NodeError::Other(other.to_string()),
+            });
+
+        match node_error {
+            Some(e) => WxorcaError::Tool(e),
+            None => WxorcaError::Execution(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_errors_render_with_category() {
+        let error = WxorcaError::from(NodeError::Other("search backend down".to_string()));
+        assert!(matches!(error, WxorcaError::Tool(_)));
+        assert!(error.to_string().starts_with("Tool error: "));
+
+        let error = WxorcaError::Database(anyhow::anyhow!("Failed to load conversation"));
+        assert_eq!(error.to_string(), "Failed to load conversation");
+    }
+
+    struct FailingNode;
+
+    #[async_trait::async_trait]
+    impl NodeExecutor for FailingNode {
+        fn id(&self) -> &str {
+            "fail"
+        }
+
+        async fn execute(&self, _state: SharedState) -> Result<NodeOutput, NodeError> {
+            Err(NodeError::ToolError("search backend down".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_node_is_a_tool_error() {
+        let graph = GraphBuilder::new()
+            .name("failing")
+            .add_node(FailingNode)
+            .set_entry_point("fail")
+            .add_edge("fail", transitions::END)
+            .compile()
+            .unwrap();
+        let error = GraphRunner::new(graph, RunnerConfig::default())
+            .invoke(AgentState::new())
+            .await
+            .unwrap_err();
+
+        match WxorcaError::from_run(error) {
+            WxorcaError::Tool(NodeError::ToolError(message)) => {
+                assert!(message.contains("search backend down"))
+            }
+            other => panic!("expected a tool error, got {:?}", other),
+        }
+    }
+}
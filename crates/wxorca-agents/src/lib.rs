@@ -8,29 +8,61 @@
 //! - Documentation navigation
 
 pub mod agents;
+pub mod cache;
 pub mod db;
+pub mod embedding;
+pub mod error;
+pub mod moderation;
+pub mod redact;
+pub mod render;
+pub mod runner;
 pub mod state;
+mod text;
 pub mod tools;
 
 pub use agents::{
     AdminSetupAgent, BestPracticesAgent, DocsHelperAgent, TroubleshootAgent, UsageAssistantAgent,
 };
 pub use db::Database;
-pub use state::{AgentType, Message, WxoContext, WxorcaState};
+pub use error::WxorcaError;
+pub use moderation::{check_message, Blocklist, ModerationCategory, ModerationResult};
+pub use render::ResponseFormat;
+pub use runner::{run_agent, AgentResponse};
+pub use state::{
+    all_agent_tool_specs, AgentType, ConversationSummary, DocCategory, Language, Message,
+    PromptOverrides, UserRole, WxoContext, WxorcaState,
+};
 
 /// Re-exports from oxidizedgraph for convenience
 pub mod prelude {
     pub use oxidizedgraph::prelude::*;
 
-    pub use crate::agents::build_agent_graph;
+    pub use crate::agents::{
+        build_agent_graph, build_orchestrator_graph, classify_agent, export_agent_graph_dot,
+    };
     pub use crate::agents::{
         AdminSetupAgent, BestPracticesAgent, DocsHelperAgent, TroubleshootAgent,
         UsageAssistantAgent,
     };
+    pub use crate::agents::{
+        DiagnosisRules, GraphOptions, LlmClient, RenderContext, ResponseRenderer,
+    };
+    pub use crate::cache::ResponseCache;
     pub use crate::db::Database;
+    pub use crate::error::WxorcaError;
+    pub use crate::moderation::{check_message, Blocklist};
+    pub use crate::render::ResponseFormat;
+    pub use crate::runner::{
+        convert_to_agent_state, run_agent, run_agent_with, run_orchestrated, run_turn,
+        AgentResponse, RunOptions,
+    };
     // Note: WxorcaState uses its own MessageRole which differs from oxidizedgraph's
-    pub use crate::state::{AgentType, WxoContext, WxorcaState};
+    pub use crate::state::{
+        AgentType, DocCategory, Language, PromptOverrides, UserRole, WxoContext, WxorcaState,
+    };
     pub use crate::state::MessageRole as WxorcaMessageRole;
     pub use crate::state::Message as WxorcaMessage;
-    pub use crate::tools::create_tool_registry;
+    pub use crate::tools::{
+        create_tool_registry, create_tool_registry_for, create_tool_registry_with,
+    };
 }
@@ -0,0 +1,240 @@
+//! Moderation of incoming messages
+//!
+//! The agents answer a public-facing deployment, so each message is checked
+//! against a blocklist before an agent runs. Flagged messages get a polite
+//! refusal instead of a reply and are not persisted. The built-in blocklist
+//! is deliberately small; deployments can replace it with their own rules
+//! (an object with a `rules` array, loaded with [`Blocklist::load`]) and tune
+//! it using the categories reported for refused messages.
+
+use anyhow::Context;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// Why a message was flagged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationCategory {
+    /// Insults or abuse aimed at someone
+    Harassment,
+    /// Attacks on a group of people
+    Hate,
+    /// Threats of violence or requests for weapons
+    Violence,
+    /// Sexual content
+    Sexual,
+}
+
+impl ModerationCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModerationCategory::Harassment => "harassment",
+            ModerationCategory::Hate => "hate",
+            ModerationCategory::Violence => "violence",
+            ModerationCategory::Sexual => "sexual",
+        }
+    }
+}
+
+impl std::fmt::Display for ModerationCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Built-in patterns per category, matched case-insensitively against the
+/// message with its whitespace collapsed
+const BUILTIN_RULES: &[(ModerationCategory, &[&str])] = &[
+    (
+        ModerationCategory::Harassment,
+        &[
+            r"\bgo (?:kill|hurt) yourself\b",
+            r"\byou(?:'re| are)? (?:a |an )?(?:stupid|worthless|pathetic|useless) (?:idiot|moron|piece of \w+)\b",
+        ],
+    ),
+    (
+        ModerationCategory::Hate,
+        &[
+            r"\b(?:all|those) \w+ (?:people|folks) (?:should|must|deserve to) (?:die|be killed|be exterminated)\b",
+        ],
+    ),
+    (
+        ModerationCategory::Violence,
+        &[
+            r"\bi(?:'m| am)? (?:going to|gonna|will) (?:kill|hurt|shoot|stab|murder) (?:you|him|her)\b",
+            r"\bhow (?:do i|to|can i) (?:make|build) (?:a |an )?(?:bomb|explosive|pipe bomb)\b",
+        ],
+    ),
+    (
+        ModerationCategory::Sexual,
+        &[
+            r"\b(?:send|show) (?:me )?nudes?\b",
+            r"\b(?:write|tell) (?:me )?(?:an? )?(?:explicit|erotic) (?:sex )?(?:story|stories|content)\b",
+        ],
+    ),
+];
+
+static DEFAULT_BLOCKLIST: LazyLock<Blocklist> = LazyLock::new(Blocklist::default);
+
+/// Outcome of checking a message
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ModerationResult {
+    /// Whether the message should be refused
+    pub flagged: bool,
+    /// Categories the message matched, in blocklist order
+    pub categories: Vec<ModerationCategory>,
+}
+
+/// One blocklist category and the patterns that flag it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlocklistRule {
+    pub category: ModerationCategory,
+    /// Regex patterns, matched case-insensitively
+    pub patterns: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct BlocklistFile {
+    rules: Vec<BlocklistRule>,
+}
+
+/// Patterns that flag a message, grouped by category
+#[derive(Debug, Clone)]
+pub struct Blocklist {
+    rules: Vec<(ModerationCategory, Vec<Regex>)>,
+}
+
+impl Default for Blocklist {
+    fn default() -> Self {
+        let rules = BUILTIN_RULES
+            .iter()
+            .map(|&(category, patterns)| BlocklistRule {
+                category,
+                patterns: patterns.iter().map(|p| p.to_string()).collect(),
+            })
+            .collect();
+
+        Self::from_rules(rules).expect("valid built-in blocklist")
+    }
+}
+
+impl Blocklist {
+    /// A blocklist that flags nothing
+    pub fn empty() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    /// Compile `rules`, rejecting invalid or empty patterns
+    pub fn from_rules(rules: Vec<BlocklistRule>) -> anyhow::Result<Self> {
+        let rules = rules
+            .into_iter()
+            .map(|rule| {
+                let patterns = rule
+                    .patterns
+                    .iter()
+                    .map(|pattern| {
+                        if pattern.trim().is_empty() {
+                            anyhow::bail!("Blocklist rule {} has an empty pattern", rule.category);
+                        }
+                        RegexBuilder::new(pattern)
+                            .case_insensitive(true)
+                            .build()
+                            .with_context(|| {
+                                format!("Invalid {} pattern: {}", rule.category, pattern)
+                            })
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                Ok((rule.category, patterns))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Self { rules })
+    }
+
+    /// Parse a blocklist from JSON
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let file: BlocklistFile = serde_json::from_str(json).context("Invalid blocklist")?;
+        Self::from_rules(file.rules)
+    }
+
+    /// Load a blocklist from a JSON file
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::from_json(&json).with_context(|| format!("Failed to load {}", path.display()))
+    }
+
+    /// Check `content` against every rule
+    pub fn check(&self, content: &str) -> ModerationResult {
+        let content = content.split_whitespace().collect::<Vec<_>>().join(" ");
+
+        let mut categories: Vec<ModerationCategory> = Vec::new();
+        for (category, patterns) in &self.rules {
+            if !categories.contains(category) && patterns.iter().any(|p| p.is_match(&content)) {
+                categories.push(*category);
+            }
+        }
+
+        ModerationResult {
+            flagged: !categories.is_empty(),
+            categories,
+        }
+    }
+}
+
+/// Check `content` against the built-in blocklist
+pub fn check_message(content: &str) -> ModerationResult {
+    DEFAULT_BLOCKLIST.check(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_abusive_messages() {
+        let result = check_message("I am going to   KILL you if this breaks again");
+        assert!(result.flagged);
+        assert_eq!(result.categories, vec![ModerationCategory::Violence]);
+
+        let result = check_message("You stupid idiot, go kill yourself");
+        assert_eq!(result.categories, vec![ModerationCategory::Harassment]);
+    }
+
+    #[test]
+    fn test_support_questions_pass() {
+        for message in [
+            "How do I kill a stuck workflow run?",
+            "My skill is useless when the API returns 401",
+            "Why does this stupid integration keep timing out?",
+            "How do I make a bot that answers HR questions?",
+        ] {
+            assert_eq!(
+                check_message(message),
+                ModerationResult::default(),
+                "{}",
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn test_custom_blocklist() {
+        let blocklist = Blocklist::from_json(
+            r#"{"rules": [{"category": "harassment", "patterns": ["\\bdumb bot\\b"]}]}"#,
+        )
+        .unwrap();
+
+        assert!(blocklist.check("What a Dumb Bot").flagged);
+        assert!(!blocklist.check("I'm going to kill you").flagged);
+        assert!(!Blocklist::empty().check("I'm going to kill you").flagged);
+
+        let invalid = r#"{"rules": [{"category": "hate", "patterns": ["(unclosed"]}]}"#;
+        assert!(Blocklist::from_json(invalid).is_err());
+        let unknown = r#"{"rules": [{"category": "spam", "patterns": ["buy now"]}]}"#;
+        assert!(Blocklist::from_json(unknown).is_err());
+    }
+}
@@ -0,0 +1,350 @@
+//! Response rendering
+//!
+//! Agents author their replies as Markdown. Clients that can't display
+//! Markdown (chat bots, web UIs) can have the final reply converted to plain
+//! text or basic HTML instead.
+
+use serde::{Deserialize, Serialize};
+
+/// Output format for the final assistant message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// Markdown as authored by the agent
+    #[default]
+    Markdown,
+    /// Markdown syntax removed
+    PlainText,
+    /// Basic HTML (headings, paragraphs, lists, code, links)
+    Html,
+}
+
+impl ResponseFormat {
+    /// Render a Markdown reply in this format
+    ///
+    /// `strip_emoji` only applies to `PlainText`; Markdown and HTML keep
+    /// emoji as authored.
+    pub fn render(&self, markdown: &str, strip_emoji: bool) -> String {
+        match self {
+            ResponseFormat::Markdown => markdown.to_string(),
+            ResponseFormat::PlainText => {
+                let text = markdown_to_plain_text(markdown);
+                if strip_emoji {
+                    remove_emoji(&text)
+                } else {
+                    text
+                }
+            }
+            ResponseFormat::Html => markdown_to_html(markdown),
+        }
+    }
+}
+
+/// Remove Markdown syntax, keeping the text and link targets
+fn markdown_to_plain_text(markdown: &str) -> String {
+    let mut lines = Vec::new();
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            continue;
+        }
+        let line = match heading_level(trimmed) {
+            Some(level) => &trimmed[level + 1..],
+            None => line,
+        };
+        lines.push(render_inline(line, InlineStyle::Plain));
+    }
+
+    lines.join("\n").trim_end().to_string()
+}
+
+/// Convert Markdown to basic HTML
+fn markdown_to_html(markdown: &str) -> String {
+    let mut html = String::new();
+    let mut paragraph: Vec<String> = Vec::new();
+    let mut list: Option<&str> = None;
+    let mut in_code = false;
+
+    fn flush_paragraph(html: &mut String, paragraph: &mut Vec<String>) {
+        if !paragraph.is_empty() {
+            html.push_str(&format!("<p>{}</p>\n", paragraph.join("<br>\n")));
+            paragraph.clear();
+        }
+    }
+
+    fn close_list(html: &mut String, list: &mut Option<&str>) {
+        if let Some(tag) = list.take() {
+            html.push_str(&format!("</{}>\n", tag));
+        }
+    }
+
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+
+        if trimmed.starts_with("```") {
+            flush_paragraph(&mut html, &mut paragraph);
+            close_list(&mut html, &mut list);
+            html.push_str(if in_code {
+                "</code></pre>\n"
+            } else {
+                "<pre><code>"
+            });
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            html.push_str(&escape_html(line));
+            html.push('\n');
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            flush_paragraph(&mut html, &mut paragraph);
+            close_list(&mut html, &mut list);
+        } else if let Some(level) = heading_level(trimmed) {
+            flush_paragraph(&mut html, &mut paragraph);
+            close_list(&mut html, &mut list);
+            html.push_str(&format!(
+                "<h{level}>{}</h{level}>\n",
+                render_inline(&trimmed[level + 1..], InlineStyle::Html),
+            ));
+        } else if let Some((tag, item)) = list_item(trimmed) {
+            flush_paragraph(&mut html, &mut paragraph);
+            if list != Some(tag) {
+                close_list(&mut html, &mut list);
+                html.push_str(&format!("<{}>\n", tag));
+                list = Some(tag);
+            }
+            html.push_str(&format!(
+                "<li>{}</li>\n",
+                render_inline(item, InlineStyle::Html)
+            ));
+        } else {
+            close_list(&mut html, &mut list);
+            paragraph.push(render_inline(trimmed, InlineStyle::Html));
+        }
+    }
+
+    flush_paragraph(&mut html, &mut paragraph);
+    close_list(&mut html, &mut list);
+    if in_code {
+        html.push_str("</code></pre>\n");
+    }
+
+    html.trim_end().to_string()
+}
+
+/// Get the level of a `#` heading line (1-6)
+fn heading_level(line: &str) -> Option<usize> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    ((1..=6).contains(&level) && line[level..].starts_with(' ')).then_some(level)
+}
+
+/// Split a list item into its HTML list tag and text
+fn list_item(line: &str) -> Option<(&'static str, &str)> {
+    if let Some(item) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+        return Some(("ul", item));
+    }
+
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 {
+        if let Some(item) = line[digits..].strip_prefix(". ") {
+            return Some(("ol", item));
+        }
+    }
+
+    None
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum InlineStyle {
+    Plain,
+    Html,
+}
+
+/// Render inline Markdown (`**bold**`, `*italic*`, `` `code` ``, links)
+fn render_inline(text: &str, style: InlineStyle) -> String {
+    let mut out = String::new();
+    let mut bold = false;
+    let mut italic = false;
+    let mut code = false;
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        if c == '`' {
+            if style == InlineStyle::Html {
+                out.push_str(if code { "</code>" } else { "<code>" });
+            }
+            code = !code;
+            rest = &rest[1..];
+        } else if code {
+            push_text(&mut out, c, style);
+            rest = &rest[c.len_utf8()..];
+        } else if rest.starts_with("**") {
+            if style == InlineStyle::Html {
+                out.push_str(if bold { "</strong>" } else { "<strong>" });
+            }
+            bold = !bold;
+            rest = &rest[2..];
+        } else if c == '*' && (italic || rest[1..].contains('*')) {
+            if style == InlineStyle::Html {
+                out.push_str(if italic { "</em>" } else { "<em>" });
+            }
+            italic = !italic;
+            rest = &rest[1..];
+        } else if let Some((label, url, len)) = parse_link(rest) {
+            match style {
+                InlineStyle::Plain => out.push_str(&format!("{} ({})", label, url)),
+                InlineStyle::Html if is_safe_href(url) => out.push_str(&format!(
+                    "<a href=\"{}\">{}</a>",
+                    escape_html(url),
+                    escape_html(label)
+                )),
+                // Never link `javascript:` and the like; show them as text
+                InlineStyle::Html => out.push_str(&escape_html(&format!("{} ({})", label, url))),
+            }
+            rest = &rest[len..];
+        } else {
+            push_text(&mut out, c, style);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+
+    out
+}
+
+fn push_text(out: &mut String, c: char, style: InlineStyle) {
+    match (style, c) {
+        (InlineStyle::Html, '&') => out.push_str("&amp;"),
+        (InlineStyle::Html, '<') => out.push_str("&lt;"),
+        (InlineStyle::Html, '>') => out.push_str("&gt;"),
+        (InlineStyle::Html, '"') => out.push_str("&quot;"),
+        _ => out.push(c),
+    }
+}
+
+/// Parse a `[label](url)` link at the start of `text`, returning the label,
+/// URL and the length of the link syntax
+fn parse_link(text: &str) -> Option<(&str, &str, usize)> {
+    let label_end = text.strip_prefix('[')?.find("](")? + 1;
+    let url_end = text[label_end + 2..].find(')')? + label_end + 2;
+    Some((
+        &text[1..label_end],
+        &text[label_end + 2..url_end],
+        url_end + 1,
+    ))
+}
+
+/// URL schemes links may point to in HTML output
+const SAFE_LINK_SCHEMES: &[&str] = &["http://", "https://", "mailto:"];
+
+/// Whether `url` may be used as an `href`
+fn is_safe_href(url: &str) -> bool {
+    let url = url.trim().to_ascii_lowercase();
+    SAFE_LINK_SCHEMES
+        .iter()
+        .any(|scheme| url.starts_with(scheme))
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        push_text(&mut out, c, InlineStyle::Html);
+    }
+    out
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1F000..=0x1FAFF   // Pictographs, emoticons, transport, symbols
+            | 0x2600..=0x27BF // Miscellaneous symbols and dingbats
+            | 0x2B00..=0x2BFF // Arrows and stars (e.g. ⭐)
+            | 0xFE0F          // Variation selector
+            | 0x200D // Zero-width joiner
+    )
+}
+
+/// Remove emoji, along with the space that followed each one
+fn remove_emoji(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if is_emoji(c) {
+            if chars.peek() == Some(&' ') {
+                chars.next();
+            }
+        } else {
+            out.push(c);
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPLY: &str = "## 🏆 Best Practices\n\n\
+                         Use **modular** workflows and the `retry` action.\n\n\
+                         - Test <edge> cases\n\
+                         - See [Admin Guide](https://example.com/admin)\n\n\
+                         1. First\n\
+                         2. Second";
+
+    #[test]
+    fn test_markdown_is_unchanged() {
+        assert_eq!(ResponseFormat::Markdown.render(REPLY, true), REPLY);
+    }
+
+    #[test]
+    fn test_plain_text_strips_markdown() {
+        let text = ResponseFormat::PlainText.render(REPLY, false);
+        assert!(text.starts_with("🏆 Best Practices\n"));
+        assert!(text.contains("Use modular workflows and the retry action."));
+        assert!(text.contains("- See Admin Guide (https://example.com/admin)"));
+        assert!(!text.contains("**"));
+        assert!(!text.contains('`'));
+    }
+
+    #[test]
+    fn test_plain_text_strips_emoji() {
+        let text = ResponseFormat::PlainText.render(REPLY, true);
+        assert!(text.starts_with("Best Practices\n"));
+        assert_eq!(remove_emoji("✅ Done ⚠️ careful"), "Done careful");
+    }
+
+    #[test]
+    fn test_html_rendering() {
+        let html = ResponseFormat::Html.render(REPLY, true);
+        assert!(html.starts_with("<h2>🏆 Best Practices</h2>"));
+        assert!(html.contains(
+            "<p>Use <strong>modular</strong> workflows and the <code>retry</code> action.</p>"
+        ));
+        assert!(html.contains("<ul>\n<li>Test &lt;edge&gt; cases</li>"));
+        assert!(html.contains("<li>See <a href=\"https://example.com/admin\">Admin Guide</a></li>"));
+        assert!(html.contains("<ol>\n<li>First</li>\n<li>Second</li>\n</ol>"));
+    }
+
+    #[test]
+    fn test_html_links_only_to_safe_schemes() {
+        let render = |text: &str| ResponseFormat::Html.render(text, true);
+
+        assert!(render("[Mail](mailto:help@example.com)")
+            .contains("<a href=\"mailto:help@example.com\">Mail</a>"));
+        for url in [
+            "javascript:alert(1)",
+            " JavaScript:alert(1)",
+            "data:text/html,<script>",
+            "vbscript:msgbox",
+        ] {
+            let html = render(&format!("[Click]({})", url));
+            assert!(!html.contains("<a "), "{}", html);
+            assert!(!html.contains("<script>"), "{}", html);
+            assert!(html.contains("Click ("), "{}", html);
+        }
+    }
+}
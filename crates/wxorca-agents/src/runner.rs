@@ -0,0 +1,1435 @@
+//! Agent runner
+//!
+//! Library-level entry point that builds an agent graph, runs a single
+//! conversational turn and packages the reply, so embedders (the CLI, HTTP
+//! servers) don't have to reimplement the glue.
+
+use crate::agents::{
+    awaiting_user_input, build_agent_graph_with_options, build_orchestrator_graph_with_options,
+    classify_agent, orchestrated_agents, promoted_context, relevant_docs, suggested_followups,
+    tool_call_records, tool_iterations, tools_degraded, turn_messages, CommonTemplates,
+    DiagnosisRules, Escalation, GraphOptions, IntentClassifier, LlmClient, NodeObserver,
+    ResponseRenderer, ToolCallRecord,
+};
+use crate::cache::{CacheScope, CachedResponse, ResponseCache};
+use crate::error::WxorcaError;
+use crate::moderation::{check_message, Blocklist, ModerationCategory};
+use crate::render::ResponseFormat;
+use crate::state::{
+    AgentType, DocReference, Language, MessageRole as WxorcaMessageRole, PendingToolCall,
+    PromptOverrides, WxorcaState,
+};
+use oxidizedgraph::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Maximum graph iterations per turn
+const MAX_ITERATIONS: usize = 10;
+
+/// Reply returned when the graph finishes without an assistant message
+const FALLBACK_RESPONSE: &str = "I apologize, but I couldn't generate a response.";
+
+/// Result of running one turn of an agent
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentResponse {
+    pub session_id: String,
+    pub agent_type: String,
+    pub response: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolCallRecord>>,
+    /// Escalation data from the troubleshoot agent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub escalation: Option<Escalation>,
+    /// Documents the reply draws on, for rendering as citations
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<DocReference>,
+    /// Follow-up questions suggested at the end of the reply
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Why the message was refused without running the agent, if it was
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub flagged: Vec<ModerationCategory>,
+    /// Whether the reply came from the response cache
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
+    /// Whether the reply asks the user a clarifying question, so the next
+    /// message should answer it rather than start a new topic
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub needs_input: bool,
+    /// Whether every tool call failed (e.g. the docs database is down), so
+    /// the reply has no live documentation results
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub degraded: bool,
+    /// Wall-clock time the turn took, in milliseconds
+    pub duration_ms: u64,
+    /// Time spent in each graph node, in execution order
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub node_timings: Vec<NodeTiming>,
+}
+
+/// Time spent in one graph node during a turn
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NodeTiming {
+    /// Node id
+    pub node: String,
+    /// Time from the node starting until the next node (or the run) finished
+    pub duration_ms: u64,
+}
+
+impl AgentResponse {
+    /// A response carrying only an error, e.g. a rendered [`WxorcaError`]
+    pub fn failed(
+        session_id: impl Into<String>,
+        agent_type: AgentType,
+        error: impl Into<String>,
+    ) -> Self {
+        Self {
+            session_id: session_id.into(),
+            agent_type: agent_type.to_string(),
+            response: String::new(),
+            tool_calls: None,
+            escalation: None,
+            sources: Vec::new(),
+            suggestions: Vec::new(),
+            error: Some(error.into()),
+            flagged: Vec::new(),
+            cached: false,
+            needs_input: false,
+            degraded: false,
+            duration_ms: 0,
+            node_timings: Vec::new(),
+        }
+    }
+
+    /// A polite refusal, in `language`, for a message flagged as `categories`
+    pub fn refused(
+        session_id: impl Into<String>,
+        agent_type: AgentType,
+        language: Language,
+        categories: Vec<ModerationCategory>,
+    ) -> Self {
+        Self {
+            session_id: session_id.into(),
+            agent_type: agent_type.to_string(),
+            response: CommonTemplates::for_language(language).refusal.to_string(),
+            tool_calls: None,
+            escalation: None,
+            sources: Vec::new(),
+            suggestions: Vec::new(),
+            error: None,
+            flagged: categories,
+            cached: false,
+            needs_input: false,
+            degraded: false,
+            duration_ms: 0,
+            node_timings: Vec::new(),
+        }
+    }
+}
+
+/// Options for [`run_agent_with`]
+#[derive(Clone, Default)]
+pub struct RunOptions {
+    /// Include the tool calls made during the turn in the response
+    pub show_tool_calls: bool,
+    /// Notified as each graph node executes
+    pub observer: Option<NodeObserver>,
+    /// Model used to generate replies instead of the built-in templates
+    pub llm: Option<Arc<dyn LlmClient>>,
+    /// Renderers used for template replies instead of the agents' defaults,
+    /// by agent
+    pub renderers: HashMap<AgentType, Arc<dyn ResponseRenderer>>,
+    /// Format the reply is rendered in
+    pub response_format: ResponseFormat,
+    /// Remove emoji from plain-text replies
+    pub strip_emoji: bool,
+    /// Cap on tool execution passes per turn (defaults to
+    /// [`DEFAULT_MAX_TOOL_ITERATIONS`](crate::agents::DEFAULT_MAX_TOOL_ITERATIONS))
+    pub max_tool_iterations: Option<usize>,
+    /// Docs or code examples listed in a template reply (defaults to
+    /// [`DEFAULT_MAX_RESULTS_IN_RESPONSE`](crate::agents::DEFAULT_MAX_RESULTS_IN_RESPONSE))
+    pub max_results_in_response: Option<usize>,
+    /// Cache consulted for the opening question of a conversation; not used
+    /// with custom diagnosis rules, classifiers or renderers, which can't be
+    /// told apart between runs
+    pub cache: Option<Arc<ResponseCache>>,
+    /// Context window of the model, in tokens; a warning is logged when the
+    /// conversation's [`token_estimate`](WxorcaState::token_estimate) exceeds it
+    pub model_token_limit: Option<usize>,
+    /// Use mock tool data and sequential tool call ids, for reproducible
+    /// output (see [`DETERMINISTIC_ENV`](crate::agents::DETERMINISTIC_ENV))
+    pub deterministic: bool,
+    /// Tools the agent calls instead of the built-in registry (see
+    /// [`create_tool_registry_with`](crate::tools::create_tool_registry_with))
+    pub tool_registry: Option<Arc<ToolRegistry>>,
+    /// System prompts used instead of the built-in ones
+    pub prompts: Option<Arc<PromptOverrides>>,
+    /// Rules the troubleshooting agent diagnoses issues with instead of the
+    /// built-in ones
+    pub diagnosis_rules: Option<Arc<DiagnosisRules>>,
+    /// Meaningful keywords a query needs before the agents search for it
+    pub min_keywords: Option<usize>,
+    /// Classifier the agents detect intents with instead of the built-in one
+    pub classifier: Option<Arc<IntentClassifier>>,
+    /// Time a single tool call may take (defaults to
+    /// [`DEFAULT_TOOL_TIMEOUT`](crate::agents::DEFAULT_TOOL_TIMEOUT))
+    pub tool_timeout: Option<Duration>,
+    /// Blocklist incoming messages are checked against instead of the
+    /// built-in one
+    pub blocklist: Option<Arc<Blocklist>>,
+}
+
+impl RunOptions {
+    /// Include the tool calls made during the turn in the response
+    pub fn show_tool_calls(mut self, show: bool) -> Self {
+        self.show_tool_calls = show;
+        self
+    }
+
+    /// Notify `observer` as each graph node executes
+    pub fn observer(mut self, observer: NodeObserver) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Generate replies with `llm`
+    pub fn llm(mut self, llm: Arc<dyn LlmClient>) -> Self {
+        self.llm = Some(llm);
+        self
+    }
+
+    /// Render `agent_type`'s template replies with `renderer`
+    pub fn renderer_for(
+        mut self,
+        agent_type: AgentType,
+        renderer: Arc<dyn ResponseRenderer>,
+    ) -> Self {
+        self.renderers.insert(agent_type, renderer);
+        self
+    }
+
+    /// Render the reply in `format`
+    pub fn response_format(mut self, format: ResponseFormat) -> Self {
+        self.response_format = format;
+        self
+    }
+
+    /// Remove emoji from plain-text replies
+    pub fn strip_emoji(mut self, strip: bool) -> Self {
+        self.strip_emoji = strip;
+        self
+    }
+
+    /// Stop executing tools after `max` passes in a turn
+    pub fn max_tool_iterations(mut self, max: usize) -> Self {
+        self.max_tool_iterations = Some(max);
+        self
+    }
+
+    /// List at most `max` docs or code examples in template replies
+    pub fn max_results_in_response(mut self, max: usize) -> Self {
+        self.max_results_in_response = Some(max);
+        self
+    }
+
+    /// Reuse replies from `cache`
+    pub fn cache(mut self, cache: Arc<ResponseCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Warn when a conversation is estimated to exceed `limit` tokens
+    pub fn model_token_limit(mut self, limit: usize) -> Self {
+        self.model_token_limit = Some(limit);
+        self
+    }
+
+    /// Use mock tool data and sequential tool call ids
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Execute tool calls with `tool_registry`
+    pub fn tool_registry(mut self, tool_registry: Arc<ToolRegistry>) -> Self {
+        self.tool_registry = Some(tool_registry);
+        self
+    }
+
+    /// Prefer system prompts from `prompts` to the built-in ones
+    pub fn prompts(mut self, prompts: Arc<PromptOverrides>) -> Self {
+        self.prompts = Some(prompts);
+        self
+    }
+
+    /// Diagnose troubleshooting queries with `rules`
+    pub fn diagnosis_rules(mut self, rules: Arc<DiagnosisRules>) -> Self {
+        self.diagnosis_rules = Some(rules);
+        self
+    }
+
+    /// Only search for queries with at least `min_keywords` meaningful
+    /// keywords
+    pub fn min_keywords(mut self, min_keywords: usize) -> Self {
+        self.min_keywords = Some(min_keywords);
+        self
+    }
+
+    /// Detect intents with `classifier`
+    pub fn classifier(mut self, classifier: Arc<IntentClassifier>) -> Self {
+        self.classifier = Some(classifier);
+        self
+    }
+
+    /// Abandon tool calls that take longer than `timeout`
+    pub fn tool_timeout(mut self, timeout: Duration) -> Self {
+        self.tool_timeout = Some(timeout);
+        self
+    }
+
+    /// Refuse messages flagged by `blocklist`
+    pub fn blocklist(mut self, blocklist: Arc<Blocklist>) -> Self {
+        self.blocklist = Some(blocklist);
+        self
+    }
+}
+
+/// Run one turn of the given agent with default options
+pub async fn run_agent(
+    agent_type: AgentType,
+    session_id: Option<&str>,
+    message: &str,
+) -> Result<AgentResponse, WxorcaError> {
+    run_agent_with(agent_type, session_id, message, RunOptions::default()).await
+}
+
+/// Run one turn of the given agent
+pub async fn run_agent_with(
+    agent_type: AgentType,
+    session_id: Option<&str>,
+    message: &str,
+    options: RunOptions,
+) -> Result<AgentResponse, WxorcaError> {
+    // Create or restore state
+    let mut state = if let Some(sid) = session_id {
+        WxorcaState::with_session_id(agent_type, sid)
+    } else {
+        WxorcaState::new(agent_type)
+    };
+
+    run_turn(&mut state, message, options).await
+}
+
+/// Run one turn of the orchestrator, which picks the agent(s) to answer
+///
+/// A new conversation's agent type is the [`classify_agent`] pick for
+/// `message`.
+pub async fn run_orchestrated(
+    session_id: Option<&str>,
+    message: &str,
+    options: RunOptions,
+) -> Result<AgentResponse, WxorcaError> {
+    let agent_type = classify_agent(message);
+    let mut state = if let Some(sid) = session_id {
+        WxorcaState::with_session_id(agent_type, sid)
+    } else {
+        WxorcaState::new(agent_type)
+    };
+
+    run_orchestrated_turn(&mut state, message, options).await
+}
+
+/// Run one turn of an existing conversation
+///
+/// The user message and the agent's reply are appended to `state`, so
+/// calling this repeatedly with the same state carries the conversation
+/// history across turns.
+///
+/// Only the opening question of a conversation is answered from
+/// `options.cache`, since later replies depend on the history.
+///
+/// Messages flagged by moderation (see [`check_message`]) get a refusal
+/// without running the agent. If the message is refused or the graph can't
+/// be built, `state` is left unchanged.
+pub async fn run_turn(
+    state: &mut WxorcaState,
+    message: &str,
+    options: RunOptions,
+) -> Result<AgentResponse, WxorcaError> {
+    run_graph_turn(state, message, options, build_agent_graph_with_options).await
+}
+
+/// Run one turn of an existing conversation through the orchestrator
+///
+/// Like [`run_turn`], but each turn goes to the agent(s) the orchestrator
+/// picks for the message rather than `state.agent_type`. The response's
+/// `agent_type` is the primary agent. `options.cache` is ignored, since a
+/// merged reply doesn't belong to any one agent.
+pub async fn run_orchestrated_turn(
+    state: &mut WxorcaState,
+    message: &str,
+    options: RunOptions,
+) -> Result<AgentResponse, WxorcaError> {
+    let options = RunOptions {
+        cache: None,
+        ..options
+    };
+    run_graph_turn(state, message, options, |_, graph_options| {
+        build_orchestrator_graph_with_options(graph_options)
+    })
+    .await
+}
+
+async fn run_graph_turn(
+    state: &mut WxorcaState,
+    message: &str,
+    options: RunOptions,
+    build_graph: fn(AgentType, GraphOptions) -> Result<CompiledGraph, GraphError>,
+) -> Result<AgentResponse, WxorcaError> {
+    let started = Instant::now();
+    let agent_type = state.agent_type;
+
+    let moderation = match options.blocklist {
+        Some(ref blocklist) => blocklist.check(message),
+        None => check_message(message),
+    };
+    if moderation.flagged {
+        tracing::info!(
+            session_id = %state.session_id,
+            categories = ?moderation.categories,
+            "Refused flagged message"
+        );
+        let language = explicit_language(state).unwrap_or_else(|| Language::detect(message));
+        let mut response = AgentResponse::refused(
+            &state.session_id,
+            agent_type,
+            language,
+            moderation.categories,
+        );
+        response.duration_ms = elapsed_ms(started);
+        return Ok(response);
+    }
+
+    // Graph options, recording when each node starts
+    let node_starts = Arc::new(Mutex::new(Vec::new()));
+    let graph_options = GraphOptions {
+        observer: Some(timing_observer(node_starts.clone(), options.observer)),
+        llm: options.llm,
+        renderers: options.renderers,
+        deterministic: options.deterministic,
+        tool_registry: options.tool_registry,
+        prompts: options.prompts,
+        diagnosis_rules: options.diagnosis_rules,
+        min_keywords: options.min_keywords,
+        classifier: options.classifier,
+        tool_timeout: options.tool_timeout,
+    };
+    let system_prompt = graph_options.system_prompt(agent_type);
+
+    let cache = options.cache.clone().filter(|_| {
+        state.last_user_message().is_none() && is_cacheable(&graph_options, agent_type)
+    });
+    let scope = cache_scope(
+        state,
+        &system_prompt,
+        options.max_results_in_response,
+        graph_options.min_keywords,
+    );
+
+    if let Some(cached) = cache
+        .as_ref()
+        .and_then(|c| c.get(agent_type, &scope, message))
+    {
+        state.add_user_message(message);
+        state.add_assistant_message(&cached.response);
+        state.context.relevant_docs = cached.sources.clone();
+
+        return Ok(AgentResponse {
+            session_id: state.session_id.clone(),
+            agent_type: agent_type.to_string(),
+            response: options
+                .response_format
+                .render(&cached.response, options.strip_emoji),
+            tool_calls: options.show_tool_calls.then(Vec::new),
+            escalation: cached.escalation,
+            sources: cached.sources,
+            suggestions: cached.suggestions,
+            error: None,
+            flagged: Vec::new(),
+            cached: true,
+            needs_input: cached.needs_input,
+            degraded: false,
+            duration_ms: elapsed_ms(started),
+            node_timings: Vec::new(),
+        });
+    }
+
+    // Build the agent graph
+    let graph = build_graph(agent_type, graph_options).map_err(WxorcaError::GraphBuild)?;
+
+    // Add the user message
+    state.add_user_message(message);
+    if let Some(limit) = options.model_token_limit {
+        let estimate = state.token_estimate();
+        if estimate > limit {
+            tracing::warn!(
+                session_id = %state.session_id,
+                estimate,
+                limit,
+                "Conversation is estimated to exceed the model's context window"
+            );
+        }
+    }
+
+    // Convert to AgentState for the runner
+    let mut agent_state = convert_with_system_prompt(state, &system_prompt);
+    if let Some(max) = options.max_tool_iterations {
+        agent_state.set_context("max_tool_iterations", serde_json::json!(max));
+    }
+    if let Some(max) = options.max_results_in_response {
+        agent_state.set_context("max_results_in_response", serde_json::json!(max));
+    }
+    if options.deterministic {
+        agent_state.set_context("deterministic", serde_json::json!(true));
+    }
+
+    // Run the graph
+    let runner = GraphRunner::new(
+        graph,
+        RunnerConfig::default()
+            .max_iterations(MAX_ITERATIONS)
+            .verbose(false),
+    );
+
+    let outcome = runner.invoke(agent_state).await;
+    let node_timings = node_starts
+        .lock()
+        .map(|starts| node_timings(&starts, Instant::now()))
+        .unwrap_or_default();
+
+    match outcome {
+        Ok(result_state) => {
+            // Extract the assistant's response
+            let response = result_state
+                .last_assistant_message()
+                .map(|m| m.content.clone())
+                .unwrap_or_else(|| FALLBACK_RESPONSE.to_string());
+            // Record the tool calls and results ahead of the reply
+            state.messages.extend(turn_messages(&result_state));
+            state.add_assistant_message(&response);
+            state.pending_tool_calls = result_state
+                .tool_calls
+                .iter()
+                .map(PendingToolCall::from)
+                .collect();
+            for _ in 0..tool_iterations(&result_state) {
+                state.increment_iteration();
+            }
+
+            let escalation = result_state.get_context::<Escalation>("escalation");
+            let sources = relevant_docs(&result_state);
+            let suggestions = suggested_followups(&result_state);
+            let needs_input = awaiting_user_input(&result_state);
+            let degraded = tools_degraded(&result_state);
+            // A question for the user leaves the conversation open
+            state.is_complete = result_state.is_complete && !needs_input;
+            // Keep the topic, docs and language so follow-ups in later turns
+            // (and restored conversations) continue from this turn
+            state.context.merge(&promoted_context(&result_state));
+            if let Some(cache) = cache {
+                let cacheable = response != FALLBACK_RESPONSE && !degraded;
+                if cacheable && cache.allows_tool_calls(&tool_call_records(&result_state)) {
+                    cache.insert(
+                        agent_type,
+                        &scope,
+                        message,
+                        CachedResponse {
+                            response: response.clone(),
+                            escalation: escalation.clone(),
+                            sources: sources.clone(),
+                            suggestions: suggestions.clone(),
+                            needs_input,
+                        },
+                    );
+                }
+            }
+
+            let response = options
+                .response_format
+                .render(&response, options.strip_emoji);
+            let tool_calls = options
+                .show_tool_calls
+                .then(|| tool_call_records(&result_state));
+
+            let responding_agent = orchestrated_agents(&result_state)
+                .first()
+                .copied()
+                .unwrap_or(agent_type);
+
+            Ok(AgentResponse {
+                session_id: state.session_id.clone(),
+                agent_type: responding_agent.to_string(),
+                response,
+                tool_calls,
+                escalation,
+                sources,
+                suggestions,
+                error: None,
+                flagged: Vec::new(),
+                cached: false,
+                needs_input,
+                degraded,
+                duration_ms: elapsed_ms(started),
+                node_timings,
+            })
+        }
+        Err(e) => Err(WxorcaError::from_run(e)),
+    }
+}
+
+/// The settings that shape this turn's reply, which a cached reply must
+/// share to be reused
+fn cache_scope(
+    state: &WxorcaState,
+    system_prompt: &str,
+    max_results_in_response: Option<usize>,
+    min_keywords: Option<usize>,
+) -> CacheScope {
+    CacheScope {
+        role: state.context.role(),
+        deployment_type: state.context.deployment_type.clone(),
+        language: explicit_language(state),
+        max_results_in_response,
+        min_keywords,
+        system_prompt: state.assemble_system_prompt(system_prompt),
+    }
+}
+
+/// Whether `agent_type`'s replies can be cached with these options
+///
+/// Custom diagnosis rules, classifiers and renderers shape the reply but
+/// can't be compared, so a [`CacheScope`] can't tell them apart.
+fn is_cacheable(options: &GraphOptions, agent_type: AgentType) -> bool {
+    let custom_rules = agent_type == AgentType::Troubleshoot && options.diagnosis_rules.is_some();
+    !custom_rules && options.classifier.is_none() && !options.renderers.contains_key(&agent_type)
+}
+
+/// The language the client set in the conversation's `language` metadata,
+/// if any
+///
+/// The language detected from a query is saved as `detected_language`
+/// instead, and doesn't carry over to later turns.
+fn explicit_language(state: &WxorcaState) -> Option<Language> {
+    state
+        .get_metadata("language")
+        .and_then(|l| l.as_str())
+        .and_then(|l| l.parse::<Language>().ok())
+}
+
+fn elapsed_ms(started: Instant) -> u64 {
+    started.elapsed().as_millis() as u64
+}
+
+/// Observer that records each node's start time in `starts` before
+/// notifying `inner`
+fn timing_observer(
+    starts: Arc<Mutex<Vec<(String, Instant)>>>,
+    inner: Option<NodeObserver>,
+) -> NodeObserver {
+    Arc::new(move |id: &str, description: Option<&str>| {
+        if let Ok(mut starts) = starts.lock() {
+            starts.push((id.to_string(), Instant::now()));
+        }
+        if let Some(inner) = &inner {
+            inner(id, description);
+        }
+    })
+}
+
+/// Turn node start times into durations; nodes run one at a time, so each
+/// lasts until the next one starts, and the last until `finished`
+fn node_timings(starts: &[(String, Instant)], finished: Instant) -> Vec<NodeTiming> {
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, (node, start))| {
+            let end = starts.get(i + 1).map_or(finished, |(_, next)| *next);
+            NodeTiming {
+                node: node.clone(),
+                duration_ms: end.saturating_duration_since(*start).as_millis() as u64,
+            }
+        })
+        .collect()
+}
+
+/// Convert a WXOrca conversation into the oxidizedgraph state the runner expects
+pub fn convert_to_agent_state(wxorca_state: &WxorcaState) -> AgentState {
+    convert_with_system_prompt(wxorca_state, wxorca_state.agent_type.system_prompt())
+}
+
+/// Like [`convert_to_agent_state`], but starting from `system_prompt`
+/// instead of the agent's built-in prompt
+///
+/// The user's role, WXO version and deployment type are appended to the
+/// prompt when known; see [`WxorcaState::assemble_system_prompt`]. The
+/// appended block is also stored as the `prompt_context` context key.
+fn convert_with_system_prompt(wxorca_state: &WxorcaState, system_prompt: &str) -> AgentState {
+    let grounding = wxorca_state.context.prompt_block();
+    let system_prompt = wxorca_state.assemble_system_prompt(system_prompt);
+    let system_prompt = system_prompt.as_str();
+    // Replay the cleaned-up conversation; the raw messages stay as stored
+    let messages = wxorca_state.normalized_messages();
+
+    let mut agent_state = AgentState::new();
+    agent_state.messages.push(Message::system(system_prompt));
+
+    // Tool calls and results from earlier turns are already reflected in the
+    // replies; only the current turn's results are replayed
+    let turn_start = messages
+        .iter()
+        .rposition(|m| m.role == WxorcaMessageRole::User)
+        .unwrap_or(0);
+
+    // Stored system messages come first in the normalized conversation, so
+    // they follow the system prompt ahead of the first question
+    for (i, msg) in messages.iter().enumerate() {
+        match msg.role {
+            WxorcaMessageRole::User => agent_state.add_user_message(&msg.content),
+            WxorcaMessageRole::Assistant if msg.is_tool_call() => {}
+            WxorcaMessageRole::Assistant => agent_state.add_assistant_message(&msg.content),
+            WxorcaMessageRole::System => {
+                agent_state.messages.push(Message::system(&msg.content));
+            }
+            WxorcaMessageRole::Tool if i > turn_start => {
+                if let Some(ref tool_call_id) = msg.tool_call_id {
+                    agent_state.add_tool_result(tool_call_id, &msg.content);
+                }
+            }
+            WxorcaMessageRole::Tool => {}
+        }
+    }
+
+    // Carry over tool calls left queued by the previous turn
+    agent_state.tool_calls = wxorca_state
+        .pending_tool_calls
+        .iter()
+        .map(ToolCall::from)
+        .collect();
+
+    // Set context
+    agent_state.set_context("agent_type", serde_json::json!(wxorca_state.agent_type));
+    agent_state.set_context("session_id", serde_json::json!(wxorca_state.session_id));
+    if let Some(ref deployment_type) = wxorca_state.context.deployment_type {
+        agent_state.set_context("deployment_type", serde_json::json!(deployment_type));
+    }
+    if let Some(ref topic) = wxorca_state.context.current_topic {
+        agent_state.set_context("current_topic", serde_json::json!(topic));
+    }
+    if let Some(role) = wxorca_state.context.role() {
+        agent_state.set_context("user_role", serde_json::json!(role));
+    }
+    // Deterministic tool call ids continue from earlier turns' calls
+    let earlier_calls = wxorca_state
+        .messages
+        .iter()
+        .filter(|m| m.is_tool_call())
+        .count();
+    agent_state.set_context("tool_call_offset", serde_json::json!(earlier_calls));
+    // Lets nodes that replace the system prompt keep the grounding
+    if let Some(block) = grounding {
+        agent_state.set_context("prompt_context", serde_json::json!(block));
+    }
+    // A language set by the client wins over detection
+    if let Some(language) = explicit_language(wxorca_state) {
+        agent_state.set_context("language", serde_json::json!(language));
+    }
+
+    agent_state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::{suggest_followups, RenderContext};
+    use crate::tools::testing::DownTool;
+
+    #[tokio::test]
+    async fn test_run_agent_returns_reply() {
+        let response = run_agent(AgentType::Troubleshoot, Some("session-1"), "My skill fails")
+            .await
+            .unwrap();
+
+        assert!(response.error.is_none());
+        assert_eq!(response.session_id, "session-1");
+        assert!(!response.response.is_empty());
+        assert_ne!(response.response, FALLBACK_RESPONSE);
+        assert!(response.escalation.is_some());
+        // "My skill fails" is diagnosed as an execution issue
+        assert_eq!(
+            response.suggestions,
+            suggest_followups(AgentType::Troubleshoot, "execution", Language::English)
+        );
+        assert!(response.response.contains(&response.suggestions[0]));
+    }
+
+    #[tokio::test]
+    async fn test_needs_input_reported() {
+        let response = run_agent(AgentType::Troubleshoot, None, "Something is wrong")
+            .await
+            .unwrap();
+        assert!(response.needs_input);
+
+        let response = run_agent(AgentType::Troubleshoot, None, "My skill fails")
+            .await
+            .unwrap();
+        assert!(!response.needs_input);
+        assert!(serde_json::to_value(&response)
+            .unwrap()
+            .get("needs_input")
+            .is_none());
+    }
+
+    /// Replies with a fixed line
+    struct FixedRenderer;
+
+    impl ResponseRenderer for FixedRenderer {
+        fn render(&self, _query: &str, _context: &RenderContext) -> String {
+            "fixed reply".to_string()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_renderer_only_used_for_its_agent() {
+        let options = RunOptions::default()
+            .deterministic(true)
+            .renderer_for(AgentType::Troubleshoot, Arc::new(FixedRenderer));
+
+        let response = run_agent_with(
+            AgentType::Troubleshoot,
+            None,
+            "My skill fails",
+            options.clone(),
+        )
+        .await
+        .unwrap();
+        assert!(response.response.starts_with("fixed reply"));
+
+        let response = run_agent_with(
+            AgentType::DocsHelper,
+            None,
+            "Where is the admin guide?",
+            options,
+        )
+        .await
+        .unwrap();
+        assert!(!response.response.starts_with("fixed reply"));
+    }
+
+    #[tokio::test]
+    async fn test_question_for_user_leaves_conversation_open() {
+        let mut state = WxorcaState::new(AgentType::Troubleshoot);
+        run_turn(&mut state, "Something is wrong", RunOptions::default())
+            .await
+            .unwrap();
+        assert!(!state.is_complete);
+
+        run_turn(&mut state, "My skill fails", RunOptions::default())
+            .await
+            .unwrap();
+        assert!(state.is_complete);
+    }
+
+    #[tokio::test]
+    async fn test_flagged_message_refused() {
+        let mut state = WxorcaState::new(AgentType::Troubleshoot);
+        let response = run_turn(
+            &mut state,
+            "I'm going to kill you if my skill fails again",
+            RunOptions::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            response.response,
+            CommonTemplates::for_language(Language::English).refusal
+        );
+        assert_eq!(response.flagged, vec![ModerationCategory::Violence]);
+        assert!(response.error.is_none());
+        assert!(state.messages.is_empty());
+
+        // The refusal follows the language the client chose
+        state.set_metadata("language", "es");
+        let response = run_turn(
+            &mut state,
+            "I'm going to kill you if my skill fails again",
+            RunOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            response.response,
+            CommonTemplates::for_language(Language::Spanish).refusal
+        );
+
+        // A custom blocklist replaces the built-in one
+        let response = run_turn(
+            &mut state,
+            "I'm going to kill you if my skill fails again",
+            RunOptions::default().blocklist(Arc::new(Blocklist::empty())),
+        )
+        .await
+        .unwrap();
+        assert!(response.flagged.is_empty());
+        assert_eq!(state.messages.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_node_timings_reported() {
+        let response = run_agent(AgentType::Troubleshoot, None, "My skill fails")
+            .await
+            .unwrap();
+
+        assert_eq!(response.node_timings[0].node, "analyze");
+        let node_total: u64 = response.node_timings.iter().map(|t| t.duration_ms).sum();
+        assert!(node_total <= response.duration_ms);
+
+        let json = serde_json::to_value(&response).unwrap();
+        assert!(json["duration_ms"].is_u64());
+    }
+
+    #[test]
+    fn test_node_timings_span_until_next_node() {
+        let start = Instant::now();
+        let starts = vec![
+            ("analyze".to_string(), start),
+            ("respond".to_string(), start + Duration::from_millis(30)),
+        ];
+        let timings = node_timings(&starts, start + Duration::from_millis(50));
+
+        assert_eq!(
+            timings,
+            vec![
+                NodeTiming {
+                    node: "analyze".to_string(),
+                    duration_ms: 30
+                },
+                NodeTiming {
+                    node: "respond".to_string(),
+                    duration_ms: 20
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_total_tool_failure_degrades_reply() {
+        let query = "How do I setup SSO for my team?";
+        let registry = ToolRegistry::new()
+            .register(DownTool("search_wxo_docs"))
+            .register(DownTool("fetch_wxo_examples"));
+        let cache = Arc::new(ResponseCache::new(4).cache_tool_responses(true));
+        let options = RunOptions::default()
+            .tool_registry(Arc::new(registry))
+            .cache(cache.clone());
+
+        let response = run_agent_with(AgentType::AdminSetup, None, query, options)
+            .await
+            .unwrap();
+
+        assert!(response.degraded);
+        assert!(response.error.is_none());
+        assert!(response
+            .response
+            .contains("Live documentation lookup is temporarily unavailable"));
+        // A degraded reply isn't reused once the tools recover
+        assert!(cache.is_empty());
+
+        let response = run_agent(AgentType::AdminSetup, None, query).await.unwrap();
+        assert!(!response.degraded);
+        assert!(!response.response.contains("temporarily unavailable"));
+    }
+
+    #[tokio::test]
+    async fn test_show_tool_calls_reports_doc_search() {
+        let response = run_agent_with(
+            AgentType::AdminSetup,
+            None,
+            "How do I setup SSO for my team?",
+            RunOptions::default().show_tool_calls(true),
+        )
+        .await
+        .unwrap();
+
+        assert!(response.error.is_none());
+        let tool_calls = response.tool_calls.unwrap();
+        assert!(tool_calls.iter().any(|c| c.name == "search_wxo_docs"));
+    }
+
+    #[tokio::test]
+    async fn test_tool_calls_omitted_by_default() {
+        let response = run_agent(AgentType::AdminSetup, None, "How do I setup SSO?")
+            .await
+            .unwrap();
+
+        assert!(response.tool_calls.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_response_format_applied() {
+        let response = run_agent_with(
+            AgentType::BestPractices,
+            None,
+            "What are workflow best practices?",
+            RunOptions::default().response_format(ResponseFormat::Html),
+        )
+        .await
+        .unwrap();
+
+        assert!(response.error.is_none());
+        assert!(response.response.starts_with("<h"));
+        assert!(!response.response.contains("**"));
+    }
+
+    #[tokio::test]
+    async fn test_orchestrated_turn_reports_primary_agent() {
+        let response = run_orchestrated(
+            Some("orchestrated"),
+            "My skill fails with an error, where can I find the docs?",
+            RunOptions::default().deterministic(true),
+        )
+        .await
+        .unwrap();
+
+        assert!(response.error.is_none());
+        assert_eq!(response.agent_type, AgentType::Troubleshoot.to_string());
+        assert!(response
+            .response
+            .contains(AgentType::DocsHelper.display_name()));
+    }
+
+    #[tokio::test]
+    async fn test_deterministic_runs_match() {
+        let run = || {
+            run_agent_with(
+                AgentType::UsageAssistant,
+                Some("golden"),
+                "How do I create a custom skill?",
+                RunOptions::default()
+                    .show_tool_calls(true)
+                    .deterministic(true),
+            )
+        };
+        let first = run().await.unwrap();
+        let second = run().await.unwrap();
+
+        assert_eq!(first.response, second.response);
+        assert_eq!(
+            serde_json::to_value(&first.tool_calls).unwrap(),
+            serde_json::to_value(&second.tool_calls).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_turn_keeps_history() {
+        let mut state = WxorcaState::with_session_id(AgentType::Troubleshoot, "thread-1");
+
+        run_turn(&mut state, "My workflow is slow", RunOptions::default())
+            .await
+            .unwrap();
+        let response = run_turn(&mut state, "It still times out", RunOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(response.session_id, "thread-1");
+        let turns = state
+            .messages
+            .iter()
+            .filter(|m| m.role != WxorcaMessageRole::Tool && !m.is_tool_call())
+            .count();
+        assert_eq!(turns, 4);
+        // The earlier turn is visible to the agent, so the repeat escalates
+        assert!(response.escalation.unwrap().should_escalate);
+    }
+
+    #[tokio::test]
+    async fn test_queued_tool_calls_visible_on_state() {
+        // The admin agent queues a docs search; with no tool passes allowed
+        // it replies without running it
+        let mut state = WxorcaState::new(AgentType::AdminSetup);
+        run_turn(
+            &mut state,
+            "How do I setup SSO for my team?",
+            RunOptions::default().max_tool_iterations(0),
+        )
+        .await
+        .unwrap();
+
+        assert!(state.has_pending_tool_calls());
+        assert!(state
+            .pending_tool_calls
+            .iter()
+            .any(|call| call.name == "search_wxo_docs"));
+
+        let agent_state = convert_to_agent_state(&state);
+        assert_eq!(agent_state.tool_calls.len(), state.pending_tool_calls.len());
+    }
+
+    #[tokio::test]
+    async fn test_tool_calls_recorded_in_conversation() {
+        let mut state = WxorcaState::new(AgentType::AdminSetup);
+        run_turn(
+            &mut state,
+            "How do I setup SSO for my team?",
+            RunOptions::default().deterministic(true),
+        )
+        .await
+        .unwrap();
+
+        // user, tool call, tool result, reply
+        let roles: Vec<_> = state.messages.iter().map(|m| m.role).collect();
+        assert_eq!(
+            roles,
+            vec![
+                WxorcaMessageRole::User,
+                WxorcaMessageRole::Assistant,
+                WxorcaMessageRole::Tool,
+                WxorcaMessageRole::Assistant,
+            ]
+        );
+        let call = &state.messages[1];
+        assert!(call.is_tool_call());
+        assert_eq!(call.tool_name.as_deref(), Some("search_wxo_docs"));
+        assert_eq!(state.messages[2].tool_call_id, call.tool_call_id);
+        assert_eq!(
+            state.messages[2].tool_name.as_deref(),
+            Some("search_wxo_docs")
+        );
+        assert!(state.validate().is_ok());
+        assert!(state
+            .to_markdown_transcript()
+            .contains("### Tool call: search_wxo_docs ("));
+
+        // The next turn doesn't replay the earlier results
+        state.add_user_message("What about RBAC?");
+        let agent_state = convert_to_agent_state(&state);
+        assert!(agent_state
+            .messages
+            .iter()
+            .all(|m| m.role != MessageRole::Tool));
+        assert_eq!(
+            agent_state
+                .last_assistant_message()
+                .map(|m| m.content.as_str()),
+            state.messages.get(3).map(|m| m.content.as_str())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tool_iterations_counted_on_state() {
+        let mut state = WxorcaState::new(AgentType::DocsHelper);
+        run_turn(
+            &mut state,
+            "Where is the admin guide for configuring security?",
+            RunOptions::default(),
+        )
+        .await
+        .unwrap();
+
+        assert!(state.iteration > 0);
+    }
+
+    #[tokio::test]
+    async fn test_followup_continues_topic() {
+        let mut state = WxorcaState::new(AgentType::AdminSetup);
+        run_turn(
+            &mut state,
+            "How do I configure security?",
+            RunOptions::default(),
+        )
+        .await
+        .unwrap();
+        let response = run_turn(&mut state, "What about on-prem?", RunOptions::default())
+            .await
+            .unwrap();
+
+        assert!(response.response.contains("OpenShift routes"));
+        // The topic stays the question, however many follow-ups there are
+        assert_eq!(
+            state.context.current_topic.as_deref(),
+            Some("How do I configure security?")
+        );
+        run_turn(&mut state, "And with SSO?", RunOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            state.context.current_topic.as_deref(),
+            Some("How do I configure security?")
+        );
+
+        // A short question of its own starts a new topic
+        run_turn(&mut state, "Configure SSO", RunOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            state.context.current_topic.as_deref(),
+            Some("Configure SSO")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_language_stored_in_metadata() {
+        let mut state = WxorcaState::new(AgentType::AdminSetup);
+        let response = run_turn(
+            &mut state,
+            "¿Cómo configuro la seguridad?",
+            RunOptions::default(),
+        )
+        .await
+        .unwrap();
+
+        assert!(response.response.contains("Configuración de seguridad"));
+        assert_eq!(
+            state.get_metadata("detected_language"),
+            Some(&serde_json::json!("es"))
+        );
+        assert_eq!(state.get_metadata("language"), None);
+
+        // A detected language doesn't stick when the user switches
+        let response = run_turn(
+            &mut state,
+            "How do I configure security?",
+            RunOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert!(!response.response.contains("Configuración de seguridad"));
+        assert_eq!(
+            state.get_metadata("detected_language"),
+            Some(&serde_json::json!("en"))
+        );
+
+        // A language set by the client overrides detection
+        let mut state = WxorcaState::new(AgentType::AdminSetup);
+        state.set_metadata("language", "es-MX");
+        let response = run_turn(
+            &mut state,
+            "How do I configure security?",
+            RunOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert!(response.response.contains("Configuración de seguridad"));
+    }
+
+    #[tokio::test]
+    async fn test_cached_response_reused() {
+        let cache = Arc::new(ResponseCache::new(8));
+        let options = RunOptions::default().cache(cache.clone());
+
+        let first = run_agent_with(
+            AgentType::UsageAssistant,
+            None,
+            "How do I create a skill?",
+            options.clone(),
+        )
+        .await
+        .unwrap();
+        assert!(!first.cached);
+        assert_eq!(cache.len(), 1);
+
+        let second = run_agent_with(
+            AgentType::UsageAssistant,
+            None,
+            "how do i create a skill?",
+            options,
+        )
+        .await
+        .unwrap();
+        assert!(second.cached);
+        assert_eq!(second.response, first.response);
+    }
+
+    #[tokio::test]
+    async fn test_cache_skipped_for_custom_classifier() {
+        let cache = Arc::new(ResponseCache::new(8));
+        let options = RunOptions::default()
+            .cache(cache.clone())
+            .classifier(Arc::new(IntentClassifier::default()));
+
+        for _ in 0..2 {
+            let response = run_agent_with(
+                AgentType::UsageAssistant,
+                None,
+                "How do I create a skill?",
+                options.clone(),
+            )
+            .await
+            .unwrap();
+            assert!(!response.cached);
+        }
+        assert_eq!(cache.len(), 0);
+
+        // A different keyword minimum is a different scope
+        let options = RunOptions::default().cache(cache.clone());
+        run_agent_with(
+            AgentType::UsageAssistant,
+            None,
+            "How do I create a skill?",
+            options.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(cache.len(), 1);
+        let response = run_agent_with(
+            AgentType::UsageAssistant,
+            None,
+            "How do I create a skill?",
+            options.min_keywords(3),
+        )
+        .await
+        .unwrap();
+        assert!(!response.cached);
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_ids_unique_across_turns() {
+        let mut state = WxorcaState::new(AgentType::DocsHelper);
+        for message in [
+            "Where is the admin guide for configuring security?",
+            "Where are the API reference docs?",
+        ] {
+            run_turn(
+                &mut state,
+                message,
+                RunOptions::default().deterministic(true),
+            )
+            .await
+            .unwrap();
+        }
+
+        let ids: Vec<&str> = state
+            .messages
+            .iter()
+            .filter(|m| m.is_tool_call())
+            .filter_map(|m| m.tool_call_id.as_deref())
+            .collect();
+        assert!(ids.len() > 1);
+        for (i, id) in ids.iter().enumerate() {
+            assert!(!ids[..i].contains(id), "duplicate tool call id {}", id);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sources_reported_from_doc_search() {
+        let mut state = WxorcaState::new(AgentType::DocsHelper);
+        let response = run_turn(
+            &mut state,
+            "Where is the admin guide for configuring security?",
+            RunOptions::default(),
+        )
+        .await
+        .unwrap();
+
+        assert!(!response.sources.is_empty());
+        assert!(response.sources.iter().all(|doc| !doc.url.is_empty()));
+        assert_eq!(state.context.relevant_docs.len(), response.sources.len());
+    }
+
+    #[tokio::test]
+    async fn test_uncacheable_tool_responses_not_cached() {
+        let cache = Arc::new(ResponseCache::new(8).cacheable_tools(&[]));
+        run_agent_with(
+            AgentType::DocsHelper,
+            None,
+            "Where is the admin guide for configuring security?",
+            RunOptions::default().cache(cache.clone()),
+        )
+        .await
+        .unwrap();
+
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_convert_to_agent_state_keeps_turn_order() {
+        let mut state = WxorcaState::new(AgentType::DocsHelper);
+        state.add_user_message("Where are the API docs?");
+        state.add_assistant_message("See the API reference.");
+        state.add_user_message("And authentication?");
+
+        let agent_state = convert_to_agent_state(&state);
+        let user_messages: Vec<_> = agent_state
+            .messages
+            .iter()
+            .filter(|m| m.role == MessageRole::User)
+            .map(|m| m.content.as_str())
+            .collect();
+
+        assert_eq!(
+            user_messages,
+            vec!["Where are the API docs?", "And authentication?"]
+        );
+        assert_eq!(
+            agent_state.last_user_message().map(|m| m.content.as_str()),
+            Some("And authentication?")
+        );
+    }
+
+    #[test]
+    fn test_convert_to_agent_state_grounds_system_prompt() {
+        let mut state = WxorcaState::new(AgentType::AdminSetup);
+        state.context.wxo_version = Some("4.8".to_string());
+        state.add_user_message("How do I configure SSO?");
+
+        let agent_state = convert_to_agent_state(&state);
+
+        assert!(agent_state.messages[0].role == MessageRole::System);
+        assert!(agent_state.messages[0]
+            .content
+            .contains("- WatsonX Orchestrate version: 4.8"));
+    }
+
+    #[test]
+    fn test_convert_to_agent_state_keeps_system_messages_first() {
+        let mut state = WxorcaState::new(AgentType::DocsHelper);
+        state.add_user_message("Where are the API docs?");
+        state
+            .messages
+            .push(crate::state::Message::system("Answer in one line."));
+        state.add_assistant_message("See the API reference.");
+
+        let agent_state = convert_to_agent_state(&state);
+
+        // Same order as `normalized_messages`: system messages, then the turns
+        assert_eq!(agent_state.messages.len(), 4);
+        assert!(agent_state.messages[0].role == MessageRole::System);
+        assert!(agent_state.messages[1].role == MessageRole::System);
+        assert_eq!(agent_state.messages[1].content, "Answer in one line.");
+        assert!(agent_state.messages[2].role == MessageRole::User);
+        assert!(agent_state.messages[3].role == MessageRole::Assistant);
+    }
+
+    #[test]
+    fn test_convert_to_agent_state_merges_repeated_messages() {
+        let mut state = WxorcaState::new(AgentType::DocsHelper);
+        state.add_user_message("Where are the API docs?");
+        state.add_user_message("The REST ones.");
+        state.add_assistant_message("");
+
+        let agent_state = convert_to_agent_state(&state);
+
+        // The system prompt, then the merged question; no empty reply
+        assert_eq!(agent_state.messages.len(), 2);
+        assert!(agent_state.messages[0].role == MessageRole::System);
+        assert_eq!(
+            agent_state.last_user_message().map(|m| m.content.as_str()),
+            Some("Where are the API docs?\n\nThe REST ones.")
+        );
+        assert_eq!(state.messages.len(), 3);
+    }
+}
@@ -3,13 +3,18 @@
 //! Defines the state that flows through agent graphs, including
 //! conversation history, user context, and WatsonX Orchestrate-specific data.
 
+use anyhow::Context;
 use chrono::{DateTime, Utc};
-use oxidizedgraph::prelude::State;
+use oxidizedgraph::prelude::{State, ToolCall};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
 use uuid::Uuid;
 
+use crate::tools::truncate_excerpt;
+
 /// The type of agent handling the conversation
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
 #[serde(rename_all = "kebab-case")]
 pub enum AgentType {
     /// Guides administrators through WXO setup and configuration
@@ -37,6 +42,17 @@ impl AgentType {
         }
     }
 
+    /// Get the names accepted for this agent type when parsing
+    pub fn aliases(&self) -> &'static [&'static str] {
+        match self {
+            AgentType::AdminSetup => &["admin-setup", "admin_setup", "adminsetup"],
+            AgentType::UsageAssistant => &["usage", "usage-assistant", "usage_assistant"],
+            AgentType::Troubleshoot => &["troubleshoot", "troubleshooting"],
+            AgentType::BestPractices => &["best-practices", "best_practices", "bestpractices"],
+            AgentType::DocsHelper => &["docs", "docs-helper", "docs_helper", "documentation"],
+        }
+    }
+
     /// Get a description of what this agent does
     pub fn description(&self) -> &'static str {
         match self {
@@ -75,6 +91,23 @@ impl AgentType {
         }
     }
 
+    /// Get the system prompt for this agent type, preferring one from
+    /// `overrides` to the built-in prompt
+    pub fn system_prompt_or<'a>(&self, overrides: &'a PromptOverrides) -> &'a str {
+        overrides.get(*self).unwrap_or_else(|| self.system_prompt())
+    }
+
+    /// Get the name of this agent's prompt file, without the `.txt` extension
+    pub fn prompt_name(&self) -> &'static str {
+        match self {
+            AgentType::AdminSetup => "admin_setup",
+            AgentType::UsageAssistant => "usage_assistant",
+            AgentType::Troubleshoot => "troubleshoot",
+            AgentType::BestPractices => "best_practices",
+            AgentType::DocsHelper => "docs_helper",
+        }
+    }
+
     /// Get all agent types
     pub fn all() -> &'static [AgentType] {
         &[
@@ -85,6 +118,140 @@ impl AgentType {
             AgentType::DocsHelper,
         ]
     }
+
+    /// Get the agent at a 1-based position in [`AgentType::all`], as shown
+    /// in numbered menus
+    pub fn from_index(index: usize) -> Option<AgentType> {
+        index
+            .checked_sub(1)
+            .and_then(|i| AgentType::all().get(i))
+            .copied()
+    }
+
+    /// Get the function name used when exposing this agent as a tool
+    pub fn tool_name(&self) -> &'static str {
+        match self {
+            AgentType::AdminSetup => "wxorca_admin_setup",
+            AgentType::UsageAssistant => "wxorca_usage_assistant",
+            AgentType::Troubleshoot => "wxorca_troubleshoot",
+            AgentType::BestPractices => "wxorca_best_practices",
+            AgentType::DocsHelper => "wxorca_docs_helper",
+        }
+    }
+
+    /// Get an OpenAI-style function-calling spec for this agent
+    ///
+    /// Lets a parent LLM choose which WXOrca agent to invoke.
+    pub fn as_tool_spec(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "function",
+            "function": {
+                "name": self.tool_name(),
+                "description": self.description(),
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "message": {
+                            "type": "string",
+                            "description": "The user's message for the agent"
+                        },
+                        "session_id": {
+                            "type": "string",
+                            "description": "Optional session ID to continue an existing conversation"
+                        }
+                    },
+                    "required": ["message"]
+                }
+            }
+        })
+    }
+}
+
+/// System prompts that replace the built-in ones at runtime
+///
+/// Lets prompts be reworded without rebuilding: agents with an override use
+/// it, the rest keep the prompt embedded in the binary.
+#[derive(Debug, Clone, Default)]
+pub struct PromptOverrides {
+    prompts: HashMap<AgentType, String>,
+}
+
+impl PromptOverrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `prompt` as the system prompt of `agent_type`
+    pub fn with_prompt(mut self, agent_type: AgentType, prompt: impl Into<String>) -> Self {
+        self.prompts.insert(agent_type, prompt.into());
+        self
+    }
+
+    /// Load `<agent>.txt` files from `dir`, named like the built-in prompts
+    /// (see [`AgentType::prompt_name`]), e.g. `troubleshoot.txt`
+    ///
+    /// Files other than `.txt` are ignored. A `.txt` file that names no
+    /// agent, or holds only whitespace, is an error so a typo doesn't
+    /// silently leave the built-in prompt in place.
+    pub fn load_dir(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let dir = dir.as_ref();
+        let entries = std::fs::read_dir(dir)
+            .with_context(|| format!("Failed to read prompts directory {}", dir.display()))?;
+
+        let mut overrides = Self::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("txt") {
+                continue;
+            }
+
+            let name = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or("");
+            let Some(agent_type) = AgentType::all()
+                .iter()
+                .find(|agent| agent.prompt_name() == name)
+            else {
+                anyhow::bail!(
+                    "Unknown prompt file {} (expected one of: {})",
+                    path.display(),
+                    AgentType::all()
+                        .iter()
+                        .map(|agent| format!("{}.txt", agent.prompt_name()))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            };
+
+            let prompt = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            if prompt.trim().is_empty() {
+                anyhow::bail!("Prompt file {} is empty", path.display());
+            }
+            overrides.prompts.insert(*agent_type, prompt);
+        }
+
+        Ok(overrides)
+    }
+
+    /// Get the override for `agent_type`, if there is one
+    pub fn get(&self, agent_type: AgentType) -> Option<&str> {
+        self.prompts.get(&agent_type).map(String::as_str)
+    }
+
+    /// Whether no prompt is overridden
+    pub fn is_empty(&self) -> bool {
+        self.prompts.is_empty()
+    }
+}
+
+/// Get OpenAI-style tool specs for all agent types
+pub fn all_agent_tool_specs() -> Vec<serde_json::Value> {
+    AgentType::all()
+        .iter()
+        .map(AgentType::as_tool_spec)
+        .collect()
 }
 
 impl std::fmt::Display for AgentType {
@@ -97,13 +264,270 @@ impl std::str::FromStr for AgentType {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
-            "admin-setup" | "admin_setup" | "adminsetup" => Ok(AgentType::AdminSetup),
-            "usage" | "usage-assistant" | "usage_assistant" => Ok(AgentType::UsageAssistant),
-            "troubleshoot" | "troubleshooting" => Ok(AgentType::Troubleshoot),
-            "best-practices" | "best_practices" | "bestpractices" => Ok(AgentType::BestPractices),
-            "docs" | "docs-helper" | "docs_helper" | "documentation" => Ok(AgentType::DocsHelper),
-            _ => Err(format!("Unknown agent type: {}", s)),
+        // Numbered menus pass the 1-based position of the agent
+        if let Ok(index) = s.trim().parse::<usize>() {
+            return AgentType::from_index(index).ok_or_else(|| {
+                format!(
+                    "Agent number {} is out of range (1-{})",
+                    index,
+                    AgentType::all().len()
+                )
+            });
+        }
+
+        let lower = s.to_lowercase();
+        AgentType::all()
+            .iter()
+            .find(|agent| agent.aliases().contains(&lower.as_str()))
+            .copied()
+            .ok_or_else(|| format!("Unknown agent type: {}", s))
+    }
+}
+
+/// A WatsonX Orchestrate documentation category
+///
+/// Docs are stored and searched under these names, so agents, tools and the
+/// database all share one spelling of each category.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DocCategory {
+    /// API reference and authentication
+    Api,
+    /// Administration, security and integrations
+    Admin,
+    /// Onboarding and tutorials
+    GettingStarted,
+    /// Using skills, workflows and the catalog
+    #[default]
+    User,
+    /// Errors and known issues
+    Troubleshooting,
+    /// Release notes and roadmap
+    ReleaseNotes,
+}
+
+impl DocCategory {
+    /// Get all documentation categories
+    pub fn all() -> &'static [DocCategory] {
+        &[
+            DocCategory::Api,
+            DocCategory::Admin,
+            DocCategory::GettingStarted,
+            DocCategory::User,
+            DocCategory::Troubleshooting,
+            DocCategory::ReleaseNotes,
+        ]
+    }
+
+    /// Get the name docs are stored under for this category
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DocCategory::Api => "api",
+            DocCategory::Admin => "admin",
+            DocCategory::GettingStarted => "getting_started",
+            DocCategory::User => "user",
+            DocCategory::Troubleshooting => "troubleshooting",
+            DocCategory::ReleaseNotes => "release_notes",
+        }
+    }
+
+    /// Other spellings accepted for this category when parsing
+    pub fn aliases(&self) -> &'static [&'static str] {
+        match self {
+            DocCategory::Api => &[],
+            DocCategory::Admin => &[],
+            DocCategory::GettingStarted => &["getting-started"],
+            DocCategory::User => &[],
+            DocCategory::Troubleshooting => &["troubleshoot"],
+            DocCategory::ReleaseNotes => &["release-notes"],
+        }
+    }
+
+    /// Every name a category can be given by: each stored name, then the
+    /// aliases
+    pub fn accepted_names() -> Vec<&'static str> {
+        let all = Self::all();
+        all.iter()
+            .map(DocCategory::as_str)
+            .chain(
+                all.iter()
+                    .flat_map(|category| category.aliases().iter().copied()),
+            )
+            .collect()
+    }
+}
+
+impl std::fmt::Display for DocCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for DocCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.to_lowercase();
+        DocCategory::all()
+            .iter()
+            .copied()
+            .find(|category| {
+                category.as_str() == name || category.aliases().contains(&name.as_str())
+            })
+            .ok_or_else(|| {
+                format!(
+                    "Unknown documentation category: {} (expected one of: {})",
+                    s,
+                    DocCategory::all()
+                        .iter()
+                        .map(DocCategory::as_str)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+}
+
+/// Language of a conversation, used to pick response templates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+pub enum Language {
+    #[default]
+    #[serde(rename = "en")]
+    English,
+    #[serde(rename = "es")]
+    Spanish,
+}
+
+/// Common words that suggest a query is in English
+const ENGLISH_MARKERS: &[&str] = &[
+    "the", "a", "an", "how", "do", "i", "to", "my", "is", "are", "what", "and", "in", "of", "with",
+    "can", "for", "it", "this", "on", "why",
+];
+
+/// Common words that suggest a query is in Spanish
+const SPANISH_MARKERS: &[&str] = &[
+    "el", "la", "los", "las", "de", "del", "que", "qué", "cómo", "como", "para", "por", "una",
+    "un", "mi", "mis", "es", "está", "y", "en", "con", "puedo", "se", "al",
+];
+
+impl Language {
+    /// Get all supported languages
+    pub fn all() -> &'static [Language] {
+        &[Language::English, Language::Spanish]
+    }
+
+    /// Get the ISO 639-1 code for this language
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Spanish => "es",
+        }
+    }
+
+    /// Guess the language of `text` from common words and accents
+    ///
+    /// Falls back to English when there isn't enough evidence either way.
+    pub fn detect(text: &str) -> Self {
+        let text = text.to_lowercase();
+        let words: Vec<&str> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .collect();
+        let count = |markers: &[&str]| words.iter().filter(|w| markers.contains(w)).count();
+
+        let english = count(ENGLISH_MARKERS);
+        let spanish =
+            count(SPANISH_MARKERS) + 2 * text.chars().filter(|c| "áéíóúñ¿¡".contains(*c)).count();
+
+        if spanish > english {
+            Language::Spanish
+        } else {
+            Language::English
+        }
+    }
+}
+
+impl std::fmt::Display for Language {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl std::str::FromStr for Language {
+    type Err = String;
+
+    /// Parse a language code (`es`, `es-MX`) or English name
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.to_lowercase();
+        let primary = lower.split(['-', '_']).next().unwrap_or_default();
+        match primary {
+            "en" | "english" => Ok(Language::English),
+            "es" | "spanish" | "español" => Ok(Language::Spanish),
+            _ => Err(format!(
+                "Unsupported language: {} (expected one of: {})",
+                s,
+                Language::all()
+                    .iter()
+                    .map(Language::code)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+    }
+}
+
+/// Who is asking, which sets how much depth replies go into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum UserRole {
+    /// Runs the WXO instance; gets API and CLI detail
+    Admin,
+    /// Builds skills and integrations; gets code examples
+    Developer,
+    /// Works in the WXO UI; gets click-paths
+    #[default]
+    EndUser,
+}
+
+impl UserRole {
+    /// Get all user roles
+    pub fn all() -> &'static [UserRole] {
+        &[UserRole::Admin, UserRole::Developer, UserRole::EndUser]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserRole::Admin => "admin",
+            UserRole::Developer => "developer",
+            UserRole::EndUser => "end-user",
+        }
+    }
+}
+
+impl std::fmt::Display for UserRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for UserRole {
+    type Err = String;
+
+    /// Parse a role name, e.g. `admin`, `developer` or `end-user`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().replace('_', "-").as_str() {
+            "admin" | "administrator" => Ok(UserRole::Admin),
+            "developer" | "dev" => Ok(UserRole::Developer),
+            "end-user" | "enduser" | "user" => Ok(UserRole::EndUser),
+            _ => Err(format!(
+                "Unknown user role: {} (expected one of: {})",
+                s,
+                UserRole::all()
+                    .iter()
+                    .map(UserRole::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
         }
     }
 }
@@ -119,7 +543,7 @@ pub struct Message {
     pub content: String,
     /// When the message was created
     pub timestamp: DateTime<Utc>,
-    /// Optional tool call ID (for tool responses)
+    /// Optional tool call ID (for tool calls and tool responses)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_call_id: Option<String>,
     /// Optional tool name (for tool calls)
@@ -164,6 +588,139 @@ impl Message {
         }
     }
 
+    /// Create an assistant message recording a call to tool `name`
+    ///
+    /// The content is the arguments as JSON; the tool's result is the
+    /// [`Message::tool_result`] with the same `tool_call_id`.
+    pub fn tool_call(
+        tool_call_id: impl Into<String>,
+        name: impl Into<String>,
+        arguments: &serde_json::Value,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            role: MessageRole::Assistant,
+            content: arguments.to_string(),
+            timestamp: Utc::now(),
+            tool_call_id: Some(tool_call_id.into()),
+            tool_name: Some(name.into()),
+        }
+    }
+
+    /// Whether this message records a tool call rather than a reply
+    pub fn is_tool_call(&self) -> bool {
+        self.role == MessageRole::Assistant && self.tool_name.is_some()
+    }
+
+    /// Convert to an OpenAI chat completions message
+    ///
+    /// Tool calls become an assistant message with a one-entry `tool_calls`
+    /// list.
+    pub fn to_openai(&self) -> serde_json::Value {
+        if self.is_tool_call() {
+            return serde_json::json!({
+                "role": "assistant",
+                "content": null,
+                "tool_calls": [self.openai_tool_call()],
+            });
+        }
+
+        let mut message = serde_json::json!({
+            "role": self.role.as_str(),
+            "content": self.content,
+        });
+        if self.role.has_tool_fields() {
+            if let Some(ref tool_call_id) = self.tool_call_id {
+                message["tool_call_id"] = serde_json::json!(tool_call_id);
+            }
+            if let Some(ref tool_name) = self.tool_name {
+                message["name"] = serde_json::json!(tool_name);
+            }
+        }
+        message
+    }
+
+    /// The entry for this tool call in an OpenAI `tool_calls` list
+    fn openai_tool_call(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.tool_call_id,
+            "type": "function",
+            "function": {
+                "name": self.tool_name,
+                "arguments": self.content,
+            },
+        })
+    }
+
+    /// Parse an OpenAI chat completions message
+    ///
+    /// An assistant message with `tool_calls` becomes a [`Message::tool_call`]
+    /// for its first call; use [`WxorcaState::set_openai_messages`] to keep
+    /// every call.
+    pub fn from_openai(value: &serde_json::Value) -> Result<Self, String> {
+        Self::all_from_openai(value).map(|mut messages| messages.remove(0))
+    }
+
+    /// Parse an OpenAI chat completions message into one message, or one
+    /// per call for an assistant message with `tool_calls`
+    fn all_from_openai(value: &serde_json::Value) -> Result<Vec<Self>, String> {
+        let role: MessageRole = value
+            .get("role")
+            .and_then(|r| r.as_str())
+            .ok_or_else(|| "Message is missing a role".to_string())?
+            .parse()?;
+        let content = value
+            .get("content")
+            .and_then(|c| c.as_str())
+            .unwrap_or_default();
+        let field = |name: &str| value.get(name).and_then(|v| v.as_str()).map(String::from);
+
+        let tool_calls = value
+            .get("tool_calls")
+            .and_then(|calls| calls.as_array())
+            .filter(|calls| role == MessageRole::Assistant && !calls.is_empty());
+        if let Some(calls) = tool_calls {
+            return calls
+                .iter()
+                .map(|call| {
+                    let id = call.get("id").and_then(|id| id.as_str());
+                    let function = call.get("function");
+                    let name = function
+                        .and_then(|f| f.get("name"))
+                        .and_then(|name| name.as_str());
+                    let (Some(id), Some(name)) = (id, name) else {
+                        return Err("Tool call is missing an id or function name".to_string());
+                    };
+                    let arguments = function
+                        .and_then(|f| f.get("arguments"))
+                        .and_then(|arguments| arguments.as_str())
+                        .unwrap_or("{}");
+
+                    // Keep the arguments verbatim, even if they aren't valid JSON
+                    Ok(Self {
+                        id: Uuid::new_v4(),
+                        role: MessageRole::Assistant,
+                        content: arguments.to_string(),
+                        timestamp: Utc::now(),
+                        tool_call_id: Some(id.to_string()),
+                        tool_name: Some(name.to_string()),
+                    })
+                })
+                .collect();
+        }
+
+        // Other roles use `name` for the participant, not a tool
+        let tool_field = |name: &str| role.has_tool_fields().then(|| field(name)).flatten();
+        Ok(vec![Self {
+            id: Uuid::new_v4(),
+            role,
+            content: content.to_string(),
+            timestamp: Utc::now(),
+            tool_call_id: tool_field("tool_call_id"),
+            tool_name: tool_field("name"),
+        }])
+    }
+
     /// Create a new tool result message
     pub fn tool_result(tool_call_id: impl Into<String>, content: impl Into<String>) -> Self {
         Self {
@@ -191,10 +748,45 @@ pub enum MessageRole {
     Tool,
 }
 
+impl MessageRole {
+    /// Get the OpenAI chat role name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageRole::System => "system",
+            MessageRole::User => "user",
+            MessageRole::Assistant => "assistant",
+            MessageRole::Tool => "tool",
+        }
+    }
+
+    /// Whether OpenAI messages with this role carry `tool_call_id` and a
+    /// tool `name`
+    ///
+    /// Assistant tool calls keep theirs in `tool_calls` instead.
+    fn has_tool_fields(self) -> bool {
+        self == MessageRole::Tool
+    }
+}
+
+impl std::str::FromStr for MessageRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "system" => Ok(MessageRole::System),
+            "user" => Ok(MessageRole::User),
+            "assistant" => Ok(MessageRole::Assistant),
+            "tool" => Ok(MessageRole::Tool),
+            _ => Err(format!("Unknown message role: {}", s)),
+        }
+    }
+}
+
 /// Context about the user's WatsonX Orchestrate environment
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct WxoContext {
-    /// User's role (admin, developer, end-user)
+    /// User's role (admin, developer, end-user), which sets how deep replies
+    /// go; see [`WxoContext::role`]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user_role: Option<String>,
 
@@ -219,6 +811,79 @@ pub struct WxoContext {
     pub metadata: serde_json::Map<String, serde_json::Value>,
 }
 
+impl WxoContext {
+    /// The user's role, if `user_role` names a known [`UserRole`]
+    pub fn role(&self) -> Option<UserRole> {
+        self.user_role.as_deref().and_then(|role| role.parse().ok())
+    }
+
+    /// A system prompt section describing the user's environment, or `None`
+    /// when neither the role, version nor deployment is known
+    pub fn prompt_block(&self) -> Option<String> {
+        let known = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+
+        let lines: Vec<String> = [
+            ("Role", self.role().map(|role| role.to_string())),
+            ("WatsonX Orchestrate version", known(&self.wxo_version)),
+            ("Deployment", known(&self.deployment_type)),
+        ]
+        .into_iter()
+        .filter_map(|(label, value)| Some(format!("- {}: {}", label, value?)))
+        .collect();
+
+        if lines.is_empty() {
+            return None;
+        }
+        Some(format!("## User Context\n\n{}", lines.join("\n")))
+    }
+
+    /// Fold in context detected more recently
+    ///
+    /// Fields set in `other` win; fields it leaves unset (`None`, no docs)
+    /// keep their current value. Metadata keys are merged, with `other`'s
+    /// values replacing existing ones.
+    pub fn merge(&mut self, other: &WxoContext) {
+        fn newer(field: &mut Option<String>, other: &Option<String>) {
+            if other.is_some() {
+                field.clone_from(other);
+            }
+        }
+
+        newer(&mut self.user_role, &other.user_role);
+        newer(&mut self.current_topic, &other.current_topic);
+        newer(&mut self.wxo_version, &other.wxo_version);
+        newer(&mut self.deployment_type, &other.deployment_type);
+        if !other.relevant_docs.is_empty() {
+            self.relevant_docs.clone_from(&other.relevant_docs);
+        }
+        for (key, value) in &other.metadata {
+            self.metadata.insert(key.clone(), value.clone());
+        }
+    }
+
+    /// This context without what agent turns derived from the conversation
+    ///
+    /// The topic, docs found and [`TURN_METADATA_KEYS`] are dropped; what the
+    /// client told us about the user and their environment is kept.
+    fn without_turn_data(&self) -> WxoContext {
+        let mut context = WxoContext {
+            current_topic: None,
+            relevant_docs: Vec::new(),
+            ..self.clone()
+        };
+        for key in TURN_METADATA_KEYS {
+            context.metadata.remove(*key);
+        }
+        context
+    }
+}
+
 /// Reference to a documentation section
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DocReference {
@@ -233,9 +898,58 @@ pub struct DocReference {
     pub excerpt: Option<String>,
 }
 
+/// Metadata keys that agent turns write into the saved context
+const TURN_METADATA_KEYS: &[&str] = &[
+    "detected_language",
+    "should_escalate",
+    "support_tier",
+    "diagnosis_category",
+];
+
+/// Timestamp format used in Markdown transcripts
+const TRANSCRIPT_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S UTC";
+
+/// Maximum characters of the first user message kept in a [`ConversationSummary`]
+pub const SUMMARY_PREVIEW_CHARS: usize = 80;
+
+/// Average characters per token assumed by [`WxorcaState::token_estimate`]
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Tokens assumed for each message's role and framing
+const TOKENS_PER_MESSAGE: usize = 4;
+
+/// Version of the [`WxorcaState`] JSON shape written by this build
+///
+/// Bump it when the shape changes and teach [`migrate_state`] to upgrade
+/// payloads of the previous version.
+/// - 0: payloads saved before versioning; may lack `pending_tool_calls`,
+///   `context`, `iteration`, `is_complete` and message ids or timestamps
+/// - 1: adds `schema_version`
+pub const STATE_SCHEMA_VERSION: u32 = 1;
+
+/// Compact preview of a conversation for session lists
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationSummary {
+    pub session_id: String,
+    pub agent_type: AgentType,
+    pub message_count: usize,
+    /// The conversation's opening question, truncated to
+    /// [`SUMMARY_PREVIEW_CHARS`]
+    #[serde(default)]
+    pub first_user_message: Option<String>,
+    pub last_updated: DateTime<Utc>,
+    #[serde(default)]
+    pub is_complete: bool,
+}
+
 /// Main state type for WXOrca agents
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WxorcaState {
+    /// Version of the JSON shape this state was saved with; see
+    /// [`migrate_state`]
+    #[serde(default)]
+    pub schema_version: u32,
+
     /// Unique session identifier
     pub session_id: String,
 
@@ -255,9 +969,23 @@ pub struct WxorcaState {
     pub is_complete: bool,
 
     /// Pending tool calls to execute
+    ///
+    /// While a graph runs, nodes queue calls on the oxidizedgraph
+    /// `AgentState::tool_calls`. The runner copies these into the graph state
+    /// at the start of a turn and copies whatever is still queued back here
+    /// when the turn ends, so this list is authoritative between turns.
     #[serde(default)]
     pub pending_tool_calls: Vec<PendingToolCall>,
 
+    /// Session this conversation was forked from, if it is a branch
+    #[serde(default)]
+    pub parent_session_id: Option<String>,
+
+    /// How many leading messages are already saved to the database, so the
+    /// next save only appends the rest; see `Database::save_turn`
+    #[serde(skip)]
+    pub persisted_messages: usize,
+
     /// When this state was created
     pub created_at: DateTime<Utc>,
 
@@ -276,6 +1004,26 @@ pub struct PendingToolCall {
     pub arguments: serde_json::Value,
 }
 
+impl From<&ToolCall> for PendingToolCall {
+    fn from(call: &ToolCall) -> Self {
+        Self {
+            id: call.id.clone(),
+            name: call.name.clone(),
+            arguments: call.arguments.clone(),
+        }
+    }
+}
+
+impl From<&PendingToolCall> for ToolCall {
+    fn from(call: &PendingToolCall) -> Self {
+        ToolCall {
+            id: call.id.clone(),
+            name: call.name.clone(),
+            arguments: call.arguments.clone(),
+        }
+    }
+}
+
 impl Default for WxorcaState {
     fn default() -> Self {
         Self::new(AgentType::default())
@@ -287,6 +1035,7 @@ impl WxorcaState {
     pub fn new(agent_type: AgentType) -> Self {
         let now = Utc::now();
         Self {
+            schema_version: STATE_SCHEMA_VERSION,
             session_id: Uuid::new_v4().to_string(),
             agent_type,
             messages: Vec::new(),
@@ -294,6 +1043,8 @@ impl WxorcaState {
             iteration: 0,
             is_complete: false,
             pending_tool_calls: Vec::new(),
+            parent_session_id: None,
+            persisted_messages: 0,
             created_at: now,
             updated_at: now,
         }
@@ -306,6 +1057,30 @@ impl WxorcaState {
         state
     }
 
+    /// Branch the conversation after the message at `message_index`
+    ///
+    /// The fork gets a fresh session id, records this session as its parent
+    /// and keeps the messages up to and including `message_index` (all of
+    /// them if the index is past the end), so it can continue differently
+    /// without touching this conversation. Pending tool calls and what the
+    /// turns learned (topic, docs found, escalation, detected language)
+    /// belong to the end of the conversation, so they are only kept when
+    /// every message is; the user's role, version and deployment are.
+    pub fn fork_at(&self, message_index: usize) -> WxorcaState {
+        let end = message_index.saturating_add(1).min(self.messages.len());
+
+        let mut fork = Self::new(self.agent_type);
+        fork.messages = self.messages[..end].to_vec();
+        if end == self.messages.len() {
+            fork.context = self.context.clone();
+            fork.pending_tool_calls = self.pending_tool_calls.clone();
+        } else {
+            fork.context = self.context.without_turn_data();
+        }
+        fork.parent_session_id = Some(self.session_id.clone());
+        fork
+    }
+
     /// Add a user message to the conversation
     pub fn add_user_message(&mut self, content: impl Into<String>) {
         self.messages.push(Message::user(content));
@@ -332,12 +1107,12 @@ impl WxorcaState {
             .find(|m| m.role == MessageRole::User)
     }
 
-    /// Get the last assistant message
+    /// Get the last assistant reply, skipping tool calls
     pub fn last_assistant_message(&self) -> Option<&Message> {
         self.messages
             .iter()
             .rev()
-            .find(|m| m.role == MessageRole::Assistant)
+            .find(|m| m.role == MessageRole::Assistant && !m.is_tool_call())
     }
 
     /// Check if there are pending tool calls
@@ -388,6 +1163,422 @@ impl WxorcaState {
     pub fn get_metadata(&self, key: &str) -> Option<&serde_json::Value> {
         self.context.metadata.get(key)
     }
+
+    /// The conversation cleaned up for a model's context window
+    ///
+    /// Empty messages are dropped, system messages move to the front, and
+    /// runs of user, assistant or system messages from the same role are
+    /// merged into one, separated by a blank line. Messages that carry a tool
+    /// call id are kept as they are so each result stays attached to its
+    /// call; tool results without one are dropped. `messages` itself is left
+    /// untouched for storage.
+    pub fn normalized_messages(&self) -> Vec<Message> {
+        let (system, rest): (Vec<&Message>, Vec<&Message>) = self
+            .messages
+            .iter()
+            .partition(|m| m.role == MessageRole::System);
+
+        let mut normalized: Vec<Message> = Vec::new();
+        for message in system.into_iter().chain(rest) {
+            let linked = message.tool_call_id.is_some();
+            if !linked && (message.role == MessageRole::Tool || message.content.trim().is_empty()) {
+                continue;
+            }
+
+            match normalized.last_mut() {
+                Some(last)
+                    if !linked && last.tool_call_id.is_none() && last.role == message.role =>
+                {
+                    last.content = format!(
+                        "{}\n\n{}",
+                        last.content.trim_end(),
+                        message.content.trim_start()
+                    );
+                }
+                _ => normalized.push(message.clone()),
+            }
+        }
+        normalized
+    }
+
+    /// Serialize the conversation as OpenAI chat completions messages
+    ///
+    /// Consecutive tool calls are combined into one assistant message, so
+    /// their results follow the message that requested them.
+    pub fn to_openai_messages(&self) -> Vec<serde_json::Value> {
+        let mut openai: Vec<serde_json::Value> = Vec::new();
+        let mut previous_was_call = false;
+        for message in &self.messages {
+            let is_call = message.is_tool_call();
+            match openai.last_mut() {
+                Some(last) if is_call && previous_was_call => {
+                    if let Some(calls) = last["tool_calls"].as_array_mut() {
+                        calls.push(message.openai_tool_call());
+                    }
+                }
+                _ => openai.push(message.to_openai()),
+            }
+            previous_was_call = is_call;
+        }
+        openai
+    }
+
+    /// Replace the conversation with OpenAI chat completions messages
+    pub fn set_openai_messages(&mut self, messages: &[serde_json::Value]) -> Result<(), String> {
+        self.messages = messages
+            .iter()
+            .map(Message::all_from_openai)
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .flatten()
+            .collect();
+        self.persisted_messages = 0;
+        self.updated_at = Utc::now();
+        Ok(())
+    }
+
+    /// Check the state's invariants, returning every violation found
+    ///
+    /// Restored or hand-built states can be inconsistent, e.g. after a
+    /// partial write. A tool result is considered to reference a known call
+    /// if a pending call or a non-tool message carries its id.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut violations = Vec::new();
+
+        if self.session_id.trim().is_empty() {
+            violations.push("session_id is empty".to_string());
+        }
+
+        for (i, pair) in self.messages.windows(2).enumerate() {
+            if pair[1].timestamp < pair[0].timestamp {
+                violations.push(format!(
+                    "message {} is timestamped before message {}",
+                    i + 1,
+                    i
+                ));
+            }
+        }
+
+        let known_calls: Vec<&str> = self
+            .pending_tool_calls
+            .iter()
+            .map(|call| call.id.as_str())
+            .chain(
+                self.messages
+                    .iter()
+                    .filter(|m| m.role != MessageRole::Tool)
+                    .filter_map(|m| m.tool_call_id.as_deref()),
+            )
+            .collect();
+        for (i, message) in self.messages.iter().enumerate() {
+            if message.role != MessageRole::Tool {
+                continue;
+            }
+            match message.tool_call_id.as_deref() {
+                None => violations.push(format!("tool result {} has no tool_call_id", i)),
+                Some(id) if !known_calls.contains(&id) => {
+                    violations.push(format!("tool result {} references unknown call {}", i, id))
+                }
+                Some(_) => {}
+            }
+        }
+
+        if self.is_complete && self.last_assistant_message().is_none() {
+            violations.push("marked complete without an assistant message".to_string());
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// Summarize the conversation for a session list
+    pub fn summary(&self) -> ConversationSummary {
+        ConversationSummary {
+            session_id: self.session_id.clone(),
+            agent_type: self.agent_type,
+            message_count: self.messages.len(),
+            first_user_message: self
+                .messages
+                .iter()
+                .find(|m| m.role == MessageRole::User)
+                .map(|m| truncate_excerpt(&m.content, SUMMARY_PREVIEW_CHARS)),
+            last_updated: self.updated_at,
+            is_complete: self.is_complete,
+        }
+    }
+
+    /// Render the conversation as a readable Markdown transcript
+    ///
+    /// Each message gets a heading with its role and timestamp. Tool calls
+    /// show their arguments as JSON, and tool results are shown verbatim in
+    /// a fenced code block labeled with the tool name.
+    /// A conversation with no messages renders as just the header.
+    pub fn to_markdown_transcript(&self) -> String {
+        let mut out = format!(
+            "# {} Conversation\n\n- **Session:** `{}`\n- **Started:** {}\n",
+            self.agent_type.display_name(),
+            self.session_id,
+            self.created_at.format(TRANSCRIPT_TIME_FORMAT),
+        );
+
+        // Use a fence longer than any backtick run in the content
+        let fenced = |label: &str, content: &str| {
+            let longest_run = content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+            let fence = "`".repeat(longest_run.max(2) + 1);
+            format!("{}{}\n{}\n{}\n", fence, label, content.trim_end(), fence)
+        };
+
+        for message in &self.messages {
+            let time = message.timestamp.format(TRANSCRIPT_TIME_FORMAT);
+            match message.role {
+                MessageRole::Tool => {
+                    let tool = message
+                        .tool_name
+                        .as_deref()
+                        .or(message.tool_call_id.as_deref())
+                        .unwrap_or("tool");
+                    out.push_str(&format!(
+                        "\n### Tool: {} ({})\n\n{}",
+                        tool,
+                        time,
+                        fenced(tool, &message.content)
+                    ));
+                }
+                MessageRole::Assistant if message.is_tool_call() => {
+                    out.push_str(&format!(
+                        "\n### Tool call: {} ({})\n\n{}",
+                        message.tool_name.as_deref().unwrap_or("tool"),
+                        time,
+                        fenced("json", &message.content)
+                    ));
+                }
+                role => {
+                    let heading = match role {
+                        MessageRole::System => "System",
+                        MessageRole::User => "User",
+                        _ => "Assistant",
+                    };
+                    out.push_str(&format!(
+                        "\n### {} ({})\n\n{}\n",
+                        heading,
+                        time,
+                        message.content.trim_end()
+                    ));
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Drop the oldest messages until the conversation fits in `max_chars`
+    ///
+    /// The first system message and everything from the last user message
+    /// onward are always kept. Messages sharing a `tool_call_id` are dropped
+    /// together so a tool result never outlives its call. Returns the number
+    /// of messages dropped.
+    pub fn truncate_to_budget(&mut self, max_chars: usize) -> usize {
+        let mut total: usize = self.messages.iter().map(|m| m.content.len()).sum();
+        if total <= max_chars {
+            return 0;
+        }
+
+        let first_system = self
+            .messages
+            .iter()
+            .position(|m| m.role == MessageRole::System);
+        let tail_start = self
+            .messages
+            .iter()
+            .rposition(|m| m.role == MessageRole::User)
+            .unwrap_or(self.messages.len());
+        let is_protected = |i: usize| Some(i) == first_system || i >= tail_start;
+
+        let mut dropped = vec![false; self.messages.len()];
+
+        for i in 0..tail_start {
+            if total <= max_chars {
+                break;
+            }
+            if dropped[i] || is_protected(i) {
+                continue;
+            }
+
+            // Group this message with every other message of the same tool call
+            let group: Vec<usize> = match &self.messages[i].tool_call_id {
+                Some(call_id) => self
+                    .messages
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, m)| m.tool_call_id.as_deref() == Some(call_id.as_str()))
+                    .map(|(j, _)| j)
+                    .collect(),
+                None => vec![i],
+            };
+
+            if group.iter().any(|&j| is_protected(j)) {
+                continue;
+            }
+
+            for j in group {
+                dropped[j] = true;
+                total -= self.messages[j].content.len();
+            }
+        }
+
+        let count = dropped.iter().filter(|d| **d).count();
+        if count > 0 {
+            let mut flags = dropped.into_iter();
+            self.messages.retain(|_| !flags.next().unwrap_or(false));
+            // The saved messages no longer match, so the next save rewrites them
+            self.persisted_messages = 0;
+            self.updated_at = Utc::now();
+        }
+
+        count
+    }
+
+    /// The system prompt sent for this conversation: `base` followed by the
+    /// context's [`prompt_block`](WxoContext::prompt_block), or `base`
+    /// unchanged when nothing about the user's environment is known
+    pub fn assemble_system_prompt(&self, base: &str) -> String {
+        with_prompt_block(base, self.context.prompt_block().as_deref())
+    }
+
+    /// Roughly estimate the tokens the conversation takes up in a model's
+    /// context window
+    ///
+    /// Counts the agent's system prompt (which the runner always sends) and
+    /// every message at about four characters per token, plus a fixed
+    /// overhead per message. This is an estimate, not a tokenizer count.
+    pub fn token_estimate(&self) -> usize {
+        let estimate =
+            |text: &str| text.chars().count().div_ceil(CHARS_PER_TOKEN) + TOKENS_PER_MESSAGE;
+
+        estimate(&self.assemble_system_prompt(self.agent_type.system_prompt()))
+            + self
+                .messages
+                .iter()
+                .map(|m| estimate(&m.content))
+                .sum::<usize>()
+    }
+
+    /// Compute a stable fingerprint of the conversation content
+    ///
+    /// Hashes the agent type and the ordered user/assistant messages, ignoring
+    /// ids, timestamps, and the session id, so identical conversations share a
+    /// fingerprint. Uses FNV-1a so the value is stable across builds.
+    pub fn fingerprint(&self) -> String {
+        const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+        fn feed(hash: &mut u64, bytes: &[u8]) {
+            for byte in bytes {
+                *hash ^= u64::from(*byte);
+                *hash = hash.wrapping_mul(FNV_PRIME);
+            }
+        }
+
+        let mut hash = FNV_OFFSET;
+        feed(&mut hash, self.agent_type.tool_name().as_bytes());
+
+        for msg in &self.messages {
+            let role: &[u8] = match msg.role {
+                MessageRole::User => b"user",
+                MessageRole::Assistant => b"assistant",
+                _ => continue,
+            };
+            // Separators keep ("ab", "c") distinct from ("a", "bc")
+            feed(&mut hash, &[0]);
+            feed(&mut hash, role);
+            feed(&mut hash, &[0]);
+            feed(&mut hash, msg.content.as_bytes());
+        }
+
+        format!("{:016x}", hash)
+    }
+}
+
+/// `base` followed by a blank line and the user context `block`, or `base`
+/// unchanged without one
+pub(crate) fn with_prompt_block(base: &str, block: Option<&str>) -> String {
+    match block {
+        Some(block) => format!("{}\n\n{}", base.trim_end(), block),
+        None => base.to_string(),
+    }
+}
+
+/// Deserialize a [`WxorcaState`] saved by any earlier version of the crate
+///
+/// Payloads are upgraded one version at a time up to
+/// [`STATE_SCHEMA_VERSION`]. Payloads from a newer build are rejected rather
+/// than silently losing fields.
+pub fn migrate_state(mut value: serde_json::Value) -> anyhow::Result<WxorcaState> {
+    let Some(object) = value.as_object_mut() else {
+        anyhow::bail!("Saved state is not a JSON object");
+    };
+
+    let version = object
+        .get("schema_version")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+    if version > u64::from(STATE_SCHEMA_VERSION) {
+        anyhow::bail!(
+            "Saved state has schema version {}, newer than the supported {}",
+            version,
+            STATE_SCHEMA_VERSION
+        );
+    }
+
+    // Conversations were saved with the agent type JSON-encoded a second
+    // time (`"\"admin-setup\""`)
+    if let Some(serde_json::Value::String(agent_type)) = object.get("agent_type") {
+        if let Ok(decoded) = serde_json::from_str::<String>(agent_type) {
+            object.insert("agent_type".to_string(), decoded.into());
+        }
+    }
+
+    if version < 1 {
+        let now = serde_json::json!(Utc::now());
+        for (field, default) in [
+            ("messages", serde_json::json!([])),
+            ("pending_tool_calls", serde_json::json!([])),
+            ("context", serde_json::json!({})),
+            ("iteration", serde_json::json!(0)),
+            ("is_complete", serde_json::json!(false)),
+            ("created_at", now.clone()),
+        ] {
+            if object.get(field).filter(|v| !v.is_null()).is_none() {
+                object.insert(field.to_string(), default);
+            }
+        }
+        if object.get("updated_at").filter(|v| !v.is_null()).is_none() {
+            let created_at = object["created_at"].clone();
+            object.insert("updated_at".to_string(), created_at);
+        }
+
+        // Messages without their own timestamp are dated to the last update
+        let updated_at = object["updated_at"].clone();
+        if let Some(messages) = object.get_mut("messages").and_then(|m| m.as_array_mut()) {
+            for message in messages.iter_mut().filter_map(|m| m.as_object_mut()) {
+                message
+                    .entry("id")
+                    .or_insert_with(|| serde_json::json!(Uuid::new_v4()));
+                message
+                    .entry("timestamp")
+                    .or_insert_with(|| updated_at.clone());
+            }
+        }
+    }
+
+    object.insert(
+        "schema_version".to_string(),
+        serde_json::json!(STATE_SCHEMA_VERSION),
+    );
+    Ok(serde_json::from_value(value)?)
 }
 
 // Implement the State trait from oxidizedgraph
@@ -415,6 +1606,150 @@ impl State for WxorcaState {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_openai_messages_round_trip() {
+        let mut state = WxorcaState::new(AgentType::DocsHelper);
+        state.messages.push(Message::system("You are helpful"));
+        state.add_user_message("Find the API docs");
+        state.add_assistant_message("Searching...");
+        state.add_tool_result("call_1", "[]");
+
+        let openai = state.to_openai_messages();
+        let roles: Vec<_> = openai.iter().map(|m| m["role"].as_str().unwrap()).collect();
+        assert_eq!(roles, vec!["system", "user", "assistant", "tool"]);
+        assert_eq!(openai[3]["tool_call_id"], "call_1");
+        assert!(openai[1].get("tool_call_id").is_none());
+
+        let mut restored = WxorcaState::new(AgentType::DocsHelper);
+        restored.set_openai_messages(&openai).unwrap();
+        assert_eq!(restored.messages.len(), state.messages.len());
+        for (original, restored) in state.messages.iter().zip(&restored.messages) {
+            assert_eq!(original.role, restored.role);
+            assert_eq!(original.content, restored.content);
+            assert_eq!(original.tool_call_id, restored.tool_call_id);
+        }
+
+        assert!(Message::from_openai(&serde_json::json!({ "role": "robot" })).is_err());
+    }
+
+    #[test]
+    fn test_openai_name_is_a_tool_name_only_for_tool_messages() {
+        let user = serde_json::json!({ "role": "user", "content": "Hi", "name": "dana" });
+        let assistant =
+            serde_json::json!({ "role": "assistant", "content": "Hello", "name": "wxorca" });
+        let tool = serde_json::json!({
+            "role": "tool",
+            "content": "[]",
+            "tool_call_id": "call_1",
+            "name": "search_wxo_docs"
+        });
+
+        let user = Message::from_openai(&user).unwrap();
+        assert_eq!(user.tool_name, None);
+        let assistant = Message::from_openai(&assistant).unwrap();
+        assert_eq!(assistant.tool_name, None);
+        assert!(!assistant.is_tool_call());
+        let tool = Message::from_openai(&tool).unwrap();
+        assert_eq!(tool.tool_name.as_deref(), Some("search_wxo_docs"));
+        assert_eq!(tool.tool_call_id.as_deref(), Some("call_1"));
+        assert_eq!(tool.to_openai()["name"], "search_wxo_docs");
+    }
+
+    #[test]
+    fn test_tool_call_messages_in_openai_format() {
+        let mut state = WxorcaState::new(AgentType::UsageAssistant);
+        state.add_user_message("Show me a skill example");
+        let arguments = serde_json::json!({"query": "skill"});
+        state
+            .messages
+            .push(Message::tool_call("call_1", "search_wxo_docs", &arguments));
+        state.messages.push(Message::tool_call(
+            "call_2",
+            "fetch_wxo_examples",
+            &arguments,
+        ));
+        state.add_tool_result("call_1", "[]");
+        state.add_tool_result("call_2", "[]");
+        state.add_assistant_message("Here is one.");
+        assert_eq!(
+            state.last_assistant_message().unwrap().content,
+            "Here is one."
+        );
+
+        // Both calls go in one assistant message ahead of their results
+        let openai = state.to_openai_messages();
+        let roles: Vec<_> = openai.iter().map(|m| m["role"].as_str().unwrap()).collect();
+        assert_eq!(
+            roles,
+            vec!["user", "assistant", "tool", "tool", "assistant"]
+        );
+        assert!(openai[1]["content"].is_null());
+        let calls = openai[1]["tool_calls"].as_array().unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0]["id"], "call_1");
+        assert_eq!(calls[0]["function"]["name"], "search_wxo_docs");
+        assert_eq!(calls[1]["function"]["arguments"], r#"{"query":"skill"}"#);
+
+        let mut restored = WxorcaState::new(AgentType::UsageAssistant);
+        restored.set_openai_messages(&openai).unwrap();
+        assert_eq!(restored.messages.len(), state.messages.len());
+        for (original, restored) in state.messages.iter().zip(&restored.messages) {
+            assert_eq!(original.role, restored.role);
+            assert_eq!(original.content, restored.content);
+            assert_eq!(original.tool_call_id, restored.tool_call_id);
+            assert_eq!(original.is_tool_call(), restored.is_tool_call());
+        }
+
+        let first = Message::from_openai(&openai[1]).unwrap();
+        assert_eq!(first.tool_name.as_deref(), Some("search_wxo_docs"));
+        let nameless = serde_json::json!({
+            "role": "assistant",
+            "tool_calls": [{"id": "call_1", "function": {}}]
+        });
+        assert!(Message::from_openai(&nameless).is_err());
+    }
+
+    #[test]
+    fn test_wxo_context_merge() {
+        let mut context = WxoContext {
+            user_role: Some("admin".to_string()),
+            current_topic: Some("sso".to_string()),
+            ..WxoContext::default()
+        };
+        context.metadata.insert("language".into(), "en".into());
+        context.metadata.insert("team".into(), "platform".into());
+
+        let mut newer = WxoContext {
+            current_topic: Some("audit logging".to_string()),
+            deployment_type: Some("saas".to_string()),
+            ..WxoContext::default()
+        };
+        newer.metadata.insert("language".into(), "es".into());
+        context.merge(&newer);
+
+        assert_eq!(context.user_role.as_deref(), Some("admin"));
+        assert_eq!(context.current_topic.as_deref(), Some("audit logging"));
+        assert_eq!(context.deployment_type.as_deref(), Some("saas"));
+        assert_eq!(context.metadata["language"], "es");
+        assert_eq!(context.metadata["team"], "platform");
+    }
+
+    #[test]
+    fn test_assemble_system_prompt() {
+        let mut state = WxorcaState::new(AgentType::AdminSetup);
+        let base = AgentType::AdminSetup.system_prompt();
+        assert_eq!(state.assemble_system_prompt(base), base);
+
+        state.context.user_role = Some("administrator".to_string());
+        state.context.wxo_version = Some("4.8".to_string());
+        state.context.deployment_type = Some("  ".to_string());
+        let prompt = state.assemble_system_prompt(base);
+
+        assert!(prompt.starts_with(base.trim_end()));
+        assert!(prompt
+            .ends_with("## User Context\n\n- Role: admin\n- WatsonX Orchestrate version: 4.8"));
+    }
+
     #[test]
     fn test_agent_type_from_str() {
         assert_eq!(
@@ -426,6 +1761,166 @@ mod tests {
             AgentType::Troubleshoot
         );
         assert_eq!("docs".parse::<AgentType>().unwrap(), AgentType::DocsHelper);
+        for agent in AgentType::all() {
+            for alias in agent.aliases() {
+                assert_eq!(alias.parse::<AgentType>().as_ref(), Ok(agent));
+            }
+        }
+    }
+
+    #[test]
+    fn test_agent_type_from_menu_number() {
+        assert_eq!("1".parse::<AgentType>(), Ok(AgentType::AdminSetup));
+        assert_eq!("5".parse::<AgentType>(), Ok(AgentType::DocsHelper));
+        assert_eq!(
+            "0".parse::<AgentType>(),
+            Err("Agent number 0 is out of range (1-5)".to_string())
+        );
+        assert_eq!(
+            "6".parse::<AgentType>(),
+            Err("Agent number 6 is out of range (1-5)".to_string())
+        );
+        assert_eq!(AgentType::from_index(3), Some(AgentType::Troubleshoot));
+    }
+
+    #[test]
+    fn test_doc_category_round_trip() {
+        for category in DocCategory::all() {
+            assert_eq!(category.as_str().parse::<DocCategory>(), Ok(*category));
+            assert_eq!(
+                serde_json::json!(category),
+                serde_json::json!(category.as_str())
+            );
+        }
+        for category in DocCategory::all() {
+            for alias in category.aliases() {
+                assert_eq!(alias.parse::<DocCategory>(), Ok(*category));
+            }
+        }
+        assert_eq!(
+            "troubleshoot".parse::<DocCategory>(),
+            Ok(DocCategory::Troubleshooting)
+        );
+        assert!("skills".parse::<DocCategory>().is_err());
+    }
+
+    #[test]
+    fn test_language() {
+        assert_eq!(
+            Language::detect("How do I configure SSO for my team?"),
+            Language::English
+        );
+        assert_eq!(
+            Language::detect("¿Cómo configuro el inicio de sesión único?"),
+            Language::Spanish
+        );
+        assert_eq!(Language::detect("SSO"), Language::English);
+
+        assert_eq!("es-MX".parse::<Language>(), Ok(Language::Spanish));
+        assert_eq!("English".parse::<Language>(), Ok(Language::English));
+        assert!("fr".parse::<Language>().is_err());
+        assert_eq!(
+            serde_json::json!(Language::Spanish),
+            serde_json::json!("es")
+        );
+    }
+
+    #[test]
+    fn test_user_role() {
+        assert_eq!("Admin".parse::<UserRole>(), Ok(UserRole::Admin));
+        assert_eq!("end_user".parse::<UserRole>(), Ok(UserRole::EndUser));
+        assert!("manager".parse::<UserRole>().is_err());
+
+        let context = WxoContext {
+            user_role: Some("developer".to_string()),
+            ..WxoContext::default()
+        };
+        assert_eq!(context.role(), Some(UserRole::Developer));
+        assert_eq!(WxoContext::default().role(), None);
+        assert_eq!(
+            serde_json::json!(UserRole::EndUser),
+            serde_json::json!("end-user")
+        );
+    }
+
+    #[test]
+    fn test_agent_tool_specs() {
+        let specs = all_agent_tool_specs();
+        assert_eq!(specs.len(), AgentType::all().len());
+
+        for spec in &specs {
+            let function = &spec["function"];
+            assert!(function["name"].as_str().is_some_and(|n| !n.is_empty()));
+            assert!(function["description"].as_str().is_some_and(|d| !d.is_empty()));
+            assert!(function["parameters"]["properties"]["message"].is_object());
+            assert_eq!(function["parameters"]["required"][0], "message");
+        }
+    }
+
+    #[test]
+    fn test_prompt_overrides() {
+        let dir = std::env::temp_dir().join(format!("wxorca-prompts-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("troubleshoot.txt"), "Be brief.").unwrap();
+        std::fs::write(dir.join("README.md"), "not a prompt").unwrap();
+
+        let overrides = PromptOverrides::load_dir(&dir).unwrap();
+        assert_eq!(
+            AgentType::Troubleshoot.system_prompt_or(&overrides),
+            "Be brief."
+        );
+        assert_eq!(
+            AgentType::DocsHelper.system_prompt_or(&overrides),
+            AgentType::DocsHelper.system_prompt()
+        );
+
+        std::fs::write(dir.join("troubleshooting.txt"), "Typo").unwrap();
+        let error = PromptOverrides::load_dir(&dir).unwrap_err().to_string();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(error.contains("troubleshooting.txt"));
+    }
+
+    #[test]
+    fn test_normalized_messages() {
+        let mut state = WxorcaState::new(AgentType::UsageAssistant);
+        state.add_user_message("How do I create a skill?");
+        state.add_user_message("In the UI, please.");
+        state.add_assistant_message("");
+        state
+            .messages
+            .push(Message::system("Answer for end users."));
+
+        let mut call = Message::assistant("");
+        call.tool_call_id = Some("call_1".to_string());
+        state.messages.push(call);
+        state.add_tool_result("call_1", "found 2 docs");
+        state.messages.push(Message::tool_result("", "orphan"));
+        state.messages.last_mut().unwrap().tool_call_id = None;
+        state.add_assistant_message("Open the Skills page.");
+        state.add_assistant_message("Then click Add skill.");
+
+        let normalized = state.normalized_messages();
+        let shape: Vec<(MessageRole, &str)> = normalized
+            .iter()
+            .map(|m| (m.role, m.content.as_str()))
+            .collect();
+        assert_eq!(
+            shape,
+            vec![
+                (MessageRole::System, "Answer for end users."),
+                (
+                    MessageRole::User,
+                    "How do I create a skill?\n\nIn the UI, please."
+                ),
+                (MessageRole::Assistant, ""),
+                (MessageRole::Tool, "found 2 docs"),
+                (
+                    MessageRole::Assistant,
+                    "Open the Skills page.\n\nThen click Add skill."
+                ),
+            ]
+        );
+        assert_eq!(state.messages.len(), 9);
     }
 
     #[test]
@@ -450,4 +1945,231 @@ mod tests {
         state.clear_tool_calls();
         assert!(!state.has_pending_tool_calls());
     }
+
+    #[test]
+    fn test_truncate_to_budget() {
+        let mut state = WxorcaState::new(AgentType::UsageAssistant);
+        state.messages.push(Message::system("system prompt"));
+        state.add_user_message("first question ".repeat(10));
+
+        let mut call = Message::assistant("calling search_docs");
+        call.tool_call_id = Some("call_1".to_string());
+        call.tool_name = Some("search_docs".to_string());
+        state.messages.push(call);
+        state.add_tool_result("call_1", "tool output ".repeat(10));
+
+        state.add_assistant_message("first answer ".repeat(10));
+        state.add_user_message("second question");
+        state.add_assistant_message("second answer");
+
+        assert_eq!(state.truncate_to_budget(usize::MAX), 0);
+
+        let dropped = state.truncate_to_budget(100);
+        assert_eq!(dropped, 4);
+        assert_eq!(state.messages.len(), 3);
+        assert_eq!(state.messages[0].role, MessageRole::System);
+        assert_eq!(state.last_user_message().unwrap().content, "second question");
+        assert_eq!(state.last_assistant_message().unwrap().content, "second answer");
+        assert!(state.messages.iter().all(|m| m.tool_call_id.is_none()));
+    }
+
+    #[test]
+    fn test_truncate_keeps_last_exchange() {
+        let mut state = WxorcaState::new(AgentType::UsageAssistant);
+        state.add_user_message("only question ".repeat(20));
+        state.add_assistant_message("only answer ".repeat(20));
+
+        assert_eq!(state.truncate_to_budget(10), 0);
+        assert_eq!(state.messages.len(), 2);
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut state = WxorcaState::with_session_id(AgentType::AdminSetup, "s1");
+        state.messages.push(Message::system("You are helpful"));
+        state.add_user_message("x".repeat(200));
+        state.add_assistant_message("Done");
+        state.mark_complete();
+
+        let summary = state.summary();
+        assert_eq!(summary.session_id, "s1");
+        assert_eq!(summary.agent_type, AgentType::AdminSetup);
+        assert_eq!(summary.message_count, 3);
+        assert_eq!(
+            summary.first_user_message.unwrap().chars().count(),
+            SUMMARY_PREVIEW_CHARS + 3
+        );
+        assert_eq!(summary.last_updated, state.updated_at);
+        assert!(summary.is_complete);
+
+        assert!(WxorcaState::new(AgentType::DocsHelper)
+            .summary()
+            .first_user_message
+            .is_none());
+    }
+
+    #[test]
+    fn test_markdown_transcript() {
+        let mut state = WxorcaState::with_session_id(AgentType::Troubleshoot, "support-1");
+        assert!(state.to_markdown_transcript().ends_with("UTC\n"));
+        assert!(!state.to_markdown_transcript().contains("###"));
+
+        state.add_user_message("My skill fails");
+        let mut result = Message::tool_result("call_1", "[{\"title\": \"Skill errors\"}]");
+        result.tool_name = Some("search_wxo_docs".to_string());
+        state.messages.push(result);
+        state.add_assistant_message("Check the **credentials**.");
+        state.messages.insert(
+            1,
+            Message::tool_call(
+                "call_1",
+                "search_wxo_docs",
+                &serde_json::json!({"q": "`x`"}),
+            ),
+        );
+
+        let transcript = state.to_markdown_transcript();
+        assert!(transcript.starts_with("# Troubleshooting Bot Conversation\n"));
+        assert!(transcript.contains("- **Session:** `support-1`"));
+        assert!(transcript.contains("### User ("));
+        assert!(transcript.contains("\n\nMy skill fails\n"));
+        assert!(transcript.contains("### Tool: search_wxo_docs ("));
+        let fenced = "```search_wxo_docs\n[{\"title\": \"Skill errors\"}]\n```\n";
+        assert!(transcript.contains(fenced));
+        assert!(transcript.contains("### Tool call: search_wxo_docs ("));
+        assert!(transcript.contains("```json\n{\"q\":\"`x`\"}\n```\n"));
+        assert!(transcript.contains("### Assistant ("));
+    }
+
+    #[test]
+    fn test_token_estimate() {
+        let mut state = WxorcaState::new(AgentType::DocsHelper);
+        let base = state.token_estimate();
+        assert!(base > TOKENS_PER_MESSAGE);
+
+        state.add_user_message("a".repeat(40));
+        assert_eq!(state.token_estimate(), base + 10 + TOKENS_PER_MESSAGE);
+
+        state.add_assistant_message("abc");
+        assert_eq!(
+            state.token_estimate(),
+            base + 10 + 1 + 2 * TOKENS_PER_MESSAGE
+        );
+    }
+
+    #[test]
+    fn test_state_fingerprint() {
+        let mut a = WxorcaState::with_session_id(AgentType::DocsHelper, "session-a");
+        a.add_user_message("Where are the API docs?");
+        a.add_assistant_message("Here they are.");
+
+        let mut b = WxorcaState::with_session_id(AgentType::DocsHelper, "session-b");
+        b.add_user_message("Where are the API docs?");
+        b.add_assistant_message("Here they are.");
+
+        assert_eq!(a.fingerprint(), b.fingerprint());
+
+        b.add_user_message("Thanks!");
+        assert_ne!(a.fingerprint(), b.fingerprint());
+
+        let mut c = WxorcaState::new(AgentType::Troubleshoot);
+        c.add_user_message("Where are the API docs?");
+        c.add_assistant_message("Here they are.");
+        assert_ne!(a.fingerprint(), c.fingerprint());
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut state = WxorcaState::with_session_id(AgentType::Troubleshoot, "session-1");
+        state.add_user_message("My skill fails");
+        state.add_tool_call("call_1", "search_wxo_docs", serde_json::json!({}));
+        state.add_tool_result("call_1", "[]");
+        state.add_assistant_message("Check the credentials");
+        state.mark_complete();
+        assert!(state.validate().is_ok());
+
+        state.session_id = " ".to_string();
+        state.messages.push(Message::tool_result("call_9", "[]"));
+        state.messages[0].timestamp = Utc::now() + chrono::Duration::hours(1);
+        state.messages.retain(|m| m.role != MessageRole::Assistant);
+
+        let violations = state.validate().unwrap_err();
+        assert_eq!(violations.len(), 4, "{:?}", violations);
+        assert!(violations.contains(&"session_id is empty".to_string()));
+        assert!(violations.contains(&"message 1 is timestamped before message 0".to_string()));
+        assert!(violations.contains(&"tool result 2 references unknown call call_9".to_string()));
+        assert!(violations.contains(&"marked complete without an assistant message".to_string()));
+    }
+
+    #[test]
+    fn test_fork_at() {
+        let mut state = WxorcaState::with_session_id(AgentType::Troubleshoot, "session-1");
+        state.add_user_message("My skill fails");
+        state.add_assistant_message("Which error do you see?");
+        state.add_user_message("A 401");
+        state.add_assistant_message("Check the credentials");
+        state.context.deployment_type = Some("saas".to_string());
+        state.context.current_topic = Some("A 401".to_string());
+        state
+            .context
+            .metadata
+            .insert("team".into(), "platform".into());
+        state
+            .context
+            .metadata
+            .insert("support_tier".into(), "tier_2".into());
+
+        let fork = state.fork_at(1);
+
+        assert_ne!(fork.session_id, state.session_id);
+        assert_eq!(fork.parent_session_id.as_deref(), Some("session-1"));
+        assert_eq!(fork.agent_type, AgentType::Troubleshoot);
+        assert_eq!(fork.messages.len(), 2);
+        assert_eq!(fork.messages[1].content, "Which error do you see?");
+        assert_eq!(fork.context.deployment_type.as_deref(), Some("saas"));
+        // What the later turns found out doesn't carry into the fork
+        assert_eq!(fork.context.current_topic, None);
+        assert_eq!(fork.context.metadata.get("team"), Some(&"platform".into()));
+        assert!(!fork.context.metadata.contains_key("support_tier"));
+        assert!(fork.validate().is_ok());
+        // The original conversation is untouched
+        assert_eq!(state.messages.len(), 4);
+        assert_eq!(state.parent_session_id, None);
+
+        let whole = state.fork_at(99);
+        assert_eq!(whole.messages.len(), 4);
+        assert_eq!(whole.context.current_topic.as_deref(), Some("A 401"));
+    }
+
+    #[test]
+    fn test_migrate_state() {
+        // Saved before versioning, with the agent type encoded twice
+        let legacy = serde_json::json!({
+            "session_id": "session-1",
+            "agent_type": "\"troubleshoot\"",
+            "messages": [{ "role": "user", "content": "My skill fails" }],
+            "updated_at": "2025-01-01T00:00:00Z"
+        });
+
+        let state = migrate_state(legacy).unwrap();
+
+        assert_eq!(state.schema_version, STATE_SCHEMA_VERSION);
+        assert_eq!(state.agent_type, AgentType::Troubleshoot);
+        assert_eq!(state.messages[0].content, "My skill fails");
+        assert_eq!(state.messages[0].timestamp, state.updated_at);
+        assert!(state.pending_tool_calls.is_empty());
+        assert_eq!(state.iteration, 0);
+        assert!(state.validate().is_ok());
+
+        // Current payloads round-trip unchanged
+        let current = WxorcaState::with_session_id(AgentType::DocsHelper, "session-2");
+        let migrated = migrate_state(serde_json::to_value(&current).unwrap()).unwrap();
+        assert_eq!(migrated.session_id, "session-2");
+        assert_eq!(migrated.created_at, current.created_at);
+
+        let mut newer = serde_json::to_value(&current).unwrap();
+        newer["schema_version"] = serde_json::json!(STATE_SCHEMA_VERSION + 1);
+        assert!(migrate_state(newer).is_err());
+        assert!(migrate_state(serde_json::json!([])).is_err());
+    }
 }
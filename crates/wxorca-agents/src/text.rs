@@ -0,0 +1,10 @@
+//! Text helpers shared by the agents and their tools
+
+/// Words that carry no search meaning (greetings, pronouns, filler)
+pub(crate) const STOPWORDS: &[&str] = &[
+    "a", "an", "the", "and", "or", "but", "is", "are", "was", "were", "be", "to", "of", "in", "on",
+    "for", "with", "at", "by", "from", "it", "this", "that", "i", "me", "my", "you", "your", "we",
+    "our", "do", "does", "how", "what", "when", "where", "why", "who", "can", "could", "would",
+    "should", "please", "hi", "hello", "hey", "there", "thanks", "thank", "ok", "okay", "yes",
+    "no", "bye", "so", "just", "very", "much",
+];
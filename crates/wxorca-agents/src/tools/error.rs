@@ -0,0 +1,181 @@
+//! Categorized tool failures
+//!
+//! `NodeError::ToolError` only carries a message, so tools tag the message
+//! with a [`ToolErrorKind`]. `ExecuteToolsNode` reads the tag back to decide
+//! whether a failed call is worth retrying, and records a [`ToolStatus`] for
+//! every call so response nodes can tell a failure from an empty result.
+
+use oxidizedgraph::prelude::NodeError;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// Why a tool call failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolErrorKind {
+    /// The arguments were malformed or out of range; retrying won't help
+    InvalidArguments,
+    /// A backing service (e.g. SurrealDB) could not be reached
+    Unavailable,
+    /// The call took longer than the tool timeout; not retried, since the
+    /// retry would likely wait just as long
+    Timeout,
+    /// The call waited too long for the tool rate limiter; not retried, for
+    /// the same reason
+    RateLimited,
+    /// Any other failure, including errors from untagged tools
+    Internal,
+}
+
+impl ToolErrorKind {
+    pub fn all() -> &'static [ToolErrorKind] {
+        &[
+            ToolErrorKind::InvalidArguments,
+            ToolErrorKind::Unavailable,
+            ToolErrorKind::Timeout,
+            ToolErrorKind::RateLimited,
+            ToolErrorKind::Internal,
+        ]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ToolErrorKind::InvalidArguments => "invalid_arguments",
+            ToolErrorKind::Unavailable => "unavailable",
+            ToolErrorKind::Timeout => "timeout",
+            ToolErrorKind::RateLimited => "rate_limited",
+            ToolErrorKind::Internal => "internal",
+        }
+    }
+
+    /// Whether running the same call again may succeed
+    pub fn is_transient(&self) -> bool {
+        matches!(self, ToolErrorKind::Unavailable)
+    }
+
+    /// A tool error whose message is tagged with this kind
+    pub fn error(self, message: impl Display) -> NodeError {
+        NodeError::ToolError(format!("[{}] {}", self.as_str(), message))
+    }
+
+    /// Read the kind tagged at the start of an error message; untagged
+    /// errors are [`ToolErrorKind::Internal`]
+    ///
+    /// The tag may follow the error's own label (`Tool error: [timeout] ...`),
+    /// but a `[kind]` further into the message, e.g. quoted from a backend,
+    /// doesn't count.
+    pub fn from_message(message: &str) -> Self {
+        let message = message.trim_start();
+        let tagged = message.strip_prefix('[').or_else(|| {
+            message
+                .split_once(": ")
+                .and_then(|(_, rest)| rest.strip_prefix('['))
+        });
+        tagged
+            .and_then(|rest| rest.split_once(']'))
+            .and_then(|(tag, _)| {
+                Self::all()
+                    .iter()
+                    .copied()
+                    .find(|kind| kind.as_str() == tag)
+            })
+            .unwrap_or(ToolErrorKind::Internal)
+    }
+}
+
+impl std::fmt::Display for ToolErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Outcome of a tool call, recorded alongside its output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", content = "kind", rename_all = "snake_case")]
+pub enum ToolStatus {
+    /// The tool returned a result
+    Success,
+    /// The tool ran but found nothing (an empty list, object or string)
+    Empty,
+    /// The tool failed
+    Failed(ToolErrorKind),
+}
+
+impl ToolStatus {
+    /// Classify a call from its output, or from its error message if it failed
+    pub fn from_output(output: &str, error: Option<&str>) -> Self {
+        if let Some(error) = error {
+            return ToolStatus::Failed(ToolErrorKind::from_message(error));
+        }
+
+        match output.trim() {
+            "" | "[]" | "{}" | "null" => ToolStatus::Empty,
+            _ => ToolStatus::Success,
+        }
+    }
+
+    pub fn is_failure(&self) -> bool {
+        matches!(self, ToolStatus::Failed(_))
+    }
+}
+
+impl std::fmt::Display for ToolStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ToolStatus::Success => f.write_str("success"),
+            ToolStatus::Empty => f.write_str("empty"),
+            ToolStatus::Failed(kind) => write!(f, "failed ({})", kind),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_kind_round_trips_through_message() {
+        for kind in ToolErrorKind::all() {
+            let message = kind.error("backend down").to_string();
+            assert_eq!(ToolErrorKind::from_message(&message), *kind);
+        }
+        assert_eq!(
+            ToolErrorKind::from_message("Tool error: something broke"),
+            ToolErrorKind::Internal
+        );
+        // Only the leading tag counts
+        assert_eq!(
+            ToolErrorKind::from_message("[timeout] upstream said [unavailable]"),
+            ToolErrorKind::Timeout
+        );
+        assert_eq!(
+            ToolErrorKind::from_message("Search failed: backend returned '[unavailable]'"),
+            ToolErrorKind::Internal
+        );
+        assert!(ToolErrorKind::Unavailable.is_transient());
+        assert!(!ToolErrorKind::InvalidArguments.is_transient());
+        assert!(!ToolErrorKind::Timeout.is_transient());
+        assert!(!ToolErrorKind::RateLimited.is_transient());
+    }
+
+    #[test]
+    fn test_status_from_output() {
+        assert_eq!(ToolStatus::from_output("[]", None), ToolStatus::Empty);
+        assert_eq!(
+            ToolStatus::from_output("[{\"title\": \"Admin Setup Guide\"}]", None),
+            ToolStatus::Success
+        );
+        assert_eq!(
+            ToolStatus::from_output("", Some("[invalid_arguments] missing field `query`")),
+            ToolStatus::Failed(ToolErrorKind::InvalidArguments)
+        );
+        assert_eq!(
+            serde_json::to_value(ToolStatus::Failed(ToolErrorKind::Unavailable)).unwrap(),
+            serde_json::json!({"status": "failed", "kind": "unavailable"})
+        );
+        assert_eq!(
+            ToolStatus::Failed(ToolErrorKind::Timeout).to_string(),
+            "failed (timeout)"
+        );
+    }
+}
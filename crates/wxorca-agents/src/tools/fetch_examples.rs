@@ -1,15 +1,81 @@
 //! Fetch code examples tool for WatsonX Orchestrate
 
+use super::error::ToolErrorKind;
+use crate::db::{Database, DbConfig, ExampleRecord};
 use async_trait::async_trait;
 use oxidizedgraph::prelude::{NodeError, Tool};
 use serde::{Deserialize, Serialize};
+use tokio::sync::OnceCell;
+use tracing;
 
 /// Tool for fetching code examples for WatsonX Orchestrate
-pub struct FetchExamplesTool;
+///
+/// Examples come from the `wxo_examples` table, connecting on first use
+/// (without reconnecting). The built-in examples are used when the table has
+/// no matching examples, and when the database is unreachable or the query
+/// fails unless built with [`FetchExamplesTool::without_mock_fallback`]. The
+/// [`mock`](Self::mock) tool, used in deterministic mode, only serves the
+/// built-in examples.
+pub struct FetchExamplesTool {
+    db: OnceCell<Option<Database>>,
+    allow_mock_fallback: bool,
+}
 
 impl FetchExamplesTool {
     pub fn new() -> Self {
-        Self
+        Self {
+            db: OnceCell::new(),
+            allow_mock_fallback: true,
+        }
+    }
+
+    /// Fail with a tool error when the database can't be searched instead
+    /// of serving the built-in examples, for production deployments
+    pub fn without_mock_fallback() -> Self {
+        Self {
+            db: OnceCell::new(),
+            allow_mock_fallback: false,
+        }
+    }
+
+    /// Only serve the built-in examples, never connecting to the database
+    pub fn mock() -> Self {
+        Self {
+            db: OnceCell::new_with(None),
+            allow_mock_fallback: true,
+        }
+    }
+
+    /// Use an already connected database instead of connecting from
+    /// environment settings
+    pub fn with_database(db: Database) -> Self {
+        Self {
+            db: OnceCell::new_with(Some(db)),
+            allow_mock_fallback: true,
+        }
+    }
+
+    /// Whether to serve the built-in examples when the database can't be
+    /// searched (default: true)
+    pub fn allow_mock_fallback(mut self, allow: bool) -> Self {
+        self.allow_mock_fallback = allow;
+        self
+    }
+
+    /// Get the shared database, connecting on first use
+    async fn database(&self) -> Option<&Database> {
+        self.db
+            .get_or_init(|| async {
+                match Database::connect(&DbConfig::from_env()).await {
+                    Ok(db) => Some(db),
+                    Err(e) => {
+                        tracing::warn!("SurrealDB unavailable for examples: {}", e);
+                        None
+                    }
+                }
+            })
+            .await
+            .as_ref()
     }
 }
 
@@ -41,6 +107,18 @@ struct CodeExample {
     tags: Vec<String>,
 }
 
+impl From<ExampleRecord> for CodeExample {
+    fn from(record: ExampleRecord) -> Self {
+        Self {
+            title: record.title,
+            description: record.description,
+            language: record.language,
+            code: record.code,
+            tags: record.tags,
+        }
+    }
+}
+
 #[async_trait]
 impl Tool for FetchExamplesTool {
     fn name(&self) -> &str {
@@ -75,13 +153,37 @@ impl Tool for FetchExamplesTool {
     }
 
     async fn execute(&self, arguments: serde_json::Value) -> Result<String, NodeError> {
-        let input: FetchExamplesInput = serde_json::from_value(arguments)
-            .map_err(|e| NodeError::ToolError(format!("Invalid arguments: {}", e)))?;
-
-        let examples = get_mock_examples(&input.topic, input.language.as_deref(), input.limit);
+        let input: FetchExamplesInput = serde_json::from_value(arguments).map_err(|e| {
+            ToolErrorKind::InvalidArguments.error(format!("Invalid arguments: {}", e))
+        })?;
+
+        let language = input.language.as_deref();
+
+        let records = match self.database().await {
+            Some(db) => db
+                .search_examples(&input.topic, language, input.limit)
+                .await
+                .map_err(|e| {
+                    ToolErrorKind::Unavailable.error(format!("Failed to search examples: {}", e))
+                }),
+            None => Err(ToolErrorKind::Unavailable.error("SurrealDB is unavailable")),
+        };
+
+        let examples = match records {
+            Ok(records) if !records.is_empty() => {
+                records.into_iter().map(CodeExample::from).collect()
+            }
+            Ok(_) => get_mock_examples(&input.topic, language, input.limit),
+            Err(e) if self.allow_mock_fallback => {
+                tracing::warn!("Example search failed, using built-in examples: {}", e);
+                get_mock_examples(&input.topic, language, input.limit)
+            }
+            Err(e) => return Err(e),
+        };
 
-        serde_json::to_string_pretty(&examples)
-            .map_err(|e| NodeError::ToolError(format!("Failed to serialize examples: {}", e)))
+        serde_json::to_string_pretty(&examples).map_err(|e| {
+            ToolErrorKind::Internal.error(format!("Failed to serialize examples: {}", e))
+        })
     }
 }
 
@@ -364,10 +466,11 @@ token = get_wxo_token(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::Utc;
 
     #[tokio::test]
     async fn test_fetch_examples() {
-        let tool = FetchExamplesTool::new();
+        let tool = FetchExamplesTool::mock();
 
         let result = tool
             .execute(serde_json::json!({
@@ -384,7 +487,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_fetch_examples_with_language() {
-        let tool = FetchExamplesTool::new();
+        let tool = FetchExamplesTool::mock();
 
         let result = tool
             .execute(serde_json::json!({
@@ -399,4 +502,72 @@ mod tests {
             assert_eq!(example.language.to_lowercase(), "python");
         }
     }
+
+    #[tokio::test]
+    async fn test_fetch_examples_from_database() {
+        let db = Database::connect(&DbConfig::memory()).await.unwrap();
+        db.init_schema().await.unwrap();
+        db.add_example(&ExampleRecord {
+            id: None,
+            title: "Teams Notification".to_string(),
+            description: "Post a workflow result to a channel".to_string(),
+            language: "json".to_string(),
+            code: "{}".to_string(),
+            tags: vec!["teams".to_string()],
+            created_at: Utc::now(),
+        })
+        .await
+        .unwrap();
+        let tool = FetchExamplesTool::with_database(db);
+
+        let result = tool
+            .execute(serde_json::json!({ "topic": "teams" }))
+            .await
+            .unwrap();
+        let examples: Vec<CodeExample> = serde_json::from_str(&result).unwrap();
+        let titles: Vec<&str> = examples.iter().map(|e| e.title.as_str()).collect();
+        assert_eq!(titles, vec!["Teams Notification"]);
+
+        // Nothing stored for the topic, so the built-in examples are used
+        let result = tool
+            .execute(serde_json::json!({ "topic": "salesforce" }))
+            .await
+            .unwrap();
+        let examples: Vec<CodeExample> = serde_json::from_str(&result).unwrap();
+        assert_eq!(examples[0].title, "Salesforce Integration Config");
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_database_falls_back_to_builtin_examples() {
+        // As if connecting from the environment had failed
+        let tool = FetchExamplesTool {
+            db: OnceCell::new_with(None),
+            allow_mock_fallback: true,
+        };
+
+        let result = tool
+            .execute(serde_json::json!({ "topic": "skill" }))
+            .await
+            .unwrap();
+        let examples: Vec<CodeExample> = serde_json::from_str(&result).unwrap();
+        assert!(!examples.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_database_is_an_error() {
+        // As if connecting from the environment had failed
+        let tool = FetchExamplesTool {
+            db: OnceCell::new_with(None),
+            ..FetchExamplesTool::without_mock_fallback()
+        };
+
+        let error = tool
+            .execute(serde_json::json!({ "topic": "skill" }))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            ToolErrorKind::from_message(&error.to_string()),
+            ToolErrorKind::Unavailable
+        );
+    }
 }
@@ -3,20 +3,151 @@
 //! Provides specialized tools for searching documentation,
 //! validating configurations, and fetching examples.
 
+mod error;
 mod fetch_examples;
+mod rate_limit;
+mod schema;
+mod search_backends;
 mod search_docs;
+#[cfg(test)]
+pub(crate) mod testing;
 mod validate_config;
 
+pub use error::{ToolErrorKind, ToolStatus};
 pub use fetch_examples::FetchExamplesTool;
-pub use search_docs::SearchDocsTool;
+pub use rate_limit::{
+    RateLimiter, DEFAULT_MAX_RATE_LIMIT_WAIT, TOOL_RATE_LIMIT_ENV, TOOL_RATE_LIMIT_MAX_WAIT_ENV,
+};
+pub use schema::{validate_tool_schema, validate_tool_schemas, SchemaError};
+pub use search_backends::{DocSearchBackend, MockBackend, SurrealBackend, FEEDBACK_STATS_TTL};
+pub(crate) use search_docs::truncate_excerpt;
+pub use search_docs::{
+    document_frequencies, make_snippet, score_relevance, score_relevance_fuzzy,
+    score_relevance_weighted, text_similarity, DocResult, SearchDocsTool, TermStats,
+};
 pub use validate_config::ValidateConfigTool;
 
-use oxidizedgraph::prelude::ToolRegistry;
+use crate::db::Database;
+use async_trait::async_trait;
+use oxidizedgraph::prelude::{NodeError, Tool, ToolRegistry};
+use std::sync::{Arc, OnceLock};
+
+/// Environment variable that turns off the mock data fallback
+///
+/// Set it to `1` or `true` in production so the registries built here fail
+/// with a tool error when SurrealDB can't be searched, rather than quietly
+/// answering from mock docs and examples.
+pub const NO_MOCK_FALLBACK_ENV: &str = "WXORCA_NO_MOCK_FALLBACK";
+
+/// Whether the built-in tools may serve mock data, i.e. unless
+/// [`NO_MOCK_FALLBACK_ENV`] is set to `1` or `true`
+pub fn mock_fallback_from_env() -> bool {
+    !std::env::var(NO_MOCK_FALLBACK_ENV)
+        .map(|value| value == "1" || value.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
 
 /// Create a tool registry with all WXOrca tools
 pub fn create_tool_registry() -> ToolRegistry {
+    register_all(ToolRegistry::new(), builtin_tools())
+}
+
+/// The tools [`create_tool_registry`] registers
+pub(crate) fn builtin_tools() -> Vec<Box<dyn Tool>> {
+    let (search_docs, fetch_examples) = if mock_fallback_from_env() {
+        (SearchDocsTool::new(), FetchExamplesTool::new())
+    } else {
+        (
+            SearchDocsTool::without_mock_fallback(),
+            FetchExamplesTool::without_mock_fallback(),
+        )
+    };
+    vec![
+        Box::new(search_docs),
+        Box::new(ValidateConfigTool::new()),
+        Box::new(fetch_examples),
+    ]
+}
+
+/// Create a tool registry with all WXOrca tools, searching `db` instead of
+/// connecting from environment settings
+pub fn create_tool_registry_for(db: &Database) -> ToolRegistry {
+    let allow_mock_fallback = mock_fallback_from_env();
     ToolRegistry::new()
-        .register(SearchDocsTool::new())
+        .register(SearchDocsTool::with_backend(
+            SurrealBackend::with_client(db.client().clone())
+                .allow_mock_fallback(allow_mock_fallback),
+        ))
         .register(ValidateConfigTool::new())
-        .register(FetchExamplesTool::new())
+        .register(
+            FetchExamplesTool::with_database(db.clone()).allow_mock_fallback(allow_mock_fallback),
+        )
+}
+
+/// The process-wide registry of all WXOrca tools
+///
+/// Built once, so the SurrealDB connection made on first use is kept across
+/// turns instead of reconnecting for every graph.
+pub fn shared_tool_registry() -> Arc<ToolRegistry> {
+    static SHARED: OnceLock<Arc<ToolRegistry>> = OnceLock::new();
+    SHARED
+        .get_or_init(|| Arc::new(create_tool_registry()))
+        .clone()
+}
+
+/// The process-wide registry of mock-data tools, see
+/// [`create_mock_tool_registry`]
+pub fn shared_mock_tool_registry() -> Arc<ToolRegistry> {
+    static SHARED: OnceLock<Arc<ToolRegistry>> = OnceLock::new();
+    SHARED
+        .get_or_init(|| Arc::new(create_mock_tool_registry()))
+        .clone()
+}
+
+/// Create a tool registry with all WXOrca tools plus `extra`
+///
+/// Use this to give agents tools of your own: pass the registry to
+/// [`GraphOptions::tool_registry`](crate::agents::GraphOptions::tool_registry)
+/// and queue calls to the tools by name like any built-in one.
+pub fn create_tool_registry_with(extra: Vec<Box<dyn Tool>>) -> ToolRegistry {
+    register_all(create_tool_registry(), extra)
+}
+
+/// Create a tool registry whose tools only use built-in mock data
+///
+/// Nothing connects to SurrealDB, so results are the same on every run.
+pub fn create_mock_tool_registry() -> ToolRegistry {
+    ToolRegistry::new()
+        .register(SearchDocsTool::with_backend(MockBackend))
+        .register(ValidateConfigTool::new())
+        .register(FetchExamplesTool::mock())
+}
+
+/// Register each of `tools` on `registry`
+fn register_all(registry: ToolRegistry, tools: Vec<Box<dyn Tool>>) -> ToolRegistry {
+    tools.into_iter().fold(registry, |registry, tool| {
+        registry.register(CustomTool(tool))
+    })
+}
+
+/// Registers a boxed tool, forwarding every call to it
+struct CustomTool(Box<dyn Tool>);
+
+#[async_trait]
+impl Tool for CustomTool {
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    fn description(&self) -> &str {
+        self.0.description()
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        self.0.parameters_schema()
+    }
+
+    async fn execute(&self, arguments: serde_json::Value) -> Result<String, NodeError> {
+        self.0.execute(arguments).await
+    }
 }
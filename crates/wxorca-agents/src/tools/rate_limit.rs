@@ -0,0 +1,157 @@
+//! Rate limiting for tool calls
+//!
+//! Tools hit the external doc service (and, through agents, the LLM), so
+//! `ExecuteToolsNode` takes a token from a [`RateLimiter`] before each call.
+//! By default every graph shares one limiter configured from the
+//! environment, which keeps parallel sessions under the limit together.
+
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Environment variable setting the shared limit, in tool calls per second
+///
+/// Unset or `0` means unlimited.
+pub const TOOL_RATE_LIMIT_ENV: &str = "WXORCA_TOOL_RATE_LIMIT";
+
+/// Environment variable setting how long a call may wait for the shared
+/// limiter, in milliseconds
+pub const TOOL_RATE_LIMIT_MAX_WAIT_ENV: &str = "WXORCA_TOOL_RATE_LIMIT_MAX_WAIT_MS";
+
+/// Default cap on how long a call waits for its turn
+pub const DEFAULT_MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(10);
+
+/// Token bucket limiting how many tool calls start per second
+///
+/// The bucket holds up to one second's worth of calls, so a burst of up to
+/// the limit starts at once and later calls are spaced evenly. Callers over
+/// the limit wait for a token; a call that would wait longer than the max
+/// wait is rejected instead.
+#[derive(Debug)]
+pub struct RateLimiter {
+    calls_per_second: u32,
+    max_wait: Duration,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Tokens left; negative when calls are queued for future tokens
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl RateLimiter {
+    /// Allow `calls_per_second` calls per second (at least one)
+    pub fn new(calls_per_second: u32) -> Self {
+        let calls_per_second = calls_per_second.max(1);
+        Self {
+            calls_per_second,
+            max_wait: DEFAULT_MAX_RATE_LIMIT_WAIT,
+            bucket: Mutex::new(Bucket {
+                tokens: calls_per_second as f64,
+                refilled: Instant::now(),
+            }),
+        }
+    }
+
+    /// Reject calls that would wait longer than `max_wait` for a token
+    pub fn with_max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = max_wait;
+        self
+    }
+
+    /// Build a limiter from [`TOOL_RATE_LIMIT_ENV`] and
+    /// [`TOOL_RATE_LIMIT_MAX_WAIT_ENV`], or `None` if no limit is set
+    pub fn from_env() -> Option<Self> {
+        let calls_per_second = std::env::var(TOOL_RATE_LIMIT_ENV)
+            .ok()
+            .and_then(|limit| limit.parse::<u32>().ok())
+            .filter(|limit| *limit > 0)?;
+        let max_wait = std::env::var(TOOL_RATE_LIMIT_MAX_WAIT_ENV)
+            .ok()
+            .and_then(|ms| ms.parse::<u64>().ok())
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_MAX_RATE_LIMIT_WAIT);
+
+        Some(Self::new(calls_per_second).with_max_wait(max_wait))
+    }
+
+    /// The process-wide limiter configured from the environment, if any
+    pub fn shared() -> Option<Arc<RateLimiter>> {
+        static SHARED: OnceLock<Option<Arc<RateLimiter>>> = OnceLock::new();
+        SHARED
+            .get_or_init(|| RateLimiter::from_env().map(Arc::new))
+            .clone()
+    }
+
+    pub fn calls_per_second(&self) -> u32 {
+        self.calls_per_second
+    }
+
+    /// Wait for a token, returning how long the call waited
+    ///
+    /// Fails with the wait it would have needed if that exceeds the max wait;
+    /// no token is taken in that case.
+    pub async fn acquire(&self) -> Result<Duration, Duration> {
+        let wait = self.reserve()?;
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+        Ok(wait)
+    }
+
+    /// Take a token, possibly one that is only available after the returned
+    /// wait
+    fn reserve(&self) -> Result<Duration, Duration> {
+        let rate = self.calls_per_second as f64;
+        let mut bucket = self
+            .bucket
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let now = Instant::now();
+        let elapsed = now.saturating_duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(rate);
+        bucket.refilled = now;
+
+        let wait = if bucket.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - bucket.tokens) / rate)
+        };
+        if wait > self.max_wait {
+            return Err(wait);
+        }
+
+        bucket.tokens -= 1.0;
+        Ok(wait)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_calls_over_the_limit_are_throttled() {
+        let limiter = RateLimiter::new(10);
+
+        let started = Instant::now();
+        for _ in 0..10 {
+            assert_eq!(limiter.acquire().await, Ok(Duration::ZERO));
+        }
+        let waited = limiter.acquire().await.unwrap();
+
+        assert!(waited >= Duration::from_millis(50), "waited {:?}", waited);
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn test_wait_beyond_max_is_rejected() {
+        let limiter = RateLimiter::new(1).with_max_wait(Duration::from_millis(10));
+
+        assert!(limiter.acquire().await.is_ok());
+        let rejected = limiter.acquire().await.unwrap_err();
+        assert!(rejected > Duration::from_millis(10));
+    }
+}
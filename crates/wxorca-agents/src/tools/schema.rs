@@ -0,0 +1,146 @@
+//! Structural validation of tool parameter schemas
+
+use oxidizedgraph::prelude::Tool;
+
+/// A problem found in a tool's parameter schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+    /// Name of the tool whose schema is invalid
+    pub tool: String,
+    /// Description of the problem
+    pub message: String,
+}
+
+impl std::fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.tool, self.message)
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+/// Validate the parameter schemas of a set of tools
+///
+/// Each schema must be an object schema with a `properties` map and a
+/// `required` list that only references declared properties.
+pub fn validate_tool_schemas(tools: &[&dyn Tool]) -> Result<(), Vec<SchemaError>> {
+    let errors: Vec<SchemaError> = tools
+        .iter()
+        .flat_map(|tool| validate_tool_schema(*tool))
+        .collect();
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Validate a single tool's parameter schema
+pub fn validate_tool_schema(tool: &dyn Tool) -> Vec<SchemaError> {
+    let schema = tool.parameters_schema();
+    let mut errors = Vec::new();
+    let mut error = |message: String| {
+        errors.push(SchemaError {
+            tool: tool.name().to_string(),
+            message,
+        })
+    };
+
+    let Some(schema) = schema.as_object() else {
+        error("schema must be a JSON object".to_string());
+        return errors;
+    };
+
+    if schema.get("type").and_then(|t| t.as_str()) != Some("object") {
+        error("schema \"type\" must be \"object\"".to_string());
+    }
+
+    let properties = match schema.get("properties").and_then(|p| p.as_object()) {
+        Some(properties) => properties,
+        None => {
+            error("schema must have a \"properties\" map".to_string());
+            return errors;
+        }
+    };
+
+    for (name, property) in properties {
+        match property.as_object() {
+            Some(property) if property.get("type").is_some_and(|t| t.is_string()) => {}
+            Some(_) => error(format!(
+                "property \"{}\" must declare a string \"type\"",
+                name
+            )),
+            None => error(format!("property \"{}\" must be an object", name)),
+        }
+    }
+
+    match schema.get("required").and_then(|r| r.as_array()) {
+        Some(required) => {
+            for field in required {
+                match field.as_str() {
+                    Some(field) if properties.contains_key(field) => {}
+                    Some(field) => error(format!(
+                        "required field \"{}\" is not a declared property",
+                        field
+                    )),
+                    None => error("\"required\" entries must be strings".to_string()),
+                }
+            }
+        }
+        None => error("schema must have a \"required\" list".to_string()),
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::builtin_tools;
+    use async_trait::async_trait;
+    use oxidizedgraph::prelude::NodeError;
+
+    struct BrokenTool;
+
+    #[async_trait]
+    impl Tool for BrokenTool {
+        fn name(&self) -> &str {
+            "broken"
+        }
+
+        fn description(&self) -> &str {
+            "A tool with an inconsistent schema"
+        }
+
+        fn parameters_schema(&self) -> serde_json::Value {
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "query": { "description": "Missing a type" }
+                },
+                "required": ["query", "limit"]
+            })
+        }
+
+        async fn execute(&self, _arguments: serde_json::Value) -> Result<String, NodeError> {
+            Ok(String::new())
+        }
+    }
+
+    #[test]
+    fn test_builtin_tool_schemas_are_valid() {
+        let tools = builtin_tools();
+        let tools: Vec<&dyn Tool> = tools.iter().map(|tool| tool.as_ref()).collect();
+
+        let result = validate_tool_schemas(&tools);
+        assert!(result.is_ok(), "Invalid tool schemas: {:?}", result.err());
+    }
+
+    #[test]
+    fn test_inconsistent_schema_is_reported() {
+        let errors = validate_tool_schemas(&[&BrokenTool]).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|e| e.tool == "broken"));
+    }
+}
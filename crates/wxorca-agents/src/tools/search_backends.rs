@@ -0,0 +1,678 @@
+//! Documentation search backends
+//!
+//! `SearchDocsTool` delegates lookups to a [`DocSearchBackend`], so agent
+//! graphs can search docs indexed outside SurrealDB (e.g. Elasticsearch).
+
+use super::error::ToolErrorKind;
+use super::search_docs::{
+    blend_feedback, make_snippet, query_terms, score_relevance, score_relevance_fuzzy,
+    score_relevance_weighted, DocResult, TermStats,
+};
+use crate::db::{Database, DbConfig, SearchIndex, HIGH_RATING};
+use crate::state::DocCategory;
+use async_trait::async_trait;
+use oxidizedgraph::prelude::NodeError;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use surrealdb::{engine::any::Any, Surreal};
+use tokio::sync::OnceCell;
+
+/// A source of documentation search results
+#[async_trait]
+pub trait DocSearchBackend: Send + Sync {
+    /// Find docs matching `query`, optionally limited to one category
+    ///
+    /// Results are sorted by descending relevance.
+    async fn search(
+        &self,
+        query: &str,
+        category: Option<DocCategory>,
+        limit: usize,
+    ) -> Result<Vec<DocResult>, NodeError>;
+
+    /// Like [`DocSearchBackend::search`], but boost docs that appeared in
+    /// highly rated sessions
+    ///
+    /// Backends without access to feedback run a plain search.
+    async fn search_with_feedback(
+        &self,
+        query: &str,
+        category: Option<DocCategory>,
+        limit: usize,
+    ) -> Result<Vec<DocResult>, NodeError> {
+        self.search(query, category, limit).await
+    }
+
+    /// Like [`DocSearchBackend::search`], but tolerate typos in query terms,
+    /// optionally boosting by feedback as well
+    ///
+    /// Backends without typo tolerance run their usual search.
+    async fn search_fuzzy(
+        &self,
+        query: &str,
+        category: Option<DocCategory>,
+        limit: usize,
+        use_feedback: bool,
+    ) -> Result<Vec<DocResult>, NodeError> {
+        if use_feedback {
+            self.search_with_feedback(query, category, limit).await
+        } else {
+            self.search(query, category, limit).await
+        }
+    }
+}
+
+/// How long feedback stats are reused before being counted again
+pub const FEEDBACK_STATS_TTL: Duration = Duration::from_secs(300);
+
+/// Rows fetched from SurrealDB per requested result, so there are still
+/// enough docs left after duplicates are dropped
+const DB_OVERFETCH_FACTOR: usize = 3;
+
+/// Searches the `wxo_docs` table in SurrealDB
+///
+/// Query terms are weighted by their IDF once the search index has been
+/// built with `Database::rebuild_search_index`, counting the documents a term
+/// matches the same way scoring does. The index vocabulary is loaded once
+/// and reloaded only after the index is rebuilt; feedback stats are counted
+/// again at most every [`FEEDBACK_STATS_TTL`].
+///
+/// The connection is established on first use and shared by all later
+/// searches. If that first connection fails, or a query fails, the backend
+/// serves mock data (without reconnecting) unless
+/// [`allow_mock_fallback`](SurrealBackend::allow_mock_fallback) is turned
+/// off, in which case the search fails. A query that finds nothing returns
+/// no docs either way.
+pub struct SurrealBackend {
+    config: DbConfig,
+    allow_mock_fallback: bool,
+    client: OnceCell<Option<Surreal<Any>>>,
+    index: RwLock<Option<Arc<SearchIndex>>>,
+    feedback_stats: RwLock<Option<(Instant, Arc<HashMap<String, usize>>)>>,
+}
+
+impl SurrealBackend {
+    /// Connect lazily using the `SURREAL_*` environment variables (see
+    /// [`DbConfig::from_env`])
+    pub fn new() -> Self {
+        Self::with_config(DbConfig::from_env())
+    }
+
+    /// Connect lazily using `config`
+    pub fn with_config(config: DbConfig) -> Self {
+        Self {
+            config,
+            allow_mock_fallback: true,
+            client: OnceCell::new(),
+            index: RwLock::new(None),
+            feedback_stats: RwLock::new(None),
+        }
+    }
+
+    /// Whether to serve mock docs when SurrealDB can't be searched (default:
+    /// true); turn off in production so an outage surfaces as a tool error
+    pub fn allow_mock_fallback(mut self, allow: bool) -> Self {
+        self.allow_mock_fallback = allow;
+        self
+    }
+
+    /// Use an already connected client (e.g. from `Database::client`) instead
+    /// of connecting from environment settings
+    pub fn with_client(client: Surreal<Any>) -> Self {
+        Self {
+            client: OnceCell::new_with(Some(client)),
+            ..Self::new()
+        }
+    }
+
+    /// Get the shared client, connecting on first use
+    async fn client(&self) -> Option<&Surreal<Any>> {
+        self.client
+            .get_or_init(|| async {
+                match self.connect_db().await {
+                    Ok(client) => Some(client),
+                    Err(e) => {
+                        tracing::warn!("SurrealDB unavailable: {}", e);
+                        None
+                    }
+                }
+            })
+            .await
+            .as_ref()
+    }
+
+    async fn connect_db(&self) -> Result<Surreal<Any>, NodeError> {
+        let db = Database::connect(&self.config).await.map_err(|e| {
+            ToolErrorKind::Unavailable.error(format!("Failed to connect to SurrealDB: {:#}", e))
+        })?;
+
+        Ok(db.client().clone())
+    }
+
+    /// Get the search index vocabulary, reloading it only if the index has
+    /// been rebuilt since it was last loaded
+    async fn search_index(
+        &self,
+        client: &Surreal<Any>,
+    ) -> anyhow::Result<Option<Arc<SearchIndex>>> {
+        let db = Database::from_client(client.clone());
+        let Some(built_at) = db.search_index_built_at().await? else {
+            return Ok(None);
+        };
+
+        let cached = self.index.read().ok().and_then(|index| index.clone());
+        if let Some(index) = cached.filter(|index| index.built_at == built_at) {
+            return Ok(Some(index));
+        }
+
+        let index = db.load_search_index().await?.map(Arc::new);
+        if let Ok(mut cached) = self.index.write() {
+            *cached = index.clone();
+        }
+        Ok(index)
+    }
+
+    /// Get how many highly rated sessions each doc URL appeared in, counting
+    /// them again once the last count is older than [`FEEDBACK_STATS_TTL`]
+    async fn feedback_stats(
+        &self,
+        client: &Surreal<Any>,
+    ) -> anyhow::Result<Arc<HashMap<String, usize>>> {
+        let cached = self
+            .feedback_stats
+            .read()
+            .ok()
+            .and_then(|stats| stats.clone());
+        if let Some((counted_at, stats)) = cached {
+            if counted_at.elapsed() < FEEDBACK_STATS_TTL {
+                return Ok(stats);
+            }
+        }
+
+        let stats = Arc::new(
+            Database::from_client(client.clone())
+                .get_doc_feedback_stats(HIGH_RATING)
+                .await?,
+        );
+        if let Ok(mut cached) = self.feedback_stats.write() {
+            *cached = Some((Instant::now(), stats.clone()));
+        }
+        Ok(stats)
+    }
+
+    async fn query_surreal_db(
+        &self,
+        client: &Surreal<Any>,
+        search: &str,
+        category: Option<DocCategory>,
+        limit: usize,
+        use_feedback: bool,
+        fuzzy: bool,
+    ) -> Result<Vec<DocResult>, NodeError> {
+        // Build query based on whether category filter is present
+        let query_str = if category.is_some() {
+            r#"
+            SELECT title, content, url, category FROM wxo_docs
+            WHERE category = $category
+            LIMIT $limit
+            "#
+        } else {
+            r#"
+            SELECT title, content, url, category FROM wxo_docs
+            LIMIT $limit
+            "#
+        };
+
+        let mut query = client
+            .query(query_str)
+            .bind(("limit", limit.saturating_mul(DB_OVERFETCH_FACTOR)));
+
+        if let Some(cat) = category {
+            query = query.bind(("category", cat.as_str()));
+        }
+
+        let mut result = query
+            .await
+            .map_err(|e| ToolErrorKind::Unavailable.error(format!("Query failed: {}", e)))?;
+
+        #[derive(Debug, Deserialize)]
+        struct DbDoc {
+            title: String,
+            content: String,
+            url: String,
+            category: String,
+        }
+
+        let db_docs: Vec<DbDoc> = result.take(0).map_err(|e| {
+            ToolErrorKind::Internal.error(format!("Failed to parse results: {}", e))
+        })?;
+
+        let stats = match self.search_index(client).await {
+            Ok(index) => index.map(|index| index.term_stats(&query_terms(search), fuzzy)),
+            Err(e) => {
+                tracing::warn!("Failed to load search index stats, not weighting: {}", e);
+                None
+            }
+        };
+
+        // Convert to DocResult with relevance scoring
+        let mut results: Vec<DocResult> = db_docs
+            .into_iter()
+            .map(|doc| {
+                let relevance = relevance(search, &doc.title, &doc.content, fuzzy, stats.as_ref());
+
+                DocResult {
+                    title: doc.title,
+                    content: make_snippet(&doc.content, search, 500),
+                    url: doc.url,
+                    category: doc.category,
+                    relevance,
+                }
+            })
+            .collect();
+
+        if use_feedback {
+            match self.feedback_stats(client).await {
+                Ok(stats) => {
+                    for doc in &mut results {
+                        let sessions = stats.get(&doc.url).copied().unwrap_or(0);
+                        doc.relevance = blend_feedback(doc.relevance, sessions);
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to load feedback stats, not boosting: {}", e),
+            }
+        }
+
+        results.sort_by(|a, b| b.relevance.total_cmp(&a.relevance));
+
+        let mut results = dedup_docs(results);
+        results.truncate(limit);
+        Ok(results)
+    }
+
+    /// Search SurrealDB, falling back to mock docs if it is unreachable or
+    /// the query fails and the fallback is allowed
+    async fn search_or_mock(
+        &self,
+        query: &str,
+        category: Option<DocCategory>,
+        limit: usize,
+        use_feedback: bool,
+        fuzzy: bool,
+    ) -> Result<Vec<DocResult>, NodeError> {
+        let result = match self.client().await {
+            Some(client) => {
+                self.query_surreal_db(client, query, category, limit, use_feedback, fuzzy)
+                    .await
+            }
+            None => Err(ToolErrorKind::Unavailable.error("SurrealDB is unavailable")),
+        };
+
+        match result {
+            Ok(docs) => Ok(docs),
+            Err(e) if self.allow_mock_fallback => {
+                tracing::warn!("SurrealDB search failed, using mock data: {}", e);
+                Ok(get_mock_docs(query, limit, category, fuzzy))
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Default for SurrealBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl DocSearchBackend for SurrealBackend {
+    async fn search(
+        &self,
+        query: &str,
+        category: Option<DocCategory>,
+        limit: usize,
+    ) -> Result<Vec<DocResult>, NodeError> {
+        self.search_or_mock(query, category, limit, false, false)
+            .await
+    }
+
+    async fn search_with_feedback(
+        &self,
+        query: &str,
+        category: Option<DocCategory>,
+        limit: usize,
+    ) -> Result<Vec<DocResult>, NodeError> {
+        self.search_or_mock(query, category, limit, true, false)
+            .await
+    }
+
+    async fn search_fuzzy(
+        &self,
+        query: &str,
+        category: Option<DocCategory>,
+        limit: usize,
+        use_feedback: bool,
+    ) -> Result<Vec<DocResult>, NodeError> {
+        self.search_or_mock(query, category, limit, use_feedback, true)
+            .await
+    }
+}
+
+/// Searches a small built-in set of docs, for offline use and tests
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MockBackend;
+
+#[async_trait]
+impl DocSearchBackend for MockBackend {
+    async fn search(
+        &self,
+        query: &str,
+        category: Option<DocCategory>,
+        limit: usize,
+    ) -> Result<Vec<DocResult>, NodeError> {
+        Ok(get_mock_docs(query, limit, category, false))
+    }
+
+    async fn search_fuzzy(
+        &self,
+        query: &str,
+        category: Option<DocCategory>,
+        limit: usize,
+        _use_feedback: bool,
+    ) -> Result<Vec<DocResult>, NodeError> {
+        Ok(get_mock_docs(query, limit, category, true))
+    }
+}
+
+fn relevance(
+    query: &str,
+    title: &str,
+    content: &str,
+    fuzzy: bool,
+    stats: Option<&TermStats>,
+) -> f32 {
+    if let Some(stats) = stats {
+        score_relevance_weighted(query, title, content, fuzzy, stats)
+    } else if fuzzy {
+        score_relevance_fuzzy(query, title, content)
+    } else {
+        score_relevance(query, title, content)
+    }
+}
+
+fn get_mock_docs(
+    query: &str,
+    limit: usize,
+    category: Option<DocCategory>,
+    fuzzy: bool,
+) -> Vec<DocResult> {
+    // Mock documentation database (relevance is scored per query below)
+    let all_docs = vec![
+        DocResult {
+            title: "Getting Started with WatsonX Orchestrate".to_string(),
+            content: "WatsonX Orchestrate is an AI-powered automation platform that helps you \
+                     work more efficiently by automating repetitive tasks and providing \
+                     intelligent assistance.".to_string(),
+            url: "https://www.ibm.com/docs/watsonx-orchestrate/getting-started".to_string(),
+            category: DocCategory::User.to_string(),
+            relevance: 0.0,
+        },
+        DocResult {
+            title: "Admin Setup Guide".to_string(),
+            content: "This guide walks administrators through the initial setup of WatsonX \
+                     Orchestrate, including user management, security configuration, and \
+                     integration setup.".to_string(),
+            url: "https://www.ibm.com/docs/watsonx-orchestrate/admin-guide".to_string(),
+            category: DocCategory::Admin.to_string(),
+            relevance: 0.0,
+        },
+        DocResult {
+            title: "Creating Custom Skills".to_string(),
+            content: "Learn how to create custom skills in WatsonX Orchestrate. Skills are \
+                     reusable automation components that can be combined into workflows.".to_string(),
+            url: "https://www.ibm.com/docs/watsonx-orchestrate/skills".to_string(),
+            category: DocCategory::User.to_string(),
+            relevance: 0.0,
+        },
+        DocResult {
+            title: "API Reference".to_string(),
+            content: "Complete API reference for WatsonX Orchestrate, including authentication, \
+                     skill management, and workflow execution endpoints.".to_string(),
+            url: "https://www.ibm.com/docs/watsonx-orchestrate/api".to_string(),
+            category: DocCategory::Api.to_string(),
+            relevance: 0.0,
+        },
+        DocResult {
+            title: "Troubleshooting Common Issues".to_string(),
+            content: "Solutions for common issues including authentication failures, skill \
+                     execution errors, and integration problems.".to_string(),
+            url: "https://www.ibm.com/docs/watsonx-orchestrate/troubleshooting".to_string(),
+            category: DocCategory::Troubleshooting.to_string(),
+            relevance: 0.0,
+        },
+        DocResult {
+            title: "Integration with Salesforce".to_string(),
+            content: "Step-by-step guide for integrating WatsonX Orchestrate with Salesforce, \
+                     enabling CRM automation and data synchronization.".to_string(),
+            url: "https://www.ibm.com/docs/watsonx-orchestrate/integrations/salesforce".to_string(),
+            category: DocCategory::Admin.to_string(),
+            relevance: 0.0,
+        },
+        DocResult {
+            title: "Security Best Practices".to_string(),
+            content: "Security recommendations for WatsonX Orchestrate deployments, including \
+                     authentication, access control, and data protection.".to_string(),
+            url: "https://www.ibm.com/docs/watsonx-orchestrate/security".to_string(),
+            category: DocCategory::Admin.to_string(),
+            relevance: 0.0,
+        },
+        DocResult {
+            title: "Workflow Automation Patterns".to_string(),
+            content: "Common workflow patterns and best practices for building efficient \
+                     automations in WatsonX Orchestrate.".to_string(),
+            url: "https://www.ibm.com/docs/watsonx-orchestrate/workflows".to_string(),
+            category: DocCategory::User.to_string(),
+            relevance: 0.0,
+        },
+    ];
+
+    let mut filtered: Vec<DocResult> = all_docs
+        .into_iter()
+        .filter_map(|mut doc| {
+            // Filter by category if specified
+            if let Some(cat) = category {
+                if doc.category != cat.as_str() {
+                    return None;
+                }
+            }
+
+            doc.relevance = relevance(query, &doc.title, &doc.content, fuzzy, None);
+            (doc.relevance > 0.0).then_some(doc)
+        })
+        .collect();
+
+    // Sort by relevance
+    filtered.sort_by(|a, b| b.relevance.total_cmp(&a.relevance));
+
+    // Limit results
+    let mut filtered = dedup_docs(filtered);
+    filtered.truncate(limit);
+
+    filtered
+}
+
+/// Drop repeated docs, keyed on url (or title for docs without one)
+///
+/// Expects `docs` sorted by descending relevance, so the most relevant copy
+/// of each doc is the one kept.
+fn dedup_docs(docs: Vec<DocResult>) -> Vec<DocResult> {
+    let mut seen = HashSet::new();
+    docs.into_iter()
+        .filter(|doc| {
+            let key = if doc.url.is_empty() {
+                doc.title.clone()
+            } else {
+                doc.url.clone()
+            };
+            seen.insert(key)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::DocRecord;
+    use chrono::Utc;
+    use surrealdb::engine::any;
+
+    #[tokio::test]
+    async fn test_surreal_backend_connects_with_config() {
+        let backend = SurrealBackend::with_config(DbConfig::memory());
+        assert!(backend.client().await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_mock_backend_filters_by_category() {
+        let docs = MockBackend
+            .search("guide", Some(DocCategory::Admin), 5)
+            .await
+            .unwrap();
+
+        assert!(!docs.is_empty());
+        assert!(docs.iter().all(|d| d.category == "admin"));
+    }
+
+    #[test]
+    fn test_dedup_keeps_most_relevant_copy() {
+        let doc = |title: &str, url: &str, relevance: f32| DocResult {
+            title: title.to_string(),
+            content: String::new(),
+            url: url.to_string(),
+            category: "admin".to_string(),
+            relevance,
+        };
+        let docs = vec![
+            doc("Admin Setup Guide", "https://example.com/admin", 0.9),
+            doc("Admin Setup Guide (copy)", "https://example.com/admin", 0.5),
+            doc("Untitled draft", "", 0.4),
+            doc("Untitled draft", "", 0.2),
+        ];
+
+        let deduped = dedup_docs(docs);
+
+        assert_eq!(deduped.len(), 2);
+        assert_eq!(deduped[0].title, "Admin Setup Guide");
+        assert_eq!(deduped[0].relevance, 0.9);
+        assert_eq!(deduped[1].relevance, 0.4);
+    }
+
+    #[tokio::test]
+    async fn test_failed_connection_is_not_retried() {
+        let backend = SurrealBackend {
+            db_port: 1,
+            ..SurrealBackend::new()
+        };
+
+        for _ in 0..2 {
+            let docs = backend.search("setup", None, 5).await.unwrap();
+            assert!(!docs.is_empty());
+        }
+
+        // The failed first attempt is cached rather than retried per call
+        assert!(matches!(backend.client.get(), Some(None)));
+    }
+
+    #[tokio::test]
+    async fn test_failed_connection_without_mock_fallback_is_an_error() {
+        let backend = SurrealBackend {
+            db_port: 1,
+            ..SurrealBackend::new()
+        }
+        .allow_mock_fallback(false);
+
+        let error = backend.search("setup", None, 5).await.unwrap_err();
+        assert_eq!(
+            ToolErrorKind::from_message(&error.to_string()),
+            ToolErrorKind::Unavailable
+        );
+    }
+
+    #[tokio::test]
+    async fn test_empty_database_returns_no_docs() {
+        let client = any::connect("mem://").await.unwrap();
+        client.use_ns("wxorca").use_db("main").await.unwrap();
+
+        for allow in [true, false] {
+            let backend = SurrealBackend::with_client(client.clone()).allow_mock_fallback(allow);
+            let docs = backend.search("setup", None, 5).await.unwrap();
+            assert!(docs.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_index_reloaded_after_rebuild() {
+        let db = Database::connect(&DbConfig::memory()).await.unwrap();
+        db.init_schema().await.unwrap();
+        let doc = |title: &str, content: &str| DocRecord {
+            id: None,
+            title: title.to_string(),
+            content: content.to_string(),
+            category: "usage".to_string(),
+            url: None,
+            embedding: Vec::new(),
+            created_at: Utc::now(),
+        };
+        db.bulk_add_docs(&[doc("Skill basics", "Create a skill")])
+            .await
+            .unwrap();
+        let backend = SurrealBackend::with_client(db.client().clone());
+        assert!(backend.search_index(db.client()).await.unwrap().is_none());
+
+        db.rebuild_search_index().await.unwrap();
+        let index = backend.search_index(db.client()).await.unwrap().unwrap();
+        let cached = backend.search_index(db.client()).await.unwrap().unwrap();
+        assert!(Arc::ptr_eq(&index, &cached));
+
+        db.bulk_add_docs(&[doc("Webhooks", "Trigger a skill from a webhook")])
+            .await
+            .unwrap();
+        db.rebuild_search_index().await.unwrap();
+        let rebuilt = backend.search_index(db.client()).await.unwrap().unwrap();
+        assert!(!Arc::ptr_eq(&index, &rebuilt));
+        assert_eq!(rebuilt.vocabulary["webhook"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_duplicates_dont_use_up_the_limit() {
+        let db = Database::connect(&DbConfig::memory()).await.unwrap();
+        db.init_schema().await.unwrap();
+        let doc = |title: &str, url: &str| DocRecord {
+            id: None,
+            title: title.to_string(),
+            content: format!("{} for single sign-on", title),
+            category: "admin".to_string(),
+            url: Some(url.to_string()),
+            embedding: Vec::new(),
+            created_at: Utc::now(),
+        };
+        db.bulk_add_docs(&[
+            doc("SSO setup", "https://example.com/sso"),
+            doc("SSO setup (mirror)", "https://example.com/sso"),
+            doc("SSO setup (old)", "https://example.com/sso"),
+            doc("SAML reference", "https://example.com/saml"),
+            doc("OIDC reference", "https://example.com/oidc"),
+        ])
+        .await
+        .unwrap();
+
+        let backend = SurrealBackend::with_client(db.client().clone());
+        let docs = backend.search("sso setup", None, 2).await.unwrap();
+
+        assert_eq!(docs.len(), 2);
+        assert_ne!(docs[0].url, docs[1].url);
+    }
+}
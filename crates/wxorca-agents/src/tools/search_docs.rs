@@ -1,57 +1,46 @@
 //! Search WatsonX Orchestrate documentation tool
 
+use super::error::ToolErrorKind;
+use super::search_backends::{DocSearchBackend, SurrealBackend};
+use crate::state::DocCategory;
+use crate::text::STOPWORDS;
 use async_trait::async_trait;
 use oxidizedgraph::prelude::{NodeError, Tool};
 use serde::{Deserialize, Serialize};
-use surrealdb::{
-    engine::remote::ws::{Client, Ws},
-    opt::auth::Root,
-    Surreal,
-};
-use tracing;
+use std::collections::{HashMap, HashSet};
+use surrealdb::{engine::any::Any, Surreal};
 
 /// Tool for searching WatsonX Orchestrate documentation
+///
+/// Lookups are delegated to a [`DocSearchBackend`]. The default is
+/// [`SurrealBackend`], which serves mock docs when SurrealDB is unavailable
+/// unless built with [`SearchDocsTool::without_mock_fallback`].
 pub struct SearchDocsTool {
-    db_host: String,
-    db_port: u16,
-    db_user: String,
-    db_pass: String,
+    backend: Box<dyn DocSearchBackend>,
 }
 
 impl SearchDocsTool {
     pub fn new() -> Self {
-        Self {
-            db_host: std::env::var("SURREAL_HOST").unwrap_or_else(|_| "localhost".to_string()),
-            db_port: std::env::var("SURREAL_PORT")
-                .ok()
-                .and_then(|p| p.parse().ok())
-                .unwrap_or(8000),
-            db_user: std::env::var("SURREAL_USER").unwrap_or_else(|_| "root".to_string()),
-            db_pass: std::env::var("SURREAL_PASS").unwrap_or_else(|_| "root".to_string()),
-        }
+        Self::with_backend(SurrealBackend::new())
     }
 
-    async fn connect_db(&self) -> Result<Surreal<Client>, NodeError> {
-        let url = format!("{}:{}", self.db_host, self.db_port);
-        let client = Surreal::new::<Ws>(&url)
-            .await
-            .map_err(|e| NodeError::ToolError(format!("Failed to connect to SurrealDB: {}", e)))?;
-
-        client
-            .signin(Root {
-                username: &self.db_user,
-                password: &self.db_pass,
-            })
-            .await
-            .map_err(|e| NodeError::ToolError(format!("Failed to authenticate: {}", e)))?;
+    /// Fail with a tool error when SurrealDB can't be searched instead of
+    /// serving mock docs, for production deployments
+    pub fn without_mock_fallback() -> Self {
+        Self::with_backend(SurrealBackend::new().allow_mock_fallback(false))
+    }
 
-        client
-            .use_ns("wxorca")
-            .use_db("main")
-            .await
-            .map_err(|e| NodeError::ToolError(format!("Failed to select database: {}", e)))?;
+    /// Use an already connected client (e.g. from `Database::client`) instead
+    /// of connecting from environment settings
+    pub fn with_client(client: Surreal<Any>) -> Self {
+        Self::with_backend(SurrealBackend::with_client(client))
+    }
 
-        Ok(client)
+    /// Search with a custom backend
+    pub fn with_backend(backend: impl DocSearchBackend + 'static) -> Self {
+        Self {
+            backend: Box::new(backend),
+        }
     }
 }
 
@@ -68,19 +57,31 @@ struct SearchDocsInput {
     limit: usize,
     #[serde(default)]
     category: Option<String>,
+    /// Boost docs that appeared in highly rated sessions
+    #[serde(default)]
+    use_feedback: bool,
+    /// Drop results scoring below this relevance
+    #[serde(default)]
+    min_relevance: Option<f32>,
+    /// Match query terms that are misspelled by a character or two
+    #[serde(default)]
+    fuzzy: bool,
 }
 
 fn default_limit() -> usize {
     5
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct DocResult {
-    title: String,
-    content: String,
-    url: String,
-    category: String,
-    relevance: f32,
+/// A documentation search result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocResult {
+    pub title: String,
+    /// Doc content, possibly cut to a snippet around the first query match
+    pub content: String,
+    pub url: String,
+    pub category: String,
+    /// Relevance to the query, from 0.0 to 1.0
+    pub relevance: f32,
 }
 
 #[async_trait]
@@ -110,7 +111,24 @@ impl Tool for SearchDocsTool {
                 },
                 "category": {
                     "type": "string",
-                    "description": "Optional category filter (e.g., 'admin', 'user', 'api', 'troubleshooting')"
+                    "description": "Optional category filter",
+                    "enum": DocCategory::accepted_names()
+                },
+                "use_feedback": {
+                    "type": "boolean",
+                    "description": "Boost docs that appeared in highly rated conversations (default: false)",
+                    "default": false
+                },
+                "min_relevance": {
+                    "type": "number",
+                    "description": "Only return results with at least this relevance, from 0.0 to 1.0",
+                    "minimum": 0.0,
+                    "maximum": 1.0
+                },
+                "fuzzy": {
+                    "type": "boolean",
+                    "description": "Tolerate typos in the query, e.g. \"authentification\" (default: false)",
+                    "default": false
                 }
             },
             "required": ["query"]
@@ -118,220 +136,387 @@ impl Tool for SearchDocsTool {
     }
 
     async fn execute(&self, arguments: serde_json::Value) -> Result<String, NodeError> {
-        let input: SearchDocsInput = serde_json::from_value(arguments)
-            .map_err(|e| NodeError::ToolError(format!("Invalid arguments: {}", e)))?;
-
-        // Try to query SurrealDB, fall back to mock data if connection fails
-        let results = match self.query_surreal_db(&input).await {
-            Ok(docs) if !docs.is_empty() => docs,
-            Ok(_) => {
-                // No results from DB, use mock data
-                get_mock_docs(&input.query, input.limit, input.category.as_deref())
-            }
-            Err(e) => {
-                tracing::warn!("SurrealDB query failed, using mock data: {}", e);
-                get_mock_docs(&input.query, input.limit, input.category.as_deref())
-            }
+        let input: SearchDocsInput = serde_json::from_value(arguments).map_err(|e| {
+            ToolErrorKind::InvalidArguments.error(format!("Invalid arguments: {}", e))
+        })?;
+
+        // Reject unknown categories rather than silently matching nothing
+        let category = input
+            .category
+            .as_deref()
+            .map(str::parse::<DocCategory>)
+            .transpose()
+            .map_err(|e| ToolErrorKind::InvalidArguments.error(e))?;
+
+        let mut results = if input.fuzzy {
+            self.backend
+                .search_fuzzy(&input.query, category, input.limit, input.use_feedback)
+                .await?
+        } else if input.use_feedback {
+            self.backend
+                .search_with_feedback(&input.query, category, input.limit)
+                .await?
+        } else {
+            self.backend
+                .search(&input.query, category, input.limit)
+                .await?
         };
+        // An empty list lets the agent say nothing relevant was found
+        if let Some(min_relevance) = input.min_relevance {
+            results.retain(|doc| doc.relevance >= min_relevance);
+        }
 
-        let response = serde_json::to_string_pretty(&results)
-            .map_err(|e| NodeError::ToolError(format!("Failed to serialize results: {}", e)))?;
+        let response = serde_json::to_string_pretty(&results).map_err(|e| {
+            ToolErrorKind::Internal.error(format!("Failed to serialize results: {}", e))
+        })?;
 
         Ok(response)
     }
 }
 
-impl SearchDocsTool {
-    async fn query_surreal_db(&self, input: &SearchDocsInput) -> Result<Vec<DocResult>, NodeError> {
-        let client = self.connect_db().await?;
-
-        // Build query based on whether category filter is present
-        let query_str = if input.category.is_some() {
-            r#"
-            SELECT title, content, url, category FROM wxo_docs
-            WHERE category = $category
-            LIMIT $limit
-            "#
-        } else {
-            r#"
-            SELECT title, content, url, category FROM wxo_docs
-            LIMIT $limit
-            "#
-        };
+/// Weight of title matches in [`score_relevance`]; content gets the remainder
+const TITLE_WEIGHT: f32 = 0.6;
+
+/// Term-frequency saturation constant (as in BM25's `k1`)
+const TF_SATURATION: f32 = 1.2;
 
-        let mut query = client.query(query_str).bind(("limit", input.limit));
+/// Term length from which [`score_relevance_fuzzy`] allows two edits instead of one
+const FUZZY_LONG_TERM: usize = 8;
+
+/// Share of the blended relevance that comes from feedback
+const FEEDBACK_WEIGHT: f32 = 0.25;
+
+/// Number of highly rated sessions at which the feedback signal reaches half strength
+const FEEDBACK_SATURATION: f32 = 3.0;
+
+/// Query terms shorter than this don't anchor a snippet, so words like "do"
+/// or "is" don't pick the window
+const MIN_SNIPPET_TERM_CHARS: usize = 3;
+
+/// Blend textual relevance with how often a doc appeared in highly rated sessions
+///
+/// The result is `(1 - FEEDBACK_WEIGHT) * relevance + FEEDBACK_WEIGHT * signal`,
+/// where `signal = sessions / (sessions + FEEDBACK_SATURATION)` grows towards
+/// 1.0 as the doc appears in more well-rated sessions. Feedback only boosts
+/// docs that match the query at all, so an irrelevant doc stays at 0.0.
+pub(crate) fn blend_feedback(relevance: f32, sessions: usize) -> f32 {
+    if relevance <= 0.0 {
+        return 0.0;
+    }
+
+    let sessions = sessions as f32;
+    let signal = sessions / (sessions + FEEDBACK_SATURATION);
+    (1.0 - FEEDBACK_WEIGHT) * relevance + FEEDBACK_WEIGHT * signal
+}
+
+/// Shorten `text` to at most `max_chars` characters, appending "…" if cut
+///
+/// Cuts on a character boundary, so multi-byte text can't cause a panic.
+pub(crate) fn truncate_excerpt(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Cut a `window`-character snippet of `content` centered on the first word
+/// matching a query term, marking cut ends with "…"
+///
+/// Terms match words they are a prefix of, as in [`score_relevance`]. If no
+/// term matches, the snippet is the leading excerpt of `content`.
+pub fn make_snippet(content: &str, query: &str, window: usize) -> String {
+    let terms: Vec<String> = tokenize(query)
+        .into_iter()
+        .filter(|term| term.chars().count() >= MIN_SNIPPET_TERM_CHARS)
+        .collect();
+    let hit = words(content).into_iter().find(|(_, word)| {
+        let word = word.to_lowercase();
+        terms.iter().any(|term| word.starts_with(term.as_str()))
+    });
+    let Some((hit, _)) = hit else {
+        return truncate_excerpt(content, window);
+    };
+
+    let total = content.chars().count();
+    if total <= window {
+        return content.to_string();
+    }
+    let hit = content[..hit].chars().count();
+    let start = hit.saturating_sub(window / 2).min(total - window);
+    let end = start + window;
+    let byte = |index: usize| {
+        content
+            .char_indices()
+            .nth(index)
+            .map_or(content.len(), |(byte, _)| byte)
+    };
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push('…');
+    }
+    snippet.push_str(content[byte(start)..byte(end)].trim());
+    if end < total {
+        snippet.push('…');
+    }
+    snippet
+}
 
-        if let Some(ref cat) = input.category {
-            query = query.bind(("category", cat.clone()));
+/// Split `text` into words, with the byte offset each one starts at
+fn words(text: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in text
+        .char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+    {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                words.push((s, &text[s..i]));
+                start = None;
+            }
+            _ => {}
         }
+    }
+    words
+}
 
-        let mut result = query
-            .await
-            .map_err(|e| NodeError::ToolError(format!("Query failed: {}", e)))?;
-
-        #[derive(Debug, Deserialize)]
-        struct DbDoc {
-            title: String,
-            content: String,
-            url: String,
-            category: String,
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_lowercase())
+        .collect()
+}
+
+/// The words of `text` that carry search meaning, leaving out stopwords
+fn meaningful_tokens(text: &str) -> Vec<String> {
+    tokenize(text)
+        .into_iter()
+        .filter(|token| !STOPWORDS.contains(&token.as_str()))
+        .collect()
+}
+
+/// Score how relevant a document is to a query, from 0.0 (no match) to 1.0
+///
+/// Each query term contributes a title component (present or not) and a
+/// content component that grows with term frequency but saturates, BM25
+/// style. Title matches are weighted higher than content matches, and the
+/// total is averaged over the query terms. A term matches any word it is a
+/// prefix of, so "skill" matches "skills". Stopwords in the query are
+/// ignored, so "how do I" doesn't dilute the score or match "domain".
+pub fn score_relevance(query: &str, title: &str, content: &str) -> f32 {
+    score_terms(query, title, content, false, None)
+}
+
+/// Like [`score_relevance`], but a term also matches words within a small
+/// edit distance of it
+///
+/// Terms of up to 3 characters must match exactly, longer terms may be one
+/// edit away and terms of 8 or more characters two, so "authentification"
+/// matches "authentication".
+pub fn score_relevance_fuzzy(query: &str, title: &str, content: &str) -> f32 {
+    score_terms(query, title, content, true, None)
+}
+
+/// Like [`score_relevance`] (or [`score_relevance_fuzzy`] if `fuzzy`), but
+/// average the query terms weighted by their IDF in `stats`, so matching a
+/// rare term counts for more than matching a common one
+pub fn score_relevance_weighted(
+    query: &str,
+    title: &str,
+    content: &str,
+    fuzzy: bool,
+    stats: &TermStats,
+) -> f32 {
+    score_terms(query, title, content, fuzzy, Some(stats))
+}
+
+fn score_terms(
+    query: &str,
+    title: &str,
+    content: &str,
+    fuzzy: bool,
+    stats: Option<&TermStats>,
+) -> f32 {
+    let terms = meaningful_tokens(query);
+    if terms.is_empty() {
+        return 0.0;
+    }
+
+    let title_tokens = tokenize(title);
+    let content_tokens = tokenize(content);
+
+    let (total, weights) = terms
+        .iter()
+        .map(|term| {
+            let in_title = title_tokens.iter().any(|t| term_matches(term, t, fuzzy));
+            let tf = content_tokens
+                .iter()
+                .filter(|t| term_matches(term, t, fuzzy))
+                .count() as f32;
+
+            let title_score = if in_title { TITLE_WEIGHT } else { 0.0 };
+            let content_score = (1.0 - TITLE_WEIGHT) * tf / (tf + TF_SATURATION);
+            let weight = stats.map_or(1.0, |stats| stats.idf(term));
+            (weight * (title_score + content_score), weight)
+        })
+        .fold((0.0, 0.0), |(total, weights), (score, weight)| {
+            (total + score, weights + weight)
+        });
+
+    if weights <= 0.0 {
+        return 0.0;
+    }
+    (total / weights).clamp(0.0, 1.0)
+}
+
+/// Similarity of two texts, from 0.0 (no words in common) to 1.0 (the same
+/// words, in the same proportions)
+///
+/// The cosine of their word count vectors, so it is symmetric and ignores
+/// case, punctuation and word order. Stopwords are left out, so questions
+/// that only share their phrasing ("How do I ...") don't look alike.
+pub fn text_similarity(a: &str, b: &str) -> f32 {
+    let counts = |text: &str| {
+        let mut counts: HashMap<String, f32> = HashMap::new();
+        for token in meaningful_tokens(text) {
+            *counts.entry(token).or_insert(0.0) += 1.0;
         }
+        counts
+    };
+    let (a, b) = (counts(a), counts(b));
+    let norm = |counts: &HashMap<String, f32>| counts.values().map(|c| c * c).sum::<f32>().sqrt();
+    let (norm_a, norm_b) = (norm(&a), norm(&b));
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
 
-        let db_docs: Vec<DbDoc> = result.take(0).map_err(|e| {
-            NodeError::ToolError(format!("Failed to parse results: {}", e))
-        })?;
+    let dot: f32 = a
+        .iter()
+        .filter_map(|(token, count)| b.get(token).map(|other| count * other))
+        .sum();
+    (dot / (norm_a * norm_b)).clamp(0.0, 1.0)
+}
 
-        // Convert to DocResult with relevance scoring
-        let query_lower = input.query.to_lowercase();
-        let results: Vec<DocResult> = db_docs
-            .into_iter()
-            .map(|doc| {
-                // Simple relevance scoring based on query match
-                let title_lower = doc.title.to_lowercase();
-                let content_lower = doc.content.to_lowercase();
-                let mut relevance = 0.5f32;
-
-                if title_lower.contains(&query_lower) {
-                    relevance += 0.3;
-                }
-                if content_lower.contains(&query_lower) {
-                    relevance += 0.2;
-                }
-                for word in query_lower.split_whitespace() {
-                    if title_lower.contains(word) {
-                        relevance += 0.05;
-                    }
-                    if content_lower.contains(word) {
-                        relevance += 0.03;
-                    }
-                }
-                relevance = relevance.min(1.0);
-
-                DocResult {
-                    title: doc.title,
-                    content: if doc.content.len() > 500 {
-                        format!("{}...", &doc.content[..500])
-                    } else {
-                        doc.content
-                    },
-                    url: doc.url,
-                    category: doc.category,
-                    relevance,
-                }
+/// Corpus-wide term statistics used to weight query terms by rarity
+///
+/// Built by `Database::rebuild_search_index`; a search loads the entries
+/// for its own terms only.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TermStats {
+    /// Number of documents in the corpus
+    pub doc_count: usize,
+    /// Number of documents each term appears in
+    pub doc_freqs: HashMap<String, usize>,
+}
+
+impl TermStats {
+    /// BM25 inverse document frequency of `term`
+    ///
+    /// Terms the index doesn't know get the weight of a term in no
+    /// documents. With an empty corpus every term weighs the same.
+    pub fn idf(&self, term: &str) -> f32 {
+        if self.doc_count == 0 {
+            return 1.0;
+        }
+        let n = self.doc_count as f32;
+        let df = self.doc_freqs.get(term).copied().unwrap_or(0) as f32;
+        (1.0 + (n - df + 0.5) / (df + 0.5)).ln()
+    }
+
+    /// Stats for `terms`, matched against the index `vocabulary` (document
+    /// frequency by word) the way scoring matches them: by prefix, or
+    /// within a few typos when `fuzzy`
+    ///
+    /// A term matching several words gets the frequency of the most common
+    /// one, a lower bound on the documents it matches.
+    pub fn matching(
+        doc_count: usize,
+        terms: &[String],
+        vocabulary: &HashMap<String, usize>,
+        fuzzy: bool,
+    ) -> Self {
+        let doc_freqs = terms
+            .iter()
+            .filter_map(|term| {
+                vocabulary
+                    .iter()
+                    .filter(|(word, _)| term_matches(term, word, fuzzy))
+                    .map(|(_, &freq)| freq)
+                    .max()
+                    .map(|freq| (term.clone(), freq))
             })
             .collect();
+        Self {
+            doc_count,
+            doc_freqs,
+        }
+    }
+}
 
-        Ok(results)
+/// Count the documents each term appears in, from `(title, content)` pairs
+pub fn document_frequencies<'a>(
+    docs: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> HashMap<String, usize> {
+    let mut freqs = HashMap::new();
+    for (title, content) in docs {
+        let terms: HashSet<String> = tokenize(title)
+            .into_iter()
+            .chain(tokenize(content))
+            .collect();
+        for term in terms {
+            *freqs.entry(term).or_insert(0) += 1;
+        }
     }
+    freqs
 }
 
-fn get_mock_docs(query: &str, limit: usize, category: Option<&str>) -> Vec<DocResult> {
-    // Mock documentation database
-    let all_docs = vec![
-        DocResult {
-            title: "Getting Started with WatsonX Orchestrate".to_string(),
-            content: "WatsonX Orchestrate is an AI-powered automation platform that helps you \
-                     work more efficiently by automating repetitive tasks and providing \
-                     intelligent assistance.".to_string(),
-            url: "https://www.ibm.com/docs/watsonx-orchestrate/getting-started".to_string(),
-            category: "user".to_string(),
-            relevance: 0.95,
-        },
-        DocResult {
-            title: "Admin Setup Guide".to_string(),
-            content: "This guide walks administrators through the initial setup of WatsonX \
-                     Orchestrate, including user management, security configuration, and \
-                     integration setup.".to_string(),
-            url: "https://www.ibm.com/docs/watsonx-orchestrate/admin-guide".to_string(),
-            category: "admin".to_string(),
-            relevance: 0.92,
-        },
-        DocResult {
-            title: "Creating Custom Skills".to_string(),
-            content: "Learn how to create custom skills in WatsonX Orchestrate. Skills are \
-                     reusable automation components that can be combined into workflows.".to_string(),
-            url: "https://www.ibm.com/docs/watsonx-orchestrate/skills".to_string(),
-            category: "user".to_string(),
-            relevance: 0.88,
-        },
-        DocResult {
-            title: "API Reference".to_string(),
-            content: "Complete API reference for WatsonX Orchestrate, including authentication, \
-                     skill management, and workflow execution endpoints.".to_string(),
-            url: "https://www.ibm.com/docs/watsonx-orchestrate/api".to_string(),
-            category: "api".to_string(),
-            relevance: 0.85,
-        },
-        DocResult {
-            title: "Troubleshooting Common Issues".to_string(),
-            content: "Solutions for common issues including authentication failures, skill \
-                     execution errors, and integration problems.".to_string(),
-            url: "https://www.ibm.com/docs/watsonx-orchestrate/troubleshooting".to_string(),
-            category: "troubleshooting".to_string(),
-            relevance: 0.82,
-        },
-        DocResult {
-            title: "Integration with Salesforce".to_string(),
-            content: "Step-by-step guide for integrating WatsonX Orchestrate with Salesforce, \
-                     enabling CRM automation and data synchronization.".to_string(),
-            url: "https://www.ibm.com/docs/watsonx-orchestrate/integrations/salesforce".to_string(),
-            category: "admin".to_string(),
-            relevance: 0.78,
-        },
-        DocResult {
-            title: "Security Best Practices".to_string(),
-            content: "Security recommendations for WatsonX Orchestrate deployments, including \
-                     authentication, access control, and data protection.".to_string(),
-            url: "https://www.ibm.com/docs/watsonx-orchestrate/security".to_string(),
-            category: "admin".to_string(),
-            relevance: 0.75,
-        },
-        DocResult {
-            title: "Workflow Automation Patterns".to_string(),
-            content: "Common workflow patterns and best practices for building efficient \
-                     automations in WatsonX Orchestrate.".to_string(),
-            url: "https://www.ibm.com/docs/watsonx-orchestrate/workflows".to_string(),
-            category: "user".to_string(),
-            relevance: 0.72,
-        },
-    ];
-
-    let query_lower = query.to_lowercase();
-
-    let mut filtered: Vec<DocResult> = all_docs
-        .into_iter()
-        .filter(|doc| {
-            // Filter by category if specified
-            if let Some(cat) = category {
-                if doc.category != cat {
-                    return false;
-                }
-            }
+/// The distinct terms of `query`, as looked up in [`TermStats`]
+pub(crate) fn query_terms(query: &str) -> Vec<String> {
+    let mut terms = meaningful_tokens(query);
+    terms.sort();
+    terms.dedup();
+    terms
+}
 
-            // Simple relevance matching
-            let title_lower = doc.title.to_lowercase();
-            let content_lower = doc.content.to_lowercase();
+fn term_matches(term: &str, token: &str, fuzzy: bool) -> bool {
+    if token.starts_with(term) {
+        return true;
+    }
+    if !fuzzy {
+        return false;
+    }
 
-            title_lower.contains(&query_lower)
-                || content_lower.contains(&query_lower)
-                || query_lower.split_whitespace().any(|word| {
-                    title_lower.contains(word) || content_lower.contains(word)
-                })
-        })
-        .collect();
+    let max_edits = match term.chars().count() {
+        0..=3 => return false,
+        n if n < FUZZY_LONG_TERM => 1,
+        _ => 2,
+    };
+    edit_distance(term, token) <= max_edits
+}
 
-    // Sort by relevance
-    filtered.sort_by(|a, b| b.relevance.partial_cmp(&a.relevance).unwrap());
+/// Levenshtein distance between `a` and `b`, counted in characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
 
-    // Limit results
-    filtered.truncate(limit);
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
 
-    filtered
+    previous[b.len()]
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tools::MockBackend;
 
     #[tokio::test]
     async fn test_search_docs() {
@@ -367,4 +552,319 @@ mod tests {
             assert_eq!(doc.category, "admin");
         }
     }
+
+    #[tokio::test]
+    async fn test_search_docs_category_validation() {
+        let tool = SearchDocsTool::new();
+
+        // Aliases resolve to the stored category name
+        let result = tool
+            .execute(serde_json::json!({
+                "query": "error",
+                "category": "troubleshoot"
+            }))
+            .await
+            .unwrap();
+        let docs: Vec<DocResult> = serde_json::from_str(&result).unwrap();
+        assert!(!docs.is_empty());
+        assert!(docs.iter().all(|d| d.category == "troubleshooting"));
+        // The schema offers the same names the tool accepts
+        let schema = tool.parameters_schema();
+        let names = schema["properties"]["category"]["enum"].as_array().unwrap();
+        assert!(names.contains(&serde_json::json!("troubleshoot")));
+        assert!(names.contains(&serde_json::json!("release_notes")));
+
+        let err = tool
+            .execute(serde_json::json!({
+                "query": "skills",
+                "category": "skills"
+            }))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Unknown documentation category"));
+    }
+
+    /// Backend that returns one fixed doc per search
+    struct FixedBackend;
+
+    #[async_trait]
+    impl DocSearchBackend for FixedBackend {
+        async fn search(
+            &self,
+            query: &str,
+            category: Option<DocCategory>,
+            _limit: usize,
+        ) -> Result<Vec<DocResult>, NodeError> {
+            Ok(vec![DocResult {
+                title: format!("Result for {}", query),
+                content: String::new(),
+                url: "https://search.example.com/doc".to_string(),
+                category: category.unwrap_or_default().to_string(),
+                relevance: 1.0,
+            }])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_docs_with_custom_backend() {
+        let tool = SearchDocsTool::with_backend(FixedBackend);
+
+        let result = tool
+            .execute(serde_json::json!({
+                "query": "sso",
+                "category": "admin",
+                "use_feedback": true
+            }))
+            .await
+            .unwrap();
+
+        let docs: Vec<DocResult> = serde_json::from_str(&result).unwrap();
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].title, "Result for sso");
+        assert_eq!(docs[0].category, "admin");
+    }
+
+    /// Backend that returns one strong and one weak match
+    struct ScoredBackend;
+
+    #[async_trait]
+    impl DocSearchBackend for ScoredBackend {
+        async fn search(
+            &self,
+            _query: &str,
+            _category: Option<DocCategory>,
+            _limit: usize,
+        ) -> Result<Vec<DocResult>, NodeError> {
+            Ok([("Strong", 0.9), ("Weak", 0.3)]
+                .into_iter()
+                .map(|(title, relevance)| DocResult {
+                    title: title.to_string(),
+                    content: String::new(),
+                    url: format!("https://search.example.com/{}", title),
+                    category: DocCategory::default().to_string(),
+                    relevance,
+                })
+                .collect())
+        }
+    }
+
+    async fn search_scored(arguments: serde_json::Value) -> Vec<DocResult> {
+        let tool = SearchDocsTool::with_backend(ScoredBackend);
+        let result = tool.execute(arguments).await.unwrap();
+        serde_json::from_str(&result).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_search_docs_min_relevance() {
+        let docs = search_scored(serde_json::json!({"query": "sso", "min_relevance": 0.5})).await;
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].title, "Strong");
+
+        // Without a threshold every result is kept
+        let docs = search_scored(serde_json::json!({"query": "sso"})).await;
+        assert_eq!(docs.len(), 2);
+
+        let docs = search_scored(serde_json::json!({"query": "sso", "min_relevance": 0.95})).await;
+        assert!(docs.is_empty());
+    }
+
+    #[test]
+    fn test_blend_feedback() {
+        // Feedback can lift a weaker text match above a stronger one
+        let popular = blend_feedback(0.5, 10);
+        let unrated = blend_feedback(0.6, 0);
+        assert!(popular > unrated);
+        assert!(blend_feedback(1.0, 1000) <= 1.0);
+        assert_eq!(blend_feedback(0.0, 10), 0.0);
+    }
+
+    #[test]
+    fn test_truncate_excerpt_respects_char_boundaries() {
+        // 'é' is two bytes, so byte index 5 falls inside a character
+        let text = "café résumé";
+        assert_eq!(truncate_excerpt(text, 4), "café…");
+        assert_eq!(truncate_excerpt(text, 20), text);
+        assert_eq!(truncate_excerpt("🚀🚀🚀", 2), "🚀🚀…");
+    }
+
+    #[test]
+    fn test_snippet_centers_on_match() {
+        let content = format!(
+            "{}SSO is configured under Security settings. {}",
+            "Intro text. ".repeat(10),
+            "More text. ".repeat(10)
+        );
+
+        let snippet = make_snippet(&content, "how do I configure sso", 40);
+
+        assert!(snippet.starts_with('…'), "{}", snippet);
+        assert!(snippet.ends_with('…'), "{}", snippet);
+        assert!(snippet.contains("SSO is configured"), "{}", snippet);
+        assert!(snippet.chars().count() <= 42);
+    }
+
+    #[test]
+    fn test_snippet_match_at_end() {
+        let content = format!("{}Rotate keys often.", "Intro text. ".repeat(10));
+
+        let snippet = make_snippet(&content, "rotate keys", 40);
+
+        assert!(snippet.starts_with('…'), "{}", snippet);
+        assert!(snippet.ends_with("Rotate keys often."), "{}", snippet);
+        assert!(snippet.chars().count() <= 41);
+
+        // Without a match the snippet is the leading excerpt
+        assert_eq!(make_snippet(&content, "billing", 11), "Intro text.…");
+        assert_eq!(make_snippet("Rotate keys", "rotate", 40), "Rotate keys");
+    }
+
+    #[test]
+    fn test_score_relevance_prefers_title_matches() {
+        let title_match = score_relevance(
+            "admin setup guide",
+            "Admin Setup Guide",
+            "How to get started.",
+        );
+        let content_match = score_relevance(
+            "admin setup guide",
+            "Getting Started",
+            "This admin guide covers setup, and the setup guide covers admin setup.",
+        );
+
+        assert!(title_match > content_match);
+        assert!((0.0..=1.0).contains(&title_match));
+        assert!((0.0..=1.0).contains(&content_match));
+    }
+
+    #[test]
+    fn test_score_relevance_multi_word_and_no_match() {
+        let both = score_relevance("custom skills", "Creating Custom Skills", "");
+        let one = score_relevance("custom skills", "Custom Actions", "");
+
+        assert!(both > one);
+        assert!(one > 0.0);
+        assert_eq!(
+            score_relevance("salesforce", "API Reference", "Endpoints"),
+            0.0
+        );
+        assert_eq!(score_relevance("", "API Reference", "Endpoints"), 0.0);
+    }
+
+    #[test]
+    fn test_score_relevance_ignores_stopwords() {
+        let title = "Custom Skills";
+        let content = "Create a custom skill";
+        assert_eq!(
+            score_relevance("How do I create a custom skill?", title, content),
+            score_relevance("create custom skill", title, content)
+        );
+        // "do" would otherwise match "domain" as a prefix
+        assert_eq!(
+            score_relevance("how do I", "Domain setup", "Add a domain"),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_idf_weighting_favors_rare_terms() {
+        let docs = [
+            ("Skill basics", "Create a skill"),
+            ("Skill catalog", "Browse skill listings"),
+            ("Webhooks", "Trigger a skill from a webhook"),
+        ];
+        let doc_freqs = document_frequencies(docs);
+        assert_eq!(doc_freqs["skill"], 3);
+        assert_eq!(doc_freqs["webhook"], 1);
+        assert_eq!(doc_freqs["webhooks"], 1);
+
+        let stats = TermStats {
+            doc_count: docs.len(),
+            doc_freqs,
+        };
+        assert!(stats.idf("webhook") > stats.idf("skill"));
+        assert!(stats.idf("unknown") > stats.idf("webhook"));
+
+        // Each doc matches one of the two terms; the rare one now wins
+        let query = "skill webhook";
+        let common = ("Skill basics", "Create a skill");
+        let rare = ("Webhooks", "Trigger from a webhook");
+        assert_eq!(
+            score_relevance(query, common.0, common.1),
+            score_relevance(query, rare.0, rare.1)
+        );
+        assert!(
+            score_relevance_weighted(query, rare.0, rare.1, false, &stats)
+                > score_relevance_weighted(query, common.0, common.1, false, &stats)
+        );
+
+        // Terms are counted the way they match: by prefix, or with typos
+        let vocabulary = document_frequencies(docs);
+        let terms = ["web".to_string(), "skil".to_string(), "webhok".to_string()];
+        let prefix = TermStats::matching(docs.len(), &terms, &vocabulary, false);
+        assert_eq!(prefix.doc_freqs["web"], 1);
+        assert_eq!(prefix.doc_freqs["skil"], 3);
+        assert!(!prefix.doc_freqs.contains_key("webhok"));
+        let fuzzy = TermStats::matching(docs.len(), &terms, &vocabulary, true);
+        assert_eq!(fuzzy.doc_freqs["webhok"], 1);
+        assert_eq!(fuzzy.idf("skil"), stats.idf("skill"));
+
+        // Without a corpus the weighted score is the plain one
+        let empty = TermStats::default();
+        assert_eq!(
+            score_relevance_weighted(query, rare.0, rare.1, false, &empty),
+            score_relevance(query, rare.0, rare.1)
+        );
+        assert_eq!(query_terms("Skill skill webhook"), ["skill", "webhook"]);
+    }
+
+    #[test]
+    fn test_text_similarity() {
+        let sso = "How do I configure SSO?";
+        assert!((text_similarity(sso, "how do i configure sso") - 1.0).abs() < 1e-5);
+        assert_eq!(
+            text_similarity(sso, "configure SSO for my team"),
+            text_similarity("configure SSO for my team", sso)
+        );
+        assert!(
+            text_similarity(sso, "How do I configure SSO for my team?")
+                > text_similarity(sso, "How do I retry a workflow?")
+        );
+        assert_eq!(text_similarity(sso, "Workflow retry policies"), 0.0);
+        assert_eq!(text_similarity("", sso), 0.0);
+        // Sharing "How do I configure" doesn't make two questions the same
+        assert!(text_similarity(sso, "How do I configure workflows?") <= 0.5);
+        assert_eq!(text_similarity(sso, "How do I retry a workflow?"), 0.0);
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("authentification", "authentication"), 2);
+        assert_eq!(edit_distance("setup", "setup"), 0);
+        assert_eq!(edit_distance("", "sso"), 3);
+        assert_eq!(edit_distance("café", "cafe"), 1);
+    }
+
+    async fn search_mock(arguments: serde_json::Value) -> Vec<DocResult> {
+        let tool = SearchDocsTool::with_backend(MockBackend);
+        let result = tool.execute(arguments).await.unwrap();
+        serde_json::from_str(&result).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_search_docs_fuzzy() {
+        let docs = search_mock(serde_json::json!({
+            "query": "authentification",
+            "fuzzy": true
+        }))
+        .await;
+        assert!(docs
+            .iter()
+            .any(|d| d.title == "Troubleshooting Common Issues"));
+
+        let docs = search_mock(serde_json::json!({"query": "authentification"})).await;
+        assert!(docs.is_empty());
+
+        // Short terms still have to match exactly
+        assert_eq!(score_relevance_fuzzy("sso", "API Reference", "ssl"), 0.0);
+    }
 }
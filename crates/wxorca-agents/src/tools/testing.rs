@@ -0,0 +1,27 @@
+//! Tool fixtures shared by tests across the crate
+
+use super::ToolErrorKind;
+use async_trait::async_trait;
+use oxidizedgraph::prelude::{NodeError, Tool};
+
+/// A tool whose backend is down, registered under the given name
+pub(crate) struct DownTool(pub &'static str);
+
+#[async_trait]
+impl Tool for DownTool {
+    fn name(&self) -> &str {
+        self.0
+    }
+
+    fn description(&self) -> &str {
+        "Always fails as if its database were unreachable"
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        serde_json::json!({ "type": "object", "properties": {}, "required": [] })
+    }
+
+    async fn execute(&self, _arguments: serde_json::Value) -> Result<String, NodeError> {
+        Err(ToolErrorKind::Unavailable.error("connection refused"))
+    }
+}
@@ -1,8 +1,11 @@
 //! Validate WatsonX Orchestrate configuration tool
 
+use super::error::ToolErrorKind;
 use async_trait::async_trait;
 use oxidizedgraph::prelude::{NodeError, Tool};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
 
 /// Tool for validating WatsonX Orchestrate configurations
 pub struct ValidateConfigTool;
@@ -23,6 +26,9 @@ impl Default for ValidateConfigTool {
 struct ValidateConfigInput {
     config_type: ConfigType,
     config: serde_json::Value,
+    /// JSON Schema the config must conform to
+    #[serde(default)]
+    schema: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -79,6 +85,10 @@ impl Tool for ValidateConfigTool {
                 "config": {
                     "type": "object",
                     "description": "The configuration object to validate"
+                },
+                "schema": {
+                    "type": "object",
+                    "description": "Optional JSON Schema to validate the config against"
                 }
             },
             "required": ["config_type", "config"]
@@ -86,18 +96,83 @@ impl Tool for ValidateConfigTool {
     }
 
     async fn execute(&self, arguments: serde_json::Value) -> Result<String, NodeError> {
-        let input: ValidateConfigInput = serde_json::from_value(arguments)
-            .map_err(|e| NodeError::ToolError(format!("Invalid arguments: {}", e)))?;
+        let input: ValidateConfigInput = serde_json::from_value(arguments).map_err(|e| {
+            ToolErrorKind::InvalidArguments.error(format!("Invalid arguments: {}", e))
+        })?;
 
-        let result = match input.config_type {
+        let mut result = match input.config_type {
             ConfigType::Skill => validate_skill_config(&input.config),
             ConfigType::Workflow => validate_workflow_config(&input.config),
             ConfigType::Integration => validate_integration_config(&input.config),
             ConfigType::Authentication => validate_auth_config(&input.config),
         };
 
-        serde_json::to_string_pretty(&result)
-            .map_err(|e| NodeError::ToolError(format!("Failed to serialize result: {}", e)))
+        if let Some(schema) = &input.schema {
+            let schema_errors = validate_against_schema(schema, &input.config)?;
+            result.layer_on_schema(schema_errors);
+        }
+
+        serde_json::to_string_pretty(&result).map_err(|e| {
+            ToolErrorKind::Internal.error(format!("Failed to serialize result: {}", e))
+        })
+    }
+}
+
+impl ValidationResult {
+    /// Make schema violations the errors, keeping the heuristic checks as
+    /// warnings and suggestions on top
+    fn layer_on_schema(&mut self, schema_errors: Vec<ValidationError>) {
+        let heuristic_errors = std::mem::replace(&mut self.errors, schema_errors);
+        self.warnings
+            .extend(heuristic_errors.into_iter().map(|error| ValidationWarning {
+                field: error.field,
+                message: error.message,
+            }));
+        self.valid = self.errors.is_empty();
+    }
+}
+
+/// Validate `config` against a JSON Schema
+///
+/// Each violation becomes a `SCHEMA_VIOLATION` error whose field is the
+/// violating instance path. An invalid schema is a tool error.
+fn validate_against_schema(
+    schema: &serde_json::Value,
+    config: &serde_json::Value,
+) -> Result<Vec<ValidationError>, NodeError> {
+    let validator = jsonschema::validator_for(schema)
+        .map_err(|e| ToolErrorKind::InvalidArguments.error(format!("Invalid schema: {}", e)))?;
+
+    Ok(validator
+        .iter_errors(config)
+        .map(|error| ValidationError {
+            field: pointer_to_field(error.instance_path.as_str()),
+            message: error.to_string(),
+            code: "SCHEMA_VIOLATION".to_string(),
+        })
+        .collect())
+}
+
+/// Convert a JSON pointer like `/steps/0/id` to the `steps[0].id` form used
+/// by the other checks; the root is reported as `config`
+fn pointer_to_field(pointer: &str) -> String {
+    let mut field = String::new();
+    for segment in pointer.split('/').skip(1) {
+        let segment = segment.replace("~1", "/").replace("~0", "~");
+        if segment.parse::<usize>().is_ok() {
+            field.push_str(&format!("[{}]", segment));
+        } else {
+            if !field.is_empty() {
+                field.push('.');
+            }
+            field.push_str(&segment);
+        }
+    }
+
+    if field.is_empty() {
+        "config".to_string()
+    } else {
+        field
     }
 }
 
@@ -198,6 +273,9 @@ fn validate_workflow_config(config: &serde_json::Value) -> ValidationResult {
                 });
             }
         }
+
+        errors.extend(validate_step_references(steps));
+        errors.extend(validate_template_references(config, steps));
     }
 
     if config.get("error_handling").is_none() {
@@ -218,6 +296,257 @@ fn validate_workflow_config(config: &serde_json::Value) -> ValidationResult {
     }
 }
 
+/// Step fields that reference other steps by id
+const STEP_REFERENCE_FIELDS: &[&str] = &["depends_on", "next"];
+
+/// Get the step ids referenced by a `depends_on`/`next` field (a string or list of strings)
+fn step_references(value: &serde_json::Value) -> Vec<&str> {
+    match value {
+        serde_json::Value::String(id) => vec![id.as_str()],
+        serde_json::Value::Array(ids) => ids.iter().filter_map(|id| id.as_str()).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Check that step references point at existing steps and don't form a cycle
+fn validate_step_references(steps: &[serde_json::Value]) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+
+    let ids: Vec<Option<&str>> = steps
+        .iter()
+        .map(|step| step.get("id").and_then(|id| id.as_str()))
+        .collect();
+    let known: HashSet<&str> = ids.iter().flatten().copied().collect();
+
+    for (i, step) in steps.iter().enumerate() {
+        for field in STEP_REFERENCE_FIELDS {
+            let Some(value) = step.get(*field) else {
+                continue;
+            };
+            for target in step_references(value) {
+                if !known.contains(target) {
+                    errors.push(ValidationError {
+                        field: format!("steps[{}].{}", i, field),
+                        message: format!("Step references unknown step id '{}'", target),
+                        code: "UNKNOWN_STEP_REFERENCE".to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    // Depth-first search; a reference back into the current path is a cycle
+    let edges = step_edges(steps);
+    let mut visited = HashSet::new();
+    for id in ids.iter().flatten() {
+        let mut path = Vec::new();
+        find_cycles(id, &edges, &mut visited, &mut path, &mut errors);
+    }
+
+    errors
+}
+
+/// The steps that directly follow each step, by id
+///
+/// Edges come from the `depends_on`/`next` references to known steps and
+/// point from the step that runs first to the one that follows.
+fn step_edges(steps: &[serde_json::Value]) -> HashMap<&str, Vec<&str>> {
+    let ids: Vec<Option<&str>> = steps
+        .iter()
+        .map(|step| step.get("id").and_then(|id| id.as_str()))
+        .collect();
+    let known: HashSet<&str> = ids.iter().flatten().copied().collect();
+
+    let mut edges: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (step, id) in steps.iter().zip(&ids) {
+        let Some(id) = *id else {
+            continue;
+        };
+        for field in STEP_REFERENCE_FIELDS {
+            let Some(value) = step.get(*field) else {
+                continue;
+            };
+            for target in step_references(value)
+                .into_iter()
+                .filter(|t| known.contains(t))
+            {
+                if *field == "depends_on" {
+                    edges.entry(target).or_default().push(id);
+                } else {
+                    edges.entry(id).or_default().push(target);
+                }
+            }
+        }
+    }
+    edges
+}
+
+/// Whether `to` can be reached from `from` by following `edges`
+fn runs_before(edges: &HashMap<&str, Vec<&str>>, from: &str, to: &str) -> bool {
+    let mut visited = HashSet::new();
+    let mut pending = vec![from];
+    while let Some(id) = pending.pop() {
+        for &next in edges.get(id).into_iter().flatten() {
+            if next == to {
+                return true;
+            }
+            if visited.insert(next) {
+                pending.push(next);
+            }
+        }
+    }
+    false
+}
+
+/// Check the `{{steps.X...}}` and `{{trigger...}}` placeholders in step inputs
+///
+/// A step can only use the output of a step that runs before it: one it
+/// depends on, directly or through other steps, in the `depends_on`/`next`
+/// graph. Workflows without any such references run their steps in order.
+/// Trigger data only exists when the workflow defines a trigger. Other
+/// placeholders (`{{secrets...}}`, `{{workflow...}}`, ...) are not checked.
+fn validate_template_references(
+    config: &serde_json::Value,
+    steps: &[serde_json::Value],
+) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    let has_trigger = config.get("trigger").is_some_and(|t| !t.is_null());
+    let ids: Vec<Option<&str>> = steps
+        .iter()
+        .map(|step| step.get("id").and_then(|id| id.as_str()))
+        .collect();
+    let mut edges = step_edges(steps);
+    if edges.is_empty() {
+        // No explicit order, so each step follows the one before it
+        for pair in ids.windows(2) {
+            if let [Some(first), Some(next)] = pair {
+                edges.entry(*first).or_default().push(*next);
+            }
+        }
+    }
+
+    for (i, step) in steps.iter().enumerate() {
+        let Some(input) = step.get("input") else {
+            continue;
+        };
+        let mut placeholders = Vec::new();
+        collect_placeholders(input, format!("steps[{}].input", i), &mut placeholders);
+
+        for (field, placeholder) in placeholders {
+            let mut parts = placeholder.split('.');
+            let message = match (parts.next(), parts.next()) {
+                (Some("steps"), target) => {
+                    let target = target.unwrap_or_default();
+                    if ids[i] == Some(target) {
+                        errors.push(ValidationError {
+                            field,
+                            message: format!(
+                                "'{{{{{}}}}}' references the output of the step it is in",
+                                placeholder
+                            ),
+                            code: "SELF_TEMPLATE_REFERENCE".to_string(),
+                        });
+                        continue;
+                    }
+                    let runs_first = match ids[i] {
+                        Some(id) => runs_before(&edges, target, id),
+                        // Without an id the step can't be depended on, so
+                        // only its position orders it
+                        None => ids[..i].contains(&Some(target)),
+                    };
+                    if runs_first {
+                        continue;
+                    }
+                    if ids.contains(&Some(target)) {
+                        format!(
+                            "'{{{{{}}}}}' references step '{}', which doesn't run before this one",
+                            placeholder, target
+                        )
+                    } else {
+                        format!(
+                            "'{{{{{}}}}}' references unknown step id '{}'",
+                            placeholder, target
+                        )
+                    }
+                }
+                (Some("trigger"), _) if !has_trigger => format!(
+                    "'{{{{{}}}}}' uses trigger data, but the workflow has no trigger",
+                    placeholder
+                ),
+                _ => continue,
+            };
+            errors.push(ValidationError {
+                field,
+                message,
+                code: "UNRESOLVED_TEMPLATE_REFERENCE".to_string(),
+            });
+        }
+    }
+
+    errors
+}
+
+/// Collect the `{{...}}` placeholders in the strings of `value`, with the
+/// field path of each
+fn collect_placeholders(
+    value: &serde_json::Value,
+    field: String,
+    placeholders: &mut Vec<(String, String)>,
+) {
+    match value {
+        serde_json::Value::String(text) => {
+            let mut rest = text.as_str();
+            while let Some(start) = rest.find("{{") {
+                let Some(end) = rest[start + 2..].find("}}") else {
+                    break;
+                };
+                let placeholder = rest[start + 2..start + 2 + end].trim();
+                placeholders.push((field.clone(), placeholder.to_string()));
+                rest = &rest[start + 2 + end + 2..];
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                collect_placeholders(item, format!("{}[{}]", field, i), placeholders);
+            }
+        }
+        serde_json::Value::Object(fields) => {
+            for (key, item) in fields {
+                collect_placeholders(item, format!("{}.{}", field, key), placeholders);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn find_cycles<'a>(
+    id: &'a str,
+    edges: &HashMap<&'a str, Vec<&'a str>>,
+    visited: &mut HashSet<&'a str>,
+    path: &mut Vec<&'a str>,
+    errors: &mut Vec<ValidationError>,
+) {
+    if let Some(start) = path.iter().position(|step| *step == id) {
+        let mut cycle = path[start..].to_vec();
+        cycle.push(id);
+        errors.push(ValidationError {
+            field: "steps".to_string(),
+            message: format!("Workflow steps form a cycle: {}", cycle.join(" -> ")),
+            code: "CYCLE_DETECTED".to_string(),
+        });
+        return;
+    }
+    if !visited.insert(id) {
+        return;
+    }
+
+    path.push(id);
+    for target in edges.get(id).into_iter().flatten() {
+        find_cycles(target, edges, visited, path, errors);
+    }
+    path.pop();
+}
+
 fn validate_integration_config(config: &serde_json::Value) -> ValidationResult {
     let mut errors = Vec::new();
     let mut warnings = Vec::new();
@@ -240,6 +569,49 @@ fn validate_integration_config(config: &serde_json::Value) -> ValidationResult {
         });
     }
 
+    // Webhooks can't be called without an endpoint
+    if config.get("type").and_then(|t| t.as_str()) == Some("webhook")
+        && URL_FIELDS.iter().all(|field| config.get(*field).is_none())
+    {
+        errors.push(ValidationError {
+            field: "url".to_string(),
+            message: "Webhook integrations require a url".to_string(),
+            code: "MISSING_REQUIRED_FIELD".to_string(),
+        });
+    }
+
+    let production = is_production(config);
+    for field in URL_FIELDS {
+        let Some(url) = config.get(*field).and_then(|u| u.as_str()) else {
+            continue;
+        };
+        let Some((scheme, host)) = split_url(url) else {
+            errors.push(ValidationError {
+                field: field.to_string(),
+                message: format!("'{}' is not a valid URL", url),
+                code: "INVALID_URL".to_string(),
+            });
+            continue;
+        };
+
+        if scheme == "http" {
+            errors.push(ValidationError {
+                field: field.to_string(),
+                message: "Use https so credentials aren't sent in plain text".to_string(),
+                code: "INSECURE_URL".to_string(),
+            });
+        }
+        if production && is_private_host(&host) {
+            warnings.push(ValidationWarning {
+                field: field.to_string(),
+                message: format!(
+                    "'{}' is a private or loopback address and won't be reachable from production",
+                    host
+                ),
+            });
+        }
+    }
+
     // Check for security issues
     if let Some(creds) = config.get("credentials") {
         if creds.get("password").is_some() {
@@ -268,6 +640,57 @@ fn validate_integration_config(config: &serde_json::Value) -> ValidationResult {
     }
 }
 
+/// Integration fields that hold the service's connection URL
+const URL_FIELDS: &[&str] = &["url", "instance_url", "endpoint"];
+
+/// Whether an integration targets production; integrations without an
+/// `environment` are assumed to
+fn is_production(config: &serde_json::Value) -> bool {
+    match config.get("environment").and_then(|e| e.as_str()) {
+        Some(env) => matches!(env.to_lowercase().as_str(), "production" | "prod"),
+        None => true,
+    }
+}
+
+/// Split a URL into its lowercased scheme and host
+fn split_url(url: &str) -> Option<(String, String)> {
+    let (scheme, rest) = url.trim().split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    // Drop any user info
+    let host_port = authority.rsplit('@').next()?;
+
+    let host = if let Some(bracketed) = host_port.strip_prefix('[') {
+        // IPv6 literal, e.g. [::1]:8080
+        bracketed.split(']').next()?
+    } else {
+        host_port.split(':').next()?
+    };
+    if scheme.is_empty() || host.is_empty() {
+        return None;
+    }
+
+    Some((scheme.to_lowercase(), host.to_lowercase()))
+}
+
+/// Whether `host` is localhost or a loopback, private or link-local IP
+fn is_private_host(host: &str) -> bool {
+    if host == "localhost" || host.ends_with(".localhost") {
+        return true;
+    }
+
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => ip.is_loopback() || ip.is_private() || ip.is_link_local(),
+        Ok(IpAddr::V6(ip)) => {
+            let first = ip.segments()[0];
+            ip.is_loopback()
+                // Unique local (fc00::/7) and link-local (fe80::/10)
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+        }
+        Err(_) => false,
+    }
+}
+
 fn validate_auth_config(config: &serde_json::Value) -> ValidationResult {
     let mut errors = Vec::new();
     let mut warnings = Vec::new();
@@ -373,4 +796,321 @@ mod tests {
         assert!(!validation.valid);
         assert!(!validation.errors.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_validate_workflow_step_cycle() {
+        let tool = ValidateConfigTool::new();
+
+        let result = tool
+            .execute(serde_json::json!({
+                "config_type": "workflow",
+                "config": {
+                    "name": "onboarding",
+                    "steps": [
+                        { "id": "collect", "action": "form", "depends_on": ["notify"] },
+                        { "id": "approve", "action": "approval", "depends_on": "collect" },
+                        { "id": "notify", "action": "email", "depends_on": ["approve"] }
+                    ]
+                }
+            }))
+            .await
+            .unwrap();
+
+        let validation: ValidationResult = serde_json::from_str(&result).unwrap();
+        assert!(!validation.valid);
+        let cycles: Vec<_> = validation
+            .errors
+            .iter()
+            .filter(|e| e.code == "CYCLE_DETECTED")
+            .collect();
+        assert_eq!(cycles.len(), 1);
+        assert!(cycles[0]
+            .message
+            .contains("collect -> approve -> notify -> collect"));
+    }
+
+    #[tokio::test]
+    async fn test_validate_workflow_unknown_step_reference() {
+        let tool = ValidateConfigTool::new();
+
+        let result = tool
+            .execute(serde_json::json!({
+                "config_type": "workflow",
+                "config": {
+                    "name": "onboarding",
+                    "steps": [
+                        { "id": "collect", "action": "form" },
+                        { "id": "approve", "action": "approval", "depends_on": ["colect"] }
+                    ]
+                }
+            }))
+            .await
+            .unwrap();
+
+        let validation: ValidationResult = serde_json::from_str(&result).unwrap();
+        assert!(!validation.valid);
+        assert_eq!(validation.errors.len(), 1);
+        assert_eq!(validation.errors[0].code, "UNKNOWN_STEP_REFERENCE");
+        assert_eq!(validation.errors[0].field, "steps[1].depends_on");
+    }
+
+    async fn validate_workflow(config: serde_json::Value) -> ValidationResult {
+        let result = ValidateConfigTool::new()
+            .execute(serde_json::json!({"config_type": "workflow", "config": config}))
+            .await
+            .unwrap();
+        serde_json::from_str(&result).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_template_references_follow_step_order() {
+        let validation = validate_workflow(serde_json::json!({
+            "name": "customer_onboarding",
+            "trigger": { "type": "webhook" },
+            "steps": [
+                { "id": "validate_input", "skill_id": "data_validator",
+                  "input": { "data": "{{trigger.customer_data}}" } },
+                { "id": "create_account", "skill_id": "crm_create_account",
+                  "input": { "customer": "{{ steps.validate_input.output }}" } },
+                { "id": "send_welcome", "skill_id": "email_sender",
+                  "input": {
+                      "to": "{{trigger.customer_data.email}}",
+                      "account": ["{{steps.create_account.output.id}}"],
+                      "api_key": "{{secrets.EMAIL_API_KEY}}"
+                  } }
+            ],
+            "error_handling": { "on_failure": "notify_admin" }
+        }))
+        .await;
+
+        assert!(validation.valid, "{:?}", validation.errors);
+    }
+
+    #[tokio::test]
+    async fn test_template_forward_reference_is_unresolved() {
+        let validation = validate_workflow(serde_json::json!({
+            "name": "customer_onboarding",
+            "steps": [
+                { "id": "create_account", "skill_id": "crm_create_account",
+                  "input": { "customer": "{{steps.validate_input.output}}" } },
+                { "id": "validate_input", "skill_id": "data_validator",
+                  "input": { "data": ["{{trigger.customer_data}}"] } }
+            ]
+        }))
+        .await;
+
+        assert!(!validation.valid);
+        let fields: Vec<(&str, &str)> = validation
+            .errors
+            .iter()
+            .map(|e| (e.code.as_str(), e.field.as_str()))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("UNRESOLVED_TEMPLATE_REFERENCE", "steps[0].input.customer"),
+                ("UNRESOLVED_TEMPLATE_REFERENCE", "steps[1].input.data[0]"),
+            ]
+        );
+        assert!(validation.errors[0]
+            .message
+            .contains("doesn't run before this one"));
+    }
+
+    #[tokio::test]
+    async fn test_template_references_follow_dependencies() {
+        // Listed out of order, but `depends_on` runs `validate_input` first
+        let validation = validate_workflow(serde_json::json!({
+            "name": "customer_onboarding",
+            "steps": [
+                { "id": "create_account", "skill_id": "crm_create_account",
+                  "depends_on": ["validate_input"],
+                  "input": { "customer": "{{steps.validate_input.output}}" } },
+                { "id": "validate_input", "skill_id": "data_validator" },
+                { "id": "audit", "skill_id": "audit_log",
+                  "input": { "account": "{{steps.create_account.output}}" } }
+            ],
+            "error_handling": { "on_failure": "notify_admin" }
+        }))
+        .await;
+
+        // `audit` is listed last but nothing orders it after `create_account`
+        let codes: Vec<(&str, &str)> = validation
+            .errors
+            .iter()
+            .map(|e| (e.code.as_str(), e.field.as_str()))
+            .collect();
+        assert_eq!(
+            codes,
+            vec![("UNRESOLVED_TEMPLATE_REFERENCE", "steps[2].input.account")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_template_self_reference() {
+        let validation = validate_workflow(serde_json::json!({
+            "name": "retry_loop",
+            "steps": [
+                { "id": "fetch", "skill_id": "http_get",
+                  "input": { "cursor": "{{steps.fetch.output.next}}" } }
+            ]
+        }))
+        .await;
+
+        assert_eq!(validation.errors.len(), 1);
+        assert_eq!(validation.errors[0].code, "SELF_TEMPLATE_REFERENCE");
+        assert_eq!(validation.errors[0].field, "steps[0].input.cursor");
+    }
+
+    async fn validate_integration(config: serde_json::Value) -> ValidationResult {
+        let result = ValidateConfigTool::new()
+            .execute(serde_json::json!({
+                "config_type": "integration",
+                "config": config
+            }))
+            .await
+            .unwrap();
+        serde_json::from_str(&result).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_http_url_is_insecure() {
+        let validation = validate_integration(serde_json::json!({
+            "type": "webhook",
+            "url": "http://hooks.example.com/notify",
+            "credentials": { "api_key": "secret" }
+        }))
+        .await;
+
+        assert!(!validation.valid);
+        let error = validation
+            .errors
+            .iter()
+            .find(|e| e.code == "INSECURE_URL")
+            .expect("http url should be flagged");
+        assert_eq!(error.field, "url");
+    }
+
+    #[tokio::test]
+    async fn test_https_url_is_accepted() {
+        let validation = validate_integration(serde_json::json!({
+            "type": "webhook",
+            "endpoint": "https://hooks.example.com/notify",
+            "credentials": { "api_key": "secret" }
+        }))
+        .await;
+
+        assert!(validation.valid);
+        assert!(validation.warnings.iter().all(|w| w.field != "endpoint"));
+    }
+
+    #[tokio::test]
+    async fn test_localhost_url_warns_in_production() {
+        let config = serde_json::json!({
+            "type": "webhook",
+            "instance_url": "http://localhost:8080/hook",
+            "credentials": { "api_key": "secret" }
+        });
+        let validation = validate_integration(config.clone()).await;
+
+        assert!(validation
+            .errors
+            .iter()
+            .any(|e| e.code == "INSECURE_URL" && e.field == "instance_url"));
+        assert!(validation
+            .warnings
+            .iter()
+            .any(|w| w.field == "instance_url"));
+
+        // Local endpoints are expected outside production
+        let mut config = config;
+        config["environment"] = serde_json::json!("development");
+        let validation = validate_integration(config).await;
+        assert!(validation
+            .warnings
+            .iter()
+            .all(|w| w.field != "instance_url"));
+    }
+
+    #[test]
+    fn test_private_hosts() {
+        assert_eq!(
+            split_url("HTTPS://user@[::1]:443/path"),
+            Some(("https".to_string(), "::1".to_string()))
+        );
+        assert!(is_private_host("::1"));
+        assert!(is_private_host("10.0.0.5"));
+        assert!(is_private_host("192.168.1.10"));
+        assert!(!is_private_host("8.8.8.8"));
+        assert!(!is_private_host("hooks.example.com"));
+        assert_eq!(split_url("not a url"), None);
+    }
+
+    #[tokio::test]
+    async fn test_validate_against_schema() {
+        let tool = ValidateConfigTool::new();
+        let schema = serde_json::json!({
+            "type": "object",
+            "required": ["name", "steps"],
+            "properties": {
+                "name": { "type": "string" },
+                "steps": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": { "timeout": { "type": "integer" } }
+                    }
+                }
+            }
+        });
+
+        let result = tool
+            .execute(serde_json::json!({
+                "config_type": "workflow",
+                "config": {
+                    "steps": [{ "id": "a", "action": "notify", "timeout": "soon" }]
+                },
+                "schema": schema
+            }))
+            .await
+            .unwrap();
+
+        let validation: ValidationResult = serde_json::from_str(&result).unwrap();
+        assert!(!validation.valid);
+        assert!(validation
+            .errors
+            .iter()
+            .all(|e| e.code == "SCHEMA_VIOLATION"));
+        let fields: Vec<_> = validation.errors.iter().map(|e| e.field.as_str()).collect();
+        assert!(fields.contains(&"config"));
+        assert!(fields.contains(&"steps[0].timeout"));
+        // The heuristic missing-name error is kept as a warning
+        assert!(validation
+            .warnings
+            .iter()
+            .any(|w| w.message == "Workflow name is required"));
+        assert!(!validation.suggestions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_schema_is_rejected() {
+        let tool = ValidateConfigTool::new();
+
+        let result = tool
+            .execute(serde_json::json!({
+                "config_type": "skill",
+                "config": { "name": "my_skill" },
+                "schema": { "type": "not-a-type" }
+            }))
+            .await;
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_pointer_to_field() {
+        assert_eq!(pointer_to_field(""), "config");
+        assert_eq!(pointer_to_field("/steps/0/id"), "steps[0].id");
+        assert_eq!(pointer_to_field("/credentials/a~1b"), "credentials.a/b");
+    }
 }